@@ -0,0 +1,39 @@
+/* The renderer's C interface, built as a shared library by cargo. See
+ * src/ffi.rs for the details of each call. */
+
+#ifndef RAY_TRACER_H
+#define RAY_TRACER_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct Scene RayTracerScene;
+
+/* Null if the scene couldn't be loaded; see ray_tracer_last_error. */
+RayTracerScene *ray_tracer_scene_from_json(const char *json);
+void ray_tracer_scene_free(RayTracerScene *scene);
+
+/* 0 for a null scene. */
+size_t ray_tracer_scene_width(const RayTracerScene *scene);
+size_t ray_tracer_scene_height(const RayTracerScene *scene);
+
+/* Fills width * height * 4 bytes of RGBA. Returns 0, or -1 on failure,
+ * including a panic inside the renderer. */
+int ray_tracer_render_rgba(const RayTracerScene *scene, uint8_t *buffer, size_t length);
+
+/* From 0 to 1, or -1 for a null scene; may be called from another thread
+ * during a render. */
+double ray_tracer_progress(const RayTracerScene *scene);
+
+/* Why the last call on this thread failed, or null. */
+const char *ray_tracer_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
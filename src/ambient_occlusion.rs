@@ -0,0 +1,94 @@
+use intersection::Intersection;
+use point::Point;
+use ray::Ray;
+use sampling::cosine_hemisphere;
+use serde::{Deserialize, Serialize};
+use world::World;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AmbientOcclusion {
+    pub distance: f64,
+    pub samples: usize,
+}
+
+impl AmbientOcclusion {
+    pub fn new(samples: usize, distance: f64) -> AmbientOcclusion {
+        AmbientOcclusion { distance, samples }
+    }
+
+    // The fraction of hemisphere rays that escape further than `distance`.
+    pub fn visibility(&self, world: &World, point: &Point, normal: &Point) -> f64 {
+        if self.samples == 0 {
+            return 1.;
+        }
+        let mut unoccluded = 0;
+        for _ in 0..self.samples {
+            let ray = Ray {
+                origin: *point,
+                direction: cosine_hemisphere(normal),
+            };
+            match Intersection::hit(&mut ray.intersect_world(world)) {
+                Some(ref hit) if hit.t < self.distance => (),
+                _ => unoccluded += 1,
+            }
+        }
+        f64::from(unoccluded) / self.samples as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ambient_occlusion::AmbientOcclusion;
+    use matrix::Matrix4;
+    use point::point;
+    use point::vector;
+    use shape::Shape;
+    use std::sync::Arc;
+    use world::World;
+
+    #[test]
+    fn test_visibility_in_open_space() {
+        let mut world = World::new();
+        world.objects = vec![Shape::plane()];
+        let ao = AmbientOcclusion::new(16, 1.);
+
+        assert_eq!(
+            ao.visibility(&world, &point(0., 0.0001, 0.), &vector(0., 1., 0.)),
+            1.
+        );
+    }
+
+    #[test]
+    fn test_visibility_when_enclosed() {
+        let mut world = World::new();
+        let mut sphere = Shape::sphere();
+        Arc::get_mut(&mut sphere)
+            .unwrap()
+            .set_transform(Matrix4::scaling(2., 2., 2.))
+            .unwrap();
+        world.objects = vec![sphere];
+        let ao = AmbientOcclusion::new(16, 3.);
+
+        assert_eq!(
+            ao.visibility(&world, &point(0., 0., 0.), &vector(0., 1., 0.)),
+            0.
+        );
+    }
+
+    #[test]
+    fn test_visibility_beyond_distance() {
+        let mut world = World::new();
+        let mut sphere = Shape::sphere();
+        Arc::get_mut(&mut sphere)
+            .unwrap()
+            .set_transform(Matrix4::scaling(2., 2., 2.))
+            .unwrap();
+        world.objects = vec![sphere];
+        let ao = AmbientOcclusion::new(16, 1.);
+
+        assert_eq!(
+            ao.visibility(&world, &point(0., 0., 0.), &vector(0., 1., 0.)),
+            1.
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use canvas::Canvas;
+use error::Error;
+use png;
+use std::io::Write;
+
+// Collects rendered frames, such as a turntable, and writes them out as an
+// animated PNG. Every frame must be the size of the first, so frames only
+// go in through `push`.
+pub struct Animation {
+    // Seconds each frame stays up.
+    pub delay: f64,
+    frames: Vec<Canvas>,
+    // How many times to play through; zero repeats forever.
+    pub plays: u32,
+}
+
+impl Animation {
+    pub fn new(frames_per_second: f64) -> Animation {
+        Animation {
+            delay: 1. / frames_per_second,
+            frames: Vec::new(),
+            plays: 0,
+        }
+    }
+
+    pub fn frames(&self) -> &[Canvas] {
+        &self.frames
+    }
+
+    pub fn push(&mut self, frame: Canvas) -> Result<(), Error> {
+        if let Some(first) = self.frames.first() {
+            if (frame.width, frame.height) != (first.width, first.height) {
+                return Err(Error::SizeMismatch {
+                    left: (first.width, first.height),
+                    right: (frame.width, frame.height),
+                });
+            }
+        }
+        self.frames.push(frame);
+        Ok(())
+    }
+
+    pub fn write_apng<W: Write>(&self, writer: W) -> Result<(), png::EncodingError> {
+        let first = match self.frames.first() {
+            Some(first) => first,
+            None => return Ok(()),
+        };
+        let mut encoder = png::Encoder::new(writer, first.width as u32, first.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(self.frames.len() as u32, self.plays)?;
+        // Delays are stored in thousandths of a second.
+        encoder.set_frame_delay((self.delay * 1000.).round() as u16, 1000)?;
+        let mut writer = encoder.write_header()?;
+        for frame in &self.frames {
+            let mut bytes = Vec::with_capacity(frame.pixels.len() * 3);
+            for pixel in &frame.pixels {
+                bytes.extend_from_slice(&pixel.encode(frame.encoding).bytes());
+            }
+            writer.write_image_data(&bytes)?;
+        }
+        writer.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use animation::Animation;
+    use canvas::Canvas;
+    use color::Color;
+    use error::Error;
+    use png;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_write_apng() {
+        let mut animation = Animation::new(25.);
+        for i in 0..3 {
+            let mut frame = Canvas::empty(2, 2);
+            frame.write_pixel(i % 2, 0, &Color::white());
+            animation.push(frame).unwrap();
+        }
+        let mut bytes = Vec::new();
+
+        animation.write_apng(&mut bytes).unwrap();
+
+        let mut reader = png::Decoder::new(Cursor::new(&bytes)).read_info().unwrap();
+        let control = *reader.info().animation_control().unwrap();
+        assert_eq!(control.num_frames, 3);
+        assert_eq!(control.num_plays, 0);
+        let frame = reader.info().frame_control().unwrap();
+        assert_eq!((frame.delay_num, frame.delay_den), (40, 1000));
+        let mut buffer = vec![0; reader.output_buffer_size().unwrap()];
+        reader.next_frame(&mut buffer).unwrap();
+        assert_eq!(&buffer[..6], &[255, 255, 255, 0, 0, 0]);
+        reader.next_frame(&mut buffer).unwrap();
+        assert_eq!(&buffer[..6], &[0, 0, 0, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_push_mismatched_frame() {
+        let mut animation = Animation::new(10.);
+        animation.push(Canvas::empty(2, 2)).unwrap();
+
+        match animation.push(Canvas::empty(3, 2)) {
+            Err(Error::SizeMismatch { left, right }) => {
+                assert_eq!((left, right), ((2, 2), (3, 2)))
+            }
+            _ => panic!("expected a size mismatch"),
+        }
+        assert_eq!(animation.frames().len(), 1);
+    }
+}
@@ -0,0 +1,154 @@
+use color::Color;
+use patternable::Patternable;
+use point::Point;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sky {
+    pub ground: Color,
+    pub horizon: Color,
+    pub sun: Color,
+    // Angular radius of the sun disc, in radians.
+    pub sun_size: f64,
+    pub zenith: Color,
+}
+
+impl Sky {
+    pub fn new() -> Sky {
+        Sky {
+            ground: Color::new(0.3, 0.28, 0.25),
+            horizon: Color::new(0.85, 0.9, 1.),
+            sun: Color::new(1., 0.95, 0.85),
+            sun_size: 0.02,
+            zenith: Color::new(0.25, 0.45, 0.85),
+        }
+    }
+
+    fn color_in(&self, direction: &Point, sun_direction: &Point) -> Color {
+        let elevation = direction.y;
+        if elevation < 0. {
+            return self.ground;
+        }
+        let sky = self.horizon.add(
+            &self
+                .zenith
+                .sub(&self.horizon)
+                .multiply_scalar(elevation.sqrt()),
+        );
+        let cos = direction.dot(sun_direction);
+        if cos >= self.sun_size.cos() {
+            sky.add(&self.sun)
+        } else {
+            // A soft glow that fades away from the sun.
+            sky.add(&self.sun.multiply_scalar(0.25 * cos.max(0.).powi(16)))
+        }
+    }
+}
+
+impl Default for Sky {
+    fn default() -> Sky {
+        Sky::new()
+    }
+}
+
+// What a ray that escapes the scene sees. The sky's sun sits in the
+// direction of the world's light, and a pattern is sampled with the ray
+// direction, so a cube map makes a skybox.
+#[derive(Debug, Clone)]
+pub enum Background {
+    Gradient(Color, Color),
+    Pattern(Patternable),
+    Sky(Sky),
+    Solid(Color),
+}
+
+impl Background {
+    pub fn color_in(&self, direction: &Point, sun_direction: &Point) -> Color {
+        let direction = direction.normalize();
+        match *self {
+            Background::Gradient(horizon, zenith) => {
+                let t = direction.y.max(0.);
+                horizon.add(&zenith.sub(&horizon).multiply_scalar(t))
+            }
+            Background::Pattern(ref pattern) => pattern.color_at(&direction),
+            Background::Sky(ref sky) => sky.color_in(&direction, &sun_direction.normalize()),
+            Background::Solid(color) => color,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use background::{Background, Sky};
+    use color::Color;
+    use patternable::Patternable;
+    use point::vector;
+
+    #[test]
+    fn test_solid_background() {
+        let b = Background::Solid(Color::new(0.1, 0.2, 0.3));
+
+        assert_eq!(
+            b.color_in(&vector(0., 0., 1.), &vector(0., 1., 0.)),
+            Color::new(0.1, 0.2, 0.3)
+        );
+    }
+
+    #[test]
+    fn test_gradient_background() {
+        let b = Background::Gradient(Color::white(), Color::new(0., 0., 1.));
+        let sun = vector(0., 1., 0.);
+
+        assert_eq!(b.color_in(&vector(0., 0., 1.), &sun), Color::white());
+        assert_eq!(
+            b.color_in(&vector(0., 5., 0.), &sun),
+            Color::new(0., 0., 1.)
+        );
+        assert_eq!(
+            b.color_in(&vector(0., 1., 1.), &sun),
+            Color::new(0.29289, 0.29289, 1.)
+        );
+    }
+
+    #[test]
+    fn test_skybox_background() {
+        let faces = [
+            Patternable::solid(Color::new(1., 0., 0.)),
+            Patternable::solid(Color::new(0., 1., 0.)),
+            Patternable::solid(Color::new(0., 0., 1.)),
+            Patternable::solid(Color::new(1., 1., 0.)),
+            Patternable::solid(Color::white()),
+            Patternable::solid(Color::black()),
+        ];
+        let b = Background::Pattern(Patternable::cube_map(faces));
+        let sun = vector(0., 1., 0.);
+
+        assert_eq!(b.color_in(&vector(0., 3., 1.), &sun), Color::white());
+        assert_eq!(
+            b.color_in(&vector(-5., 1., 1.), &sun),
+            Color::new(1., 0., 0.)
+        );
+    }
+
+    #[test]
+    fn test_sky_background() {
+        let sky = Sky::new();
+        let b = Background::Sky(sky);
+        let sun = vector(1., 1., 0.);
+
+        assert_eq!(b.color_in(&vector(0., -1., 0.), &sun), sky.ground);
+        assert_eq!(
+            b.color_in(&vector(0., 1., 0.), &vector(0., -1., 0.)),
+            sky.zenith
+        );
+        assert_eq!(
+            b.color_in(&sun, &sun),
+            sky.horizon
+                .add(
+                    &sky.zenith
+                        .sub(&sky.horizon)
+                        .multiply_scalar(0.5_f64.sqrt().sqrt())
+                )
+                .add(&sky.sun)
+        );
+    }
+}
@@ -0,0 +1,334 @@
+use bsdf::Bsdf;
+use color::Color;
+use intersection::{Intersection, Precompute};
+use point::Point;
+use ray::Ray;
+use sampling::{cosine_hemisphere, random, square_to_sphere};
+use shape::Shape;
+use std::f64::consts::PI;
+use std::sync::Arc;
+use utilities::EPSILON;
+use world::World;
+
+// Bidirectional path tracing. One subpath is traced from the camera and
+// another from the light, and each vertex of one is joined to each vertex
+// of the other by a shadow ray. Light that only reaches the visible
+// surfaces through a gap, which camera paths rarely find, gets carried
+// through by the light's own subpath.
+//
+// The same complete path can be made by several of these joins, so its
+// light is split evenly among the ones that could have made it. A join
+// can't be made at a delta lobe, and a point light can't be hit by chance.
+// `remaining` caps how many surfaces a whole path may touch.
+//
+// Light falls off with the square of distance here, unlike in the Whitted
+// shading, so scenes usually want a brighter light or more exposure.
+pub fn radiance(world: &World, ray: &Ray, remaining: i32) -> Color {
+    radiance_between(world, ray, remaining, 0., f64::INFINITY)
+}
+
+// As `radiance`, seeing only the surfaces between `near` and `far` along
+// the camera ray. Every bounce after it sees the whole world.
+pub fn radiance_between(world: &World, ray: &Ray, remaining: i32, near: f64, far: f64) -> Color {
+    let max = remaining.max(0) as usize;
+    let light = &world.light_source;
+    let area_light = light.area() > 0.;
+    let mut color = Color::black();
+
+    // The camera's subpath picks up whatever light or sky it runs into.
+    let mut eye: Vec<Vertex> = Vec::new();
+    let mut ray = Ray {
+        origin: ray.origin,
+        direction: ray.direction,
+    };
+    let (mut near, mut far) = (near, far);
+    let mut beta = Color::white();
+    loop {
+        let hit = world.hit_between(&ray, near, far);
+        let light_hit = light.intersect(&ray).filter(|t| (near..=far).contains(t));
+        if let Some(t) = light_hit {
+            if hit.as_ref().is_none_or(|hit| t < hit.t) {
+                let mut deltas: Vec<bool> = eye.iter().map(|vertex| vertex.delta).collect();
+                deltas.push(false);
+                let weight = weight(&deltas, area_light);
+                color = color.add(
+                    &beta
+                        .hadamard_product(&light.radiance())
+                        .multiply_scalar(weight),
+                );
+                break;
+            }
+        }
+        let precompute = match hit {
+            Some(precompute) => precompute,
+            // Only the camera's subpath can find the sky, so it keeps it all.
+            None => {
+                color = color.add(&beta.hadamard_product(&world.background_color(&ray)));
+                break;
+            }
+        };
+        if eye.len() == max {
+            break;
+        }
+        let vertex = Vertex::new(&precompute, beta);
+        match vertex.next {
+            Some((ref next, weight)) => {
+                beta = beta.hadamard_product(&weight);
+                ray = Ray {
+                    origin: next.origin,
+                    direction: next.direction,
+                };
+                near = 0.;
+                far = f64::INFINITY;
+            }
+            None => {
+                eye.push(vertex);
+                break;
+            }
+        }
+        eye.push(vertex);
+    }
+
+    let light_path = if max > 1 {
+        light_subpath(world, max - 1)
+    } else {
+        Vec::new()
+    };
+
+    for (i, vertex) in eye.iter().enumerate() {
+        let mut deltas: Vec<bool> = eye[..i].iter().map(|vertex| vertex.delta).collect();
+        deltas.push(false);
+
+        let mut to_light = deltas.clone();
+        to_light.push(false);
+        color = color
+            .add(&connect_to_light(world, vertex).multiply_scalar(weight(&to_light, area_light)));
+
+        for (j, light_vertex) in light_path.iter().enumerate().take(max - i - 1) {
+            let mut joined = deltas.clone();
+            joined.push(false);
+            joined.extend(light_path[..j].iter().rev().map(|vertex| vertex.delta));
+            joined.push(false);
+            color = color.add(
+                &connect(world, vertex, light_vertex).multiply_scalar(weight(&joined, area_light)),
+            );
+        }
+    }
+    color
+}
+
+// Where a subpath scattered, the light it carried to get there, and how it
+// went on.
+struct Vertex {
+    beta: Color,
+    bsdf: Bsdf,
+    // Whether the subpath left here by a delta lobe.
+    delta: bool,
+    next: Option<(Ray, Color)>,
+    object: Arc<Shape>,
+    point: Point,
+}
+
+impl Vertex {
+    fn new(precompute: &Precompute, beta: Color) -> Vertex {
+        let bsdf = Bsdf::new(precompute);
+        let sample = bsdf.sample();
+        Vertex {
+            beta,
+            bsdf,
+            delta: sample.as_ref().is_some_and(|sample| sample.delta),
+            next: sample.map(|sample| {
+                (
+                    Ray {
+                        origin: sample.origin,
+                        direction: sample.direction,
+                    },
+                    sample.weight,
+                )
+            }),
+            object: precompute.object.clone(),
+            point: precompute.point,
+        }
+    }
+}
+
+// Leaves the light in a random direction; from a surface, weighted toward
+// the normal of the point it leaves from.
+fn light_subpath(world: &World, max: usize) -> Vec<Vertex> {
+    let light = &world.light_source;
+    let (origin, normal) = light.sample_surface();
+    let direction = if light.area() > 0. {
+        cosine_hemisphere(&normal)
+    } else {
+        square_to_sphere((random(), random()))
+    };
+    // Either way the intensity over the probability of the ray works out
+    // the same.
+    let mut beta = light.intensity_at(&origin).multiply_scalar(4. * PI);
+    let mut ray = Ray { origin, direction };
+    let mut vertices = Vec::new();
+    while vertices.len() < max {
+        let precompute = match world.hit(&ray) {
+            Some(precompute) => precompute,
+            None => break,
+        };
+        let vertex = Vertex::new(&precompute, beta);
+        let next = match vertex.next {
+            Some((ref next, weight)) => {
+                beta = beta.hadamard_product(&weight);
+                Some(Ray {
+                    origin: next.origin,
+                    direction: next.direction,
+                })
+            }
+            None => None,
+        };
+        vertices.push(vertex);
+        match next {
+            Some(next) => ray = next,
+            None => break,
+        }
+    }
+    vertices
+}
+
+// Joins a camera vertex to a fresh point on the light, if the light is
+// linked to the shape the vertex is on.
+fn connect_to_light(world: &World, vertex: &Vertex) -> Color {
+    let light = &world.light_source;
+    if !light.illuminates(&vertex.object) {
+        return Color::black();
+    }
+    let (point, normal) = light.sample_surface();
+    let to_light = point.sub(&vertex.point);
+    let distance = to_light.magnitude();
+    let direction = to_light.normalize();
+    let f = vertex.bsdf.evaluate(&direction);
+    if f == Color::black() {
+        return Color::black();
+    }
+    let area = light.area();
+    let incoming = if area > 0. {
+        let cos = -normal.dot(&direction);
+        if cos <= 0. {
+            return Color::black();
+        }
+        // Over the chance of picking this point on the surface.
+        light
+            .radiance()
+            .multiply_scalar(cos * area / (distance * distance))
+    } else {
+        light
+            .intensity_at(&vertex.point)
+            .multiply_scalar(1. / (distance * distance))
+    };
+    if !visible(world, &vertex.bsdf.over_point(), &point) {
+        return Color::black();
+    }
+    vertex
+        .beta
+        .hadamard_product(&f)
+        .hadamard_product(&incoming)
+        .multiply_scalar(vertex.bsdf.normal().dot(&direction))
+}
+
+fn connect(world: &World, eye: &Vertex, light: &Vertex) -> Color {
+    let between = light.point.sub(&eye.point);
+    let distance = between.magnitude();
+    let direction = between.normalize();
+    let back = direction.multiply_scalar(-1.);
+    let f_eye = eye.bsdf.evaluate(&direction);
+    let f_light = light.bsdf.evaluate(&back);
+    if f_eye == Color::black() || f_light == Color::black() {
+        return Color::black();
+    }
+    if !visible(world, &eye.bsdf.over_point(), &light.bsdf.over_point()) {
+        return Color::black();
+    }
+    let geometry =
+        eye.bsdf.normal().dot(&direction) * light.bsdf.normal().dot(&back) / (distance * distance);
+    eye.beta
+        .hadamard_product(&f_eye)
+        .hadamard_product(&f_light)
+        .hadamard_product(&light.beta)
+        .multiply_scalar(geometry)
+}
+
+fn visible(world: &World, from: &Point, to: &Point) -> bool {
+    let between = to.sub(from);
+    let distance = between.magnitude();
+    let ray = Ray {
+        origin: *from,
+        direction: between.normalize(),
+    };
+    match Intersection::hit(&mut ray.intersect_world(world)) {
+        Some(hit) => hit.t >= distance - EPSILON,
+        None => true,
+    }
+}
+
+// One over the number of joins that could have made a path, given whether
+// each point on it, from the camera end to the light, was left by a delta
+// lobe. Hitting the light counts as one more way for a light with an area.
+fn weight(deltas: &[bool], area_light: bool) -> f64 {
+    let joins = deltas
+        .windows(2)
+        .filter(|pair| !pair[0] && !pair[1])
+        .count();
+    1. / (joins + area_light as usize) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use bidirectional::{radiance, weight};
+    use color::Color;
+    use point::point;
+    use point_light::{LightLink, PointLight};
+    use std::sync::Arc;
+    use test_support::{floor_world, ray, FLOOR_RADIANCE};
+
+    #[test]
+    fn test_direct_light_from_point_light() {
+        let world = floor_world(0.);
+
+        let color = radiance(&world, &ray(), 1);
+
+        assert_eq!(
+            color,
+            Color::new(FLOOR_RADIANCE, FLOOR_RADIANCE, FLOOR_RADIANCE)
+        );
+    }
+
+    #[test]
+    fn test_no_direct_light_on_unlinked_shapes() {
+        let mut world = floor_world(0.);
+        let mut light = PointLight::new(point(0., 2., 0.), Color::white());
+        light.illuminates = LightLink::Include(Vec::new());
+        world.light_source = Arc::new(light);
+
+        assert_eq!(radiance(&world, &ray(), 1), Color::black());
+    }
+
+    // A sphere lights a surface facing it as a point light would, by
+    // hitting it and by joining to it.
+    #[test]
+    fn test_direct_light_from_spherical_light() {
+        let world = floor_world(0.5);
+        let samples = 40000;
+
+        let mut total = 0.;
+        for _ in 0..samples {
+            total += radiance(&world, &ray(), 1).red;
+        }
+
+        assert!((total / samples as f64 - FLOOR_RADIANCE).abs() < FLOOR_RADIANCE * 0.05);
+    }
+
+    #[test]
+    fn test_weight() {
+        assert_eq!(weight(&[false, false], false), 1.);
+        assert_eq!(weight(&[false, false], true), 0.5);
+        assert_eq!(weight(&[false, false, false, false], false), 1. / 3.);
+        assert_eq!(weight(&[false, true, false, false], true), 0.5);
+    }
+}
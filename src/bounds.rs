@@ -1,10 +1,11 @@
-use point::point;
-use point::Point;
+use matrix::Matrix4;
+use point::{self, point, Point};
 use ray::Ray;
-use std::f64::INFINITY;
+use std::fmt;
 use utilities::EPSILON;
 use utilities::{max, min};
 
+#[derive(Clone, Copy, Debug)]
 pub struct Bounds {
     pub max: Point,
     pub min: Point,
@@ -18,6 +19,33 @@ impl Bounds {
         }
     }
 
+    // The box around this one's corners once they are moved by `transform`.
+    // An unbounded box stays unbounded.
+    pub fn transformed(&self, transform: &Matrix4) -> Bounds {
+        let mut corners: Vec<Point> = Vec::new();
+        for &x in &[self.min.x, self.max.x] {
+            for &y in &[self.min.y, self.max.y] {
+                for &z in &[self.min.z, self.max.z] {
+                    corners.push(transform.multiply_point(&point(x, y, z)));
+                }
+            }
+        }
+        if corners
+            .iter()
+            .any(|c| !(c.x.is_finite() && c.y.is_finite() && c.z.is_finite()))
+        {
+            let infinity = f64::INFINITY;
+            return Bounds::new(
+                -infinity, infinity, -infinity, infinity, -infinity, infinity,
+            );
+        }
+        point::bounds(corners)
+    }
+
+    pub fn union(&self, other: &Bounds) -> Bounds {
+        point::bounds(vec![self.min, self.max, other.min, other.max])
+    }
+
     fn check_axis(&self, origin: f64, direction: f64, min: f64, max: f64) -> (f64, f64) {
         let tmin: f64;
         let tmax: f64;
@@ -27,8 +55,8 @@ impl Bounds {
             tmin = tmin_numerator / direction;
             tmax = tmax_numerator / direction;
         } else {
-            tmin = tmin_numerator * INFINITY;
-            tmax = tmax_numerator * INFINITY;
+            tmin = tmin_numerator * f64::INFINITY;
+            tmax = tmax_numerator * f64::INFINITY;
         }
         if tmin > tmax {
             (tmax, tmin)
@@ -48,6 +76,18 @@ impl Bounds {
         let tmin = max(&mins);
         let tmax = min(&maxs);
 
-        tmin < tmax
+        // Equal for flat boxes, like a plane's.
+        tmin <= tmax
+    }
+}
+
+// The opposite corners, lowest first.
+impl fmt::Display for Bounds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "({}, {}, {}) to ({}, {}, {})",
+            self.min.x, self.min.y, self.min.z, self.max.x, self.max.y, self.max.z
+        )
     }
 }
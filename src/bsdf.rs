@@ -0,0 +1,286 @@
+use color::Color;
+use intersection::{Intersection, Precompute};
+use point::Point;
+use sampling::{cosine_hemisphere, phong_lobe, random};
+use std::f64::consts::PI;
+
+// How a surface scatters light, for the path tracing integrators. It keeps
+// the material's Whitted terms: a diffuse lobe, a Phong highlight lobe, and
+// mirror reflection and refraction weighted by `reflective` and
+// `transparency`, split by Schlick's approximation when a surface has both.
+// The mirror and refraction lobes are deltas, which scatter in just one
+// direction, so they can be sampled but never connected to.
+//
+// Everything is relative to `eyev` of the precompute, the direction back
+// along the ray that found the surface, whether that came from the camera
+// or from a light.
+pub struct Bsdf {
+    albedo: Color,
+    mirror: f64,
+    normal: Point,
+    over_point: Point,
+    reflectv: Point,
+    refraction: Option<Point>,
+    shininess: f64,
+    specular: f64,
+    transmission: f64,
+    under_point: Point,
+}
+
+// A direction picked by `Bsdf::sample`, with the BSDF times the cosine
+// divided by the probability of picking it.
+pub struct BsdfSample {
+    pub delta: bool,
+    pub direction: Point,
+    // Where to continue from: above the surface or, for refraction, below.
+    pub origin: Point,
+    pub weight: Color,
+}
+
+impl Bsdf {
+    pub fn new(precompute: &Precompute) -> Bsdf {
+        let material = precompute.material().for_side(precompute.inside);
+        let object = &precompute.object;
+        let point = &precompute.point;
+        let reflective = material.reflective.at(object, point);
+        let transparency = material.transparency.at(object, point);
+        let refraction = refracted_direction(precompute).filter(|_| transparency > 0.);
+        let (mirror, transmission) = match refraction {
+            Some(_) if reflective > 0. => {
+                let reflectance = Intersection::schlick(precompute);
+                (reflective * reflectance, transparency * (1. - reflectance))
+            }
+            Some(_) => (0., transparency),
+            None => (reflective, 0.),
+        };
+        Bsdf {
+            albedo: material
+                .color_at_hit(object, point, &precompute.surface_hit())
+                .multiply_scalar(material.diffuse),
+            mirror,
+            normal: precompute.normalv,
+            over_point: precompute.over_point,
+            reflectv: precompute.reflectv,
+            refraction,
+            shininess: material.shininess.at(object, point),
+            specular: material.specular,
+            transmission,
+            under_point: precompute.under_point,
+        }
+    }
+
+    // The diffuse and highlight lobes for light leaving along `direction`.
+    // They only reflect, so directions below the surface get nothing.
+    pub fn evaluate(&self, direction: &Point) -> Color {
+        if self.normal.dot(direction) <= 0. {
+            return Color::black();
+        }
+        let diffuse = self.albedo.multiply_scalar(1. / PI);
+        let highlight = self.reflectv.dot(direction);
+        if self.specular == 0. || highlight <= 0. {
+            return diffuse;
+        }
+        // Normalized so the lobe reflects `specular` of what arrives head on.
+        let phong =
+            self.specular * (self.shininess + 2.) / (2. * PI) * highlight.powf(self.shininess);
+        diffuse.add(&Color::new(phong, phong, phong))
+    }
+
+    // Picks a lobe in proportion to its weight, then a direction from it.
+    // Nothing comes back for a surface that absorbs everything.
+    pub fn sample(&self) -> Option<BsdfSample> {
+        let (_, total) = self.weights();
+        if total <= 0. {
+            return None;
+        }
+        let pick = random() * total;
+        if pick < self.mirror {
+            return Some(BsdfSample {
+                delta: true,
+                direction: self.reflectv,
+                origin: self.over_point,
+                weight: Color::new(total, total, total),
+            });
+        }
+        if pick < self.mirror + self.transmission {
+            return self.refraction.map(|direction| BsdfSample {
+                delta: true,
+                direction,
+                origin: self.under_point,
+                weight: Color::new(total, total, total),
+            });
+        }
+        // The highlight lobe is sampled about the mirror direction, so shiny
+        // surfaces don't rely on luck to find it.
+        let direction = if pick - self.mirror - self.transmission < self.specular {
+            phong_lobe(&self.reflectv, self.shininess)
+        } else {
+            cosine_hemisphere(&self.normal)
+        };
+        let cos = self.normal.dot(&direction);
+        // Some of the highlight lobe falls below the surface and is lost.
+        if cos <= 0. {
+            return None;
+        }
+        Some(BsdfSample {
+            delta: false,
+            direction,
+            origin: self.over_point,
+            weight: self
+                .evaluate(&direction)
+                .multiply_scalar(cos / self.pdf(&direction)),
+        })
+    }
+
+    // The density with which `sample` picks `direction` from the lobes that
+    // aren't deltas, per unit of solid angle.
+    pub fn pdf(&self, direction: &Point) -> f64 {
+        let (base, total) = self.weights();
+        let cos = self.normal.dot(direction);
+        if total <= 0. || cos <= 0. {
+            return 0.;
+        }
+        let diffuse = (base - self.specular) * cos / PI;
+        let highlight = self.reflectv.dot(direction).max(0.);
+        let phong =
+            self.specular * (self.shininess + 1.) / (2. * PI) * highlight.powf(self.shininess);
+        (diffuse + phong) / total
+    }
+
+    // How likely the diffuse and highlight lobes are to be sampled, against
+    // all of them.
+    fn weights(&self) -> (f64, f64) {
+        let base = self.albedo.red.max(self.albedo.green).max(self.albedo.blue) + self.specular;
+        (base, base + self.mirror + self.transmission)
+    }
+
+    pub fn normal(&self) -> Point {
+        self.normal
+    }
+
+    pub fn over_point(&self) -> Point {
+        self.over_point
+    }
+}
+
+// None under total internal reflection.
+fn refracted_direction(precompute: &Precompute) -> Option<Point> {
+    let n_ratio = precompute.n1 / precompute.n2;
+    let cos_i = precompute.normalv.dot(&precompute.eyev);
+    let sin2_t = n_ratio.powi(2) * (1. - cos_i.powi(2));
+    if sin2_t > 1. {
+        return None;
+    }
+    let cos_t = (1. - sin2_t).sqrt();
+    Some(
+        precompute
+            .normalv
+            .multiply_scalar(n_ratio * cos_i - cos_t)
+            .sub(&precompute.eyev.multiply_scalar(n_ratio)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use bsdf::Bsdf;
+    use color::Color;
+    use intersection::Intersection;
+    use material::ChannelValue;
+    use point::{point, vector};
+    use ray::Ray;
+    use sampling::{random, square_to_sphere};
+    use shape::Shape;
+    use std::f64::consts::PI;
+    use std::sync::Arc;
+
+    fn bsdf_for(shape: Arc<Shape>) -> Bsdf {
+        let ray = Ray {
+            origin: point(0., 1., -1.),
+            direction: vector(0., -1., 1.).normalize(),
+        };
+        let hit = Intersection::new(2_f64.sqrt(), shape.clone());
+        Bsdf::new(&hit.precompute(&ray, vec![hit.clone()]))
+    }
+
+    fn bsdf_from_above(shape: Arc<Shape>) -> Bsdf {
+        let ray = Ray {
+            origin: point(0., 1., 0.),
+            direction: vector(0., -1., 0.),
+        };
+        let hit = Intersection::new(1., shape.clone());
+        Bsdf::new(&hit.precompute(&ray, vec![hit.clone()]))
+    }
+
+    fn shiny_plane(diffuse: f64, shininess: f64) -> Arc<Shape> {
+        let mut plane = Shape::plane();
+        {
+            let plane = Arc::get_mut(&mut plane).unwrap();
+            plane.material.diffuse = diffuse;
+            plane.material.specular = 0.9;
+            plane.material.shininess = ChannelValue::Constant(shininess);
+        }
+        plane
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let mut plane = Shape::plane();
+        {
+            let plane = Arc::get_mut(&mut plane).unwrap();
+            plane.material.diffuse = 0.5;
+            plane.material.specular = 0.;
+        }
+        let bsdf = bsdf_for(plane);
+
+        let diffuse = Color::new(0.5 / PI, 0.5 / PI, 0.5 / PI);
+        assert_eq!(bsdf.evaluate(&vector(0., 1., 0.)), diffuse);
+        assert_eq!(bsdf.evaluate(&vector(1., 1., 0.).normalize()), diffuse);
+        assert_eq!(bsdf.evaluate(&vector(0., -1., 0.)), Color::black());
+    }
+
+    #[test]
+    fn test_sample_mirror() {
+        let mut plane = Shape::plane();
+        {
+            let plane = Arc::get_mut(&mut plane).unwrap();
+            plane.material.diffuse = 0.;
+            plane.material.specular = 0.;
+            plane.material.reflective = ChannelValue::Constant(0.8);
+        }
+        let bsdf = bsdf_for(plane);
+
+        let sample = bsdf.sample().unwrap();
+        assert!(sample.delta);
+        assert_eq!(sample.direction, vector(0., 1., 1.).normalize());
+        assert_eq!(sample.weight, Color::new(0.8, 0.8, 0.8));
+    }
+
+    #[test]
+    fn test_pdf_covers_the_hemisphere() {
+        let bsdf = bsdf_from_above(shiny_plane(0.5, 10.));
+        let samples = 200000;
+
+        let mut total = 0.;
+        for _ in 0..samples {
+            let direction = square_to_sphere((random(), random()));
+            total += bsdf.pdf(&direction) * 4. * PI;
+        }
+
+        assert!((total / samples as f64 - 1.).abs() < 0.02);
+    }
+
+    // Sampling the highlight lobe itself keeps the weights of even a very
+    // shiny surface small.
+    #[test]
+    fn test_sample_highlight() {
+        let bsdf = bsdf_for(shiny_plane(0., 500.));
+
+        for _ in 0..1000 {
+            if let Some(sample) = bsdf.sample() {
+                assert!(!sample.delta);
+                assert!(sample.weight.red <= 0.9 * 502. / 501.);
+                assert!(sample.direction.dot(&vector(0., 1., 1.).normalize()) > 0.9);
+            }
+        }
+    }
+}
@@ -1,18 +1,96 @@
 use canvas::Canvas;
 use color::Color;
+use error::Error;
+use gbuffer::{GBuffer, SurfaceSample};
 use matrix::Matrix4;
 use matrix::IDENTITY_MATRIX;
 use point::point;
+use point::vector;
 use ray::Ray;
 use rayon::prelude::*;
-use world::World;
+use sampling::{reseed, shuffle, square_to_disk, Sampler};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use world::{RenderMode, World};
 
+// Once a pixel's first samples are in, keeps adding that many more until
+// the variance of their colors drops to `threshold` or `max_samples` have
+// been taken, so flat areas stay cheap and edges get refined.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Adaptive {
+    pub max_samples: usize,
+    pub threshold: f64,
+}
+
+// How far a render has got, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    pub done: usize,
+    pub elapsed: Duration,
+    pub total: usize,
+}
+
+impl Progress {
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            return 1.;
+        }
+        self.done as f64 / self.total as f64
+    }
+
+    // The time left if the rest of the pixels take as long on average as
+    // the finished ones, which is unknown until one has finished.
+    pub fn remaining(&self) -> Option<Duration> {
+        if self.done == 0 {
+            return None;
+        }
+        let left = (self.total - self.done) as f64 / self.done as f64;
+        Some(self.elapsed.mul_f64(left))
+    }
+}
+
+// A thin lens camera. With more than one sample, each pixel averages rays
+// spread across its area by `sampler`. With an aperture radius above zero
+// they also leave from points spread over the lens, converging on the
+// plane `focal_distance` in front of it, which stays sharp.
+//
+// Tilt turns that plane about the camera's horizontal (`tilt_x`) and
+// vertical (`tilt_y`) axes, in radians, so it can lie along a receding
+// surface. Shift slides the frame across the image plane by a fraction of
+// its width (`shift_x`, rightward) or height (`shift_y`, upward), which
+// keeps verticals parallel where tilting the whole camera would not.
 pub struct Camera {
+    pub adaptive: Option<Adaptive>,
+    pub aperture: f64,
+    // Adds whatever exposure brings the finished image to middle grey.
+    pub auto_exposure: bool,
+    // How many times a ray may bounce through reflection and refraction.
+    pub depth: i32,
+    // Stops of exposure compensation; each one doubles the brightness.
+    pub exposure: f64,
+    // Surfaces nearer than `near` or further than `far` are cut away, for
+    // sections through a model or to look inside a closed shape.
+    pub far: f64,
     field_of_view: f64,
+    pub focal_distance: f64,
     half_height: f64,
     half_width: f64,
     pub hsize: usize,
-    pub transform: Matrix4,
+    pub near: f64,
+    pub sampler: Sampler,
+    pub samples: usize,
+    // Makes every pixel draw the same random numbers each render, so the
+    // same image comes out.
+    pub seed: Option<u64>,
+    pub shift_x: f64,
+    pub shift_y: f64,
+    pub tilt_x: f64,
+    pub tilt_y: f64,
+    // Only set together, by `set_transform`, so each ray is aimed without
+    // inverting the view transform again.
+    transform: Matrix4,
+    inverse: Matrix4,
     pub vsize: usize,
 }
 
@@ -31,14 +109,53 @@ impl Camera {
             half_width = half_view * aspect;
         }
 
-        return Camera {
+        Camera {
+            adaptive: None,
+            aperture: 0.,
+            auto_exposure: false,
+            depth: 8,
+            exposure: 0.,
+            far: f64::INFINITY,
             field_of_view,
+            focal_distance: 1.,
             half_height,
             half_width,
             hsize,
+            near: 0.,
+            sampler: Sampler::Stratified,
+            samples: 1,
+            seed: None,
+            shift_x: 0.,
+            shift_y: 0.,
+            tilt_x: 0.,
+            tilt_y: 0.,
             transform: IDENTITY_MATRIX,
+            inverse: IDENTITY_MATRIX,
             vsize,
-        };
+        }
+    }
+
+    // Replaces the view transform, unless it can't be undone to aim rays
+    // from the camera, as when looking from a point at itself.
+    pub fn set_transform(&mut self, transform: Matrix4) -> Result<(), Error> {
+        self.inverse = transform.try_inverse()?;
+        self.transform = transform;
+        Ok(())
+    }
+
+    pub fn transform(&self) -> Matrix4 {
+        self.transform
+    }
+
+    pub fn field_of_view(&self) -> f64 {
+        self.field_of_view
+    }
+
+    // Sets the exposure a real camera would get with these settings, taking
+    // f/1 at one second and ISO 100 as the neutral exposure. Only
+    // brightness changes; the aperture for depth of field is separate.
+    pub fn expose(&mut self, f_stop: f64, shutter: f64, iso: f64) {
+        self.exposure = -(f_stop * f_stop / shutter * 100. / iso).log2();
     }
 
     pub fn pixel_size(&self) -> f64 {
@@ -46,44 +163,361 @@ impl Camera {
     }
 
     pub fn ray_for_pixel(&self, h: usize, v: usize) -> Ray {
-        let x_offset = (h as f64 + 0.5) * self.pixel_size();
-        let y_offset = (v as f64 + 0.5) * self.pixel_size();
-        let world_x = self.half_width - x_offset;
-        let world_y = self.half_height - y_offset;
-        let inverse = self.transform.inverse();
-        let pixel = inverse.multiply_point(&point(world_x, world_y, -1.0));
-        let origin = inverse.multiply_point(&point(0.0, 0.0, 0.0));
-        return Ray {
+        self.ray_for_sample(h, v, (0.5, 0.5), (0., 0.))
+    }
+
+    // `offset` is where in the pixel the ray passes, from 0 to 1 on each
+    // axis, and `lens` is a point on the unit disc, scaled to the aperture.
+    pub fn ray_for_sample(&self, h: usize, v: usize, offset: (f64, f64), lens: (f64, f64)) -> Ray {
+        let x_offset = (h as f64 + offset.0) * self.pixel_size();
+        let y_offset = (v as f64 + offset.1) * self.pixel_size();
+        let world_x = self.half_width - x_offset - self.shift_x * 2.0 * self.half_width;
+        let world_y = self.half_height - y_offset + self.shift_y * 2.0 * self.half_height;
+        let inverse = &self.inverse;
+        // Where the ray through the lens centre meets the focal plane.
+        let pinhole = vector(world_x, world_y, -1.0);
+        let normal = Matrix4::rotation_x(self.tilt_x)
+            .multiply(&Matrix4::rotation_y(self.tilt_y))
+            .multiply_point(&vector(0.0, 0.0, 1.0));
+        let t = -self.focal_distance * normal.z / normal.dot(&pinhole);
+        let focus = inverse.multiply_point(&point(0.0, 0.0, 0.0).add(&pinhole.multiply_scalar(t)));
+        let origin =
+            inverse.multiply_point(&point(lens.0 * self.aperture, lens.1 * self.aperture, 0.0));
+        Ray {
             origin,
-            direction: (pixel.sub(&origin)).normalize(),
-        };
+            direction: (focus.sub(&origin)).normalize(),
+        }
+    }
+
+    fn radiance(&self, world: &World, ray: &Ray) -> Color {
+        world.radiance_between(ray, self.depth, self.near, self.far)
+    }
+
+    pub fn color_for_pixel(&self, world: &World, h: usize, v: usize) -> Color {
+        self.color_and_count_for_pixel(world, h, v).0
+    }
+
+    // The pixel's color along with how many samples went into it.
+    fn color_and_count_for_pixel(&self, world: &World, h: usize, v: usize) -> (Color, usize) {
+        self.reseed_pixel(0, h, v);
+        if self.samples <= 1 && self.adaptive.is_none() {
+            return (self.radiance(world, &self.ray_for_pixel(h, v)), 1);
+        }
+        let colors = self.samples_for_pixel(world, h, v);
+        let color = colors
+            .iter()
+            .fold(Color::black(), |total, color| total.add(color))
+            .divide(colors.len() as f64);
+        (color, colors.len())
+    }
+
+    // Gives a seeded camera the same random numbers for a pixel on every
+    // run, whichever thread renders it. Each pass over the image gets a
+    // stream of its own.
+    fn reseed_pixel(&self, pass: usize, h: usize, v: usize) {
+        if let Some(seed) = self.seed {
+            let pixels = self.hsize * self.vsize;
+            reseed(seed, (pass * pixels + v * self.hsize + h) as u64);
+        }
+    }
+
+    fn samples_for_pixel(&self, world: &World, h: usize, v: usize) -> Vec<Color> {
+        let batch = self.samples.max(1);
+        let mut colors = self.sample_pixel(world, h, v, batch);
+        if let Some(adaptive) = self.adaptive {
+            while colors.len() < adaptive.max_samples && variance(&colors) > adaptive.threshold {
+                let count = batch.min(adaptive.max_samples - colors.len());
+                colors.extend(self.sample_pixel(world, h, v, count));
+            }
+        }
+        colors
+    }
+
+    fn sample_pixel(&self, world: &World, h: usize, v: usize, count: usize) -> Vec<Color> {
+        let offsets = self.sampler.square(count);
+        // Shuffled so a pixel position isn't paired with the same part of
+        // the lens every time.
+        let mut lenses = self.sampler.square(count);
+        shuffle(&mut lenses);
+        offsets
+            .iter()
+            .zip(lenses.iter())
+            .map(|(&offset, &lens)| {
+                self.radiance(
+                    world,
+                    &self.ray_for_sample(h, v, offset, square_to_disk(lens)),
+                )
+            })
+            .collect()
+    }
+
+    // Exposure only brightens shaded renders, leaving diagnostic colors as
+    // they are.
+    fn scale(&self, world: &World) -> f64 {
+        match world.render_mode {
+            RenderMode::Shaded => 2_f64.powf(self.exposure),
+            _ => 1.,
+        }
     }
 
     pub fn render(&self, world: &World) -> Canvas {
+        self.render_with_progress(world, |_| ())
+    }
+
+    // Renders as `render` does, handing `callback` the progress so far each
+    // time another row's worth of pixels is done. Pixels finish on many
+    // threads at once, so it is called from any of them.
+    pub fn render_with_progress<F>(&self, world: &World, callback: F) -> Canvas
+    where
+        F: Fn(&Progress) + Sync,
+    {
         let mut canvas = Canvas::empty(self.hsize as i64, self.vsize as i64);
 
         let pixels: Vec<usize> = (0..canvas.pixels.len()).collect();
+        let scale = self.scale(world);
+        let start = now();
+        let done = AtomicUsize::new(0);
         let ps: Vec<Color> = pixels
             .par_iter()
-            .map(|i| world.color_at(&self.ray_for_pixel(i % self.hsize, i / self.hsize), 8))
+            .map(|i| {
+                let color = self
+                    .color_for_pixel(world, i % self.hsize, i / self.hsize)
+                    .multiply_scalar(scale);
+                let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+                if done.is_multiple_of(self.hsize) || done == pixels.len() {
+                    callback(&Progress {
+                        done,
+                        elapsed: start.map(|start| start.elapsed()).unwrap_or_default(),
+                        total: pixels.len(),
+                    });
+                }
+                color
+            })
             .collect();
         canvas.pixels = ps;
-        canvas
+        self.post_process(world, canvas)
+    }
+
+    // What every render does to the finished image: depth and heat renders
+    // are spread over the whole range, and shaded ones auto exposed if the
+    // camera asks for it.
+    fn post_process(&self, world: &World, canvas: Canvas) -> Canvas {
+        match world.render_mode {
+            RenderMode::Depth => canvas.normalized(),
+            RenderMode::Heat => {
+                let mut canvas = canvas.normalized();
+                for pixel in canvas.pixels.iter_mut() {
+                    *pixel = Color::heat(pixel.red);
+                }
+                canvas
+            }
+            RenderMode::Shaded if self.auto_exposure => {
+                let mut canvas = canvas;
+                let scale = 2_f64.powf(canvas.auto_exposure());
+                for pixel in canvas.pixels.iter_mut() {
+                    *pixel = pixel.multiply_scalar(scale);
+                }
+                canvas
+            }
+            _ => canvas,
+        }
+    }
+
+    // The image as bytes for an HTML canvas's ImageData: four per pixel,
+    // red, green, blue and an opaque alpha, row by row from the top.
+    pub fn render_to_rgba(&self, world: &World) -> Vec<u8> {
+        self.render(world).to_rgba()
+    }
+
+    // The image along with a diagnostic canvas showing, in false color, how
+    // many samples each pixel took out of the most it could have.
+    pub fn render_sample_counts(&self, world: &World) -> (Canvas, Canvas) {
+        let mut canvas = Canvas::empty(self.hsize as i64, self.vsize as i64);
+        let mut counts = Canvas::empty(self.hsize as i64, self.vsize as i64);
+        let scale = self.scale(world);
+        let most = match self.adaptive {
+            Some(adaptive) => adaptive.max_samples.max(self.samples),
+            None => self.samples,
+        }
+        .max(1);
+        let pixels: Vec<(Color, usize)> = (0..canvas.pixels.len())
+            .into_par_iter()
+            .map(|i| self.color_and_count_for_pixel(world, i % self.hsize, i / self.hsize))
+            .collect();
+        for (i, &(color, count)) in pixels.iter().enumerate() {
+            canvas.pixels[i] = color.multiply_scalar(scale);
+            counts.pixels[i] = Color::heat(count as f64 / most as f64);
+        }
+        (self.post_process(world, canvas), counts)
+    }
+
+    // The beauty image along with the auxiliary passes, which are taken
+    // from a single ray through each pixel centre.
+    pub fn render_passes(&self, world: &World) -> (Canvas, GBuffer) {
+        let canvas = self.render(world);
+        let mut gbuffer = GBuffer::new(self.hsize as i64, self.vsize as i64);
+        let scale = self.scale(world);
+        let pixels: Vec<usize> = (0..self.hsize * self.vsize).collect();
+        let samples: Vec<SurfaceSample> = pixels
+            .par_iter()
+            .map(|i| {
+                let (h, v) = (i % self.hsize, i / self.hsize);
+                self.reseed_pixel(0, h, v);
+                let mut sample = world.surface_sample(&self.ray_for_pixel(h, v), self.depth);
+                sample.direct = sample.direct.multiply_scalar(scale);
+                sample.indirect = sample.indirect.multiply_scalar(scale);
+                sample
+            })
+            .collect();
+        for (i, sample) in samples.iter().enumerate() {
+            gbuffer.write_sample(i % self.hsize, i / self.hsize, sample);
+        }
+        (canvas, gbuffer)
+    }
+
+    // Renders a band of rows at a time, handing the partly finished canvas
+    // to `callback` after each band so it can be shown as it fills in. The
+    // canvases handed over are post processed as they stand.
+    pub fn render_rows<F>(&self, world: &World, mut callback: F) -> Canvas
+    where
+        F: FnMut(&Canvas),
+    {
+        let mut canvas = Canvas::empty(self.hsize as i64, self.vsize as i64);
+        let scale = self.scale(world);
+        let band = rayon::current_num_threads().max(1);
+        for first in (0..self.vsize).step_by(band) {
+            let rows = first * self.hsize..(first + band).min(self.vsize) * self.hsize;
+            let colors: Vec<Color> = rows
+                .clone()
+                .into_par_iter()
+                .map(|i| {
+                    self.color_for_pixel(world, i % self.hsize, i / self.hsize)
+                        .multiply_scalar(scale)
+                })
+                .collect();
+            canvas.pixels[rows].copy_from_slice(&colors);
+            callback(&self.post_process(world, canvas.clone()));
+        }
+        self.post_process(world, canvas)
     }
+
+    // Renders square tiles `size` pixels across, left to right and top to
+    // bottom, handing each finished tile and its top left corner to
+    // `callback`, which can save it straight away. Tiles go out before the
+    // rest of the image is known, so only the canvas returned is post
+    // processed.
+    pub fn render_tiles<F>(&self, world: &World, size: usize, mut callback: F) -> Canvas
+    where
+        F: FnMut(&Canvas, usize, usize),
+    {
+        let mut canvas = Canvas::empty(self.hsize as i64, self.vsize as i64);
+        let scale = self.scale(world);
+        let size = size.max(1);
+        for y in (0..self.vsize).step_by(size) {
+            for x in (0..self.hsize).step_by(size) {
+                let width = size.min(self.hsize - x);
+                let height = size.min(self.vsize - y);
+                let mut tile = Canvas::empty(width as i64, height as i64);
+                tile.pixels = (0..width * height)
+                    .into_par_iter()
+                    .map(|i| {
+                        self.color_for_pixel(world, x + i % width, y + i / width)
+                            .multiply_scalar(scale)
+                    })
+                    .collect();
+                canvas.blit(&tile, x, y);
+                callback(&tile, x, y);
+            }
+        }
+        self.post_process(world, canvas)
+    }
+
+    // Renders a quick pass with one ray through each pixel centre, then
+    // keeps adding `samples` more rays per pixel, handing each improved
+    // canvas and its pass number to `callback` until it returns false.
+    pub fn render_progressive<F>(&self, world: &World, mut callback: F) -> Canvas
+    where
+        F: FnMut(&Canvas, usize) -> bool,
+    {
+        let pixels: Vec<usize> = (0..self.hsize * self.vsize).collect();
+        let scale = self.scale(world);
+        let batch = self.samples.max(1);
+        let mut totals: Vec<Color> = pixels
+            .par_iter()
+            .map(|i| {
+                let (h, v) = (i % self.hsize, i / self.hsize);
+                self.reseed_pixel(0, h, v);
+                self.radiance(world, &self.ray_for_pixel(h, v))
+            })
+            .collect();
+        let mut taken = 1;
+        let mut pass = 0;
+        loop {
+            let mut canvas = Canvas::empty(self.hsize as i64, self.vsize as i64);
+            canvas.pixels = totals
+                .iter()
+                .map(|total| total.divide(taken as f64).multiply_scalar(scale))
+                .collect();
+            let canvas = self.post_process(world, canvas);
+            if !callback(&canvas, pass) {
+                return canvas;
+            }
+            totals = pixels
+                .par_iter()
+                .map(|&i| {
+                    let (h, v) = (i % self.hsize, i / self.hsize);
+                    self.reseed_pixel(pass + 1, h, v);
+                    self.sample_pixel(world, h, v, batch)
+                        .iter()
+                        .fold(totals[i], |total, color| total.add(color))
+                })
+                .collect();
+            taken += batch;
+            pass += 1;
+        }
+    }
+}
+
+// The largest variance of any one channel.
+fn variance(colors: &[Color]) -> f64 {
+    let n = colors.len() as f64;
+    let mean = colors
+        .iter()
+        .fold(Color::black(), |total, color| total.add(color))
+        .divide(n);
+    let squares = colors.iter().fold(Color::black(), |total, color| {
+        let d = color.sub(&mean);
+        total.add(&d.hadamard_product(&d))
+    });
+    squares.red.max(squares.green).max(squares.blue) / n
+}
+
+// Browsers have no clock Instant can read, so renders there report no
+// time taken.
+#[cfg(not(target_arch = "wasm32"))]
+fn now() -> Option<Instant> {
+    Some(Instant::now())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn now() -> Option<Instant> {
+    None
 }
 
 #[cfg(test)]
 mod tests {
-    use camera::Camera;
+    use camera::{variance, Adaptive, Camera, Progress};
     use color::Color;
     use matrix::Matrix4;
     use matrix::IDENTITY_MATRIX;
     use point::point;
     use point::vector;
     use std::f64::consts::PI;
+    use std::sync::Mutex;
+    use std::time::Duration;
     use transformation_matrix::TransformationMatrix;
     use utilities::equal;
-    use world::World;
+    use world::{RenderMode, World};
 
     #[test]
     fn test_camera_new() {
@@ -92,7 +526,22 @@ mod tests {
         assert_eq!(camera.hsize, 160);
         assert_eq!(camera.vsize, 120);
         assert_eq!(camera.field_of_view, PI / 2.0);
-        assert_eq!(camera.transform, IDENTITY_MATRIX);
+        assert_eq!(camera.transform(), IDENTITY_MATRIX);
+    }
+
+    #[test]
+    fn test_camera_set_transform() {
+        let mut camera = Camera::new(160, 120, PI / 2.0);
+        let from = point(0.0, 0.0, -5.0);
+        let up = vector(0.0, 1.0, 0.0);
+
+        assert!(camera
+            .set_transform(TransformationMatrix::new(&from, &point(0.0, 0.0, 0.0), &up))
+            .is_ok());
+        assert!(camera
+            .set_transform(TransformationMatrix::new(&from, &from, &up))
+            .is_err());
+        assert_eq!(camera.ray_for_pixel(80, 60).origin, from);
     }
 
     #[test]
@@ -123,8 +572,11 @@ mod tests {
     #[test]
     fn test_camera_ray_for_pixel_3() {
         let mut camera = Camera::new(201, 101, PI / 2.0);
-        camera.transform =
-            Matrix4::rotation_y(PI / 4.0).multiply(&Matrix4::translation(0.0, -2.0, 5.0));
+        camera
+            .set_transform(
+                Matrix4::rotation_y(PI / 4.0).multiply(&Matrix4::translation(0.0, -2.0, 5.0)),
+            )
+            .unwrap();
         let r = camera.ray_for_pixel(100, 50);
 
         assert_eq!(r.origin, point(0.0, 2.0, -5.0));
@@ -134,6 +586,421 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_camera_ray_for_sample() {
+        let mut camera = Camera::new(201, 101, PI / 2.0);
+        camera.aperture = 0.5;
+        camera.focal_distance = 4.0;
+        let r = camera.ray_for_sample(100, 50, (0.5, 0.5), (1.0, 0.0));
+
+        assert_eq!(r.origin, point(0.5, 0.0, 0.0));
+        assert_eq!(r.direction, vector(-0.5, 0.0, -4.0).normalize());
+
+        let r = camera.ray_for_sample(0, 0, (0.5, 0.5), (0.0, -1.0));
+        let focus = point(3.98010, 1.99005, -4.0);
+
+        assert_eq!(r.origin, point(0.0, -0.5, 0.0));
+        assert_eq!(r.direction, focus.sub(&r.origin).normalize());
+
+        let r = camera.ray_for_sample(0, 0, (0.0, 0.0), (0.0, 0.0));
+        let corner = vector(1.0, 0.50249, -1.0);
+
+        assert_eq!(r.origin, point(0.0, 0.0, 0.0));
+        assert_eq!(r.direction, corner.normalize());
+    }
+
+    #[test]
+    fn test_camera_shift() {
+        let mut camera = Camera::new(201, 101, PI / 2.0);
+        camera.shift_y = 0.5;
+        let r = camera.ray_for_pixel(100, 50);
+
+        assert_eq!(r.direction, vector(0.0, 0.50249, -1.0).normalize());
+
+        camera.shift_y = 0.0;
+        camera.shift_x = 0.25;
+        let r = camera.ray_for_pixel(100, 50);
+
+        assert_eq!(r.direction, vector(-0.5, 0.0, -1.0).normalize());
+    }
+
+    #[test]
+    fn test_camera_tilted_focal_plane() {
+        let mut camera = Camera::new(201, 201, PI / 2.0);
+        camera.aperture = 0.5;
+        camera.focal_distance = 4.0;
+        camera.tilt_x = PI / 4.0;
+
+        // The centre stays in focus at the focal distance.
+        let r = camera.ray_for_sample(100, 100, (0.5, 0.5), (0.0, 1.0));
+        let t = -4.0 / r.direction.z;
+        assert_eq!(
+            r.origin.add(&r.direction.multiply_scalar(t)),
+            point(0.0, 0.0, -4.0)
+        );
+
+        // Tilted forward, the plane comes nearer towards the top of the frame.
+        let r = camera.ray_for_sample(100, 50, (0.5, 0.5), (0.0, 1.0));
+        let focus = point(0.0, 1.32890, -2.67110);
+        assert_eq!(r.direction, focus.sub(&r.origin).normalize());
+    }
+
+    #[test]
+    fn test_camera_lens_rays_meet_on_focal_plane() {
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        camera.aperture = 0.1;
+        camera.focal_distance = 4.0;
+        let sharp = camera.ray_for_pixel(3, 7);
+
+        for lens in &[(1.0, 0.0), (0.0, 1.0), (-0.6, -0.8)] {
+            let r = camera.ray_for_sample(3, 7, (0.5, 0.5), *lens);
+            let t = -4.0 / r.direction.z;
+
+            assert_eq!(
+                r.origin.add(&r.direction.multiply_scalar(t)),
+                sharp.direction.multiply_scalar(-4.0 / sharp.direction.z)
+            );
+        }
+    }
+
+    #[test]
+    fn test_camera_samples_average_pixel() {
+        let world = World::new();
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        camera
+            .set_transform(TransformationMatrix::new(
+                &point(0.0, 0.0, -5.0),
+                &point(0.0, 0.0, 0.0),
+                &point(0.0, 1.0, 0.0),
+            ))
+            .unwrap();
+        camera.samples = 16;
+
+        let color = camera.color_for_pixel(&world, 0, 0);
+        let center = camera.color_for_pixel(&world, 5, 5);
+
+        assert_eq!(color, Color::black());
+        // Spread over the pixel, the sphere's falloff darkens it slightly.
+        assert!(center.red > 0.3 && center.red < 0.38066);
+    }
+
+    #[test]
+    fn test_camera_adaptive_sampling() {
+        let world = World::new();
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        camera
+            .set_transform(TransformationMatrix::new(
+                &point(0.0, 0.0, -5.0),
+                &point(0.0, 0.0, 0.0),
+                &point(0.0, 1.0, 0.0),
+            ))
+            .unwrap();
+        camera.samples = 16;
+        camera.adaptive = Some(Adaptive {
+            max_samples: 64,
+            threshold: 0.000001,
+        });
+
+        // Empty background, then the sphere's edge.
+        assert_eq!(camera.samples_for_pixel(&world, 0, 0).len(), 16);
+        assert_eq!(camera.samples_for_pixel(&world, 4, 5).len(), 64);
+    }
+
+    #[test]
+    fn test_render_sample_counts() {
+        let world = World::new();
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        camera
+            .set_transform(TransformationMatrix::new(
+                &point(0.0, 0.0, -5.0),
+                &point(0.0, 0.0, 0.0),
+                &point(0.0, 1.0, 0.0),
+            ))
+            .unwrap();
+        camera.samples = 16;
+        camera.adaptive = Some(Adaptive {
+            max_samples: 64,
+            threshold: 0.000001,
+        });
+
+        let (_, counts) = camera.render_sample_counts(&world);
+
+        assert_eq!(counts.pixel_at(0, 0), Color::heat(0.25));
+        assert_eq!(counts.pixel_at(4, 5), Color::white());
+    }
+
+    #[test]
+    fn test_variance() {
+        let flat = vec![Color::white(); 4];
+        let mixed = vec![Color::white(), Color::black()];
+
+        assert_eq!(variance(&flat), 0.0);
+        assert_eq!(variance(&mixed), 0.25);
+    }
+
+    #[test]
+    fn test_camera_exposure() {
+        let world = World::new();
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        camera
+            .set_transform(TransformationMatrix::new(
+                &point(0.0, 0.0, -5.0),
+                &point(0.0, 0.0, 0.0),
+                &point(0.0, 1.0, 0.0),
+            ))
+            .unwrap();
+        camera.exposure = 1.0;
+
+        assert_eq!(
+            camera.render(&world).pixel_at(5, 5),
+            Color::new(0.76132, 0.95166, 0.571)
+        );
+
+        camera.expose(2.0, 1.0 / 4.0, 100.0);
+        assert!(equal(camera.exposure, -4.0));
+
+        camera.expose(1.0, 1.0, 800.0);
+        assert!(equal(camera.exposure, 3.0));
+    }
+
+    #[test]
+    fn test_camera_auto_exposure() {
+        let world = World::new();
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        camera
+            .set_transform(TransformationMatrix::new(
+                &point(0.0, 0.0, -5.0),
+                &point(0.0, 0.0, 0.0),
+                &point(0.0, 1.0, 0.0),
+            ))
+            .unwrap();
+        camera.auto_exposure = true;
+
+        let image = camera.render(&world);
+        camera.exposure = 3.0;
+
+        assert!(camera.render(&world).approx_eq(&image, 1e-9));
+        assert!(equal(image.auto_exposure(), 0.0));
+    }
+
+    #[test]
+    fn test_render_passes() {
+        let world = World::new();
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        camera
+            .set_transform(TransformationMatrix::new(
+                &point(0.0, 0.0, -5.0),
+                &point(0.0, 0.0, 0.0),
+                &point(0.0, 1.0, 0.0),
+            ))
+            .unwrap();
+
+        let (image, passes) = camera.render_passes(&world);
+
+        assert_eq!(image.pixel_at(5, 5), Color::new(0.38066, 0.47583, 0.2855));
+        assert_eq!(passes.depth.pixel_at(5, 5), Color::new(4.0, 4.0, 4.0));
+        assert_eq!(passes.normal.pixel_at(5, 5), Color::new(0.5, 0.5, 0.0));
+        assert_eq!(
+            passes
+                .direct
+                .pixel_at(5, 5)
+                .add(&passes.indirect.pixel_at(5, 5)),
+            image.pixel_at(5, 5)
+        );
+        assert_eq!(passes.object_id.pixel_at(0, 0), Color::black());
+    }
+
+    #[test]
+    fn test_render_with_progress() {
+        let world = World::new();
+        let mut camera = Camera::new(11, 5, PI / 2.0);
+        camera
+            .set_transform(TransformationMatrix::new(
+                &point(0.0, 0.0, -5.0),
+                &point(0.0, 0.0, 0.0),
+                &point(0.0, 1.0, 0.0),
+            ))
+            .unwrap();
+        let reports = Mutex::new(Vec::new());
+
+        let image = camera.render_with_progress(&world, |progress| {
+            reports.lock().unwrap().push(*progress);
+        });
+
+        let mut reports = reports.into_inner().unwrap();
+        reports.sort_by_key(|progress| progress.done);
+        assert_eq!(
+            reports
+                .iter()
+                .map(|progress| progress.done)
+                .collect::<Vec<_>>(),
+            vec![11, 22, 33, 44, 55]
+        );
+        assert_eq!(reports[4].fraction(), 1.);
+        assert_eq!(reports[4].remaining(), Some(Duration::from_secs(0)));
+        assert_eq!(image.pixels, camera.render(&world).pixels);
+    }
+
+    #[test]
+    fn test_seeded_render_repeats() {
+        let world = World::new();
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        camera
+            .set_transform(TransformationMatrix::new(
+                &point(0.0, 0.0, -5.0),
+                &point(0.0, 0.0, 0.0),
+                &point(0.0, 1.0, 0.0),
+            ))
+            .unwrap();
+        camera.aperture = 0.5;
+        camera.focal_distance = 3.0;
+        camera.samples = 4;
+        camera.seed = Some(1);
+
+        let first = camera.render(&world);
+        assert_eq!(first.pixels, camera.render(&world).pixels);
+        camera.seed = Some(2);
+        assert_ne!(first.pixels, camera.render(&world).pixels);
+    }
+
+    #[test]
+    fn test_seeded_progressive_render_repeats() {
+        let world = World::new();
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        camera
+            .set_transform(TransformationMatrix::new(
+                &point(0.0, 0.0, -5.0),
+                &point(0.0, 0.0, 0.0),
+                &point(0.0, 1.0, 0.0),
+            ))
+            .unwrap();
+        camera.aperture = 0.5;
+        camera.focal_distance = 3.0;
+        camera.samples = 4;
+        camera.seed = Some(1);
+        let render = || camera.render_progressive(&world, |_, pass| pass < 2).pixels;
+
+        assert_eq!(render(), render());
+    }
+
+    #[test]
+    fn test_progress_remaining() {
+        let progress = Progress {
+            done: 25,
+            elapsed: Duration::from_secs(10),
+            total: 100,
+        };
+
+        assert_eq!(progress.fraction(), 0.25);
+        assert_eq!(progress.remaining(), Some(Duration::from_secs(30)));
+        assert_eq!(
+            Progress {
+                done: 0,
+                ..progress
+            }
+            .remaining(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_render_rows() {
+        let world = World::new();
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        camera
+            .set_transform(TransformationMatrix::new(
+                &point(0.0, 0.0, -5.0),
+                &point(0.0, 0.0, 0.0),
+                &point(0.0, 1.0, 0.0),
+            ))
+            .unwrap();
+        let mut updates = 0;
+
+        let image = camera.render_rows(&world, |_| updates += 1);
+
+        assert!(updates >= 1);
+        assert_eq!(image.pixels, camera.render(&world).pixels);
+    }
+
+    #[test]
+    fn test_render_tiles() {
+        let world = World::new();
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        camera
+            .set_transform(TransformationMatrix::new(
+                &point(0.0, 0.0, -5.0),
+                &point(0.0, 0.0, 0.0),
+                &point(0.0, 1.0, 0.0),
+            ))
+            .unwrap();
+        let mut corners = Vec::new();
+
+        let image = camera.render_tiles(&world, 8, |tile, x, y| {
+            corners.push((x, y, tile.width, tile.height))
+        });
+
+        assert_eq!(
+            corners,
+            vec![(0, 0, 8, 8), (8, 0, 3, 8), (0, 8, 8, 3), (8, 8, 3, 3)]
+        );
+        assert_eq!(image.pixels, camera.render(&world).pixels);
+    }
+
+    #[test]
+    fn test_every_render_post_processes() {
+        let mut world = World::new();
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        camera
+            .set_transform(TransformationMatrix::new(
+                &point(0.0, 0.0, -5.0),
+                &point(0.0, 0.0, 0.0),
+                &point(0.0, 1.0, 0.0),
+            ))
+            .unwrap();
+        camera.auto_exposure = true;
+
+        for mode in [RenderMode::Shaded, RenderMode::Depth] {
+            world.render_mode = mode;
+            let expected = camera.render(&world).pixels;
+
+            assert_eq!(camera.render_rows(&world, |_| ()).pixels, expected);
+            assert_eq!(
+                camera.render_tiles(&world, 4, |_, _, _| ()).pixels,
+                expected
+            );
+            assert_eq!(
+                camera.render_progressive(&world, |_, _| false).pixels,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_render_progressive() {
+        let world = World::new();
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        camera
+            .set_transform(TransformationMatrix::new(
+                &point(0.0, 0.0, -5.0),
+                &point(0.0, 0.0, 0.0),
+                &point(0.0, 1.0, 0.0),
+            ))
+            .unwrap();
+        camera.samples = 4;
+        let mut passes = Vec::new();
+
+        let image = camera.render_progressive(&world, |canvas, pass| {
+            passes.push((pass, canvas.pixel_at(5, 5)));
+            pass < 2
+        });
+
+        assert_eq!(passes.len(), 3);
+        assert_eq!(passes[0], (0, Color::new(0.38066, 0.47583, 0.2855)));
+        assert_eq!(passes[2].0, 2);
+        assert_eq!(image.pixel_at(5, 5), passes[2].1);
+        assert_eq!(image.pixel_at(0, 0), Color::black());
+    }
+
     #[test]
     fn test_world_with_camera() {
         let world = World::new();
@@ -142,7 +1009,9 @@ mod tests {
         let from = point(0.0, 0.0, -5.0);
         let to = point(0.0, 0.0, 0.0);
         let up = point(0.0, 1.0, 0.0);
-        camera.transform = TransformationMatrix::new(&from, &to, &up);
+        camera
+            .set_transform(TransformationMatrix::new(&from, &to, &up))
+            .unwrap();
 
         let image = camera.render(&world);
         assert_eq!(image.pixel_at(5, 5), Color::new(0.38066, 0.47583, 0.2855));
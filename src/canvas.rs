@@ -1,7 +1,109 @@
-use color::Color;
+use color::{Color, Encoding};
+use error::Error;
+use png;
+use std::f64::consts::PI;
+use std::io;
+use std::io::Write;
 use std::vec::Vec;
 
+const MAX_PPM_LINE: usize = 70;
+
+// A 3x5 pixel font for labelling debug renders, one row per entry with the
+// leftmost pixel in the highest bit.
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b011, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+const MINUS: [u8; 5] = [0b000, 0b000, 0b111, 0b000, 0b000];
+const POINT: [u8; 5] = [0b000, 0b000, 0b000, 0b000, 0b010];
+
+// Auto exposure aims the image's average luminance at middle grey.
+const MIDDLE_GREY: f64 = 0.18;
+
+// Reconstruction filters for `Canvas::resize`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    Bilinear,
+    Box,
+    Lanczos,
+}
+
+impl Filter {
+    fn radius(&self) -> f64 {
+        match *self {
+            Filter::Bilinear => 1.,
+            Filter::Box => 0.5,
+            Filter::Lanczos => 3.,
+        }
+    }
+
+    fn weight(&self, x: f64) -> f64 {
+        match *self {
+            Filter::Bilinear => (1. - x.abs()).max(0.),
+            Filter::Box => {
+                if (-0.5..0.5).contains(&x) {
+                    1.
+                } else {
+                    0.
+                }
+            }
+            Filter::Lanczos => {
+                if x == 0. {
+                    1.
+                } else if x.abs() < 3. {
+                    let pi_x = PI * x;
+                    3. * pi_x.sin() * (pi_x / 3.).sin() / (pi_x * pi_x)
+                } else {
+                    0.
+                }
+            }
+        }
+    }
+
+    // For each destination index, the source indices and normalized weights
+    // that contribute to it. Downscaling stretches the filter over the
+    // source pixels it covers.
+    fn contributions(&self, from: usize, to: usize) -> Vec<Vec<(usize, f64)>> {
+        let scale = from as f64 / to as f64;
+        let stretch = scale.max(1.);
+        let radius = self.radius() * stretch;
+        (0..to)
+            .map(|i| {
+                let center = (i as f64 + 0.5) * scale - 0.5;
+                let first = (center - radius).floor() as i64;
+                let last = (center + radius).ceil() as i64;
+                let mut weights: Vec<(usize, f64)> = (first..=last)
+                    .map(|j| {
+                        let source = j.clamp(0, from as i64 - 1) as usize;
+                        (source, self.weight((j as f64 - center) / stretch))
+                    })
+                    .filter(|&(_, weight)| weight != 0.)
+                    .collect();
+                let total: f64 = weights.iter().map(|&(_, weight)| weight).sum();
+                for weight in weights.iter_mut() {
+                    weight.1 /= total;
+                }
+                weights
+            })
+            .collect()
+    }
+}
+
+// Pixels hold linear colors; `encoding` is applied as they are written out,
+// and then they are quantized to `bit_depth` bits per channel. PPM takes
+// any depth from 1 to 16; PNG is written at 16 bits above 8.
+#[derive(Clone)]
 pub struct Canvas {
+    pub bit_depth: u8,
+    pub encoding: Encoding,
     pub height: i64,
     pub width: i64,
     pub pixels: Vec<Color>,
@@ -17,11 +119,13 @@ impl Canvas {
                 red: 0.0,
             });
         }
-        return Canvas {
+        Canvas {
+            bit_depth: 8,
+            encoding: Encoding::Linear,
             width,
             height,
             pixels,
-        };
+        }
     }
 
     pub fn write_pixel(&mut self, column: usize, row: usize, color: &Color) {
@@ -36,7 +140,289 @@ impl Canvas {
         self.pixels[index]
     }
 
-    fn write_all_pixels(&mut self, color: &Color) {
+    // Like pixel_at, but off the canvas is an error rather than a panic or
+    // a pixel from the next row.
+    pub fn try_pixel_at(&self, column: usize, row: usize) -> Result<Color, Error> {
+        if self.contains(column, row) {
+            Ok(self.pixel_at(column, row))
+        } else {
+            Err(Error::PixelOutOfBounds { column, row })
+        }
+    }
+
+    pub fn try_write_pixel(
+        &mut self,
+        column: usize,
+        row: usize,
+        color: &Color,
+    ) -> Result<(), Error> {
+        if self.contains(column, row) {
+            self.write_pixel(column, row, color);
+            Ok(())
+        } else {
+            Err(Error::PixelOutOfBounds { column, row })
+        }
+    }
+
+    // Every pixel with its column and row, row by row from the top.
+    pub fn iter_pixels(&self) -> impl Iterator<Item = (usize, usize, &Color)> {
+        let width = self.width.max(1) as usize;
+        self.pixels
+            .iter()
+            .enumerate()
+            .map(move |(index, color)| (index % width, index / width, color))
+    }
+
+    // Each row of pixels with its index, from the top.
+    pub fn enumerate_rows(&self) -> impl Iterator<Item = (usize, &[Color])> {
+        self.rows().enumerate()
+    }
+
+    pub fn as_slice(&self) -> &[Color] {
+        &self.pixels
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [Color] {
+        &mut self.pixels
+    }
+
+    fn contains(&self, column: usize, row: usize) -> bool {
+        column < self.width as usize && row < self.height as usize
+    }
+
+    // Counts pixels by log2 luminance, in `bins` equal steps between the
+    // darkest and brightest lit pixels. Black pixels are counted apart,
+    // having no log.
+    pub fn histogram(&self, bins: usize) -> Histogram {
+        let logs: Vec<f64> = self
+            .pixels
+            .iter()
+            .map(|pixel| pixel.luminance())
+            .filter(|&luminance| luminance > 0.)
+            .map(f64::log2)
+            .collect();
+        let min_log = logs.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max_log = logs.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let (min_log, max_log) = if logs.is_empty() {
+            (0., 0.)
+        } else {
+            (min_log, max_log)
+        };
+        let mut histogram = Histogram {
+            bins: vec![0; bins.max(1)],
+            black: self.pixels.len() - logs.len(),
+            max_log,
+            min_log,
+        };
+        for log in logs {
+            let bin = histogram.bin(log);
+            histogram.bins[bin] += 1;
+        }
+        histogram
+    }
+
+    // Stops of exposure that bring the average luminance to middle grey,
+    // ignoring the darkest and brightest tenth of the pixels so a few
+    // highlights or shadows do not swing it.
+    pub fn auto_exposure(&self) -> f64 {
+        let histogram = self.histogram(64);
+        match histogram.mean_log(0.1, 0.9) {
+            Some(mean) => MIDDLE_GREY.log2() - mean,
+            None => 0.,
+        }
+    }
+
+    // Scaled so the brightest channel of any pixel is one, unless the
+    // canvas is all black.
+    pub fn normalized(&self) -> Canvas {
+        let brightest = self.pixels.iter().fold(0_f64, |brightest, pixel| {
+            brightest.max(pixel.red).max(pixel.green).max(pixel.blue)
+        });
+        let mut canvas = Canvas::empty(self.width, self.height);
+        canvas.pixels = self.pixels.clone();
+        if brightest > 0. {
+            for pixel in canvas.pixels.iter_mut() {
+                *pixel = pixel.divide(brightest);
+            }
+        }
+        canvas
+    }
+
+    // Resamples to a new size, one axis at a time. Rendering large and
+    // downscaling is a cheap form of antialiasing.
+    pub fn resize(&self, width: i64, height: i64, filter: Filter) -> Canvas {
+        let mut canvas = Canvas::empty(width, height);
+        canvas.encoding = self.encoding;
+        if self.pixels.is_empty() || canvas.pixels.is_empty() {
+            return canvas;
+        }
+        let (from_width, from_height) = (self.width as usize, self.height as usize);
+        let (to_width, to_height) = (width as usize, height as usize);
+
+        let columns = filter.contributions(from_width, to_width);
+        let mut horizontal = Vec::with_capacity(to_width * from_height);
+        for row in 0..from_height {
+            for weights in &columns {
+                horizontal.push(weights.iter().fold(Color::black(), |sum, &(x, weight)| {
+                    sum.add(&self.pixels[row * from_width + x].multiply_scalar(weight))
+                }));
+            }
+        }
+
+        let rows = filter.contributions(from_height, to_height);
+        for (row, weights) in rows.iter().enumerate() {
+            for column in 0..to_width {
+                canvas.pixels[row * to_width + column] =
+                    weights.iter().fold(Color::black(), |sum, &(y, weight)| {
+                        sum.add(&horizontal[y * to_width + column].multiply_scalar(weight))
+                    });
+            }
+        }
+        canvas
+    }
+
+    // Compares two canvases of the same size pixel by pixel, for checking a
+    // render against a golden image.
+    pub fn diff(&self, other: &Canvas) -> Result<DiffReport, Error> {
+        if (self.width, self.height) != (other.width, other.height) {
+            return Err(Error::SizeMismatch {
+                left: (self.width, self.height),
+                right: (other.width, other.height),
+            });
+        }
+        let mut squared = Color::black();
+        let mut errors = Vec::with_capacity(self.pixels.len());
+        for (a, b) in self.pixels.iter().zip(other.pixels.iter()) {
+            let difference = a.sub(b);
+            squared = squared.add(&difference.hadamard_product(&difference));
+            errors.push(
+                difference
+                    .red
+                    .abs()
+                    .max(difference.green.abs())
+                    .max(difference.blue.abs()),
+            );
+        }
+        let count = self.pixels.len().max(1) as f64;
+        let mean = squared.divide(count);
+        let max_error = errors.iter().fold(0_f64, |max, error| max.max(*error));
+        Ok(DiffReport {
+            errors,
+            height: self.height,
+            max_error,
+            rmse: Color::new(mean.red.sqrt(), mean.green.sqrt(), mean.blue.sqrt()),
+            width: self.width,
+        })
+    }
+
+    // True when no channel of any pixel differs by more than `tolerance`.
+    pub fn approx_eq(&self, other: &Canvas, tolerance: f64) -> bool {
+        self.diff(other)
+            .is_ok_and(|report| report.within(tolerance))
+    }
+
+    // A copy of the region whose top left corner is at (x, y). Any part of
+    // the region beyond this canvas comes out black.
+    pub fn crop(&self, x: usize, y: usize, width: i64, height: i64) -> Canvas {
+        let mut canvas = Canvas::empty(width, height);
+        canvas.encoding = self.encoding;
+        for row in 0..height as usize {
+            for column in 0..width as usize {
+                if x + column < self.width as usize && y + row < self.height as usize {
+                    canvas.write_pixel(column, row, &self.pixel_at(x + column, y + row));
+                }
+            }
+        }
+        canvas
+    }
+
+    // Copies `source` in with its top left corner at (x, y), dropping
+    // whatever falls outside this canvas.
+    pub fn blit(&mut self, source: &Canvas, x: usize, y: usize) {
+        let width = (source.width as usize).min((self.width as usize).saturating_sub(x));
+        let height = (source.height as usize).min((self.height as usize).saturating_sub(y));
+        for row in 0..height {
+            let from = row * source.width as usize;
+            let to = (y + row) * self.width as usize + x;
+            self.pixels[to..to + width].copy_from_slice(&source.pixels[from..from + width]);
+        }
+    }
+
+    // Like write_pixel, but quietly skips anything off the canvas, so
+    // overlays can be drawn without clipping them first.
+    pub fn plot(&mut self, x: i64, y: i64, color: &Color) {
+        if x >= 0 && y >= 0 && x < self.width && y < self.height {
+            self.write_pixel(x as usize, y as usize, color);
+        }
+    }
+
+    pub fn draw_line(&mut self, from: (i64, i64), to: (i64, i64), color: &Color) {
+        let (mut x, mut y) = from;
+        let dx = (to.0 - x).abs();
+        let dy = -(to.1 - y).abs();
+        let step_x = if x < to.0 { 1 } else { -1 };
+        let step_y = if y < to.1 { 1 } else { -1 };
+        let mut error = dx + dy;
+        loop {
+            self.plot(x, y, color);
+            if (x, y) == to {
+                break;
+            }
+            let doubled = 2 * error;
+            if doubled >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if doubled <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    // The outline of a rectangle with its top left corner at (x, y).
+    pub fn draw_rect(&mut self, x: i64, y: i64, width: i64, height: i64, color: &Color) {
+        if width <= 0 || height <= 0 {
+            return;
+        }
+        let (right, bottom) = (x + width - 1, y + height - 1);
+        self.draw_line((x, y), (right, y), color);
+        self.draw_line((x, bottom), (right, bottom), color);
+        self.draw_line((x, y), (x, bottom), color);
+        self.draw_line((right, y), (right, bottom), color);
+    }
+
+    // Writes digits, minus signs and decimal points at (x, y), each glyph
+    // 3x5 pixels grown by `scale`. Other characters leave a gap.
+    pub fn draw_text(&mut self, x: i64, y: i64, text: &str, scale: i64, color: &Color) {
+        for (i, character) in text.chars().enumerate() {
+            let glyph = match character {
+                '0'..='9' => DIGITS[character as usize - '0' as usize],
+                '-' => MINUS,
+                '.' => POINT,
+                _ => continue,
+            };
+            let left = x + i as i64 * 4 * scale;
+            for (row, bits) in glyph.iter().enumerate() {
+                for column in 0..3 {
+                    if bits & (0b100 >> column) == 0 {
+                        continue;
+                    }
+                    for dy in 0..scale {
+                        for dx in 0..scale {
+                            self.plot(
+                                left + column * scale + dx,
+                                y + row as i64 * scale + dy,
+                                color,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn write_all_pixels(&mut self, color: &Color) {
         let mut pixels: Vec<Color> = Vec::with_capacity((self.width * self.height) as usize);
         for _i in 0..(self.width * self.height) {
             pixels.push(*color);
@@ -45,54 +431,221 @@ impl Canvas {
     }
 
     pub fn render_ppm(&self) -> String {
-        return format!(
-            "P3
-{} {}
-255
-{}",
-            self.width,
-            self.height,
-            self.pixels_to_ppm()
-        );
+        let mut bytes = Vec::new();
+        self.write_ppm(&mut bytes)
+            .expect("writing to a Vec cannot fail");
+        String::from_utf8(bytes).expect("PPM output is ASCII")
+    }
+
+    // Binary (P6) PPM, a fraction of the size of render_ppm's and much
+    // quicker to build for large images.
+    pub fn render_ppm_binary(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.pixels.len() * 3 + 20);
+        self.write_ppm_binary(&mut bytes)
+            .expect("writing to a Vec cannot fail");
+        bytes
     }
 
-    fn pixels_to_ppm(&self) -> String {
-        let mut rows: Vec<String> = Vec::new();
-        for i in 0..self.height {
-            let mut pixels: Vec<String> = Vec::new();
-            for j in 0..self.width {
-                pixels.push(self.pixels[(i * self.width + j) as usize].ppm());
+    // Streams a plain (P3) PPM a row at a time, wrapping lines before they
+    // pass 70 characters.
+    pub fn write_ppm<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.write_ppm_header(writer, "P3")?;
+        let mut line = String::with_capacity(MAX_PPM_LINE);
+        for row in self.rows() {
+            line.clear();
+            for pixel in row {
+                for value in pixel.encode(self.encoding).quantize(self.bit_depth).iter() {
+                    let value = value.to_string();
+                    if !line.is_empty() && line.len() + 1 + value.len() > MAX_PPM_LINE {
+                        writer.write_all(line.as_bytes())?;
+                        writer.write_all(b"\n")?;
+                        line.clear();
+                    }
+                    if !line.is_empty() {
+                        line.push(' ');
+                    }
+                    line.push_str(&value);
+                }
             }
-            rows.push(Canvas::chunks(pixels.join(" "), 70).join("\n"));
+            writer.write_all(line.as_bytes())?;
+            writer.write_all(b"\n")?;
         }
+        Ok(())
+    }
+
+    pub fn write_ppm_binary<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.write_ppm_header(writer, "P6")?;
+        let mut bytes = Vec::with_capacity(self.width as usize * 6);
+        for row in self.rows() {
+            bytes.clear();
+            for pixel in row {
+                for value in pixel.encode(self.encoding).quantize(self.bit_depth).iter() {
+                    // Samples above a byte are stored most significant first.
+                    if self.bit_depth > 8 {
+                        bytes.extend_from_slice(&value.to_be_bytes());
+                    } else {
+                        bytes.push(*value as u8);
+                    }
+                }
+            }
+            writer.write_all(&bytes)?;
+        }
+        Ok(())
+    }
+
+    fn write_ppm_header<W: Write>(&self, writer: &mut W, magic: &str) -> io::Result<()> {
+        self.check_bit_depth()?;
+        let max = (1_u32 << self.bit_depth) - 1;
+        write!(
+            writer,
+            "{}\n{} {}\n{}\n",
+            magic, self.width, self.height, max
+        )
+    }
+
+    fn check_bit_depth(&self) -> io::Result<()> {
+        if (1..=16).contains(&self.bit_depth) {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("bit depth must be from 1 to 16, not {}", self.bit_depth),
+            ))
+        }
+    }
 
-        let mut string = rows.join("\n");
-        string.push_str("\n");
-        return string;
+    // Encoded 8 bit red, green, blue and alpha for each pixel, row by row,
+    // the layout of a browser's ImageData.
+    pub fn to_rgba(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.pixels.len() * 4);
+        for pixel in &self.pixels {
+            bytes.extend_from_slice(&pixel.encode(self.encoding).bytes());
+            bytes.push(255);
+        }
+        bytes
     }
 
-    fn chunks(string: String, size: usize) -> Vec<String> {
-        let mut strings: Vec<String> = Vec::new();
-        if string.len() > size {
-            let mut i = size - 1;
-            let string_chars: Vec<char> = string.chars().collect();
-            while string_chars[i] != ' ' {
-                i = i - 1;
+    // Streams the image to the encoder a row at a time, like the PPM
+    // writers, so only one quantized row is held beside the canvas.
+    pub fn write_png<W: Write>(&self, writer: W) -> Result<(), png::EncodingError> {
+        self.check_bit_depth()?;
+        let wide = self.bit_depth > 8;
+        let mut encoder = png::Encoder::new(writer, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(if wide {
+            png::BitDepth::Sixteen
+        } else {
+            png::BitDepth::Eight
+        });
+        let mut writer = encoder.write_header()?;
+        let mut stream = writer.stream_writer()?;
+        let mut bytes = Vec::with_capacity(self.width as usize * if wide { 6 } else { 3 });
+        for row in self.rows() {
+            bytes.clear();
+            for pixel in row {
+                let pixel = pixel.encode(self.encoding);
+                if wide {
+                    // Samples above a byte are stored most significant first.
+                    for value in pixel.quantize(16).iter() {
+                        bytes.extend_from_slice(&value.to_be_bytes());
+                    }
+                } else {
+                    bytes.extend_from_slice(&pixel.bytes());
+                }
             }
-            let (beginning, end) = string.split_at(i);
-            strings.extend(Canvas::chunks(String::from(beginning), size));
-            strings.push(String::from(end.trim_matches(' ')));
+            stream.write_all(&bytes)?;
+        }
+        stream.finish()?;
+        writer.finish()
+    }
+
+    fn rows(&self) -> ::std::slice::Chunks<'_, Color> {
+        self.pixels.chunks(self.width.max(1) as usize)
+    }
+}
+
+pub struct Histogram {
+    pub bins: Vec<usize>,
+    pub black: usize,
+    pub max_log: f64,
+    pub min_log: f64,
+}
+
+impl Histogram {
+    // The log2 luminance at the middle of a bin.
+    pub fn bin_log(&self, bin: usize) -> f64 {
+        let width = (self.max_log - self.min_log) / self.bins.len() as f64;
+        self.min_log + width * (bin as f64 + 0.5)
+    }
+
+    fn bin(&self, log: f64) -> usize {
+        if self.max_log <= self.min_log {
+            return 0;
+        }
+        let t = (log - self.min_log) / (self.max_log - self.min_log);
+        ((t * self.bins.len() as f64) as usize).min(self.bins.len() - 1)
+    }
+
+    // The average log2 luminance of the lit pixels ranked between the `low`
+    // and `high` fractions, or None if there are none.
+    pub fn mean_log(&self, low: f64, high: f64) -> Option<f64> {
+        let count: usize = self.bins.iter().sum();
+        let (low, high) = (low * count as f64, high * count as f64);
+        let mut seen = 0.;
+        let mut total = 0.;
+        let mut weight = 0.;
+        for (bin, &pixels) in self.bins.iter().enumerate() {
+            let start = seen;
+            seen += pixels as f64;
+            let taken = seen.min(high) - start.max(low);
+            if taken > 0. {
+                total += taken * self.bin_log(bin);
+                weight += taken;
+            }
+        }
+        if weight > 0. {
+            Some(total / weight)
         } else {
-            strings.push(String::from(string.trim_matches(' ')));
+            None
+        }
+    }
+}
+
+// The result of `Canvas::diff`. `errors` holds the largest channel
+// difference at each pixel.
+pub struct DiffReport {
+    pub errors: Vec<f64>,
+    pub height: i64,
+    pub max_error: f64,
+    pub rmse: Color,
+    pub width: i64,
+}
+
+impl DiffReport {
+    pub fn within(&self, tolerance: f64) -> bool {
+        self.max_error <= tolerance
+    }
+
+    // Per-pixel error in false color, scaled so the worst pixel is white.
+    pub fn heatmap(&self) -> Canvas {
+        let mut canvas = Canvas::empty(self.width, self.height);
+        if self.max_error > 0. {
+            canvas.pixels = self
+                .errors
+                .iter()
+                .map(|error| Color::heat(error / self.max_error))
+                .collect();
         }
-        return strings;
+        canvas
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use canvas::Canvas;
-    use color::Color;
+    use canvas::{Canvas, Filter};
+    use color::{Color, Encoding};
+    use error::Error;
+    use utilities::equal;
 
     #[test]
     fn test_canvas() {
@@ -117,6 +670,241 @@ mod tests {
         assert_eq!(canvas.pixels[32], red);
     }
 
+    #[test]
+    fn test_pixel_iterators() {
+        let mut canvas = Canvas::empty(3, 2);
+        canvas.write_pixel(2, 1, &Color::white());
+
+        let lit: Vec<(usize, usize)> = canvas
+            .iter_pixels()
+            .filter(|&(_, _, color)| *color == Color::white())
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        assert_eq!(lit, vec![(2, 1)]);
+        assert_eq!(canvas.iter_pixels().count(), 6);
+
+        let rows: Vec<(usize, usize)> = canvas
+            .enumerate_rows()
+            .map(|(y, row)| (y, row.len()))
+            .collect();
+        assert_eq!(rows, vec![(0, 3), (1, 3)]);
+
+        for pixel in canvas.as_mut_slice() {
+            *pixel = pixel.add(&Color::new(0.5, 0., 0.));
+        }
+        assert_eq!(canvas.as_slice()[0], Color::new(0.5, 0., 0.));
+        assert_eq!(canvas.pixel_at(2, 1), Color::new(1.5, 1., 1.));
+    }
+
+    #[test]
+    fn test_pixels_off_the_canvas() {
+        let mut canvas = Canvas::empty(10, 20);
+
+        assert!(canvas.try_write_pixel(9, 19, &Color::white()).is_ok());
+        assert_eq!(canvas.try_pixel_at(9, 19).unwrap(), Color::white());
+        match canvas.try_pixel_at(10, 0) {
+            Err(Error::PixelOutOfBounds { column, row }) => assert_eq!((column, row), (10, 0)),
+            other => panic!("expected an error, got {:?}", other),
+        }
+        assert!(canvas.try_write_pixel(0, 20, &Color::white()).is_err());
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut a = Canvas::empty(2, 2);
+        let mut b = Canvas::empty(2, 2);
+        a.write_pixel(0, 0, &Color::new(0.5, 0.2, 0.0));
+        b.write_pixel(0, 0, &Color::new(0.5, 0.0, 0.0));
+        b.write_pixel(1, 1, &Color::new(0.0, 0.0, 0.1));
+
+        let report = a.diff(&b).unwrap();
+
+        assert_eq!(report.rmse, Color::new(0.0, 0.1, 0.05));
+        assert!((report.max_error - 0.2).abs() < 1e-12);
+        assert!(report.within(0.2));
+        assert!(!report.within(0.1));
+        assert!(a.approx_eq(&b, 0.25));
+        assert!(!a.approx_eq(&Canvas::empty(2, 1), 1.0));
+
+        let heatmap = report.heatmap();
+        assert_eq!(heatmap.pixel_at(0, 0), Color::white());
+        assert_eq!(heatmap.pixel_at(1, 1), Color::new(1.0, 0.5, 0.0));
+        assert_eq!(heatmap.pixel_at(1, 0), Color::black());
+        assert!(a.diff(&a).unwrap().within(0.0));
+        match a.diff(&Canvas::empty(2, 1)) {
+            Err(Error::SizeMismatch { left, right }) => {
+                assert_eq!((left, right), ((2, 2), (2, 1)))
+            }
+            _ => panic!("expected a size mismatch"),
+        }
+    }
+
+    #[test]
+    fn test_resize_box_downscale() {
+        let mut canvas = Canvas::empty(4, 2);
+        canvas.write_pixel(0, 0, &Color::new(1.0, 0.0, 0.0));
+        canvas.write_pixel(1, 1, &Color::new(0.0, 1.0, 0.0));
+        canvas.write_pixel(2, 0, &Color::new(0.4, 0.4, 0.4));
+
+        let small = canvas.resize(2, 1, Filter::Box);
+
+        assert_eq!(small.width, 2);
+        assert_eq!(small.height, 1);
+        assert_eq!(small.pixel_at(0, 0), Color::new(0.25, 0.25, 0.0));
+        assert_eq!(small.pixel_at(1, 0), Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn test_resize_bilinear_upscale() {
+        let mut canvas = Canvas::empty(2, 1);
+        canvas.write_pixel(1, 0, &Color::new(1.0, 1.0, 1.0));
+
+        let large = canvas.resize(4, 1, Filter::Bilinear);
+
+        assert_eq!(large.pixel_at(0, 0), Color::black());
+        assert_eq!(large.pixel_at(1, 0), Color::new(0.25, 0.25, 0.25));
+        assert_eq!(large.pixel_at(2, 0), Color::new(0.75, 0.75, 0.75));
+        assert_eq!(large.pixel_at(3, 0), Color::white());
+    }
+
+    #[test]
+    fn test_resize_lanczos_keeps_flat_color() {
+        let mut canvas = Canvas::empty(7, 5);
+        canvas.write_all_pixels(&Color::new(0.2, 0.5, 0.8));
+
+        for &(width, height) in [(3, 2), (14, 10)].iter() {
+            let resized = canvas.resize(width, height, Filter::Lanczos);
+            assert!(resized
+                .pixels
+                .iter()
+                .all(|pixel| *pixel == Color::new(0.2, 0.5, 0.8)));
+        }
+    }
+
+    #[test]
+    fn test_histogram() {
+        let mut canvas = Canvas::empty(4, 1);
+        canvas.write_pixel(0, 0, &Color::new(0.25, 0.25, 0.25));
+        canvas.write_pixel(1, 0, &Color::new(0.5, 0.5, 0.5));
+        canvas.write_pixel(2, 0, &Color::new(2.0, 2.0, 2.0));
+
+        let histogram = canvas.histogram(3);
+
+        assert_eq!(histogram.bins, vec![1, 1, 1]);
+        assert_eq!(histogram.black, 1);
+        assert!(equal(histogram.min_log, -2.0));
+        assert!(equal(histogram.max_log, 1.0));
+        assert!(equal(histogram.bin_log(1), -0.5));
+    }
+
+    #[test]
+    fn test_auto_exposure() {
+        let mut canvas = Canvas::empty(10, 10);
+        canvas.write_all_pixels(&Color::new(0.045, 0.045, 0.045));
+        // A single hot pixel should not darken the rest. The answer is only as
+        // fine as the histogram bins.
+        canvas.write_pixel(0, 0, &Color::new(100.0, 100.0, 100.0));
+
+        assert!((canvas.auto_exposure() - 2.0).abs() < 0.1);
+        assert!(equal(Canvas::empty(2, 2).auto_exposure(), 0.0));
+    }
+
+    #[test]
+    fn test_crop() {
+        let mut canvas = Canvas::empty(4, 3);
+        canvas.write_pixel(1, 1, &Color::new(1.0, 0.0, 0.0));
+        canvas.write_pixel(3, 2, &Color::new(0.0, 1.0, 0.0));
+
+        let cropped = canvas.crop(1, 1, 3, 3);
+
+        assert_eq!(cropped.width, 3);
+        assert_eq!(cropped.height, 3);
+        assert_eq!(cropped.pixel_at(0, 0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(cropped.pixel_at(2, 1), Color::new(0.0, 1.0, 0.0));
+        assert_eq!(cropped.pixel_at(2, 2), Color::black());
+    }
+
+    #[test]
+    fn test_blit() {
+        let mut canvas = Canvas::empty(4, 3);
+        let mut tile = Canvas::empty(2, 2);
+        tile.write_all_pixels(&Color::white());
+
+        canvas.blit(&tile, 1, 0);
+        canvas.blit(&tile, 3, 2);
+
+        assert_eq!(canvas.pixel_at(0, 0), Color::black());
+        assert_eq!(canvas.pixel_at(1, 0), Color::white());
+        assert_eq!(canvas.pixel_at(2, 1), Color::white());
+        assert_eq!(canvas.pixel_at(1, 2), Color::black());
+        assert_eq!(canvas.pixel_at(3, 2), Color::white());
+        assert!(canvas.crop(1, 0, 2, 2).approx_eq(&tile, 0.0));
+    }
+
+    #[test]
+    fn test_draw_line() {
+        let mut canvas = Canvas::empty(5, 5);
+
+        canvas.draw_line((0, 0), (4, 2), &Color::white());
+        canvas.draw_line((-3, 4), (2, 4), &Color::white());
+
+        let lit: Vec<(usize, usize)> = (0..25)
+            .filter(|i| canvas.pixels[*i] == Color::white())
+            .map(|i| (i % 5, i / 5))
+            .collect();
+        assert_eq!(
+            lit,
+            vec![
+                (0, 0),
+                (1, 1),
+                (2, 1),
+                (3, 2),
+                (4, 2),
+                (0, 4),
+                (1, 4),
+                (2, 4)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_draw_rect() {
+        let mut canvas = Canvas::empty(4, 4);
+
+        canvas.draw_rect(1, 1, 3, 3, &Color::white());
+
+        assert_eq!(canvas.pixel_at(1, 1), Color::white());
+        assert_eq!(canvas.pixel_at(3, 3), Color::white());
+        assert_eq!(canvas.pixel_at(3, 2), Color::white());
+        assert_eq!(canvas.pixel_at(2, 2), Color::black());
+        assert_eq!(canvas.pixel_at(0, 0), Color::black());
+    }
+
+    #[test]
+    fn test_draw_text() {
+        let mut canvas = Canvas::empty(8, 5);
+
+        canvas.draw_text(0, 0, "17", 1, &Color::white());
+
+        let rows: Vec<String> = (0..5)
+            .map(|row| {
+                (0..8)
+                    .map(|column| {
+                        if canvas.pixel_at(column, row) == Color::white() {
+                            '#'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![".#..###.", "##....#.", ".#...#..", ".#...#..", "###..#.."]
+        );
+    }
+
     #[test]
     fn test_render_to_ppm() {
         let mut canvas = Canvas::empty(5, 3);
@@ -140,6 +928,105 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_render_to_binary_ppm() {
+        let mut canvas = Canvas::empty(2, 1);
+        canvas.write_pixel(0, 0, &Color::new(1.5, 0.0, 0.0));
+        canvas.write_pixel(1, 0, &Color::new(0.0, 0.5, -0.5));
+
+        let mut expected = b"P6\n2 1\n255\n".to_vec();
+        expected.extend_from_slice(&[255, 0, 0, 0, 128, 0]);
+
+        assert_eq!(canvas.render_ppm_binary(), expected);
+    }
+
+    #[test]
+    fn test_render_sixteen_bit_ppm() {
+        let mut canvas = Canvas::empty(2, 1);
+        canvas.bit_depth = 16;
+        canvas.write_pixel(0, 0, &Color::new(1.0, 0.5, 0.0));
+        canvas.write_pixel(1, 0, &Color::new(0.001, 0.0, 2.0));
+
+        assert_eq!(
+            canvas.render_ppm(),
+            "P3\n2 1\n65535\n65535 32768 0 66 0 65535\n"
+        );
+
+        let mut expected = b"P6\n2 1\n65535\n".to_vec();
+        expected.extend_from_slice(&[255, 255, 128, 0, 0, 0, 0, 66, 0, 0, 255, 255]);
+        assert_eq!(canvas.render_ppm_binary(), expected);
+    }
+
+    #[test]
+    fn test_writers_refuse_bit_depths_out_of_range() {
+        let mut canvas = Canvas::empty(1, 1);
+        for depth in &[0, 17] {
+            canvas.bit_depth = *depth;
+
+            assert!(canvas.write_ppm(&mut Vec::new()).is_err());
+            assert!(canvas.write_ppm_binary(&mut Vec::new()).is_err());
+            assert!(canvas.write_png(&mut Vec::new()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_write_sixteen_bit_png() {
+        let mut canvas = Canvas::empty(1, 1);
+        canvas.bit_depth = 16;
+        canvas.write_pixel(0, 0, &Color::new(0.001, 0.5, 1.0));
+        let mut bytes = Vec::new();
+
+        canvas.write_png(&mut bytes).unwrap();
+
+        let image = image::load_from_memory(&bytes).unwrap().to_rgb16();
+        assert_eq!(image.get_pixel(0, 0).0, [66, 32768, 65535]);
+    }
+
+    #[test]
+    fn test_render_to_ppm_with_encoding() {
+        let mut canvas = Canvas::empty(3, 1);
+        canvas.write_pixel(0, 0, &Color::new(0.5, 0.0, 1.0));
+        canvas.write_pixel(1, 0, &Color::new(0.001, 0.2, 2.0));
+        canvas.write_pixel(2, 0, &Color::new(0.25, 0.25, 0.25));
+
+        canvas.encoding = Encoding::Srgb;
+        assert_eq!(
+            canvas.render_ppm(),
+            "P3\n3 1\n255\n188 0 255 3 124 255 137 137 137\n"
+        );
+
+        canvas.encoding = Encoding::Gamma(2.0);
+        assert_eq!(
+            canvas.render_ppm(),
+            "P3\n3 1\n255\n180 0 255 8 114 255 128 128 128\n"
+        );
+    }
+
+    #[test]
+    fn test_write_png() {
+        let mut canvas = Canvas::empty(2, 3);
+        canvas.write_pixel(0, 0, &Color::new(1.0, 0.5, 0.0));
+        canvas.write_pixel(1, 2, &Color::new(0.0, 0.0, 1.0));
+        let mut bytes = Vec::new();
+
+        canvas.write_png(&mut bytes).unwrap();
+
+        let image = image::load_from_memory(&bytes).unwrap().to_rgb8();
+        assert_eq!(image.dimensions(), (2, 3));
+        assert_eq!(image.get_pixel(0, 0).0, [255, 128, 0]);
+        assert_eq!(image.get_pixel(1, 0).0, [0, 0, 0]);
+        assert_eq!(image.get_pixel(1, 2).0, [0, 0, 255]);
+    }
+
+    #[test]
+    fn test_to_rgba() {
+        let mut canvas = Canvas::empty(2, 1);
+        canvas.encoding = Encoding::Srgb;
+        canvas.write_pixel(0, 0, &Color::new(1.0, 0.5, 0.0));
+
+        assert_eq!(canvas.to_rgba(), vec![255, 188, 0, 255, 0, 0, 0, 255]);
+    }
+
     #[test]
     fn test_render_to_ppm_split_long_lines() {
         let mut canvas = Canvas::empty(10, 2);
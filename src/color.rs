@@ -1,81 +1,341 @@
+use float::Float;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops;
 use utilities::clamp;
 use utilities::equal;
 
+// How linear colors are turned into stored values when an image is
+// written. Linear keeps them as they are, for HDR workflows.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    Gamma(f64),
+    Linear,
+    Srgb,
+}
+
+impl Encoding {
+    pub fn encode(&self, value: f64) -> f64 {
+        match *self {
+            Encoding::Gamma(gamma) => value.max(0.).powf(1. / gamma),
+            Encoding::Linear => value,
+            Encoding::Srgb => {
+                if value <= 0.0031308 {
+                    12.92 * value
+                } else {
+                    1.055 * value.powf(1. / 2.4) - 0.055
+                }
+            }
+        }
+    }
+
+    // The inverse of `encode`, for reading stored values back as linear.
+    pub fn decode(&self, value: f64) -> f64 {
+        match *self {
+            Encoding::Gamma(gamma) => value.max(0.).powf(gamma),
+            Encoding::Linear => value,
+            Encoding::Srgb => {
+                if value <= 0.04045 {
+                    value / 12.92
+                } else {
+                    ((value + 0.055) / 1.055).powf(2.4)
+                }
+            }
+        }
+    }
+}
+
+// Linear red, green and blue over any `Float`. The rest of the crate
+// works in f64 through `Color`.
 #[derive(Copy, Clone, Debug)]
-pub struct Color {
-    pub blue: f64,
-    pub green: f64,
-    pub red: f64,
+pub struct GenericColor<T> {
+    pub blue: T,
+    pub green: T,
+    pub red: T,
 }
 
-impl Color {
-    pub fn new(red: f64, green: f64, blue: f64) -> Color {
-        return Color { red, green, blue };
+pub type Color = GenericColor<f64>;
+pub type ColorF32 = GenericColor<f32>;
+
+impl<T: Float> GenericColor<T> {
+    pub fn new(red: T, green: T, blue: T) -> GenericColor<T> {
+        GenericColor { red, green, blue }
     }
 
-    pub fn white() -> Color {
-        Color::new(1.0, 1.0, 1.0)
+    pub fn white() -> GenericColor<T> {
+        GenericColor::new(T::ONE, T::ONE, T::ONE)
     }
 
-    pub fn black() -> Color {
-        Color::new(0.0, 0.0, 0.0)
+    pub fn black() -> GenericColor<T> {
+        GenericColor::new(T::ZERO, T::ZERO, T::ZERO)
+    }
+
+    // The same color at another precision.
+    pub fn cast<U: Float>(&self) -> GenericColor<U> {
+        GenericColor::new(
+            U::from_f64(self.red.to_f64()),
+            U::from_f64(self.green.to_f64()),
+            U::from_f64(self.blue.to_f64()),
+        )
     }
 
-    pub fn add(&self, other: &Color) -> Color {
-        Color {
+    pub fn add(&self, other: &GenericColor<T>) -> GenericColor<T> {
+        GenericColor {
             blue: self.blue + other.blue,
             green: self.green + other.green,
             red: self.red + other.red,
         }
     }
 
-    pub fn sub(&self, other: &Color) -> Color {
-        Color {
+    pub fn sub(&self, other: &GenericColor<T>) -> GenericColor<T> {
+        GenericColor {
             blue: self.blue - other.blue,
             green: self.green - other.green,
             red: self.red - other.red,
         }
     }
 
-    pub fn hadamard_product(&self, other: &Color) -> Color {
-        return Color {
+    pub fn hadamard_product(&self, other: &GenericColor<T>) -> GenericColor<T> {
+        GenericColor {
             blue: self.blue * other.blue,
             green: self.green * other.green,
             red: self.red * other.red,
-        };
+        }
     }
 
-    pub fn multiply_scalar(&self, factor: f64) -> Color {
-        Color {
+    pub fn multiply_scalar(&self, factor: T) -> GenericColor<T> {
+        GenericColor {
             blue: self.blue * factor,
             green: self.green * factor,
             red: self.red * factor,
         }
     }
 
-    pub fn divide(&self, factor: f64) -> Color {
-        Color {
+    pub fn divide(&self, factor: T) -> GenericColor<T> {
+        GenericColor {
             blue: self.blue / factor,
             green: self.green / factor,
             red: self.red / factor,
         }
     }
 
+    // A false color for `t` from 0 to 1, running black through red and
+    // yellow to white.
+    pub fn heat(t: T) -> GenericColor<T> {
+        let t = T::from_f64(3.) * t;
+        GenericColor::new(
+            clamp(t, T::ZERO, T::ONE),
+            clamp(t - T::ONE, T::ZERO, T::ONE),
+            clamp(t - T::from_f64(2.), T::ZERO, T::ONE),
+        )
+    }
+
+    // Relative luminance of a linear Rec. 709 color.
+    pub fn luminance(&self) -> T {
+        T::from_f64(0.2126) * self.red
+            + T::from_f64(0.7152) * self.green
+            + T::from_f64(0.0722) * self.blue
+    }
+
+    pub fn encode(&self, encoding: Encoding) -> GenericColor<T> {
+        let encode = |value: T| T::from_f64(encoding.encode(value.to_f64()));
+        GenericColor::new(encode(self.red), encode(self.green), encode(self.blue))
+    }
+
+    pub fn decode(&self, encoding: Encoding) -> GenericColor<T> {
+        let decode = |value: T| T::from_f64(encoding.decode(value.to_f64()));
+        GenericColor::new(decode(self.red), decode(self.green), decode(self.blue))
+    }
+
     pub fn ppm(&self) -> String {
-        return format!(
-            "{} {} {}",
-            (clamp(self.red, 0.0, 1.0) * 255.0).round(),
-            (clamp(self.green, 0.0, 1.0) * 255.0).round(),
-            (clamp(self.blue, 0.0, 1.0) * 255.0).round()
-        );
+        let [red, green, blue] = self.bytes();
+        format!("{} {} {}", red, green, blue)
+    }
+
+    pub fn bytes(&self) -> [u8; 3] {
+        let [red, green, blue] = self.quantize(8);
+        [red as u8, green as u8, blue as u8]
+    }
+
+    // Each channel clamped to 0..1 and rounded to an integer of `bits` bits,
+    // from 1 to 16.
+    pub fn quantize(&self, bits: u8) -> [u16; 3] {
+        let max = f64::from((1_u32 << bits) - 1);
+        [
+            (clamp(self.red.to_f64(), 0.0, 1.0) * max).round() as u16,
+            (clamp(self.green.to_f64(), 0.0, 1.0) * max).round() as u16,
+            (clamp(self.blue.to_f64(), 0.0, 1.0) * max).round() as u16,
+        ]
+    }
+}
+
+impl<T: Float> PartialEq for GenericColor<T> {
+    fn eq(&self, other: &GenericColor<T>) -> bool {
+        equal(self.red, other.red) && equal(self.green, other.green) && equal(self.blue, other.blue)
+    }
+}
+
+impl<T: Float> ops::Add for GenericColor<T> {
+    type Output = GenericColor<T>;
+
+    fn add(self, other: GenericColor<T>) -> GenericColor<T> {
+        GenericColor::add(&self, &other)
+    }
+}
+
+impl<T: Float> ops::AddAssign for GenericColor<T> {
+    fn add_assign(&mut self, other: GenericColor<T>) {
+        *self = GenericColor::add(self, &other);
+    }
+}
+
+impl<T: Float> ops::Sub for GenericColor<T> {
+    type Output = GenericColor<T>;
+
+    fn sub(self, other: GenericColor<T>) -> GenericColor<T> {
+        GenericColor::sub(&self, &other)
     }
 }
 
-impl PartialEq for Color {
-    fn eq(&self, other: &Color) -> bool {
-        return equal(self.red, other.red)
-            && equal(self.green, other.green)
-            && equal(self.blue, other.blue);
+impl<T: Float> ops::SubAssign for GenericColor<T> {
+    fn sub_assign(&mut self, other: GenericColor<T>) {
+        *self = GenericColor::sub(self, &other);
+    }
+}
+
+// Channel by channel, like `hadamard_product`.
+impl<T: Float> ops::Mul for GenericColor<T> {
+    type Output = GenericColor<T>;
+
+    fn mul(self, other: GenericColor<T>) -> GenericColor<T> {
+        self.hadamard_product(&other)
+    }
+}
+
+impl<T: Float> ops::MulAssign for GenericColor<T> {
+    fn mul_assign(&mut self, other: GenericColor<T>) {
+        *self = self.hadamard_product(&other);
+    }
+}
+
+impl<T: Float> ops::Mul<T> for GenericColor<T> {
+    type Output = GenericColor<T>;
+
+    fn mul(self, factor: T) -> GenericColor<T> {
+        self.multiply_scalar(factor)
+    }
+}
+
+impl ops::Mul<Color> for f64 {
+    type Output = Color;
+
+    fn mul(self, color: Color) -> Color {
+        color.multiply_scalar(self)
+    }
+}
+
+impl ops::Mul<ColorF32> for f32 {
+    type Output = ColorF32;
+
+    fn mul(self, color: ColorF32) -> ColorF32 {
+        color.multiply_scalar(self)
+    }
+}
+
+impl<T: Float> ops::MulAssign<T> for GenericColor<T> {
+    fn mul_assign(&mut self, factor: T) {
+        *self = self.multiply_scalar(factor);
+    }
+}
+
+impl<T: Float> ops::Div<T> for GenericColor<T> {
+    type Output = GenericColor<T>;
+
+    fn div(self, factor: T) -> GenericColor<T> {
+        self.divide(factor)
+    }
+}
+
+impl<T: Float> ops::DivAssign<T> for GenericColor<T> {
+    fn div_assign(&mut self, factor: T) {
+        *self = self.divide(factor);
+    }
+}
+
+// Red, green and blue, in that order.
+impl<T> ops::Index<usize> for GenericColor<T> {
+    type Output = T;
+
+    fn index(&self, channel: usize) -> &T {
+        match channel {
+            0 => &self.red,
+            1 => &self.green,
+            2 => &self.blue,
+            _ => panic!("a color has no channel {}", channel),
+        }
+    }
+}
+
+impl<T> ops::IndexMut<usize> for GenericColor<T> {
+    fn index_mut(&mut self, channel: usize) -> &mut T {
+        match channel {
+            0 => &mut self.red,
+            1 => &mut self.green,
+            2 => &mut self.blue,
+            _ => panic!("a color has no channel {}", channel),
+        }
+    }
+}
+
+// Bytes are taken as they are, without decoding, and made the same way
+// as `bytes`.
+impl<T: Float> From<[u8; 3]> for GenericColor<T> {
+    fn from([red, green, blue]: [u8; 3]) -> GenericColor<T> {
+        let channel = |byte: u8| T::from_f64(f64::from(byte) / 255.);
+        GenericColor::new(channel(red), channel(green), channel(blue))
+    }
+}
+
+impl<T: Float> From<GenericColor<T>> for [u8; 3] {
+    fn from(color: GenericColor<T>) -> [u8; 3] {
+        color.bytes()
+    }
+}
+
+impl From<[f32; 3]> for Color {
+    fn from([red, green, blue]: [f32; 3]) -> Color {
+        Color::new(f64::from(red), f64::from(green), f64::from(blue))
+    }
+}
+
+impl From<Color> for [f32; 3] {
+    fn from(color: Color) -> [f32; 3] {
+        [color.red as f32, color.green as f32, color.blue as f32]
+    }
+}
+
+impl<T: Float> From<[T; 3]> for GenericColor<T> {
+    fn from([red, green, blue]: [T; 3]) -> GenericColor<T> {
+        GenericColor::new(red, green, blue)
+    }
+}
+
+impl<T> From<GenericColor<T>> for [T; 3] {
+    fn from(color: GenericColor<T>) -> [T; 3] {
+        [color.red, color.green, color.blue]
+    }
+}
+
+impl<T: Float> fmt::Display for GenericColor<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "color(")?;
+        fmt::Display::fmt(&self.red, f)?;
+        write!(f, ", ")?;
+        fmt::Display::fmt(&self.green, f)?;
+        write!(f, ", ")?;
+        fmt::Display::fmt(&self.blue, f)?;
+        write!(f, ")")
     }
 }
 
@@ -119,6 +379,16 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_quantize_color() {
+        let color = Color::new(0.5, -1.0, 1.5);
+
+        assert_eq!(color.quantize(1), [1, 0, 1]);
+        assert_eq!(color.quantize(4), [8, 0, 15]);
+        assert_eq!(color.quantize(16), [32768, 0, 65535]);
+        assert_eq!(color.bytes(), [128, 0, 255]);
+    }
+
     #[test]
     fn test_multiply_color() {
         let a = Color {
@@ -141,4 +411,39 @@ mod tests {
             }
         )
     }
+
+    #[test]
+    fn test_color_operators() {
+        let a = Color::new(1.0, 0.2, 0.4);
+        let b = Color::new(0.9, 1.0, 0.1);
+
+        assert_eq!(a + b * 2.0, Color::new(2.8, 2.2, 0.6));
+        assert_eq!(a - b, a.sub(&b));
+        assert_eq!(a * b, a.hadamard_product(&b));
+        assert_eq!(0.5 * a, a / 2.0);
+
+        let mut c = a;
+        c += b;
+        c *= 2.0;
+        c /= 4.0;
+        assert_eq!(c, Color::new(0.95, 0.6, 0.25));
+        c[2] = 1.0;
+        assert_eq!((c[0], c[1], c[2]), (0.95, 0.6, 1.0));
+    }
+
+    #[test]
+    fn test_color_conversions() {
+        let c = Color::from([255_u8, 51, 0]);
+        assert_eq!(c, Color::new(1.0, 0.2, 0.0));
+        assert_eq!(<[u8; 3]>::from(c), [255, 51, 0]);
+        assert_eq!(<[u8; 3]>::from(Color::new(2.0, -1.0, 0.5)), [255, 0, 128]);
+
+        let c: Color = [0.25_f32, 0.5, 1.0].into();
+        assert_eq!(c, Color::new(0.25, 0.5, 1.0));
+        assert_eq!(<[f32; 3]>::from(c), [0.25, 0.5, 1.0]);
+        assert_eq!(
+            <[f64; 3]>::from(Color::from([0.1, 0.2, 0.3])),
+            [0.1, 0.2, 0.3]
+        );
+    }
 }
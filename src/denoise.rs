@@ -0,0 +1,145 @@
+use canvas::Canvas;
+use color::Color;
+use gbuffer::GBuffer;
+use rayon::prelude::*;
+
+// A joint bilateral filter. Neighbours are averaged only where the normal
+// and albedo passes agree, so noise is smoothed away without blurring
+// across silhouettes or texture edges.
+#[derive(Debug, Clone, Copy)]
+pub struct Denoiser {
+    pub albedo_sigma: f64,
+    pub color_sigma: f64,
+    pub normal_sigma: f64,
+    pub radius: usize,
+    pub spatial_sigma: f64,
+}
+
+impl Denoiser {
+    pub fn new(radius: usize) -> Denoiser {
+        Denoiser {
+            albedo_sigma: 0.1,
+            color_sigma: 0.5,
+            normal_sigma: 0.1,
+            radius,
+            spatial_sigma: radius as f64 / 2.,
+        }
+    }
+
+    pub fn apply(&self, canvas: &Canvas, guide: &GBuffer) -> Canvas {
+        let mut denoised = Canvas::empty(canvas.width, canvas.height);
+        denoised.encoding = canvas.encoding;
+        let width = canvas.width as usize;
+        denoised.pixels = (0..canvas.pixels.len())
+            .into_par_iter()
+            .map(|i| self.filter_pixel(canvas, guide, i % width, i / width))
+            .collect();
+        denoised
+    }
+
+    fn filter_pixel(&self, canvas: &Canvas, guide: &GBuffer, column: usize, row: usize) -> Color {
+        let center = canvas.pixel_at(column, row);
+        let normal = guide.normal.pixel_at(column, row);
+        let albedo = guide.albedo.pixel_at(column, row);
+        let mut sum = Color::black();
+        let mut total = 0.;
+        let last_column = (column + self.radius).min(canvas.width as usize - 1);
+        let last_row = (row + self.radius).min(canvas.height as usize - 1);
+        for y in row.saturating_sub(self.radius)..=last_row {
+            for x in column.saturating_sub(self.radius)..=last_column {
+                let dx = x as f64 - column as f64;
+                let dy = y as f64 - row as f64;
+                let color = canvas.pixel_at(x, y);
+                let weight = gaussian(dx * dx + dy * dy, self.spatial_sigma)
+                    * gaussian(distance_squared(&color, &center), self.color_sigma)
+                    * gaussian(
+                        distance_squared(&guide.normal.pixel_at(x, y), &normal),
+                        self.normal_sigma,
+                    )
+                    * gaussian(
+                        distance_squared(&guide.albedo.pixel_at(x, y), &albedo),
+                        self.albedo_sigma,
+                    );
+                sum = sum.add(&color.multiply_scalar(weight));
+                total += weight;
+            }
+        }
+        sum.divide(total)
+    }
+}
+
+fn gaussian(distance_squared: f64, sigma: f64) -> f64 {
+    if sigma <= 0. {
+        return if distance_squared == 0. { 1. } else { 0. };
+    }
+    (-distance_squared / (2. * sigma * sigma)).exp()
+}
+
+fn distance_squared(a: &Color, b: &Color) -> f64 {
+    let difference = a.sub(b);
+    difference.red * difference.red
+        + difference.green * difference.green
+        + difference.blue * difference.blue
+}
+
+#[cfg(test)]
+mod tests {
+    use canvas::Canvas;
+    use color::Color;
+    use denoise::Denoiser;
+    use gbuffer::GBuffer;
+
+    fn noisy(width: i64, height: i64) -> Canvas {
+        let mut canvas = Canvas::empty(width, height);
+        for (i, pixel) in canvas.pixels.iter_mut().enumerate() {
+            let noise = if (i * 7 + i / width as usize).is_multiple_of(3) {
+                0.2
+            } else {
+                -0.1
+            };
+            *pixel = Color::new(0.5 + noise, 0.5 + noise, 0.5 + noise);
+        }
+        canvas
+    }
+
+    fn spread(canvas: &Canvas, columns: ::std::ops::Range<usize>) -> f64 {
+        let mut min = f64::INFINITY;
+        let mut max = f64::NEG_INFINITY;
+        for row in 0..canvas.height as usize {
+            for column in columns.clone() {
+                let red = canvas.pixel_at(column, row).red;
+                min = min.min(red);
+                max = max.max(red);
+            }
+        }
+        max - min
+    }
+
+    #[test]
+    fn test_denoise_smooths_flat_regions() {
+        let canvas = noisy(8, 8);
+        let guide = GBuffer::new(8, 8);
+
+        let denoised = Denoiser::new(2).apply(&canvas, &guide);
+
+        assert!(spread(&denoised, 0..8) < spread(&canvas, 0..8) * 0.6);
+    }
+
+    #[test]
+    fn test_denoise_keeps_albedo_edges() {
+        let mut canvas = noisy(8, 4);
+        let mut guide = GBuffer::new(8, 4);
+        for row in 0..4 {
+            for column in 4..8 {
+                canvas.write_pixel(column, row, &Color::white());
+                guide.albedo.write_pixel(column, row, &Color::white());
+            }
+        }
+
+        let denoised = Denoiser::new(2).apply(&canvas, &guide);
+
+        // The white half would pull this pixel up if it leaked across.
+        assert!(denoised.pixel_at(3, 1).red < canvas.pixel_at(3, 1).red);
+        assert_eq!(denoised.pixel_at(4, 1), Color::white());
+    }
+}
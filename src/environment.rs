@@ -0,0 +1,246 @@
+use color::Color;
+use image;
+use intersection::Intersection;
+use point::{vector, Point};
+use ray::Ray;
+use sampling::{cosine_hemisphere, random};
+use std::f64::consts::PI;
+use std::path::Path;
+use texture::Texture;
+use world::World;
+
+// An equirectangular image surrounding the scene, seen by rays that miss
+// everything. With samples above zero it also lights diffuse surfaces.
+#[derive(Debug, Clone)]
+pub struct Environment {
+    // Running totals of the light from each pixel, for picking directions
+    // by brightness.
+    distribution: Vec<f64>,
+    pub intensity: f64,
+    pub samples: usize,
+    texture: Texture,
+}
+
+impl Environment {
+    pub fn new(texture: Texture) -> Environment {
+        let mut total = 0.;
+        let distribution = (0..texture.width * texture.height)
+            .map(|i| {
+                let ((u0, u1), (v0, v1)) = pixel_bounds(&texture, i);
+                let solid_angle = 2. * PI * (u1 - u0) * (latitude(v1).sin() - latitude(v0).sin());
+                total += texture
+                    .pixel_at(i % texture.width, i / texture.width)
+                    .luminance()
+                    .max(0.)
+                    * solid_angle;
+                total
+            })
+            .collect();
+        Environment {
+            distribution,
+            intensity: 1.,
+            samples: 0,
+            texture,
+        }
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> image::ImageResult<Environment> {
+        Ok(Environment::new(Texture::open(path)?))
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn color_in(&self, direction: &Point) -> Color {
+        let (u, v) = texture_coordinates(direction);
+        self.texture.color_at(u, v).multiply_scalar(self.intensity)
+    }
+
+    // A direction picked in proportion to the light arriving from it, with
+    // the density of picking it per unit of solid angle. Nothing comes back
+    // for an image that's black all over.
+    pub fn sample(&self) -> Option<(Point, f64)> {
+        let total = self.total();
+        if total <= 0. {
+            return None;
+        }
+        let pick = random() * total;
+        let i = self
+            .distribution
+            .partition_point(|&sum| sum <= pick)
+            .min(self.distribution.len() - 1);
+        let ((u0, u1), (v0, v1)) = pixel_bounds(&self.texture, i);
+        let u = u0 + random() * (u1 - u0);
+        let v = v0 + random() * (v1 - v0);
+        let (sin, cos) = latitude(v).sin_cos();
+        let azimuth = (u - 0.5) * 2. * PI;
+        let direction = vector(cos * azimuth.sin(), sin, -cos * azimuth.cos());
+        let pdf = self.pdf(&direction);
+        if pdf <= 0. {
+            return None;
+        }
+        Some((direction, pdf))
+    }
+
+    // The density of `sample` picking the direction.
+    pub fn pdf(&self, direction: &Point) -> f64 {
+        let total = self.total();
+        let d = direction.normalize();
+        let cos = (1. - d.y * d.y).max(0.).sqrt();
+        if total <= 0. || cos <= 0. {
+            return 0.;
+        }
+        let (u, v) = texture_coordinates(&d);
+        let i = self.texture.index_at(u, v);
+        let previous = if i == 0 { 0. } else { self.distribution[i - 1] };
+        let ((u0, u1), (v0, v1)) = pixel_bounds(&self.texture, i);
+        // Uniform over the pixel's share of the image, which stretches
+        // over less solid angle toward the poles.
+        (self.distribution[i] - previous) / total / ((u1 - u0) * (v1 - v0)) / (2. * PI * PI * cos)
+    }
+
+    fn total(&self) -> f64 {
+        self.distribution.last().cloned().unwrap_or(0.)
+    }
+
+    // Average light arriving at a point from the unoccluded part of the
+    // hemisphere around the normal.
+    pub fn irradiance(&self, world: &World, point: &Point, normal: &Point) -> Color {
+        if self.samples == 0 {
+            return Color::black();
+        }
+        let mut total = Color::black();
+        for _ in 0..self.samples {
+            let ray = Ray {
+                origin: *point,
+                direction: cosine_hemisphere(normal),
+            };
+            if Intersection::hit(&mut ray.intersect_world(world)).is_none() {
+                total = total.add(&self.color_in(&ray.direction));
+            }
+        }
+        total.divide(self.samples as f64)
+    }
+}
+
+// +y is the top of the image and -z its horizontal centre.
+fn texture_coordinates(direction: &Point) -> (f64, f64) {
+    let d = direction.normalize();
+    let u = 0.5 + d.x.atan2(-d.z) / (2. * PI);
+    let v = 0.5 + d.y.clamp(-1., 1.).asin() / PI;
+    (u, v)
+}
+
+fn latitude(v: f64) -> f64 {
+    (v - 0.5) * PI
+}
+
+// The texture coordinates that `Texture::color_at` rounds to pixel `i`,
+// where edge pixels only get half as much.
+fn pixel_bounds(texture: &Texture, i: usize) -> ((f64, f64), (f64, f64)) {
+    let (x0, x1) = bounds(i % texture.width, texture.width);
+    let (y0, y1) = bounds(i / texture.width, texture.height);
+    ((x0, x1), (1. - y1, 1. - y0))
+}
+
+fn bounds(i: usize, size: usize) -> (f64, f64) {
+    if size <= 1 {
+        return (0., 1.);
+    }
+    let last = (size - 1) as f64;
+    (
+        ((i as f64 - 0.5) / last).max(0.),
+        ((i as f64 + 0.5) / last).min(1.),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use color::Color;
+    use environment::Environment;
+    use point::point;
+    use point::vector;
+    use sampling::square_to_sphere;
+    use shape::Shape;
+    use std::f64::consts::PI;
+    use texture::Texture;
+    use world::World;
+
+    // Sky on the top half of the image and ground on the bottom half.
+    fn sky() -> Environment {
+        Environment::new(Texture::new(
+            1,
+            2,
+            vec![Color::new(0., 0., 1.), Color::new(0., 1., 0.)],
+        ))
+    }
+
+    #[test]
+    fn test_environment_color_in() {
+        let e = sky();
+
+        assert_eq!(e.color_in(&vector(0., 1., 0.)), Color::new(0., 0., 1.));
+        assert_eq!(e.color_in(&vector(0., -1., 0.)), Color::new(0., 1., 0.));
+        assert_eq!(e.color_in(&vector(1., 0.5, 0.)), Color::new(0., 0., 1.));
+    }
+
+    #[test]
+    fn test_environment_intensity() {
+        let mut e = sky();
+        e.intensity = 2.;
+
+        assert_eq!(e.color_in(&vector(0., 1., 0.)), Color::new(0., 0., 2.));
+    }
+
+    #[test]
+    fn test_environment_sample() {
+        let mut pixels = vec![Color::new(0.1, 0.1, 0.1); 8 * 4];
+        pixels[9] = Color::new(50., 50., 50.);
+        let e = Environment::new(Texture::new(8, 4, pixels));
+
+        // The density covers the sphere once over.
+        let n = 400;
+        let mut total = 0.;
+        for i in 0..n {
+            for j in 0..n {
+                let direction =
+                    square_to_sphere(((i as f64 + 0.5) / n as f64, (j as f64 + 0.5) / n as f64));
+                total += e.pdf(&direction) * 4. * PI / (n * n) as f64;
+            }
+        }
+        assert!((total - 1.).abs() < 0.01);
+
+        let bright = (0..100)
+            .filter(|_| {
+                let (direction, pdf) = e.sample().unwrap();
+                assert_eq!(pdf, e.pdf(&direction));
+                e.color_in(&direction).red == 50.
+            })
+            .count();
+        assert!(bright > 80);
+        assert!(Environment::new(Texture::new(1, 1, vec![Color::black()]))
+            .sample()
+            .is_none());
+    }
+
+    #[test]
+    fn test_environment_irradiance() {
+        let mut e = sky();
+        e.samples = 16;
+        let mut world = World::new();
+        world.objects = Vec::new();
+
+        assert_eq!(
+            e.irradiance(&world, &point(0., 0., 0.), &vector(0., 1., 0.)),
+            Color::new(0., 0., 1.)
+        );
+
+        world.objects = vec![Shape::plane()];
+
+        assert_eq!(
+            e.irradiance(&world, &point(0., -1., 0.), &vector(0., 1., 0.)),
+            Color::black()
+        );
+    }
+}
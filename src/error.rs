@@ -0,0 +1,117 @@
+use material::MaterialError;
+use obj_parser::ObjError;
+use scene::SceneError;
+use settings::SettingsError;
+use std::error;
+use std::fmt;
+use std::io;
+
+// Anything the library can fail at, for callers who would rather handle
+// one type. Each module's own error converts into it with `?`.
+#[derive(Debug)]
+pub enum Error {
+    // A second light for a world, which only has the one.
+    ExtraLight,
+    // A matrix that flattens space, so has no inverse.
+    InvalidMatrix,
+    InvalidMaterial(MaterialError),
+    InvalidScene(SceneError),
+    InvalidSettings(SettingsError),
+    Io(io::Error),
+    // A child for a shape that isn't a group, which has nowhere to keep it.
+    NotAGroup,
+    // A line of a model that could not be read, counting from one.
+    ObjParse { line: usize, message: String },
+    PixelOutOfBounds { column: usize, row: usize },
+    // Two canvases that must be the same size, each (width, height).
+    SizeMismatch { left: (i64, i64), right: (i64, i64) },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::ExtraLight => write!(f, "a world has only one light"),
+            Error::InvalidMatrix => write!(f, "matrix is not invertible"),
+            Error::InvalidMaterial(ref error) => write!(f, "{}", error),
+            Error::InvalidScene(ref error) => write!(f, "{}", error),
+            Error::InvalidSettings(ref error) => write!(f, "{}", error),
+            Error::Io(ref error) => write!(f, "{}", error),
+            Error::NotAGroup => write!(f, "only groups can hold other shapes"),
+            Error::ObjParse { line, ref message } => {
+                write!(f, "invalid model at line {}: {}", line, message)
+            }
+            Error::PixelOutOfBounds { column, row } => {
+                write!(f, "pixel ({}, {}) is off the canvas", column, row)
+            }
+            Error::SizeMismatch { left, right } => write!(
+                f,
+                "canvases differ in size: {}x{} and {}x{}",
+                left.0, left.1, right.0, right.1
+            ),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::InvalidMaterial(ref error) => Some(error),
+            Error::InvalidScene(ref error) => Some(error),
+            Error::InvalidSettings(ref error) => Some(error),
+            Error::Io(ref error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Error {
+        Error::Io(error)
+    }
+}
+
+impl From<MaterialError> for Error {
+    fn from(error: MaterialError) -> Error {
+        Error::InvalidMaterial(error)
+    }
+}
+
+impl From<ObjError> for Error {
+    fn from(error: ObjError) -> Error {
+        match error {
+            ObjError::Io(error) => Error::Io(error),
+            ObjError::Parse { line, message } => Error::ObjParse { line, message },
+        }
+    }
+}
+
+impl From<SceneError> for Error {
+    fn from(error: SceneError) -> Error {
+        Error::InvalidScene(error)
+    }
+}
+
+impl From<SettingsError> for Error {
+    fn from(error: SettingsError) -> Error {
+        Error::InvalidSettings(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use error::Error;
+    use obj_parser::ObjParser;
+    use std::path::Path;
+
+    fn parse_model(text: &str) -> Result<usize, Error> {
+        Ok(ObjParser::parse(text, Path::new(""))?.vertices.len())
+    }
+
+    #[test]
+    fn test_obj_errors_convert() {
+        match parse_model("v 1 2 3\nv 1 x 3\n") {
+            Err(Error::ObjParse { line, .. }) => assert_eq!(line, 2),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,263 @@
+// The safety requirements are in the comment above each function.
+#![allow(clippy::missing_safety_doc)]
+
+use camera::Camera;
+use std::any::Any;
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use world::World;
+
+// A C interface for embedding the renderer, declared in
+// include/ray_tracer.h. A host loads a scene from JSON, renders it into a
+// buffer of its own and may poll the progress from another thread while
+// it does. Failures, panics included, return null or -1, with the reason
+// kept for `ray_tracer_last_error`.
+pub struct Scene {
+    camera: Camera,
+    done: AtomicUsize,
+    world: World,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message).unwrap_or_default();
+    LAST_ERROR.with(|error| *error.borrow_mut() = Some(message));
+}
+
+// Runs `call`, keeping the reason it failed, or panicked, for the host.
+// A panic must not unwind into the host's own frames.
+fn guarded<T, F: FnOnce() -> Result<T, String>>(call: F) -> Option<T> {
+    let result = match panic::catch_unwind(AssertUnwindSafe(call)) {
+        Ok(result) => result,
+        Err(payload) => Err(format!("renderer panicked: {}", panic_message(&*payload))),
+    };
+    match result {
+        Ok(value) => Some(value),
+        Err(message) => {
+            set_last_error(message);
+            None
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message,
+        None => payload
+            .downcast_ref::<String>()
+            .map_or("unknown cause", String::as_str),
+    }
+}
+
+fn no_scene() -> String {
+    "no scene given".to_string()
+}
+
+// Why the last call on this thread failed, or null. The string belongs to
+// the library and lasts until the next failure on the same thread.
+#[no_mangle]
+pub extern "C" fn ray_tracer_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| match *error.borrow() {
+        Some(ref message) => message.as_ptr(),
+        None => ptr::null(),
+    })
+}
+
+// Loads a scene, and the camera it describes, from a JSON string. Free it
+// with `ray_tracer_scene_free`.
+//
+// # Safety
+// `json` must be null or a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ray_tracer_scene_from_json(json: *const c_char) -> *mut Scene {
+    guarded(|| {
+        if json.is_null() {
+            return Err(no_scene());
+        }
+        let json = CStr::from_ptr(json)
+            .to_str()
+            .map_err(|error| format!("scene is not UTF-8: {}", error))?;
+        let (world, camera) = World::from_json(json).map_err(|error| error.to_string())?;
+        Ok(Box::into_raw(Box::new(Scene {
+            camera,
+            done: AtomicUsize::new(0),
+            world,
+        })))
+    })
+    .unwrap_or(ptr::null_mut())
+}
+
+// # Safety
+// `scene` must be null or come from `ray_tracer_scene_from_json`, and not
+// be used again.
+#[no_mangle]
+pub unsafe extern "C" fn ray_tracer_scene_free(scene: *mut Scene) {
+    if !scene.is_null() {
+        drop(Box::from_raw(scene));
+    }
+}
+
+// The size of the image, in pixels, or 0 without a scene.
+//
+// # Safety
+// `scene` must be null or come from `ray_tracer_scene_from_json`.
+#[no_mangle]
+pub unsafe extern "C" fn ray_tracer_scene_width(scene: *const Scene) -> usize {
+    match scene.as_ref() {
+        Some(scene) => scene.camera.hsize,
+        None => {
+            set_last_error(no_scene());
+            0
+        }
+    }
+}
+
+// # Safety
+// `scene` must be null or come from `ray_tracer_scene_from_json`.
+#[no_mangle]
+pub unsafe extern "C" fn ray_tracer_scene_height(scene: *const Scene) -> usize {
+    match scene.as_ref() {
+        Some(scene) => scene.camera.vsize,
+        None => {
+            set_last_error(no_scene());
+            0
+        }
+    }
+}
+
+// Renders into `buffer`, four bytes per pixel as `Canvas::to_rgba` lays
+// them out, which needs width * height * 4 bytes. Returns 0 once done.
+//
+// # Safety
+// `scene` must be null or come from `ray_tracer_scene_from_json`, and
+// `buffer` must be writable for `length` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ray_tracer_render_rgba(
+    scene: *const Scene,
+    buffer: *mut u8,
+    length: usize,
+) -> c_int {
+    guarded(|| {
+        let scene = scene.as_ref().ok_or_else(no_scene)?;
+        let needed = scene.camera.hsize * scene.camera.vsize * 4;
+        if buffer.is_null() || length < needed {
+            return Err(format!("buffer holds {} bytes, not {}", length, needed));
+        }
+        scene.done.store(0, Ordering::Relaxed);
+        let canvas = scene.camera.render_with_progress(&scene.world, |progress| {
+            scene.done.fetch_max(progress.done, Ordering::Relaxed);
+        });
+        let buffer = slice::from_raw_parts_mut(buffer, needed);
+        buffer.copy_from_slice(&canvas.to_rgba());
+        Ok(0)
+    })
+    .unwrap_or(-1)
+}
+
+// How much of the current or last render is done, from 0 to 1, or -1
+// without a scene. Safe to call from another thread during
+// `ray_tracer_render_rgba`.
+//
+// # Safety
+// `scene` must be null or come from `ray_tracer_scene_from_json`.
+#[no_mangle]
+pub unsafe extern "C" fn ray_tracer_progress(scene: *const Scene) -> f64 {
+    let scene = match scene.as_ref() {
+        Some(scene) => scene,
+        None => {
+            set_last_error(no_scene());
+            return -1.;
+        }
+    };
+    let total = scene.camera.hsize * scene.camera.vsize;
+    if total == 0 {
+        return 1.;
+    }
+    scene.done.load(Ordering::Relaxed) as f64 / total as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use ffi::*;
+    use std::ffi::{CStr, CString};
+
+    #[test]
+    fn test_render_through_the_c_interface() {
+        let json = CString::new(
+            r#"{
+                "camera": { "width": 4, "height": 2, "field_of_view": 1.0,
+                            "from": [0, 0, -5], "to": [0, 0, 0], "up": [0, 1, 0] },
+                "light": { "position": [-10, 10, -10], "intensity": [1, 1, 1] },
+                "shapes": [{ "type": "sphere" }]
+            }"#,
+        )
+        .unwrap();
+
+        unsafe {
+            let scene = ray_tracer_scene_from_json(json.as_ptr());
+            assert!(
+                !scene.is_null(),
+                "{:?}",
+                CStr::from_ptr(ray_tracer_last_error())
+            );
+            assert_eq!(
+                (
+                    ray_tracer_scene_width(scene),
+                    ray_tracer_scene_height(scene)
+                ),
+                (4, 2)
+            );
+            assert_eq!(ray_tracer_progress(scene), 0.);
+
+            let mut buffer = vec![0; 4 * 2 * 4];
+            assert_eq!(ray_tracer_render_rgba(scene, buffer.as_mut_ptr(), 10), -1);
+            assert_eq!(
+                ray_tracer_render_rgba(scene, buffer.as_mut_ptr(), buffer.len()),
+                0
+            );
+            assert_eq!(ray_tracer_progress(scene), 1.);
+            assert!(buffer.chunks(4).all(|pixel| pixel[3] == 255));
+
+            ray_tracer_scene_free(scene);
+        }
+    }
+
+    #[test]
+    fn test_c_interface_errors() {
+        let json = CString::new("{ not json").unwrap();
+
+        unsafe {
+            assert!(ray_tracer_scene_from_json(json.as_ptr()).is_null());
+            let error = CStr::from_ptr(ray_tracer_last_error());
+            assert!(error.to_str().unwrap().starts_with("invalid scene"));
+
+            let mut buffer = vec![0; 4];
+            assert_eq!(ray_tracer_scene_width(ptr::null()), 0);
+            assert_eq!(ray_tracer_scene_height(ptr::null()), 0);
+            assert_eq!(ray_tracer_progress(ptr::null()), -1.);
+            assert_eq!(
+                ray_tracer_render_rgba(ptr::null(), buffer.as_mut_ptr(), buffer.len()),
+                -1
+            );
+            let error = CStr::from_ptr(ray_tracer_last_error());
+            assert_eq!(error.to_str().unwrap(), "no scene given");
+        }
+    }
+
+    #[test]
+    fn test_c_interface_catches_panics() {
+        let result: Option<()> = guarded(|| panic!("out of rays"));
+
+        assert!(result.is_none());
+        let error = unsafe { CStr::from_ptr(ray_tracer_last_error()) };
+        assert_eq!(error.to_str().unwrap(), "renderer panicked: out of rays");
+    }
+}
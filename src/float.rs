@@ -0,0 +1,116 @@
+use std::fmt;
+use std::ops;
+
+// The scalar the math types are built on. Everything else in the crate
+// uses f64, through the `Point`, `Color` and `Matrix4` aliases, but the
+// generic types work just as well with f32 where speed or memory matters
+// more than precision.
+pub trait Float:
+    Copy
+    + fmt::Debug
+    + fmt::Display
+    + PartialOrd
+    + ops::Add<Output = Self>
+    + ops::Sub<Output = Self>
+    + ops::Mul<Output = Self>
+    + ops::Div<Output = Self>
+    + ops::Neg<Output = Self>
+    + ops::AddAssign
+    + ops::SubAssign
+    + ops::MulAssign
+    + ops::DivAssign
+    + Send
+    + Sync
+    + 'static
+{
+    const ZERO: Self;
+    const ONE: Self;
+    const INFINITY: Self;
+    const NAN: Self;
+    // How close two values must be to count as equal.
+    const EPSILON: Self;
+
+    fn from_f64(value: f64) -> Self;
+    fn to_f64(self) -> f64;
+
+    fn abs(self) -> Self;
+    fn sqrt(self) -> Self;
+    fn powi(self, exponent: i32) -> Self;
+    fn powf(self, exponent: Self) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn min(self, other: Self) -> Self;
+    fn max(self, other: Self) -> Self;
+}
+
+macro_rules! impl_float {
+    ($float:ident, $epsilon:expr) => {
+        impl Float for $float {
+            const ZERO: $float = 0.;
+            const ONE: $float = 1.;
+            const INFINITY: $float = $float::INFINITY;
+            const NAN: $float = $float::NAN;
+            const EPSILON: $float = $epsilon;
+
+            fn from_f64(value: f64) -> $float {
+                value as $float
+            }
+
+            fn to_f64(self) -> f64 {
+                f64::from(self)
+            }
+
+            fn abs(self) -> $float {
+                $float::abs(self)
+            }
+
+            fn sqrt(self) -> $float {
+                $float::sqrt(self)
+            }
+
+            fn powi(self, exponent: i32) -> $float {
+                $float::powi(self, exponent)
+            }
+
+            fn powf(self, exponent: $float) -> $float {
+                $float::powf(self, exponent)
+            }
+
+            fn sin(self) -> $float {
+                $float::sin(self)
+            }
+
+            fn cos(self) -> $float {
+                $float::cos(self)
+            }
+
+            fn min(self, other: $float) -> $float {
+                $float::min(self, other)
+            }
+
+            fn max(self, other: $float) -> $float {
+                $float::max(self, other)
+            }
+        }
+    };
+}
+
+// f32 only has about seven digits, so it gets a looser epsilon.
+impl_float!(f32, 0.0001);
+impl_float!(f64, 0.00001);
+
+#[cfg(test)]
+mod tests {
+    use float::Float;
+
+    fn hypotenuse<T: Float>(a: T, b: T) -> T {
+        (a * a + b * b).sqrt()
+    }
+
+    #[test]
+    fn test_float() {
+        assert_eq!(hypotenuse(3_f32, 4.), 5.);
+        assert_eq!(hypotenuse(3_f64, 4.), 5.);
+        assert_eq!(f32::from_f64(0.5).to_f64(), 0.5);
+    }
+}
@@ -0,0 +1,166 @@
+use color::Color;
+use ray::Ray;
+use volume::{in_scattered, MAX_OPTICAL_DEPTH};
+use world::World;
+
+// Fog that fills the world, fading what's seen along a ray toward its
+// color the further the ray travels. `density` is how much of the light is
+// lost per unit of distance at height zero; with a `falloff`, the fog thins
+// by that rate going up and thickens going down, so it pools low. Haze on
+// the `horizon` fades toward the background in the ray's direction instead
+// of `color`, so far off things melt into the sky.
+//
+// With some `scattering`, the fog also scatters the world's light toward
+// the eye wherever the light reaches it, found by stepping along the ray
+// and testing for shadow `steps` times, so shadows cut shafts through it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fog {
+    pub color: Color,
+    pub density: f64,
+    pub falloff: f64,
+    pub horizon: bool,
+    pub scattering: f64,
+    pub steps: usize,
+}
+
+impl Fog {
+    pub fn new(color: Color, density: f64) -> Fog {
+        Fog {
+            color,
+            density,
+            falloff: 0.,
+            horizon: false,
+            scattering: 0.,
+            steps: 32,
+        }
+    }
+
+    // The color seen through the fog, where `seen` is `distance` along the
+    // ray. Rays that miss everything pass an infinite distance.
+    pub fn apply(&self, ray: &Ray, distance: f64, seen: &Color) -> Color {
+        let transmittance = (-self.optical_depth(ray, distance)).exp();
+        seen.multiply_scalar(transmittance)
+            .add(&self.color.multiply_scalar(1. - transmittance))
+    }
+
+    // The world's light scattered toward the eye by the fog in front of
+    // `distance` along the ray, less what the fog then absorbs on the way.
+    pub fn scattered(&self, world: &World, ray: &Ray, distance: f64) -> Color {
+        if self.scattering <= 0. || self.density <= 0. || self.steps == 0 {
+            return Color::black();
+        }
+        let length = ray.direction.magnitude();
+        // This also bounds rays that miss everything.
+        let reach = distance.min(MAX_OPTICAL_DEPTH / (self.density_at(ray.origin.y) * length));
+        in_scattered(
+            world,
+            ray,
+            0.,
+            reach,
+            self.steps,
+            |point| self.density_at(point.y),
+            |t| (-self.optical_depth(ray, t)).exp(),
+        )
+        .multiply_scalar(self.scattering)
+    }
+
+    fn density_at(&self, height: f64) -> f64 {
+        self.density * (-self.falloff * height).exp()
+    }
+
+    // The density integrated along the ray, in units of the ray's direction.
+    fn optical_depth(&self, ray: &Ray, distance: f64) -> f64 {
+        if self.density <= 0. || distance <= 0. {
+            return 0.;
+        }
+        let length = ray.direction.magnitude();
+        let rise = self.falloff * ray.direction.y / length;
+        let start = self.density_at(ray.origin.y);
+        let distance = distance * length;
+        if rise.abs() < 1e-9 {
+            return start * distance;
+        }
+        // Climbing rays that leave the fog behind only see a finite amount.
+        if distance.is_infinite() {
+            return if rise > 0. {
+                start / rise
+            } else {
+                f64::INFINITY
+            };
+        }
+        start * (1. - (-rise * distance).exp()) / rise
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use color::Color;
+    use fog::Fog;
+    use matrix::Matrix4;
+    use point::{point, vector};
+    use point_light::PointLight;
+    use ray::Ray;
+    use shape::Shape;
+    use std::sync::Arc;
+    use utilities::equal;
+    use world::World;
+
+    fn ray(direction_y: f64) -> Ray {
+        Ray {
+            origin: point(0., 0., 0.),
+            direction: vector(0., direction_y, 1.).normalize(),
+        }
+    }
+
+    #[test]
+    fn test_uniform_fog() {
+        let fog = Fog::new(Color::white(), 0.5);
+        let seen = Color::black();
+
+        assert_eq!(fog.apply(&ray(0.), 0., &seen), seen);
+        let faded = fog.apply(&ray(0.), 2., &seen);
+        assert!(equal(faded.red, 1. - (-1_f64).exp()));
+        assert_eq!(fog.apply(&ray(0.), f64::INFINITY, &seen), Color::white());
+    }
+
+    #[test]
+    fn test_light_shafts() {
+        let mut world = World::new();
+        world.objects = Vec::new();
+        world.light_source = Arc::new(PointLight::new(point(0., 10., 0.), Color::white()));
+        let mut fog = Fog::new(Color::black(), 0.5);
+        fog.scattering = 1.;
+        fog.steps = 256;
+
+        // All the light the fog takes out of the ray is scattered back in.
+        let lit = fog.scattered(&world, &ray(0.), f64::INFINITY);
+        assert!((lit.red - (1. - (-5_f64).exp())).abs() < 0.02);
+
+        let mut ceiling = Shape::plane();
+        Arc::get_mut(&mut ceiling)
+            .unwrap()
+            .set_transform(Matrix4::translation(0., 5., 0.))
+            .unwrap();
+        world.objects = vec![ceiling];
+        assert_eq!(
+            fog.scattered(&world, &ray(0.), f64::INFINITY),
+            Color::black()
+        );
+    }
+
+    #[test]
+    fn test_fog_with_falloff() {
+        let mut fog = Fog::new(Color::white(), 0.5);
+        fog.falloff = 1.;
+        let seen = Color::black();
+
+        let level = fog.apply(&ray(0.), 2., &seen);
+        assert!(equal(level.red, 1. - (-1_f64).exp()));
+        let climbing = fog.apply(&ray(1.), 2., &seen);
+        assert!(climbing.red < level.red);
+        let sinking = fog.apply(&ray(-1.), 2., &seen);
+        assert!(sinking.red > level.red);
+        let sky = fog.apply(&ray(1.), f64::INFINITY, &seen);
+        assert!(sky.red < 1.);
+    }
+}
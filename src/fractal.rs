@@ -0,0 +1,117 @@
+use noise::{NoiseFn, OpenSimplex, Perlin as PerlinNoise};
+use point::Point;
+
+#[derive(Debug, Clone)]
+pub enum NoiseSource {
+    Perlin(PerlinNoise),
+    Simplex(OpenSimplex),
+}
+
+impl NoiseSource {
+    pub fn perlin() -> NoiseSource {
+        NoiseSource::Perlin(PerlinNoise::new())
+    }
+
+    pub fn simplex() -> NoiseSource {
+        NoiseSource::Simplex(OpenSimplex::new())
+    }
+
+    pub fn get(&self, point: &Point) -> f64 {
+        let p = [point.x, point.y, point.z];
+        match *self {
+            NoiseSource::Perlin(ref perlin) => perlin.get(p),
+            NoiseSource::Simplex(ref simplex) => simplex.get(p),
+        }
+    }
+}
+
+// Fractal Brownian motion: octaves of a noise source, each at `lacunarity`
+// times the frequency and `gain` times the amplitude of the one before.
+// Turbulence sums the absolute values instead, giving sharp creases.
+#[derive(Debug, Clone)]
+pub struct Fbm {
+    pub gain: f64,
+    pub lacunarity: f64,
+    pub octaves: usize,
+    source: NoiseSource,
+    pub turbulence: bool,
+}
+
+impl Fbm {
+    pub fn new(source: NoiseSource) -> Fbm {
+        Fbm {
+            gain: 0.5,
+            lacunarity: 2.,
+            octaves: 4,
+            source,
+            turbulence: false,
+        }
+    }
+
+    pub fn source(&self) -> &NoiseSource {
+        &self.source
+    }
+
+    // Normalized to 0..1; plain fBm is centred on one half.
+    pub fn value_at(&self, point: &Point) -> f64 {
+        let mut total = 0.;
+        let mut total_amplitude = 0.;
+        let mut amplitude = 1.;
+        let mut frequency = 1.;
+        for _ in 0..self.octaves {
+            let noise = self.source.get(&point.multiply_scalar(frequency));
+            total += amplitude * if self.turbulence { noise.abs() } else { noise };
+            total_amplitude += amplitude;
+            amplitude *= self.gain;
+            frequency *= self.lacunarity;
+        }
+        if total_amplitude == 0. {
+            return 0.;
+        }
+        let value = total / total_amplitude;
+        if self.turbulence {
+            value.min(1.)
+        } else {
+            ((value + 1.) / 2.).clamp(0., 1.)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fractal::{Fbm, NoiseSource};
+    use point::point;
+
+    #[test]
+    fn test_fbm_range() {
+        let fbm = Fbm::new(NoiseSource::simplex());
+        let mut turbulence = Fbm::new(NoiseSource::perlin());
+        turbulence.turbulence = true;
+        for i in 0..50 {
+            let p = point(i as f64 * 0.37, i as f64 * 0.11, i as f64 * -0.23);
+            let value = fbm.value_at(&p);
+            let turbulent = turbulence.value_at(&p);
+
+            assert!((0. ..=1.).contains(&value));
+            assert!((0. ..=1.).contains(&turbulent));
+        }
+    }
+
+    #[test]
+    fn test_fbm_octaves() {
+        let mut fbm = Fbm::new(NoiseSource::simplex());
+        fbm.octaves = 1;
+        let p = point(0.3, 0.6, 0.9);
+        let single = fbm.value_at(&p);
+
+        assert_eq!(single, (NoiseSource::simplex().get(&p) + 1.) / 2.);
+
+        fbm.octaves = 6;
+
+        assert_ne!(fbm.value_at(&p), single);
+
+        fbm.octaves = 0;
+
+        assert_eq!(fbm.value_at(&p), 0.);
+    }
+}
@@ -0,0 +1,130 @@
+use canvas::Canvas;
+use color::Color;
+use point::Point;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+
+// The auxiliary values behind one pixel's first visible hit. A miss has no
+// object, a depth of zero and the background as its indirect light.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfaceSample {
+    pub albedo: Color,
+    pub depth: f64,
+    pub direct: Color,
+    pub indirect: Color,
+    pub normal: Point,
+    pub object_id: Option<usize>,
+}
+
+// One canvas per auxiliary pass, written alongside the beauty image.
+// Depth holds raw distances and normals map each axis from -1..1 to 0..1.
+pub struct GBuffer {
+    pub albedo: Canvas,
+    pub depth: Canvas,
+    pub direct: Canvas,
+    pub indirect: Canvas,
+    pub normal: Canvas,
+    pub object_id: Canvas,
+}
+
+impl GBuffer {
+    pub fn new(width: i64, height: i64) -> GBuffer {
+        GBuffer {
+            albedo: Canvas::empty(width, height),
+            depth: Canvas::empty(width, height),
+            direct: Canvas::empty(width, height),
+            indirect: Canvas::empty(width, height),
+            normal: Canvas::empty(width, height),
+            object_id: Canvas::empty(width, height),
+        }
+    }
+
+    pub fn write_sample(&mut self, column: usize, row: usize, sample: &SurfaceSample) {
+        let depth = sample.depth;
+        let normal = &sample.normal;
+        self.albedo.write_pixel(column, row, &sample.albedo);
+        self.depth
+            .write_pixel(column, row, &Color::new(depth, depth, depth));
+        self.direct.write_pixel(column, row, &sample.direct);
+        self.indirect.write_pixel(column, row, &sample.indirect);
+        self.normal.write_pixel(
+            column,
+            row,
+            &Color::new(
+                (normal.x + 1.) / 2.,
+                (normal.y + 1.) / 2.,
+                (normal.z + 1.) / 2.,
+            ),
+        );
+        let id = match sample.object_id {
+            Some(id) => id_color(id),
+            None => Color::black(),
+        };
+        self.object_id.write_pixel(column, row, &id);
+    }
+
+    // Writes each pass to `<prefix>_<pass>.ppm`, with depth scaled so the
+    // farthest hit is white.
+    pub fn save(&self, prefix: &str) -> io::Result<()> {
+        let depth = self.depth.normalized();
+        let passes = [
+            ("albedo", &self.albedo),
+            ("depth", &depth),
+            ("direct", &self.direct),
+            ("indirect", &self.indirect),
+            ("normal", &self.normal),
+            ("object_id", &self.object_id),
+        ];
+        for &(name, canvas) in passes.iter() {
+            let mut file = BufWriter::new(File::create(format!("{}_{}.ppm", prefix, name))?);
+            canvas.write_ppm_binary(&mut file)?;
+            file.flush()?;
+        }
+        Ok(())
+    }
+}
+
+// A color that tells neighbouring ids apart, from the id's digits spread
+// by the golden ratio.
+pub fn id_color(id: usize) -> Color {
+    let t = (id + 1) as f64 * 0.618_033_988_749_895;
+    Color::new(
+        0.2 + 0.8 * t.fract(),
+        0.2 + 0.8 * (t * 2.).fract(),
+        0.2 + 0.8 * (t * 3.).fract(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use color::Color;
+    use gbuffer::{id_color, GBuffer, SurfaceSample};
+    use point::vector;
+
+    #[test]
+    fn test_write_sample() {
+        let mut g = GBuffer::new(2, 1);
+        g.write_sample(
+            1,
+            0,
+            &SurfaceSample {
+                albedo: Color::new(1., 0., 0.),
+                depth: 3.5,
+                direct: Color::new(0.5, 0.5, 0.5),
+                indirect: Color::new(0.1, 0.2, 0.3),
+                normal: vector(0., 1., 0.),
+                object_id: Some(2),
+            },
+        );
+
+        assert_eq!(g.albedo.pixel_at(1, 0), Color::new(1., 0., 0.));
+        assert_eq!(g.depth.pixel_at(1, 0), Color::new(3.5, 3.5, 3.5));
+        assert_eq!(g.direct.pixel_at(1, 0), Color::new(0.5, 0.5, 0.5));
+        assert_eq!(g.indirect.pixel_at(1, 0), Color::new(0.1, 0.2, 0.3));
+        assert_eq!(g.normal.pixel_at(1, 0), Color::new(0.5, 1., 0.5));
+        assert_eq!(g.object_id.pixel_at(1, 0), id_color(2));
+        assert_eq!(g.object_id.pixel_at(0, 0), Color::black());
+        assert!(id_color(2) != id_color(3));
+    }
+}
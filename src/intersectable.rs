@@ -1,309 +1,203 @@
 use bounds::Bounds;
-use intersection::Intersection;
-use point::{bounds, point, vector, Point};
+use color::Color;
+use error::Error;
+use intersection::{FaceHit, Intersection};
+use material::Material;
+use mesh::{FaceTree, Mesh};
+use point::{vector, Point};
+use primitive::{triangle_hit, Cube, Plane, Primitive, Sphere, Triangle};
 use ray::Ray;
 use shape::Shape;
-use std::f64::INFINITY;
 use std::sync::Arc;
-use utilities::{max, min, EPSILON};
 
 #[derive(Debug, Clone)]
 pub enum IntersectableType {
     Cube,
+    // Geometry from outside the crate.
+    Custom(Arc<dyn Primitive>),
     Group,
+    // The faces, and a tree over them built once when the shape is made.
+    Mesh(Arc<Mesh>, Arc<FaceTree>),
     Plane,
     Sphere,
-    Triangle,
+    Triangle(Triangle),
 }
 
 #[derive(Debug, Clone)]
 pub struct Intersectable {
     intersectable_type: IntersectableType,
-    pub e1: Point,
-    pub e2: Point,
-    pub normal: Point,
-    pub p1: Point,
-    pub p2: Point,
-    pub p3: Point,
     children: Vec<Arc<Shape>>,
 }
 
 impl Intersectable {
-    pub fn sphere() -> Intersectable {
+    fn new(intersectable_type: IntersectableType) -> Intersectable {
         Intersectable {
             children: Vec::new(),
-            e1: point(0., 0., 0.),
-            e2: point(0., 0., 0.),
-            intersectable_type: IntersectableType::Sphere,
-            normal: point(0., 0., 0.),
-            p1: point(0., 0., 0.),
-            p2: point(0., 0., 0.),
-            p3: point(0., 0., 0.),
+            intersectable_type,
         }
     }
 
+    pub fn sphere() -> Intersectable {
+        Intersectable::new(IntersectableType::Sphere)
+    }
+
     pub fn plane() -> Intersectable {
-        Intersectable {
-            children: Vec::new(),
-            e1: point(0., 0., 0.),
-            e2: point(0., 0., 0.),
-            intersectable_type: IntersectableType::Plane,
-            normal: point(0., 0., 0.),
-            p1: point(0., 0., 0.),
-            p2: point(0., 0., 0.),
-            p3: point(0., 0., 0.),
-        }
+        Intersectable::new(IntersectableType::Plane)
     }
 
     pub fn cube() -> Intersectable {
-        Intersectable {
-            children: Vec::new(),
-            e1: point(0., 0., 0.),
-            e2: point(0., 0., 0.),
-            intersectable_type: IntersectableType::Cube,
-            normal: point(0., 0., 0.),
-            p1: point(0., 0., 0.),
-            p2: point(0., 0., 0.),
-            p3: point(0., 0., 0.),
-        }
+        Intersectable::new(IntersectableType::Cube)
     }
 
     pub fn triangle(p1: Point, p2: Point, p3: Point) -> Intersectable {
-        let e1 = p2.sub(&p1);
-        let e2 = p3.sub(&p1);
-        Intersectable {
-            children: Vec::new(),
-            p1,
-            p2,
-            p3,
-            e1,
-            e2,
-            normal: e1.cross(&e2).normalize(),
-            intersectable_type: IntersectableType::Triangle,
-        }
+        Intersectable::new(IntersectableType::Triangle(Triangle::new(p1, p2, p3)))
     }
 
     pub fn group() -> Intersectable {
-        Intersectable {
-            children: Vec::new(),
-            e1: point(0., 0., 0.),
-            e2: point(0., 0., 0.),
-            intersectable_type: IntersectableType::Group,
-            normal: point(0., 0., 0.),
-            p1: point(0., 0., 0.),
-            p2: point(0., 0., 0.),
-            p3: point(0., 0., 0.),
-        }
+        Intersectable::new(IntersectableType::Group)
+    }
+
+    pub fn primitive<P: Primitive + 'static>(primitive: P) -> Intersectable {
+        Intersectable::new(IntersectableType::Custom(Arc::new(primitive)))
+    }
+
+    pub fn mesh(mesh: Mesh) -> Intersectable {
+        let tree = FaceTree::new(&mesh);
+        Intersectable::new(IntersectableType::Mesh(Arc::new(mesh), Arc::new(tree)))
     }
 
     pub fn local_normal_at(&self, point: &Point) -> Point {
         match self.intersectable_type {
-            IntersectableType::Cube => self.local_normal_at_cube(point),
-            IntersectableType::Plane => self.local_normal_at_plane(point),
-            IntersectableType::Sphere => self.local_normal_at_sphere(point),
+            IntersectableType::Cube => Cube.local_normal_at(point),
+            IntersectableType::Custom(ref primitive) => primitive.local_normal_at(point),
+            IntersectableType::Plane => Plane.local_normal_at(point),
+            IntersectableType::Sphere => Sphere.local_normal_at(point),
+            IntersectableType::Triangle(ref triangle) => triangle.local_normal_at(point),
             _ => vector(0., 0., 0.),
         }
     }
 
-    pub fn add(&mut self, shape: Arc<Shape>) {
+    pub fn local_normal_at_face(&self, point: &Point, face: &FaceHit) -> Point {
         match self.intersectable_type {
-            IntersectableType::Group => self.add_group(shape),
-            _ => (),
+            IntersectableType::Mesh(ref mesh, _) => mesh.normal_at(face),
+            _ => self.local_normal_at(point),
         }
     }
 
-    pub fn local_intersect(&self, ray: &Ray, object: Arc<Shape>) -> Vec<Intersection> {
+    pub fn face_material(&self, face: &FaceHit) -> Option<&Material> {
         match self.intersectable_type {
-            IntersectableType::Cube => self.local_intersect_cube(ray, object),
-            IntersectableType::Sphere => self.local_intersect_sphere(ray, object),
-            IntersectableType::Plane => self.local_intersect_plane(ray, object),
-            _ => Vec::new(),
+            IntersectableType::Mesh(ref mesh, _) => mesh.material(face.index),
+            _ => None,
         }
     }
 
-    pub fn bounds(&self, shape: &Shape) -> Bounds {
+    pub fn vertex_color(&self, face: &FaceHit) -> Option<Color> {
         match self.intersectable_type {
-            IntersectableType::Cube => self.bounds_cube(shape),
-            IntersectableType::Sphere => self.bounds_sphere(shape),
-            IntersectableType::Plane => self.bounds_plane(shape),
-            _ => Bounds::new(0., 0., 0., 0., 0., 0.),
+            IntersectableType::Mesh(ref mesh, _) => mesh.vertex_color(face),
+            _ => None,
         }
     }
 
-    fn local_normal_at_sphere(&self, local_point: &Point) -> Point {
-        local_point.sub(&point(0., 0., 0.))
-    }
-
-    fn bounds_sphere(&self, _shape: &Shape) -> Bounds {
-        Bounds::new(-1.0, 1.0, -1.0, 1.0, -1.0, 1.0)
-    }
-
-    fn local_intersect_sphere(&self, ray: &Ray, object: Arc<Shape>) -> Vec<Intersection> {
-        let shape_to_ray = ray.origin.sub(&point(0., 0., 0.));
-        let a = ray.direction.dot(&ray.direction);
-        let b = ray.direction.dot(&shape_to_ray) * 2.0;
-        let c = shape_to_ray.dot(&shape_to_ray) - 1.;
-
-        let discriminant = b.powi(2) - 4.0 * a * c;
-        if discriminant < 0. {
-            Vec::new()
-        } else {
-            Intersection::intersections(
-                Intersection {
-                    t: (-b - discriminant.sqrt()) / (2.0 * a),
-                    object: object.clone(),
-                },
-                Intersection {
-                    t: (-b + discriminant.sqrt()) / (2.0 * a),
-                    object: object.clone(),
-                },
-            )
+    pub fn face_uv(&self, face: &FaceHit) -> Option<(f64, f64)> {
+        match self.intersectable_type {
+            IntersectableType::Mesh(ref mesh, _) => mesh.uv_at(face),
+            _ => None,
         }
     }
 
-    fn local_normal_at_plane(&self, _local_point: &Point) -> Point {
-        point(0., 1., 0.)
+    pub fn intersectable_type(&self) -> &IntersectableType {
+        &self.intersectable_type
     }
 
-    fn bounds_plane(&self, _shape: &Shape) -> Bounds {
-        Bounds::new(-INFINITY, INFINITY, 0.0, 0.0, -INFINITY, INFINITY)
+    pub fn children(&self) -> &[Arc<Shape>] {
+        &self.children
     }
 
-    fn local_intersect_plane(&self, ray: &Ray, object: Arc<Shape>) -> Vec<Intersection> {
-        if ray.direction.y.abs() < EPSILON {
-            return Vec::new();
-        }
-
-        vec![Intersection {
-            object: object.clone(),
-            t: -ray.origin.y / ray.direction.y,
-        }]
-    }
-
-    fn check_axis(&self, origin: f64, direction: f64) -> (f64, f64) {
-        let tmin: f64;
-        let tmax: f64;
-        let tmin_numerator = -1. - origin;
-        let tmax_numerator = 1. - origin;
-        if direction.abs() >= EPSILON {
-            tmin = tmin_numerator / direction;
-            tmax = tmax_numerator / direction;
-        } else {
-            tmin = tmin_numerator * INFINITY;
-            tmax = tmax_numerator * INFINITY;
-        }
-        if tmin > tmax {
-            (tmax, tmin)
-        } else {
-            (tmin, tmax)
-        }
+    pub fn children_mut(&mut self) -> &mut [Arc<Shape>] {
+        &mut self.children
     }
 
-    fn local_normal_at_cube(&self, local_point: &Point) -> Point {
-        let maxc = vec![
-            local_point.x.abs(),
-            local_point.y.abs(),
-            local_point.z.abs(),
-        ]
-        .iter()
-        .cloned()
-        .fold(std::f64::NAN, f64::max);
-
-        if maxc == local_point.x.abs() {
-            vector(local_point.x, 0., 0.)
-        } else if maxc == local_point.y.abs() {
-            vector(0., local_point.y, 0.)
-        } else {
-            vector(0., 0., local_point.z)
+    pub fn add(&mut self, shape: Arc<Shape>) -> Result<(), Error> {
+        match self.intersectable_type {
+            IntersectableType::Group => {
+                self.children.push(shape);
+                Ok(())
+            }
+            _ => Err(Error::NotAGroup),
         }
     }
 
-    fn bounds_cube(&self, _shape: &Shape) -> Bounds {
-        Bounds::new(-1.0, 1.0, -1.0, 1.0, -1.0, 1.0)
-    }
-
-    fn local_intersect_cube(&self, ray: &Ray, object: Arc<Shape>) -> Vec<Intersection> {
-        let (xmin, xmax) = self.check_axis(ray.origin.x, ray.direction.x);
-        let (ymin, ymax) = self.check_axis(ray.origin.y, ray.direction.y);
-        let (zmin, zmax) = self.check_axis(ray.origin.z, ray.direction.z);
-
-        let mins: Vec<f64> = vec![xmin, ymin, zmin];
-        let maxs: Vec<f64> = vec![xmax, ymax, zmax];
-
-        let tmin = max(&mins);
-        let tmax = min(&maxs);
-
-        if tmin > tmax {
-            return Vec::new();
+    pub fn local_intersect(&self, ray: &Ray, object: Arc<Shape>) -> Vec<Intersection> {
+        match self.intersectable_type {
+            IntersectableType::Cube => hits(&Cube, ray, object),
+            IntersectableType::Custom(ref primitive) => hits(&**primitive, ray, object),
+            IntersectableType::Sphere => hits(&Sphere, ray, object),
+            IntersectableType::Plane => hits(&Plane, ray, object),
+            IntersectableType::Triangle(ref triangle) => hits(triangle, ray, object),
+            IntersectableType::Mesh(ref mesh, ref tree) => {
+                self.local_intersect_mesh(mesh, tree, ray, object)
+            }
+            IntersectableType::Group => self.local_intersect_group(ray, object),
         }
+    }
 
-        vec![
-            Intersection {
-                t: tmin,
-                object: object.clone(),
-            },
-            Intersection {
-                t: tmax,
-                object: object.clone(),
-            },
-        ]
-    }
-
-    fn local_normal_at_triangle(&self, _local_point: &Point) -> Point {
-        self.normal
-    }
-
-    fn bounds_triangle(&self, _shape: &Shape) -> Bounds {
-        Bounds::new(
-            min(&[self.p1.x, self.p2.x, self.p3.x]),
-            max(&[self.p1.x, self.p2.x, self.p3.x]),
-            min(&[self.p1.y, self.p2.y, self.p3.y]),
-            max(&[self.p1.y, self.p2.y, self.p3.y]),
-            min(&[self.p1.z, self.p2.z, self.p3.z]),
-            max(&[self.p1.z, self.p2.z, self.p3.z]),
-        )
-    }
-
-    fn local_intersect_triangle(&self, ray: &Ray, object: Arc<Shape>) -> Vec<Intersection> {
-        let dir_cross_e2 = ray.direction.cross(&self.e2);
-        let det = self.e1.dot(&dir_cross_e2);
-        if det.abs() < EPSILON {
-            return Vec::new();
+    pub fn bounds(&self) -> Bounds {
+        match self.intersectable_type {
+            IntersectableType::Cube => Cube.bounds(),
+            IntersectableType::Custom(ref primitive) => primitive.bounds(),
+            IntersectableType::Sphere => Sphere.bounds(),
+            IntersectableType::Plane => Plane.bounds(),
+            IntersectableType::Triangle(ref triangle) => triangle.bounds(),
+            IntersectableType::Mesh(ref mesh, _) => mesh.bounds(),
+            IntersectableType::Group => self.bounds_group(),
         }
+    }
 
-        let f = 1. / det;
-        let p1_to_origin = ray.origin.sub(&self.p1);
-        let u = f * p1_to_origin.dot(&dir_cross_e2);
-        if u < 0. || u > 1. {
-            return Vec::new();
+    pub fn uv_at(&self, point: &Point) -> (f64, f64) {
+        match self.intersectable_type {
+            IntersectableType::Cube => Cube.uv_at(point),
+            IntersectableType::Custom(ref primitive) => primitive.uv_at(point),
+            IntersectableType::Plane => Plane.uv_at(point),
+            IntersectableType::Sphere => Sphere.uv_at(point),
+            IntersectableType::Triangle(ref triangle) => triangle.uv_at(point),
+            _ => (0., 0.),
         }
+    }
 
-        let origin_cross_e1 = p1_to_origin.cross(&self.e1);
-        let v = f * ray.direction.dot(&origin_cross_e1);
-
-        if v < 0. || (u + v) > 1. {
-            return Vec::new();
+    fn local_intersect_mesh(
+        &self,
+        mesh: &Mesh,
+        tree: &FaceTree,
+        ray: &Ray,
+        object: Arc<Shape>,
+    ) -> Vec<Intersection> {
+        let mut faces = Vec::new();
+        tree.candidates(ray, &mut faces);
+        let mut intersections = Vec::new();
+        for index in faces {
+            let (p1, p2, p3) = mesh.face_points(index);
+            if let Some((t, u, v)) = triangle_hit(&p1, &p2.sub(&p1), &p3.sub(&p1), ray) {
+                intersections.push(Intersection::with_face(
+                    t,
+                    object.clone(),
+                    FaceHit { index, u, v },
+                ));
+            }
         }
-
-        let t = f * self.e2.dot(&origin_cross_e1);
-
-        vec![Intersection {
-            object: object.clone(),
-            t,
-        }]
+        intersections
     }
 
-    fn bounds_group(&self, shape: &Shape) -> Bounds {
-        let mut child_bounds: Vec<Point> = Vec::new();
-        for child in (&self.children).iter() {
-            let bounds = child.bounds();
-            child_bounds.push(child.transform.multiply_point(&bounds.min));
-            child_bounds.push(child.transform.multiply_point(&bounds.max));
-        }
-        let mut local_bounds: Bounds = bounds(child_bounds);
-        local_bounds.min = shape.transform.inverse().multiply_point(&local_bounds.min);
-        local_bounds.max = shape.transform.inverse().multiply_point(&local_bounds.max);
-        bounds(vec![local_bounds.min, local_bounds.max])
+    // The box around every corner of every child's bounds, in the group's
+    // own space. Unbounded children, like planes, leave it unbounded.
+    fn bounds_group(&self) -> Bounds {
+        self.children
+            .iter()
+            .map(|child| child.bounds().transformed(&child.transform()))
+            .fold(None, |total: Option<Bounds>, bounds| match total {
+                Some(total) => Some(total.union(&bounds)),
+                None => Some(bounds),
+            })
+            .unwrap_or_else(|| Bounds::new(0., 0., 0., 0., 0., 0.))
     }
 
     fn local_intersect_group(&self, ray: &Ray, object: Arc<Shape>) -> Vec<Intersection> {
@@ -316,37 +210,89 @@ impl Intersectable {
         }
         intersects
     }
+}
 
-    fn add_group(&mut self, shape: Arc<Shape>) {
-        self.children.push(shape);
-    }
+fn hits(primitive: &dyn Primitive, ray: &Ray, object: Arc<Shape>) -> Vec<Intersection> {
+    primitive
+        .local_intersect(ray)
+        .into_iter()
+        .map(|t| Intersection::new(t, object.clone()))
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use intersectable::*;
-    use material::Material;
     use matrix::Matrix4;
-    use matrix::IDENTITY_MATRIX;
+    use point::point;
     use std::f64::consts::PI;
+    use utilities::equal;
 
     #[test]
     fn test_new_triangle() {
         let s = Shape::triangle(point(0., 1., 0.), point(-1., 0., 0.), point(1., 0., 0.));
 
-        assert_eq!(s.intersectable.e1, vector(-1., -1., 0.));
-        assert_eq!(s.intersectable.e2, vector(1., -1., 0.));
-        assert_eq!(s.intersectable.normal, vector(0., 0., 1.));
+        let t = match *s.intersectable.intersectable_type() {
+            IntersectableType::Triangle(ref triangle) => triangle,
+            _ => panic!("expected a triangle"),
+        };
+        assert_eq!(t.e1, vector(-1., -1., 0.));
+        assert_eq!(t.e2, vector(1., -1., 0.));
+        assert_eq!(t.normal, vector(0., 0., 1.));
+    }
+
+    #[test]
+    fn test_sphere_uv_at() {
+        let sphere = Intersectable::sphere();
+        let sqrt_2_over_2 = 2.0_f64.sqrt() / 2.;
+        let cases = vec![
+            (point(0., 0., -1.), 0.0, 0.5),
+            (point(1., 0., 0.), 0.25, 0.5),
+            (point(0., 0., 1.), 0.5, 0.5),
+            (point(-1., 0., 0.), 0.75, 0.5),
+            (point(0., 1., 0.), 0.5, 1.0),
+            (point(0., -1., 0.), 0.5, 0.0),
+            (point(sqrt_2_over_2, sqrt_2_over_2, 0.), 0.25, 0.75),
+        ];
+
+        for (p, u, v) in cases {
+            let uv = sphere.uv_at(&p);
+            assert!(equal(uv.0, u));
+            assert!(equal(uv.1, v));
+        }
+    }
+
+    #[test]
+    fn test_plane_uv_at() {
+        let plane = Intersectable::plane();
+
+        assert_eq!(plane.uv_at(&point(0.25, 0., 0.5)), (0.25, 0.5));
+        assert_eq!(plane.uv_at(&point(1.25, 0., -0.25)), (0.25, 0.75));
+    }
+
+    #[test]
+    fn test_cube_uv_at() {
+        let cube = Intersectable::cube();
+
+        assert_eq!(cube.uv_at(&point(-0.5, 0.5, 1.)), (0.25, 0.75));
+        assert_eq!(cube.uv_at(&point(0.5, -0.5, 1.)), (0.75, 0.25));
+        assert_eq!(cube.uv_at(&point(1., 0.5, -0.5)), (0.75, 0.75));
+        assert_eq!(cube.uv_at(&point(-0.5, 1., -0.5)), (0.25, 0.75));
+        assert_eq!(cube.uv_at(&point(-0.5, -1., 0.5)), (0.25, 0.75));
+    }
+
+    #[test]
+    fn test_triangle_uv_at() {
+        let t = Intersectable::triangle(point(0., 1., 0.), point(-1., 0., 0.), point(1., 0., 0.));
+
+        assert_eq!(t.uv_at(&point(0., 1., 0.)), (0., 0.));
+        assert_eq!(t.uv_at(&point(-1., 0., 0.)), (1., 0.));
+        assert_eq!(t.uv_at(&point(0., 0., 0.)), (0.5, 0.5));
     }
 
     #[test]
     fn test_group_intersect_misses() {
-        let s = Arc::new(Shape {
-            parent: None,
-            intersectable: Intersectable::group(),
-            material: Material::new(),
-            transform: IDENTITY_MATRIX,
-        });
+        let s = Arc::new(Shape::new(Intersectable::group()));
         let ray = Ray {
             origin: point(0., 0., 0.),
             direction: vector(0., 0., 1.),
@@ -355,72 +301,118 @@ mod tests {
         assert_eq!(ray.intersect(s).len(), 0);
     }
 
-    //     #[test]
-    //     fn test_group_intersect_hits() {
-    //         let mut g = Shape::group();
-    //         let s1 = Shape::sphere();
-    //         let mut s2 = Shape::sphere();
-    //         Arc::get_mut(&mut s2).unwrap().transform = Matrix4::translation(0., 0., -3.);
-    //         let mut s3 = Shape::sphere();
-    //         Arc::get_mut(&mut s3).unwrap().transform = Matrix4::translation(5., 0., 0.);
-    //         let ray = Ray {
-    //             origin: point(0., 0., -5.),
-    //             direction: vector(0., 0., 1.),
-    //         };
-
-    //         Shape::add_shape(g.clone(), s1);
-    //         Shape::add_shape(g.clone(), s2);
-    //         Shape::add_shape(g.clone(), s3);
-
-    //         assert_eq!(ray.intersect(g).len(), 4);
-    //     }
-
-    //     #[test]
-    //     fn test_group_local_to_world_space() {
-    //         let mut g1 = Shape::group();
-    //         Arc::get_mut(&mut g1).unwrap().transform = Matrix4::rotation_y(PI / 2.);
-    //         let mut g2 = Shape::group();
-    //         Arc::get_mut(&mut g2).unwrap().transform = Matrix4::scaling(2., 2., 2.);
-    //         let mut s = Shape::sphere();
-    //         Arc::get_mut(&mut s).unwrap().transform = Matrix4::translation(5., 0., 0.);
-    //         // I can't do the adding, because it consumes the shape
-    //         Arc::get_mut(&mut s).unwrap().parent = Some(g1.clone());
-    //         Shape::add_group(g2.clone(), g1);
-
-    //         assert_eq!(s.world_to_object(&point(-2., 0., -10.)), point(0., 0., -1.));
-    //     }
-
-    //     #[test]
-    //     fn test_group_local_to_world_normal() {
-    //         let mut g1 = Shape::group();
-    //         Arc::get_mut(&mut g1).unwrap().transform = Matrix4::rotation_y(PI / 2.);
-    //         let mut g2 = Shape::group();
-    //         Arc::get_mut(&mut g2).unwrap().transform = Matrix4::scaling(1., 2., 3.);
-    //         let mut s = Shape::sphere();
-    //         Arc::get_mut(&mut s).unwrap().transform = Matrix4::translation(5., 0., 0.);
-    //         // I can't do the adding, because it consumes the shape
-    //         Arc::get_mut(&mut s).unwrap().parent = Some(g2.clone());
-    //         let sqrt_3_over_3 = 3_f64.sqrt() / 3.;
-    //         let v = vector(sqrt_3_over_3, sqrt_3_over_3, sqrt_3_over_3);
-    //         Shape::add_group(g1.clone(), g2);
-
-    //         assert_eq!(s.normal_to_world(&v), vector(0.28571, 0.42857, -0.85714));
-    //     }
-
-    //     #[test]
-    //     fn test_group_normal_at_child() {
-    //         let mut g1 = Shape::group();
-    //         Arc::get_mut(&mut g1).unwrap().transform = Matrix4::rotation_y(PI / 2.);
-    //         let mut g2 = Shape::group();
-    //         Arc::get_mut(&mut g2).unwrap().transform = Matrix4::scaling(1., 2., 3.);
-    //         let mut s = Shape::sphere();
-    //         Arc::get_mut(&mut s).unwrap().transform = Matrix4::translation(5., 0., 0.);
-    //         // I can't do the adding, because it consumes the shape
-    //         Arc::get_mut(&mut s).unwrap().parent = Some(g2.clone());
-    //         let sqrt_3_over_3 = 3_f64.sqrt() / 3.;
-    //         let v = vector(sqrt_3_over_3, sqrt_3_over_3, sqrt_3_over_3);
-    //         Shape::add_group(g1.clone(), g2);
-
-    //         assert_eq!(s.normal_at(&v), vector(0.28571, 0.42857, -0.85714));
-    //     }
+    #[test]
+    fn test_group_intersect_hits() {
+        let mut group = Shape::new(Intersectable::group());
+        group.set_transform(Matrix4::scaling(2., 2., 2.)).unwrap();
+        let mut sphere = Shape::sphere();
+        Arc::get_mut(&mut sphere)
+            .unwrap()
+            .set_transform(Matrix4::translation(5., 0., 0.))
+            .unwrap();
+        group.intersectable.add(sphere).unwrap();
+        group.intersectable.add(Shape::plane()).unwrap();
+        let group = Arc::new(group);
+
+        let ray = Ray {
+            origin: point(10., 0., -10.),
+            direction: vector(0., 0., 1.),
+        };
+        let ts: Vec<f64> = ray.intersect(group.clone()).iter().map(|i| i.t).collect();
+        assert_eq!(ts, vec![8., 12.]);
+
+        let ray = Ray {
+            origin: point(0., 1., -10.),
+            direction: vector(0., -1., 1.),
+        };
+        let ts: Vec<f64> = ray.intersect(group).iter().map(|i| i.t).collect();
+        assert_eq!(ts, vec![1.]);
+    }
+
+    #[test]
+    fn test_group_intersect_hits_every_child() {
+        let mut g = Shape::new(Intersectable::group());
+        let mut s2 = Shape::new(Intersectable::sphere());
+        s2.set_transform(Matrix4::translation(0., 0., -3.)).unwrap();
+        let mut s3 = Shape::new(Intersectable::sphere());
+        s3.set_transform(Matrix4::translation(5., 0., 0.)).unwrap();
+        let ray = Ray {
+            origin: point(0., 0., -5.),
+            direction: vector(0., 0., 1.),
+        };
+
+        g.add_child(Shape::sphere()).unwrap();
+        g.add_child(s2).unwrap();
+        g.add_child(s3).unwrap();
+
+        assert_eq!(ray.intersect(g.share()).len(), 4);
+    }
+
+    #[test]
+    fn test_only_groups_take_children() {
+        let mut sphere = Shape::new(Intersectable::sphere());
+
+        match sphere.add_child(Shape::cube()) {
+            Err(Error::NotAGroup) => (),
+            _ => panic!("expected a sphere to refuse a child"),
+        }
+        assert!(sphere.intersectable.children().is_empty());
+    }
+
+    // g1 holds g2, which holds the sphere.
+    fn nested_groups(g2_transform: Matrix4) -> Arc<Shape> {
+        let mut s = Shape::new(Intersectable::sphere());
+        s.set_transform(Matrix4::translation(5., 0., 0.)).unwrap();
+        let mut g2 = Shape::new(Intersectable::group());
+        g2.set_transform(g2_transform).unwrap();
+        g2.add_child(s).unwrap();
+        let mut g1 = Shape::new(Intersectable::group());
+        g1.set_transform(Matrix4::rotation_y(PI / 2.)).unwrap();
+        g1.add_child(g2).unwrap();
+        g1.share()
+    }
+
+    #[test]
+    fn test_group_local_to_world_space() {
+        let g1 = nested_groups(Matrix4::scaling(2., 2., 2.));
+        let s = g1.descendants()[1];
+
+        assert_eq!(s.world_to_object(&point(-2., 0., -10.)), point(0., 0., -1.));
+    }
+
+    #[test]
+    fn test_group_local_to_world_normal() {
+        let g1 = nested_groups(Matrix4::scaling(1., 2., 3.));
+        let s = g1.descendants()[1];
+        let sqrt_3_over_3 = 3_f64.sqrt() / 3.;
+        let v = vector(sqrt_3_over_3, sqrt_3_over_3, sqrt_3_over_3);
+
+        assert_eq!(s.normal_to_world(&v), vector(0.28571, 0.42857, -0.85714));
+    }
+
+    #[test]
+    fn test_group_normal_at_child() {
+        let g1 = nested_groups(Matrix4::scaling(1., 2., 3.));
+        let s = g1.descendants()[1];
+
+        assert_eq!(
+            s.normal_at(&point(1.7321, 1.1547, -5.5774)),
+            vector(0.2857, 0.42854, -0.85716)
+        );
+    }
+
+    #[test]
+    fn test_sharing_a_group_leaves_shared_children_alone() {
+        let sphere = Shape::sphere();
+        let mut g = Shape::new(Intersectable::group());
+        g.set_transform(Matrix4::translation(0., 1., 0.)).unwrap();
+        g.add_child(sphere.clone()).unwrap();
+        let g = g.share();
+
+        assert!(sphere.parent.is_none());
+        assert_eq!(
+            g.descendants()[0].parent.as_ref().unwrap().transform(),
+            g.transform()
+        );
+    }
 }
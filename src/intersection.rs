@@ -1,18 +1,57 @@
+use material::Material;
 use point::Point;
 use ray::Ray;
+use serde::{Deserialize, Serialize};
 use shape::Shape;
 use std::sync::Arc;
 use utilities::EPSILON;
 
+// Which face of a mesh was hit, and where on it in barycentric terms.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FaceHit {
+    pub index: usize,
+    pub u: f64,
+    pub v: f64,
+}
+
+// What shading needs to know about a hit besides where it is: how far it
+// lies from the eye, and which mesh face it landed on.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SurfaceHit {
+    pub distance: f64,
+    pub face: Option<FaceHit>,
+}
+
+// How far over_point and under_point sit from the surface. A fixed bias
+// suits scenes near unit scale; a relative one grows with hit distance.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Bias {
+    Fixed(f64),
+    Relative(f64),
+}
+
+impl Bias {
+    pub fn offset(&self, t: f64) -> f64 {
+        match *self {
+            Bias::Fixed(offset) => offset,
+            Bias::Relative(scale) => scale * t.abs(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Intersection {
+    pub face: Option<FaceHit>,
     pub object: Arc<Shape>,
     pub t: f64,
 }
 
 #[derive(Debug, PartialEq)]
 pub struct Precompute {
+    pub bias: f64,
     pub eyev: Point,
+    pub face: Option<FaceHit>,
     pub inside: bool,
     pub normalv: Point,
     pub object: Arc<Shape>,
@@ -26,6 +65,29 @@ pub struct Precompute {
 }
 
 impl Intersection {
+    pub fn new(t: f64, object: Arc<Shape>) -> Intersection {
+        Intersection {
+            face: None,
+            object,
+            t,
+        }
+    }
+
+    pub fn with_face(t: f64, object: Arc<Shape>, face: FaceHit) -> Intersection {
+        Intersection {
+            face: Some(face),
+            object,
+            t,
+        }
+    }
+
+    // Whether the material's mask removes the surface where the ray hit it.
+    pub fn is_cut_out(&self, ray: &Ray) -> bool {
+        self.object
+            .material_at(self.face.as_ref())
+            .is_cut_out(&self.object, &ray.position(self.t))
+    }
+
     pub fn intersections(i1: Intersection, i2: Intersection) -> Vec<Intersection> {
         vec![i1, i2]
     }
@@ -46,22 +108,34 @@ impl Intersection {
     }
 
     pub fn precompute(&self, ray: &Ray, xs: Vec<Intersection>) -> Precompute {
+        self.precompute_with_bias(ray, xs, &Bias::Fixed(EPSILON))
+    }
+
+    pub fn precompute_with_bias(
+        &self,
+        ray: &Ray,
+        xs: Vec<Intersection>,
+        bias: &Bias,
+    ) -> Precompute {
+        let bias = bias.offset(self.t);
         let point = ray.position(self.t);
-        let mut normalv = self.object.normal_at(&point);
+        let mut normalv = self.object.normal_at_hit(&point, self.face.as_ref());
         let mut inside = false;
         if normalv.dot(&ray.direction.multiply_scalar(-1.)) < 0. {
             inside = true;
             normalv = normalv.multiply_scalar(-1.);
         }
         let mut precompute = Precompute {
+            bias,
             eyev: ray.direction.multiply_scalar(-1.0),
+            face: self.face,
             inside,
             n1: 1.,
             n2: 1.,
             normalv,
             object: self.object.clone(),
-            over_point: point.add(&normalv.multiply_scalar(EPSILON)),
-            under_point: point.sub(&normalv.multiply_scalar(EPSILON)),
+            over_point: point.add(&normalv.multiply_scalar(bias)),
+            under_point: point.sub(&normalv.multiply_scalar(bias)),
             point,
             reflectv: ray.direction.reflect(&normalv),
             t: self.t,
@@ -84,11 +158,7 @@ impl Intersection {
                 }
             }
             if included {
-                containers = containers
-                    .iter()
-                    .cloned()
-                    .filter(|obj| obj != &i.object)
-                    .collect()
+                containers.retain(|obj| obj != &i.object)
             } else {
                 containers.push(i.object.clone());
             }
@@ -128,8 +198,24 @@ impl Intersection {
     }
 }
 
+impl Precompute {
+    // The material of the face that was hit, which for meshes may differ from
+    // the material of the shape as a whole.
+    pub fn material(&self) -> &Material {
+        self.object.material_at(self.face.as_ref())
+    }
+
+    pub fn surface_hit(&self) -> SurfaceHit {
+        SurfaceHit {
+            distance: self.t,
+            face: self.face,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use intersection::Bias;
     use intersection::Intersection;
     use intersection::Precompute;
     use matrix::Matrix4;
@@ -144,10 +230,7 @@ mod tests {
     #[test]
     fn test_intersection() {
         let s = Shape::sphere();
-        let i = Intersection {
-            object: s.clone(),
-            t: 3.5,
-        };
+        let i = Intersection::new(3.5, s.clone());
 
         assert_eq!(i.object, s);
         assert!(equal(i.t, 3.5));
@@ -156,14 +239,8 @@ mod tests {
     #[test]
     fn test_intersections() {
         let s = Shape::sphere();
-        let i1 = Intersection {
-            object: s.clone(),
-            t: 1.0,
-        };
-        let i2 = Intersection {
-            object: s.clone(),
-            t: 2.0,
-        };
+        let i1 = Intersection::new(1.0, s.clone());
+        let i2 = Intersection::new(2.0, s.clone());
         let xs = Intersection::intersections(i1, i2);
 
         assert_eq!(xs.len(), 2);
@@ -174,14 +251,8 @@ mod tests {
     #[test]
     fn test_hits_when_all_positive() {
         let s = Shape::sphere();
-        let i1 = Intersection {
-            t: 1.0,
-            object: s.clone(),
-        };
-        let i2 = Intersection {
-            t: 2.0,
-            object: s.clone(),
-        };
+        let i1 = Intersection::new(1.0, s.clone());
+        let i2 = Intersection::new(2.0, s.clone());
 
         let hit = Intersection::hit(&mut vec![i1.clone(), i2.clone()]);
 
@@ -191,14 +262,8 @@ mod tests {
     #[test]
     fn test_hits_when_some_negative() {
         let s = Shape::sphere();
-        let i1 = Intersection {
-            t: -1.0,
-            object: s.clone(),
-        };
-        let i2 = Intersection {
-            t: 2.0,
-            object: s.clone(),
-        };
+        let i1 = Intersection::new(-1.0, s.clone());
+        let i2 = Intersection::new(2.0, s.clone());
 
         let hit = Intersection::hit(&mut vec![i1.clone(), i2.clone()]);
 
@@ -208,14 +273,8 @@ mod tests {
     #[test]
     fn test_hits_when_all_negative() {
         let s = Shape::sphere();
-        let i1 = Intersection {
-            t: -1.0,
-            object: s.clone(),
-        };
-        let i2 = Intersection {
-            t: -2.0,
-            object: s.clone(),
-        };
+        let i1 = Intersection::new(-1.0, s.clone());
+        let i2 = Intersection::new(-2.0, s.clone());
 
         let hit = Intersection::hit(&mut vec![i1.clone(), i2.clone()]);
 
@@ -229,17 +288,16 @@ mod tests {
             direction: vector(0.0, 0.0, 1.0),
         };
         let shape = Shape::sphere();
-        let i = Intersection {
-            object: shape.clone(),
-            t: 4.0,
-        };
+        let i = Intersection::new(4.0, shape.clone());
 
         let precompute = i.precompute(&r, Vec::new());
 
         assert_eq!(
             precompute,
             Precompute {
+                bias: EPSILON,
                 eyev: vector(0.0, 0.0, -1.0),
+                face: None,
                 reflectv: vector(0.0, 0.0, -1.0),
                 inside: false,
                 n1: 1.,
@@ -261,17 +319,16 @@ mod tests {
             direction: vector(0.0, 0.0, 1.0),
         };
         let shape = Shape::sphere();
-        let i = Intersection {
-            object: shape.clone(),
-            t: 1.0,
-        };
+        let i = Intersection::new(1.0, shape.clone());
 
         let precompute = i.precompute(&r, Vec::new());
 
         assert_eq!(
             precompute,
             Precompute {
+                bias: EPSILON,
                 eyev: vector(0.0, 0.0, -1.0),
+                face: None,
                 inside: true,
                 n1: 1.,
                 n2: 1.,
@@ -293,11 +350,11 @@ mod tests {
             direction: vector(0.0, 0.0, 1.0),
         };
         let mut shape = Shape::sphere();
-        Arc::get_mut(&mut shape).unwrap().transform = Matrix4::translation(0.0, 0.0, 1.0);
-        let i = Intersection {
-            object: shape,
-            t: 5.0,
-        };
+        Arc::get_mut(&mut shape)
+            .unwrap()
+            .set_transform(Matrix4::translation(0.0, 0.0, 1.0))
+            .unwrap();
+        let i = Intersection::new(5.0, shape);
 
         let precompute = i.precompute(&r, Vec::new());
 
@@ -313,10 +370,7 @@ mod tests {
             origin: point(0.0, 1.0, -1.0),
             direction: vector(0.0, -sqrt_two_over_two, sqrt_two_over_two),
         };
-        let i = Intersection {
-            object: shape,
-            t: 5.0,
-        };
+        let i = Intersection::new(5.0, shape);
 
         let precompute = i.precompute(&r, Vec::new());
 
@@ -324,4 +378,19 @@ mod tests {
             .reflectv
             .equal(&vector(0.0, sqrt_two_over_two, sqrt_two_over_two)));
     }
+
+    #[test]
+    fn test_precompute_with_relative_bias() {
+        let r = Ray {
+            origin: point(0.0, 0.0, -101.0),
+            direction: vector(0.0, 0.0, 1.0),
+        };
+        let i = Intersection::new(100.0, Shape::sphere());
+
+        let precompute = i.precompute_with_bias(&r, Vec::new(), &Bias::Relative(0.001));
+
+        assert_eq!(precompute.bias, 0.1);
+        assert!(precompute.over_point.equal(&point(0.0, 0.0, -1.1)));
+        assert!(precompute.under_point.equal(&point(0.0, 0.0, -0.9)));
+    }
 }
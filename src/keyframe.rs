@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+
+// How a value eases from one keyframe to the next, given the fraction of
+// the way through that span.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Curve {
+    EaseIn,
+    EaseInOut,
+    EaseOut,
+    #[default]
+    Linear,
+    // Holds the value until the next keyframe.
+    Step,
+}
+
+impl Curve {
+    pub fn apply(self, t: f64) -> f64 {
+        match self {
+            Curve::EaseIn => t * t,
+            Curve::EaseInOut => t * t * (3. - 2. * t),
+            Curve::EaseOut => t * (2. - t),
+            Curve::Linear => t,
+            Curve::Step => 0.,
+        }
+    }
+}
+
+pub trait Lerp {
+    fn lerp(&self, other: &Self, t: f64) -> Self;
+}
+
+impl Lerp for f64 {
+    fn lerp(&self, other: &f64, t: f64) -> f64 {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for [f64; 3] {
+    fn lerp(&self, other: &[f64; 3], t: f64) -> [f64; 3] {
+        [
+            self[0].lerp(&other[0], t),
+            self[1].lerp(&other[1], t),
+            self[2].lerp(&other[2], t),
+        ]
+    }
+}
+
+// A value at one frame. Its curve shapes the span up to the next keyframe.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Keyframe<T> {
+    #[serde(default)]
+    pub curve: Curve,
+    pub frame: f64,
+    #[serde(flatten)]
+    pub value: T,
+}
+
+// The value at `frame` of keyframes listed in order. Before the first and
+// after the last, the nearest keyframe's value holds.
+pub fn sample<T: Clone + Lerp>(keyframes: &[Keyframe<T>], frame: f64) -> Option<T> {
+    let next = keyframes.iter().position(|key| key.frame > frame);
+    match next {
+        None => keyframes.last().map(|key| key.value.clone()),
+        Some(0) => Some(keyframes[0].value.clone()),
+        Some(i) => {
+            let (from, to) = (&keyframes[i - 1], &keyframes[i]);
+            let t = (frame - from.frame) / (to.frame - from.frame);
+            Some(from.value.lerp(&to.value, from.curve.apply(t)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use keyframe::*;
+
+    fn key(frame: f64, value: f64, curve: Curve) -> Keyframe<f64> {
+        Keyframe {
+            curve,
+            frame,
+            value,
+        }
+    }
+
+    #[test]
+    fn test_sample() {
+        let keys = vec![
+            key(1., 0., Curve::Linear),
+            key(5., 4., Curve::Step),
+            key(9., 0., Curve::Linear),
+        ];
+
+        assert_eq!(sample(&keys, 0.), Some(0.));
+        assert_eq!(sample(&keys, 2.), Some(1.));
+        assert_eq!(sample(&keys, 5.), Some(4.));
+        assert_eq!(sample(&keys, 8.), Some(4.));
+        assert_eq!(sample(&keys, 9.), Some(0.));
+        assert_eq!(sample(&keys, 20.), Some(0.));
+        assert_eq!(sample::<f64>(&[], 1.), None);
+    }
+
+    #[test]
+    fn test_curves() {
+        for curve in &[
+            Curve::EaseIn,
+            Curve::EaseInOut,
+            Curve::EaseOut,
+            Curve::Linear,
+        ] {
+            assert_eq!(curve.apply(0.), 0.);
+            assert_eq!(curve.apply(1.), 1.);
+        }
+        assert_eq!(Curve::EaseIn.apply(0.5), 0.25);
+        assert_eq!(Curve::EaseOut.apply(0.5), 0.75);
+        assert_eq!(Curve::EaseInOut.apply(0.5), 0.5);
+        assert_eq!(Curve::Step.apply(0.99), 0.);
+    }
+}
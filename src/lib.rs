@@ -0,0 +1,74 @@
+// Everything the renderer does, for use from other crates. The
+// `ray_tracer` binary is a command line front end to it. Built with the
+// `wasm` feature instead of the default ones, it runs in a web page:
+// load a scene with `World::from_json` and draw `Camera::render_to_rgba`
+// into a canvas element.
+
+// pyo3's macros refer to `::core`, which this edition looks for at the
+// crate root.
+#[cfg(feature = "python")]
+extern crate core;
+extern crate image;
+#[cfg(feature = "preview")]
+extern crate minifb;
+extern crate noise;
+extern crate png;
+#[cfg(feature = "python")]
+extern crate pyo3;
+extern crate rand;
+extern crate rayon;
+extern crate serde;
+extern crate serde_json;
+extern crate toml;
+
+pub mod ambient_occlusion;
+pub mod animation;
+pub mod background;
+pub mod bidirectional;
+pub mod bounds;
+pub mod bsdf;
+pub mod camera;
+pub mod canvas;
+pub mod color;
+pub mod denoise;
+pub mod environment;
+pub mod error;
+pub mod ffi;
+pub mod float;
+pub mod fog;
+pub mod fractal;
+pub mod gbuffer;
+pub mod intersectable;
+pub mod intersection;
+pub mod keyframe;
+pub mod light;
+pub mod material;
+pub mod matrix;
+pub mod mesh;
+pub mod mtl_parser;
+pub mod obj_parser;
+pub mod obj_writer;
+pub mod path_tracer;
+pub mod pattern;
+pub mod patternable;
+pub mod point;
+pub mod point_light;
+#[cfg(feature = "preview")]
+pub mod preview;
+pub mod primitive;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod ray;
+pub mod sampling;
+pub mod scene;
+pub mod settings;
+pub mod shape;
+#[cfg(test)]
+mod test_support;
+pub mod texture;
+pub mod tile_file;
+pub mod transformation_matrix;
+pub mod utilities;
+pub mod uv_mapping;
+pub mod volume;
+pub mod world;
@@ -0,0 +1,120 @@
+use color::Color;
+use point::Point;
+use ray::Ray;
+use sampling::{random, square_to_sphere};
+use shape::Shape;
+use std::any::Any;
+
+// What shading needs to know about a light, so materials, the world and the
+// integrators don't depend on how any one kind of light is laid out.
+// `PointLight` is the only one so far; area, spot and directional lights
+// fit in here. A light without an area can't be seen or hit, only sampled.
+pub trait Light: Any + Send + Sync {
+    // Where the light is as a whole, for what treats it as a single point:
+    // the sun in a sky, and light scattered by fog and volumes.
+    fn position(&self) -> Point;
+
+    // Where to aim shadow rays from a point being lit. Their visibility is
+    // averaged, so more than one gives soft shadows.
+    fn sample_points(&self) -> Vec<Point>;
+
+    // The light arriving at a point, before anything shades it.
+    fn intensity_at(&self, point: &Point) -> Color;
+
+    // The normalized direction from a point towards the light.
+    fn direction_from(&self, point: &Point) -> Point;
+
+    // Whether the light shines on a shape at all.
+    fn illuminates(&self, _shape: &Shape) -> bool {
+        true
+    }
+
+    // Whether a shape in the way blocks the light.
+    fn casts_shadow(&self, _shape: &Shape) -> bool {
+        true
+    }
+
+    // The area of the light's surface, which the path tracers share the
+    // light's intensity over.
+    fn area(&self) -> f64 {
+        0.
+    }
+
+    // The light leaving each point of the surface.
+    fn radiance(&self) -> Color {
+        Color::black()
+    }
+
+    // How far along the ray it meets the light's surface from outside.
+    fn intersect(&self, _ray: &Ray) -> Option<f64> {
+        None
+    }
+
+    // The outward normal at a point on the light's surface.
+    fn normal_at(&self, point: &Point) -> Point {
+        point.sub(&self.position()).normalize()
+    }
+
+    // A point spread uniformly over the light's surface, and the normal
+    // there. Without a surface, the normal is any direction at all.
+    fn sample_surface(&self) -> (Point, Point) {
+        (self.position(), square_to_sphere((random(), random())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use color::Color;
+    use intersection::SurfaceHit;
+    use light::Light;
+    use material::Material;
+    use point::{point, vector, Point};
+    use shape::Shape;
+
+    // Light from straight above, as if from a sun, that only the floor
+    // casts shadows from.
+    struct Overhead;
+
+    impl Light for Overhead {
+        fn position(&self) -> Point {
+            point(0., 1000., 0.)
+        }
+
+        fn sample_points(&self) -> Vec<Point> {
+            vec![point(0., 1000., 0.)]
+        }
+
+        fn intensity_at(&self, _point: &Point) -> Color {
+            Color::new(0.5, 0.5, 0.5)
+        }
+
+        fn direction_from(&self, _point: &Point) -> Point {
+            vector(0., 1., 0.)
+        }
+
+        fn casts_shadow(&self, shape: &Shape) -> bool {
+            shape.is_named("floor")
+        }
+    }
+
+    #[test]
+    fn test_custom_light() {
+        let object = Shape::sphere();
+        let position = point(0., 1., 0.);
+        let normal = vector(0., 1., 0.);
+
+        let lit = Material::new().lighting(
+            &Overhead,
+            &position,
+            &normal,
+            &normal,
+            1.,
+            1.,
+            &SurfaceHit::default(),
+            &object,
+        );
+
+        assert_eq!(lit, Color::new(0.95, 0.95, 0.95));
+        assert!(!Overhead.casts_shadow(&object));
+    }
+}
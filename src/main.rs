@@ -1,125 +1,403 @@
 extern crate chrono;
-extern crate noise;
+extern crate clap;
+extern crate ray_tracer;
 extern crate rayon;
+extern crate serde_json;
 
-use camera::Camera;
 use chrono::prelude::*;
-use color::Color;
-use dof::Dof;
-use material::Material;
-use matrix::Matrix4;
-// use obj_parser::ObjParser;
-use patternable::*;
-use point::point;
-use point_light::PointLight;
-use shape::Shape;
-use std::f64::consts::PI;
-use std::fs::File;
-use std::io::prelude::*;
-use std::sync::Arc;
-use transformation_matrix::TransformationMatrix;
-use world::World;
-
-mod bounds;
-mod camera;
-mod canvas;
-mod color;
-mod dof;
-mod intersectable;
-mod intersection;
-mod material;
-mod matrix;
-// mod obj_parser;
-mod patternable;
-mod point;
-mod point_light;
-mod ray;
-mod shape;
-mod transformation_matrix;
-mod utilities;
-mod world;
-
-fn main() -> std::io::Result<()> {
-    let mut world = World::new();
-    world.objects = Vec::new();
-    world.light_source = PointLight {
-        intensity: Color::new(1.0, 1.0, 1.0),
-        position: point(0.0, 10.0, 0.0),
+use clap::{Args, Parser, Subcommand};
+use ray_tracer::camera::Camera;
+#[cfg(not(feature = "preview"))]
+use ray_tracer::camera::Progress;
+use ray_tracer::canvas::Canvas;
+use ray_tracer::color::Encoding;
+use ray_tracer::obj_writer::ObjWriter;
+#[cfg(feature = "preview")]
+use ray_tracer::preview;
+use ray_tracer::scene::SceneDescription;
+use ray_tracer::settings::{Format, RenderSettings};
+use ray_tracer::tile_file::TileFile;
+use ray_tracer::world::{RenderMode, World};
+use std::error::Error;
+use std::fs::{self, File};
+#[cfg(not(feature = "preview"))]
+use std::io::{self, IsTerminal};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+// Pixels along each side of the tiles written by `--tiles`.
+const TILE_SIZE: usize = 32;
+
+#[derive(Parser)]
+#[command(about = "Renders scenes described in JSON")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Render a scene to a PNG or binary PPM image
+    Render(RenderArgs),
+    /// Write the shapes of a scene as a Wavefront OBJ
+    Export(ExportArgs),
+}
+
+#[derive(Args)]
+struct ExportArgs {
+    /// Scene file, see `World::from_json` for the format
+    scene: PathBuf,
+    /// OBJ file to write
+    #[arg(short, long)]
+    output: PathBuf,
+    /// Slices around each sphere
+    #[arg(long, default_value_t = 32)]
+    segments: usize,
+    /// Width of the square written for each plane
+    #[arg(long, default_value_t = 100.)]
+    plane_size: f64,
+}
+
+// Anything left unset keeps the value from the settings file, or failing
+// that the scene file.
+#[derive(Args)]
+struct RenderArgs {
+    /// Scene file, see `World::from_json` for the format
+    scene: PathBuf,
+    /// Render settings in TOML, see `RenderSettings` for the keys
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+    /// Image to write, as PNG or PPM by its extension [default: output/<time>.ppm]
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Image width in pixels
+    #[arg(long)]
+    width: Option<usize>,
+    /// Image height in pixels
+    #[arg(long)]
+    height: Option<usize>,
+    /// Samples per pixel
+    #[arg(long)]
+    samples: Option<usize>,
+    /// Worker threads [default: one per core]
+    #[arg(long)]
+    threads: Option<usize>,
+    /// Maximum reflection and refraction bounces
+    #[arg(long)]
+    depth: Option<i32>,
+    /// Maximum reflections on any one path, within the depth
+    #[arg(long)]
+    reflection_depth: Option<i32>,
+    /// Maximum refractions on any one path, within the depth
+    #[arg(long)]
+    refraction_depth: Option<i32>,
+    /// Show depth, heat, normals, object_id or uv in false color instead of shading
+    #[arg(long, value_parser = render_mode)]
+    mode: Option<RenderMode>,
+    /// Seed the random numbers so the same image comes out every time
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Render at a quarter of the size, with one sample and two bounces
+    #[arg(long)]
+    draft: bool,
+    /// Render again whenever the scene or a file it uses changes
+    #[arg(long)]
+    watch: bool,
+    /// Render every frame of the scene's animation into this folder, as
+    /// frame_0001.png and so on
+    #[arg(long, value_name = "DIR")]
+    frames: Option<PathBuf>,
+    /// Render from one of the scene's named cameras; repeat for several
+    #[arg(long = "camera", value_name = "NAME")]
+    cameras: Vec<String>,
+    /// Render from the main camera and every named one
+    #[arg(long, conflicts_with = "cameras")]
+    all_cameras: bool,
+    /// Write each tile into this PPM as it finishes, so a render that dies
+    /// part way leaves a partial image
+    #[arg(long, value_name = "PATH")]
+    tiles: Option<PathBuf>,
+}
+
+fn main() {
+    let result = match Cli::parse().command {
+        Command::Render(args) => render(args),
+        Command::Export(args) => export(args),
     };
+    if let Err(error) = result {
+        eprintln!("error: {}", error);
+        process::exit(1);
+    }
+}
+
+impl RenderArgs {
+    fn settings(&self) -> Result<RenderSettings, Box<dyn Error>> {
+        let file = match self.config {
+            Some(ref config) => RenderSettings::load(config)?,
+            None => RenderSettings::default(),
+        };
+        Ok(file.overridden_by(RenderSettings {
+            depth: self.depth,
+            height: self.height,
+            mode: self.mode,
+            output: self.output.clone(),
+            reflection_depth: self.reflection_depth,
+            refraction_depth: self.refraction_depth,
+            samples: self.samples,
+            seed: self.seed,
+            threads: self.threads,
+            tiles: self.tiles.clone(),
+            width: self.width,
+            ..RenderSettings::default()
+        }))
+    }
+}
 
-    let mut sphere = Shape::sphere();
-    Arc::get_mut(&mut sphere).unwrap().transform =
-        Matrix4::translation(0., 0.25, 0.).multiply(&Matrix4::scaling(0.3, 0.3, 0.3));
-    let mut sphere2 = Shape::sphere();
-    Arc::get_mut(&mut sphere2).unwrap().transform = Matrix4::translation(0., 0.25, 2.)
-        .multiply(&Matrix4::scaling(0.3, 0.3, 0.3))
-        .multiply(&Matrix4::rotation_y(PI / 2.));
-
-    let mut sphere3 = Shape::sphere();
-    Arc::get_mut(&mut sphere3).unwrap().transform = Matrix4::translation(0.75, 0.25, 0.)
-        .multiply(&Matrix4::scaling(0.3, 0.3, 0.3))
-        .multiply(&Matrix4::rotation_y(-PI / 2.));
-    let mut floor = Shape::plane();
-    let mut wall = Shape::plane();
-    Arc::get_mut(&mut wall).unwrap().transform =
-        Matrix4::translation(0., 0., 4.).multiply(&Matrix4::rotation_x(PI / 2.));
-    Arc::get_mut(&mut floor).unwrap().transform = Matrix4::translation(0., -0.01, 0.);
-
-    let mut sphere_material = Material::new();
-    sphere_material.reflective = 1.0;
-    sphere_material.transparency = 1.0;
-    sphere_material.refractive_index = 1.2;
-    sphere_material.ambient = 0.1;
-    sphere_material.diffuse = 0.1;
-    sphere_material.shininess = 300.;
-    sphere_material.specular = 1.;
-    let mut floor_material = Material::new();
-    floor_material.reflective = 0.0;
-    let mut checker = Patternable::checker(Color::new(0.2, 0.4, 0.9), Color::white());
-    checker.transform = Matrix4::scaling(0.4, 0.4, 0.4);
-    floor_material.pattern = checker;
-
-    let mut pattern = Patternable::solid(Color::white());
-    let white = Patternable::solid(Color::white());
-    // let gradient = Patternable::gradient(Color::new(0.9, 0.1, 0.1), Color::white());
-    // let mut perlin = Patternable::perlin(gradient);
-    // perlin.perlin_factor = 32.0;
-    sphere_material.pattern = pattern.clone();
-    pattern.transform = Matrix4::scaling(0.25, 0.25, 0.25);
-
-    Arc::get_mut(&mut sphere).unwrap().material = sphere_material.clone();
-    Arc::get_mut(&mut sphere2).unwrap().material = sphere_material.clone();
-    Arc::get_mut(&mut sphere3).unwrap().material = sphere_material.clone();
-    Arc::get_mut(&mut floor).unwrap().material = floor_material.clone();
-    Arc::get_mut(&mut wall).unwrap().material = floor_material.clone();
-
-    world.objects.push(sphere);
-    // world.objects.push(sphere2);
-    // world.objects.push(sphere3);
-    world.objects.push(floor);
-    world.objects.push(wall);
-
-    let mut camera = Camera::new(700, 700, PI / 6.);
-    let from = point(0., 2., 0.);
-    let to = point(0., 0.2, 0.0);
-    let up = point(0., 0., 1.);
-    camera.transform = TransformationMatrix::new(&from, &to, &up);
-
-    let now = Local::now();
-    let filename = format!("output/{}.ppm", now.format("%Y-%m-%d_%H-%M-%S"));
-    let mut file = File::create(filename)?;
-
-    let mut dof = Dof {
-        camera,
-        canvases: Vec::new(),
-        from,
-        takes: 1,
-        to,
-        up,
+fn render(args: RenderArgs) -> Result<(), Box<dyn Error>> {
+    let settings = args.settings()?;
+    if let Some(threads) = settings.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()?;
+    }
+    let output = settings.output.clone().unwrap_or_else(|| {
+        let now = Local::now();
+        PathBuf::from(format!("output/{}.ppm", now.format("%Y-%m-%d_%H-%M-%S")))
+    });
+    if !args.watch {
+        return render_once(&args, &settings, &output).map(|_| ());
+    }
+
+    // Until a render succeeds only the scene itself is known to matter.
+    let mut files = vec![args.scene.clone()];
+    loop {
+        let mut stamps = modified(&files);
+        match render_once(&args, &settings, &output) {
+            Ok(dependencies) => {
+                println!(
+                    "Wrote {}",
+                    args.frames.as_ref().unwrap_or(&output).display()
+                );
+                if dependencies != files {
+                    files = dependencies;
+                    stamps = modified(&files);
+                }
+            }
+            // A broken scene is usually mid-edit, so wait for the next save.
+            Err(error) => eprintln!("{}", error),
+        }
+        while modified(&files) == stamps {
+            thread::sleep(Duration::from_millis(250));
+        }
+    }
+}
+
+// Renders the scene to `output`, or each frame of it into the `--frames`
+// folder, and returns the files it was made from. Each frame's world is
+// built once and shared by every camera.
+fn render_once(
+    args: &RenderArgs,
+    settings: &RenderSettings,
+    output: &Path,
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut description = SceneDescription::load(&args.scene)?;
+    settings.apply_to_scene(&mut description);
+    if args.draft {
+        for camera in description.cameras_mut() {
+            camera.width = (camera.width / 4).max(1);
+            camera.height = (camera.height / 4).max(1);
+            camera.samples = 1;
+        }
+    }
+    let views: Vec<Option<&str>> = if args.all_cameras {
+        Some(None)
+            .into_iter()
+            .chain(description.cameras.keys().map(|name| Some(name.as_str())))
+            .collect()
+    } else if args.cameras.is_empty() {
+        vec![None]
+    } else {
+        let mut views = Vec::new();
+        for name in &args.cameras {
+            if !views.contains(&Some(name.as_str())) {
+                views.push(Some(name.as_str()));
+            }
+        }
+        views
+    };
+    // A still shows the first frame of an animated scene.
+    let frames = match (&args.frames, &description.animation) {
+        (Some(_), Some(animation)) => animation.frames,
+        _ => 1,
     };
 
-    let canvas = dof.render(&world);
+    for frame in 1..=frames {
+        let mut world = description.build_world()?;
+        settings.apply_to_world(&mut world);
+        if let Some(ref animation) = description.animation {
+            animation.apply_to_world(frame, &mut world)?;
+        }
+        for &view in &views {
+            let mut camera = description.build_camera(view)?;
+            if let Some(ref animation) = description.animation {
+                animation.apply_to_camera(frame, view, &mut camera)?;
+            }
+            settings.apply_to_camera(&mut camera);
+            if args.draft {
+                camera.depth = camera.depth.min(2);
+            }
+            // With several cameras, each named one gets its own file.
+            let name = view.filter(|_| views.len() > 1);
+            let tiles = settings
+                .tiles
+                .as_ref()
+                .map(|tiles| name.map_or_else(|| tiles.clone(), |name| named(tiles, name)));
+            let tiles = tiles.as_deref();
+            match args.frames {
+                Some(ref dir) => {
+                    let dir = name.map_or_else(|| dir.clone(), |name| dir.join(name));
+                    let path = dir.join(format!("frame_{:04}.png", frame));
+                    render_image(&camera, &world, settings, &path, tiles, Format::Png)?;
+                    println!("Wrote {}", path.display());
+                }
+                None => {
+                    let path =
+                        name.map_or_else(|| output.to_path_buf(), |name| named(output, name));
+                    let format = settings.format(output);
+                    render_image(&camera, &world, settings, &path, tiles, format)?;
+                }
+            }
+        }
+    }
+    Ok(description.dependencies())
+}
+
+fn render_image(
+    camera: &Camera,
+    world: &World,
+    settings: &RenderSettings,
+    output: &Path,
+    tiles: Option<&Path>,
+    format: Format,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = BufWriter::new(File::create(output)?);
 
-    file.write_all(&canvas.render_ppm().into_bytes())?;
+    let mut canvas = match tiles {
+        Some(tiles) => render_to_tile_file(camera, world, tiles, settings.encoding())?,
+        #[cfg(feature = "preview")]
+        None => preview::render(camera, world)?,
+        #[cfg(not(feature = "preview"))]
+        None => render_with_progress_bar(camera, world),
+    };
+    canvas.bit_depth = settings.bit_depth();
+    canvas.encoding = settings.encoding();
+
+    match format {
+        Format::Png => canvas.write_png(&mut file)?,
+        Format::Ppm => canvas.write_ppm_binary(&mut file)?,
+    }
+    file.flush()?;
+    Ok(())
+}
+
+// Tiles are written with the camera's exposure but before anything that
+// needs the whole image, like auto exposure, which only the returned
+// canvas gets.
+fn render_to_tile_file(
+    camera: &Camera,
+    world: &World,
+    path: &Path,
+    encoding: Encoding,
+) -> Result<Canvas, Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut tiles = TileFile::create(path, camera.hsize as i64, camera.vsize as i64, encoding)?;
+    let mut failed = None;
+    let canvas = camera.render_tiles(world, TILE_SIZE, |tile, x, y| {
+        if failed.is_none() {
+            failed = tiles.write_tile(tile, x, y).err();
+        }
+    });
+    match failed {
+        Some(error) => Err(error.into()),
+        None => Ok(canvas),
+    }
+}
+
+// Draws the progress over itself on stderr, unless that isn't a terminal.
+#[cfg(not(feature = "preview"))]
+fn render_with_progress_bar(camera: &Camera, world: &World) -> Canvas {
+    if !io::stderr().is_terminal() {
+        return camera.render(world);
+    }
+    let canvas = camera.render_with_progress(world, |progress| {
+        eprint!("\r{}", progress_bar(progress));
+    });
+    eprintln!();
+    canvas
+}
+
+#[cfg(not(feature = "preview"))]
+fn progress_bar(progress: &Progress) -> String {
+    let width = 40;
+    let filled = (progress.fraction() * width as f64).round() as usize;
+    let eta = match progress.remaining() {
+        Some(remaining) => {
+            let seconds = remaining.as_secs();
+            format!("{:02}:{:02}", seconds / 60, seconds % 60)
+        }
+        None => String::from("--:--"),
+    };
+    format!(
+        "[{}{}] {:3.0}% ETA {}",
+        "#".repeat(filled),
+        ".".repeat(width - filled),
+        progress.fraction() * 100.,
+        eta
+    )
+}
+
+fn render_mode(name: &str) -> Result<RenderMode, String> {
+    serde_json::from_value(serde_json::Value::String(name.to_string()))
+        .map_err(|_| format!("unknown render mode `{}`", name))
+}
+
+// `out.png` seen from the camera `top` is written to `out_top.png`.
+fn named(output: &Path, name: &str) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let mut file = format!("{}_{}", stem, name);
+    if let Some(extension) = output.extension() {
+        file = format!("{}.{}", file, extension.to_string_lossy());
+    }
+    output.with_file_name(file)
+}
+
+fn export(args: ExportArgs) -> Result<(), Box<dyn Error>> {
+    let (world, _) = World::from_file(&args.scene)?;
+    let mut writer = ObjWriter::new();
+    writer.plane_size = args.plane_size;
+    writer.segments = args.segments;
+    let mut file = BufWriter::new(File::create(&args.output)?);
+    writer.write(&world, &mut file)?;
+    file.flush()?;
     Ok(())
 }
+
+// Files that cannot be read count as unchanged until they reappear.
+fn modified(files: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    files
+        .iter()
+        .map(|file| {
+            fs::metadata(file)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+        .collect()
+}
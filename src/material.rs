@@ -1,93 +1,483 @@
 use color::Color;
+use intersection::SurfaceHit;
+use light::Light;
 use patternable::Patternable;
 use point::Point;
-use point_light::PointLight;
+use serde::{Deserialize, Serialize};
 use shape::Shape;
+use std::borrow::Cow;
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+use texture::Texture;
 use utilities::equal;
+use volume::Volume;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backface {
+    Cull,
+    Inside(Color),
+    Shade,
+}
+
+// Where a surface is solid. Points whose opacity is below one half are cut
+// away; a pattern gives opacity by its red channel, a texture by its alpha.
+#[derive(Debug, Clone)]
+pub enum Mask {
+    Pattern(Patternable),
+    Texture(Arc<Texture>),
+}
+
+// How the highlight is computed: from the reflected light vector (Phong)
+// or from the half vector between light and eye (Blinn-Phong).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpecularModel {
+    BlinnPhong,
+    Phong,
+}
+
+// A material property that is either the same everywhere or read from a
+// pattern's red channel at each point.
+#[derive(Debug, Clone)]
+pub enum ChannelValue {
+    Constant(f64),
+    Pattern(Patternable),
+}
+
+impl ChannelValue {
+    pub fn at(&self, object: &Shape, position: &Point) -> f64 {
+        match *self {
+            ChannelValue::Constant(value) => value,
+            ChannelValue::Pattern(ref pattern) => pattern.color_at_object(object, position).red,
+        }
+    }
+
+    // Patterns are never considered equal, as they can't be compared.
+    pub fn equal(&self, other: &ChannelValue) -> bool {
+        match (self, other) {
+            (&ChannelValue::Constant(a), &ChannelValue::Constant(b)) => equal(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl From<f64> for ChannelValue {
+    fn from(value: f64) -> ChannelValue {
+        ChannelValue::Constant(value)
+    }
+}
+
+impl From<Patternable> for ChannelValue {
+    fn from(pattern: Patternable) -> ChannelValue {
+        ChannelValue::Pattern(pattern)
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Material {
     pub ambient: f64,
+    pub backface: Backface,
+    pub clearcoat: f64,
+    pub clearcoat_roughness: f64,
     pub diffuse: f64,
+    pub mask: Option<Mask>,
     pub pattern: Patternable,
-    pub reflective: f64,
+    pub reflective: ChannelValue,
     pub refractive_index: f64,
-    pub shininess: f64,
+    pub shininess: ChannelValue,
     pub specular: f64,
-    pub transparency: f64,
+    pub specular_model: SpecularModel,
+    pub texture: Option<Arc<Texture>>,
+    pub transparency: ChannelValue,
+    // Fills the shape with a medium in place of shading its surface.
+    pub volume: Option<Volume>,
 }
 
 impl Material {
     pub fn new() -> Material {
         Material {
             ambient: 0.1,
+            backface: Backface::Shade,
+            clearcoat: 0.,
+            clearcoat_roughness: 0.,
             diffuse: 0.9,
-            shininess: 200.,
+            mask: None,
+            shininess: ChannelValue::Constant(200.),
             specular: 0.9,
+            specular_model: SpecularModel::Phong,
             pattern: Patternable::solid(Color::white()),
-            reflective: 0.,
-            transparency: 0.,
+            reflective: ChannelValue::Constant(0.),
+            texture: None,
+            transparency: ChannelValue::Constant(0.),
             refractive_index: 1.,
+            volume: None,
+        }
+    }
+
+    // Starts from the defaults of `new`.
+    pub fn builder() -> MaterialBuilder {
+        MaterialBuilder {
+            material: Material::new(),
         }
     }
 
     pub fn equal(&self, other: &Material) -> bool {
         equal(self.ambient, other.ambient)
             && equal(self.diffuse, other.diffuse)
-            && equal(self.shininess, other.shininess)
+            && self.shininess.equal(&other.shininess)
             && equal(self.specular, other.specular)
     }
 
+    // The material to shade a hit with, depending on which side was hit.
+    pub fn for_side(&self, inside: bool) -> Cow<'_, Material> {
+        match self.backface {
+            Backface::Inside(color) if inside => {
+                let mut material = self.clone();
+                material.pattern = Patternable::solid(color);
+                material.texture = None;
+                Cow::Owned(material)
+            }
+            _ => Cow::Borrowed(self),
+        }
+    }
+
+    // Schlick's approximation for a lacquer layer with a refractive index of 1.5,
+    // scaled by the strength of the coat.
+    pub fn clearcoat_reflectance(&self, eye: &Point, normal: &Point) -> f64 {
+        if self.clearcoat == 0. {
+            return 0.;
+        }
+        let r0 = ((1. - 1.5) / (1. + 1.5_f64)).powi(2);
+        let cos = eye.dot(normal).max(0.);
+        self.clearcoat * (r0 + (1. - r0) * (1. - cos).powi(5))
+    }
+
+    // Phong exponent equivalent to the coat's roughness.
+    pub fn clearcoat_shininess(&self) -> f64 {
+        2. / self.clearcoat_roughness.max(0.01).powi(2) - 2.
+    }
+
+    pub fn is_cut_out(&self, object: &Shape, position: &Point) -> bool {
+        let opacity = match self.mask {
+            Some(Mask::Pattern(ref pattern)) => pattern.color_at_object(object, position).red,
+            Some(Mask::Texture(ref texture)) => {
+                let (u, v) = object.uv_at(position);
+                texture.alpha_at(u, v)
+            }
+            None => return false,
+        };
+        opacity < 0.5
+    }
+
+    // The surface color before lighting, from the texture if there is one.
+    pub fn color_at(&self, object: &Shape, position: &Point) -> Color {
+        self.color_at_hit(object, position, &SurfaceHit::default())
+    }
+
+    pub fn color_at_hit(&self, object: &Shape, position: &Point, hit: &SurfaceHit) -> Color {
+        match self.texture {
+            Some(ref texture) => {
+                let (u, v) = object.uv_at(position);
+                texture.color_at(u, v)
+            }
+            None => self.pattern.color_at_hit(object, position, hit),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn lighting(
         &self,
-        light: &PointLight,
+        light: &dyn Light,
         position: &Point,
         eye: &Point,
         normal: &Point,
-        in_shadow: bool,
+        light_visibility: f64,
+        ambient_visibility: f64,
+        hit: &SurfaceHit,
         object: &Shape,
     ) -> Color {
         let mut diffuse: Color = Color::black();
         let mut specular: Color = Color::black();
 
-        let color = self.pattern.color_at_object(&object, &position);
-        let effective_color = color.hadamard_product(&light.intensity);
-        let ambient = effective_color.multiply_scalar(self.ambient);
-        let lightv = light.position.sub(position).normalize();
+        let color = self.color_at_hit(object, position, hit);
+        let intensity = light.intensity_at(position);
+        let effective_color = color.hadamard_product(&intensity);
+        let ambient = effective_color
+            .multiply_scalar(self.ambient)
+            .multiply_scalar(ambient_visibility);
+        let lightv = light.direction_from(position);
         let light_dot_normal = lightv.dot(normal);
         if light_dot_normal >= 0.0 {
             diffuse = effective_color
                 .multiply_scalar(self.diffuse)
                 .multiply_scalar(light_dot_normal);
-            let reflectv = lightv.multiply_scalar(-1.0).reflect(normal);
-            let reflect_dot_eye = reflectv.dot(eye);
-            if reflect_dot_eye > 0.0 {
-                let factor = reflect_dot_eye.powf(self.shininess);
-                specular = light
-                    .intensity
+            let highlight = match self.specular_model {
+                SpecularModel::BlinnPhong => lightv.add(eye).normalize().dot(normal),
+                SpecularModel::Phong => lightv.multiply_scalar(-1.0).reflect(normal).dot(eye),
+            };
+            if highlight > 0.0 {
+                let factor = highlight.powf(self.shininess.at(object, position));
+                specular = intensity
                     .multiply_scalar(self.specular)
                     .multiply_scalar(factor);
             }
         }
-        if in_shadow {
-            ambient
-        } else {
-            ambient.add(&diffuse).add(&specular)
+        ambient.add(&diffuse.add(&specular).multiply_scalar(light_visibility))
+    }
+}
+
+impl Default for Material {
+    fn default() -> Material {
+        Material::new()
+    }
+}
+
+// Sets up a material one property at a time, checking when it's built
+// that each is in the range it makes sense in.
+#[derive(Debug, Clone)]
+pub struct MaterialBuilder {
+    material: Material,
+}
+
+impl MaterialBuilder {
+    pub fn ambient(mut self, ambient: f64) -> MaterialBuilder {
+        self.material.ambient = ambient;
+        self
+    }
+
+    pub fn backface(mut self, backface: Backface) -> MaterialBuilder {
+        self.material.backface = backface;
+        self
+    }
+
+    pub fn clearcoat(mut self, clearcoat: f64) -> MaterialBuilder {
+        self.material.clearcoat = clearcoat;
+        self
+    }
+
+    pub fn clearcoat_roughness(mut self, roughness: f64) -> MaterialBuilder {
+        self.material.clearcoat_roughness = roughness;
+        self
+    }
+
+    // A solid color, in place of the pattern.
+    pub fn color(self, color: Color) -> MaterialBuilder {
+        self.pattern(Patternable::solid(color))
+    }
+
+    pub fn diffuse(mut self, diffuse: f64) -> MaterialBuilder {
+        self.material.diffuse = diffuse;
+        self
+    }
+
+    pub fn mask(mut self, mask: Mask) -> MaterialBuilder {
+        self.material.mask = Some(mask);
+        self
+    }
+
+    pub fn pattern(mut self, pattern: Patternable) -> MaterialBuilder {
+        self.material.pattern = pattern;
+        self
+    }
+
+    pub fn reflective<V: Into<ChannelValue>>(mut self, reflective: V) -> MaterialBuilder {
+        self.material.reflective = reflective.into();
+        self
+    }
+
+    pub fn refractive_index(mut self, refractive_index: f64) -> MaterialBuilder {
+        self.material.refractive_index = refractive_index;
+        self
+    }
+
+    pub fn shininess<V: Into<ChannelValue>>(mut self, shininess: V) -> MaterialBuilder {
+        self.material.shininess = shininess.into();
+        self
+    }
+
+    pub fn specular(mut self, specular: f64) -> MaterialBuilder {
+        self.material.specular = specular;
+        self
+    }
+
+    pub fn specular_model(mut self, model: SpecularModel) -> MaterialBuilder {
+        self.material.specular_model = model;
+        self
+    }
+
+    pub fn texture(mut self, texture: Arc<Texture>) -> MaterialBuilder {
+        self.material.texture = Some(texture);
+        self
+    }
+
+    pub fn transparency<V: Into<ChannelValue>>(mut self, transparency: V) -> MaterialBuilder {
+        self.material.transparency = transparency.into();
+        self
+    }
+
+    pub fn volume(mut self, volume: Volume) -> MaterialBuilder {
+        self.material.volume = Some(volume);
+        self
+    }
+
+    // Values read from a pattern can't be checked ahead of time.
+    pub fn build(self) -> Result<Material, MaterialError> {
+        let material = self.material;
+        let constant = |value: &ChannelValue| match *value {
+            ChannelValue::Constant(value) => Some(value),
+            ChannelValue::Pattern(_) => None,
+        };
+        let fractions = [
+            ("clearcoat", Some(material.clearcoat)),
+            ("clearcoat_roughness", Some(material.clearcoat_roughness)),
+            ("reflective", constant(&material.reflective)),
+            ("transparency", constant(&material.transparency)),
+        ];
+        for &(property, value) in fractions.iter() {
+            check(property, value, 0., 1.)?;
+        }
+        let amounts = [
+            ("ambient", Some(material.ambient)),
+            ("diffuse", Some(material.diffuse)),
+            ("shininess", constant(&material.shininess)),
+            ("specular", Some(material.specular)),
+        ];
+        for &(property, value) in amounts.iter() {
+            check(property, value, 0., f64::INFINITY)?;
         }
+        check(
+            "refractive_index",
+            Some(material.refractive_index),
+            1.,
+            f64::INFINITY,
+        )?;
+        Ok(material)
+    }
+}
+
+fn check(
+    property: &'static str,
+    value: Option<f64>,
+    min: f64,
+    max: f64,
+) -> Result<(), MaterialError> {
+    match value {
+        Some(value) if !(value >= min && value <= max) => Err(MaterialError::OutOfRange {
+            property,
+            value,
+            min,
+            max,
+        }),
+        _ => Ok(()),
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum MaterialError {
+    OutOfRange {
+        property: &'static str,
+        value: f64,
+        min: f64,
+        max: f64,
+    },
+}
+
+impl fmt::Display for MaterialError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MaterialError::OutOfRange {
+                property,
+                value,
+                min,
+                max,
+            } if max.is_infinite() => write!(
+                f,
+                "material {} must be at least {}, not {}",
+                property, min, value
+            ),
+            MaterialError::OutOfRange {
+                property,
+                value,
+                min,
+                max,
+            } => write!(
+                f,
+                "material {} must be from {} to {}, not {}",
+                property, min, max, value
+            ),
+        }
+    }
+}
+
+impl error::Error for MaterialError {}
+
 #[cfg(test)]
 mod tests {
     use color::Color;
+    use intersection::SurfaceHit;
+    use material::Backface;
+    use material::ChannelValue;
+    use material::Mask;
     use material::Material;
+    use material::MaterialError;
+    use material::SpecularModel;
     use patternable::Patternable;
     use point::point;
     use point::vector;
     use point_light::PointLight;
     use shape::Shape;
+    use std::f64::consts::PI;
+    use std::sync::Arc;
+    use texture::Texture;
     use utilities::equal;
 
+    #[test]
+    fn test_material_builder() {
+        let m = Material::builder()
+            .color(Color::new(0.2, 0.4, 0.6))
+            .reflective(0.7)
+            .transparency(0.8)
+            .refractive_index(1.5)
+            .build()
+            .unwrap();
+
+        assert!(m.reflective.equal(&ChannelValue::Constant(0.7)));
+        assert!(m.transparency.equal(&ChannelValue::Constant(0.8)));
+        assert_eq!(m.refractive_index, 1.5);
+        assert!(m.equal(&Material::new()));
+        let object = Shape::sphere();
+        assert_eq!(
+            m.color_at(&object, &point(0., 0., 0.)),
+            Color::new(0.2, 0.4, 0.6)
+        );
+    }
+
+    #[test]
+    fn test_material_builder_checks_ranges() {
+        let error = Material::builder().transparency(1.2).build().unwrap_err();
+        assert_eq!(
+            error,
+            MaterialError::OutOfRange {
+                property: "transparency",
+                value: 1.2,
+                min: 0.,
+                max: 1.,
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "material transparency must be from 0 to 1, not 1.2"
+        );
+        assert!(Material::builder().diffuse(-0.1).build().is_err());
+        assert!(Material::builder().refractive_index(0.).build().is_err());
+        assert!(Material::builder().reflective(f64::NAN).build().is_err());
+        assert!(Material::builder()
+            .transparency(Patternable::solid(Color::white()))
+            .build()
+            .is_ok());
+    }
+
     #[test]
     fn test_default_material() {
         let m = Material::new();
@@ -95,8 +485,71 @@ mod tests {
         assert!(equal(m.ambient, 0.1));
         assert!(equal(m.diffuse, 0.9));
         assert!(equal(m.specular, 0.9));
-        assert!(equal(m.shininess, 200.0));
-        assert!(equal(m.reflective, 0.0));
+        assert!(m.shininess.equal(&ChannelValue::Constant(200.0)));
+        assert!(m.reflective.equal(&ChannelValue::Constant(0.0)));
+        assert!(equal(m.clearcoat, 0.0));
+    }
+
+    #[test]
+    fn test_material_for_side() {
+        let mut m = Material::new();
+        m.ambient = 1.0;
+        m.diffuse = 0.0;
+        m.specular = 0.0;
+        m.backface = Backface::Inside(Color::new(1., 0., 0.));
+        let object = Shape::sphere();
+        let eyev = vector(0.0, 0.0, -1.0);
+        let normalv = vector(0.0, 0.0, -1.0);
+        let light = PointLight::new(point(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        let position = point(0.0, 0.0, -1.0);
+
+        let outside = m.for_side(false).lighting(
+            &light,
+            &position,
+            &eyev,
+            &normalv,
+            1.,
+            1.,
+            &SurfaceHit::default(),
+            &object,
+        );
+        let inside = m.for_side(true).lighting(
+            &light,
+            &position,
+            &eyev,
+            &normalv,
+            1.,
+            1.,
+            &SurfaceHit::default(),
+            &object,
+        );
+
+        assert_eq!(outside, Color::white());
+        assert_eq!(inside, Color::new(1., 0., 0.));
+    }
+
+    #[test]
+    fn test_clearcoat_reflectance() {
+        let mut m = Material::new();
+        let normalv = vector(0., 0., -1.);
+
+        assert_eq!(m.clearcoat_reflectance(&vector(0., 0., -1.), &normalv), 0.);
+
+        m.clearcoat = 1.;
+        assert!(equal(
+            m.clearcoat_reflectance(&vector(0., 0., -1.), &normalv),
+            0.04
+        ));
+        assert!(equal(
+            m.clearcoat_reflectance(&vector(1., 0., 0.), &normalv),
+            1.
+        ));
+
+        m.clearcoat = 0.5;
+        assert!(equal(
+            m.clearcoat_reflectance(&vector(0., 0., -1.), &normalv),
+            0.02
+        ));
     }
 
     #[test]
@@ -104,13 +557,19 @@ mod tests {
         let object = Shape::sphere();
         let eyev = vector(0.0, 0.0, -1.0);
         let normalv = vector(0.0, 0.0, -1.0);
-        let light = PointLight {
-            intensity: Color::new(1.0, 1.0, 1.0),
-            position: point(0.0, 0.0, -10.0),
-        };
+        let light = PointLight::new(point(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
         let position = point(0.0, 0.0, 0.0);
 
-        let result = Material::new().lighting(&light, &position, &eyev, &normalv, false, &object);
+        let result = Material::new().lighting(
+            &light,
+            &position,
+            &eyev,
+            &normalv,
+            1.,
+            1.,
+            &SurfaceHit::default(),
+            &object,
+        );
 
         assert_eq!(result, Color::new(1.9, 1.9, 1.9));
     }
@@ -121,13 +580,19 @@ mod tests {
         let sqrt_2_over_2 = 2.0_f64.sqrt() / 2.0;
         let eyev = vector(0.0, sqrt_2_over_2, sqrt_2_over_2);
         let normalv = vector(0.0, 0.0, -1.0);
-        let light = PointLight {
-            intensity: Color::new(1.0, 1.0, 1.0),
-            position: point(0.0, 0.0, -10.0),
-        };
+        let light = PointLight::new(point(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
         let position = point(0.0, 0.0, 0.0);
 
-        let result = Material::new().lighting(&light, &position, &eyev, &normalv, false, &object);
+        let result = Material::new().lighting(
+            &light,
+            &position,
+            &eyev,
+            &normalv,
+            1.,
+            1.,
+            &SurfaceHit::default(),
+            &object,
+        );
 
         assert_eq!(result, Color::new(1.0, 1.0, 1.0));
     }
@@ -137,13 +602,19 @@ mod tests {
         let object = Shape::sphere();
         let eyev = vector(0.0, 0.0, -1.0);
         let normalv = vector(0.0, 0.0, -1.0);
-        let light = PointLight {
-            intensity: Color::new(1.0, 1.0, 1.0),
-            position: point(0.0, 10.0, -10.0),
-        };
+        let light = PointLight::new(point(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
         let position = point(0.0, 0.0, 0.0);
 
-        let result = Material::new().lighting(&light, &position, &eyev, &normalv, false, &object);
+        let result = Material::new().lighting(
+            &light,
+            &position,
+            &eyev,
+            &normalv,
+            1.,
+            1.,
+            &SurfaceHit::default(),
+            &object,
+        );
 
         assert_eq!(result, Color::new(0.7364, 0.7364, 0.7364));
     }
@@ -154,13 +625,19 @@ mod tests {
         let sqrt_2_over_2 = 2.0_f64.sqrt() / 2.0;
         let eyev = vector(0.0, -sqrt_2_over_2, -sqrt_2_over_2);
         let normalv = vector(0.0, 0.0, -1.0);
-        let light = PointLight {
-            intensity: Color::new(1.0, 1.0, 1.0),
-            position: point(0.0, 10.0, -10.0),
-        };
+        let light = PointLight::new(point(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
         let position = point(0.0, 0.0, 0.0);
 
-        let result = Material::new().lighting(&light, &position, &eyev, &normalv, false, &object);
+        let result = Material::new().lighting(
+            &light,
+            &position,
+            &eyev,
+            &normalv,
+            1.,
+            1.,
+            &SurfaceHit::default(),
+            &object,
+        );
 
         assert_eq!(result, Color::new(1.6364, 1.6364, 1.6364));
     }
@@ -170,33 +647,130 @@ mod tests {
         let object = Shape::sphere();
         let eyev = vector(0.0, 0.0, -1.0);
         let normalv = vector(0.0, 0.0, -1.0);
-        let light = PointLight {
-            intensity: Color::new(1.0, 1.0, 1.0),
-            position: point(0.0, 0.0, 10.0),
-        };
+        let light = PointLight::new(point(0.0, 0.0, 10.0), Color::new(1.0, 1.0, 1.0));
         let position = point(0.0, 0.0, 0.0);
 
-        let result = Material::new().lighting(&light, &position, &eyev, &normalv, false, &object);
+        let result = Material::new().lighting(
+            &light,
+            &position,
+            &eyev,
+            &normalv,
+            1.,
+            1.,
+            &SurfaceHit::default(),
+            &object,
+        );
 
         assert_eq!(result, Color::new(0.1, 0.1, 0.1));
     }
 
+    #[test]
+    fn test_channel_value() {
+        let object = Shape::sphere();
+        let constant = ChannelValue::from(0.25);
+        let pattern = ChannelValue::Pattern(Patternable::stripe(
+            Color::new(0.8, 0., 0.),
+            Color::new(0.1, 1., 1.),
+        ));
+
+        assert_eq!(constant.at(&object, &point(5., 0., 0.)), 0.25);
+        assert_eq!(pattern.at(&object, &point(0.5, 0., 0.)), 0.8);
+        assert_eq!(pattern.at(&object, &point(1.5, 0., 0.)), 0.1);
+        assert!(constant.equal(&ChannelValue::Constant(0.25)));
+        assert!(!pattern.equal(&pattern.clone()));
+    }
+
+    #[test]
+    fn test_lighting_with_blinn_phong() {
+        let object = Shape::sphere();
+        let mut m = Material::new();
+        m.specular_model = SpecularModel::BlinnPhong;
+        m.shininess = ChannelValue::Constant(10.);
+        let position = point(0.0, 0.0, 0.0);
+        let sqrt_2_over_2 = 2.0_f64.sqrt() / 2.0;
+        let normalv = vector(0.0, 0.0, -1.0);
+        let light = PointLight::new(point(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
+
+        // Looking straight down the normal the two models agree.
+        let eyev = vector(0.0, 0.0, -1.0);
+        let result = m.lighting(
+            &light,
+            &position,
+            &eyev,
+            &normalv,
+            1.,
+            1.,
+            &SurfaceHit::default(),
+            &object,
+        );
+
+        assert_eq!(result, Color::new(1.9, 1.9, 1.9));
+
+        // Off axis, the half vector is 22.5 degrees from the normal, while
+        // Phong's reflection is 45 degrees from the eye.
+        let eyev = vector(0.0, sqrt_2_over_2, -sqrt_2_over_2);
+        let result = m.lighting(
+            &light,
+            &position,
+            &eyev,
+            &normalv,
+            1.,
+            1.,
+            &SurfaceHit::default(),
+            &object,
+        );
+        let factor = (PI / 8.).cos().powf(10.);
+
+        assert_eq!(
+            result,
+            Color::new(1.0 + 0.9 * factor, 1.0 + 0.9 * factor, 1.0 + 0.9 * factor)
+        );
+    }
+
     #[test]
     fn test_lighting_with_the_surface_in_shadow() {
         let object = Shape::sphere();
         let eyev = vector(0.0, 0.0, -1.0);
         let normalv = vector(0.0, 0.0, -1.0);
-        let light = PointLight {
-            intensity: Color::new(1.0, 1.0, 1.0),
-            position: point(0.0, 0.0, -10.0),
-        };
+        let light = PointLight::new(point(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
         let position = point(0.0, 0.0, 0.0);
 
-        let result = Material::new().lighting(&light, &position, &eyev, &normalv, true, &object);
+        let result = Material::new().lighting(
+            &light,
+            &position,
+            &eyev,
+            &normalv,
+            0.,
+            1.,
+            &SurfaceHit::default(),
+            &object,
+        );
 
         assert_eq!(result, Color::new(0.1, 0.1, 0.1));
     }
 
+    #[test]
+    fn test_lighting_with_ambient_occlusion() {
+        let object = Shape::sphere();
+        let eyev = vector(0.0, 0.0, -1.0);
+        let normalv = vector(0.0, 0.0, -1.0);
+        let light = PointLight::new(point(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        let position = point(0.0, 0.0, 0.0);
+
+        let result = Material::new().lighting(
+            &light,
+            &position,
+            &eyev,
+            &normalv,
+            0.,
+            0.5,
+            &SurfaceHit::default(),
+            &object,
+        );
+
+        assert_eq!(result, Color::new(0.05, 0.05, 0.05));
+    }
+
     #[test]
     fn test_lighting_with_pattern() {
         let object = Shape::sphere();
@@ -207,16 +781,15 @@ mod tests {
         m.specular = 0.0;
         let eyev = vector(0.0, 0.0, -1.0);
         let normalv = vector(0.0, 0.0, -1.0);
-        let light = PointLight {
-            intensity: Color::new(1.0, 1.0, 1.0),
-            position: point(0.0, 0.0, -10.0),
-        };
+        let light = PointLight::new(point(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
         let c1 = m.lighting(
             &light,
             &point(0.9, 0.0, 0.0),
             &eyev,
             &normalv,
-            false,
+            1.,
+            1.,
+            &SurfaceHit::default(),
             &object,
         );
         let c2 = m.lighting(
@@ -224,11 +797,75 @@ mod tests {
             &point(1.1, 0.0, 0.0),
             &eyev,
             &normalv,
-            false,
+            1.,
+            1.,
+            &SurfaceHit::default(),
             &object,
         );
 
         assert_eq!(c1, Color::black());
         assert_eq!(c2, Color::white());
     }
+
+    #[test]
+    fn test_lighting_with_texture() {
+        let object = Shape::sphere();
+        let mut m = Material::new();
+        m.texture = Some(Arc::new(Texture::new(
+            2,
+            1,
+            vec![Color::new(1., 0., 0.), Color::new(0., 0., 1.)],
+        )));
+        m.ambient = 1.0;
+        m.diffuse = 0.0;
+        m.specular = 0.0;
+        let eyev = vector(0.0, 0.0, -1.0);
+        let normalv = vector(0.0, 0.0, -1.0);
+        let light = PointLight::new(point(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
+        let c1 = m.lighting(
+            &light,
+            &point(0.0, 0.0, -1.0),
+            &eyev,
+            &normalv,
+            1.,
+            1.,
+            &SurfaceHit::default(),
+            &object,
+        );
+        let c2 = m.lighting(
+            &light,
+            &point(0.0, 0.0, 1.0),
+            &eyev,
+            &normalv,
+            1.,
+            1.,
+            &SurfaceHit::default(),
+            &object,
+        );
+
+        assert_eq!(c1, Color::new(1., 0., 0.));
+        assert_eq!(c2, Color::new(0., 0., 1.));
+    }
+
+    #[test]
+    fn test_material_mask() {
+        let object = Shape::plane();
+        let mut m = Material::new();
+
+        assert!(!m.is_cut_out(&object, &point(0.5, 0., 0.)));
+
+        m.mask = Some(Mask::Pattern(Patternable::stripe(
+            Color::white(),
+            Color::black(),
+        )));
+
+        assert!(!m.is_cut_out(&object, &point(0.5, 0., 0.)));
+        assert!(m.is_cut_out(&object, &point(1.5, 0., 0.)));
+
+        let texture = Texture::new(2, 1, vec![Color::white(), Color::white()]);
+        m.mask = Some(Mask::Texture(Arc::new(texture.with_alpha(vec![0., 1.]))));
+
+        assert!(m.is_cut_out(&object, &point(0.1, 0., 0.5)));
+        assert!(!m.is_cut_out(&object, &point(0.9, 0., 0.5)));
+    }
 }
@@ -1,12 +1,21 @@
-use point::empty_point;
-use point::Point;
+use error::Error;
+use float::Float;
+use point::GenericPoint;
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops;
 use utilities::equal;
 
+// A transform over any `Float`. The rest of the crate works in f64
+// through `Matrix4`.
 #[derive(PartialEq, Copy, Clone, Debug)]
-pub struct Matrix4 {
-    members: [[f64; 4]; 4],
+pub struct GenericMatrix4<T> {
+    members: [[T; 4]; 4],
 }
 
+pub type Matrix4 = GenericMatrix4<f64>;
+pub type Matrix4F32 = GenericMatrix4<f32>;
+
 pub const IDENTITY_MATRIX: Matrix4 = Matrix4 {
     members: [
         [1., 0., 0., 0.],
@@ -16,18 +25,73 @@ pub const IDENTITY_MATRIX: Matrix4 = Matrix4 {
     ],
 };
 
-impl Matrix4 {
-    pub fn new(members: [[f64; 4]; 4]) -> Matrix4 {
-        Matrix4 { members: members }
+impl<T: Float> GenericMatrix4<T> {
+    pub fn new(members: [[T; 4]; 4]) -> GenericMatrix4<T> {
+        GenericMatrix4 { members }
+    }
+
+    pub fn empty() -> GenericMatrix4<T> {
+        GenericMatrix4 {
+            members: [[T::ZERO; 4]; 4],
+        }
+    }
+
+    // `IDENTITY_MATRIX` at any precision.
+    pub fn identity() -> GenericMatrix4<T> {
+        let mut result = GenericMatrix4::empty();
+        for i in 0..4 {
+            result.members[i][i] = T::ONE;
+        }
+        result
     }
 
-    pub fn empty() -> Matrix4 {
-        Matrix4 {
-            members: [[0.; 4]; 4],
+    // The same transform at another precision.
+    pub fn cast<U: Float>(&self) -> GenericMatrix4<U> {
+        let mut result = GenericMatrix4::empty();
+        for row in 0..4 {
+            for col in 0..4 {
+                result.members[row][col] = U::from_f64(self.members[row][col].to_f64());
+            }
         }
+        result
+    }
+
+    pub fn members(&self) -> [[T; 4]; 4] {
+        self.members
+    }
+
+    pub fn from_rows(rows: [[T; 4]; 4]) -> GenericMatrix4<T> {
+        GenericMatrix4 { members: rows }
+    }
+
+    // The members row by row, as `from_rows` takes them.
+    pub fn to_array(&self) -> [[T; 4]; 4] {
+        self.members
+    }
+
+    pub fn row(&self, row: usize) -> [T; 4] {
+        self.members[row]
+    }
+
+    pub fn col(&self, col: usize) -> [T; 4] {
+        [
+            self.members[0][col],
+            self.members[1][col],
+            self.members[2][col],
+            self.members[3][col],
+        ]
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = [T; 4]> {
+        IntoIterator::into_iter(self.members)
+    }
+
+    pub fn cols(&self) -> impl Iterator<Item = [T; 4]> {
+        let matrix = *self;
+        (0..4).map(move |col| matrix.col(col))
     }
 
-    pub fn equal(&self, other: &Matrix4) -> bool {
+    pub fn equal(&self, other: &GenericMatrix4<T>) -> bool {
         for x in 0..4 {
             for y in 0..4 {
                 if !equal(self.members[x][y], other.members[x][y]) {
@@ -38,8 +102,8 @@ impl Matrix4 {
         true
     }
 
-    pub fn multiply(&self, other: &Matrix4) -> Matrix4 {
-        let mut result = Matrix4::empty();
+    pub fn multiply(&self, other: &GenericMatrix4<T>) -> GenericMatrix4<T> {
+        let mut result = GenericMatrix4::empty();
         for row in 0..4 {
             for col in 0..4 {
                 result.members[row][col] = self.members[row][0] * other.members[0][col]
@@ -51,8 +115,8 @@ impl Matrix4 {
         result
     }
 
-    pub fn multiply_point(&self, point: &Point) -> Point {
-        let mut result = empty_point();
+    pub fn multiply_point(&self, point: &GenericPoint<T>) -> GenericPoint<T> {
+        let mut result = GenericPoint::new(T::ZERO, T::ZERO, T::ZERO, T::ZERO);
         result.x = self.members[0][0] * point.x
             + self.members[0][1] * point.y
             + self.members[0][2] * point.z
@@ -72,8 +136,8 @@ impl Matrix4 {
         result
     }
 
-    pub fn transpose(&self) -> Matrix4 {
-        let mut result = Matrix4::empty();
+    pub fn transpose(&self) -> GenericMatrix4<T> {
+        let mut result = GenericMatrix4::empty();
         for x in 0..4 {
             for y in 0..4 {
                 result.members[x][y] = self.members[y][x];
@@ -82,8 +146,8 @@ impl Matrix4 {
         result
     }
 
-    pub fn submatrix(&self, not_col: usize, not_row: usize) -> Matrix3 {
-        let mut result = Matrix3::empty();
+    pub fn submatrix(&self, not_col: usize, not_row: usize) -> GenericMatrix3<T> {
+        let mut result = GenericMatrix3::empty();
         let mut y = 0;
         for row in 0..4 {
             let mut x = 0;
@@ -100,67 +164,79 @@ impl Matrix4 {
         result
     }
 
-    pub fn minor(&self, col: usize, row: usize) -> f64 {
+    pub fn minor(&self, col: usize, row: usize) -> T {
         self.submatrix(col, row).determinant()
     }
 
-    pub fn cofactor(&self, col: usize, row: usize) -> f64 {
+    pub fn cofactor(&self, col: usize, row: usize) -> T {
         let minor = self.minor(col, row);
-        if (col + row) % 2 == 0 {
+        if (col + row).is_multiple_of(2) {
             minor
         } else {
-            minor * -1.
+            -minor
         }
     }
 
-    pub fn determinant(&self) -> f64 {
-        let mut result = 0.;
+    pub fn determinant(&self) -> T {
+        let mut result = T::ZERO;
         for i in 0..4 {
             result += self.members[0][i] * self.cofactor(0, i);
         }
         result
     }
 
+    // Neither a zero determinant nor a NaN one, as a view from a point at
+    // itself gives, has an inverse.
     pub fn invertible(&self) -> bool {
-        self.determinant() != 0.
+        self.determinant().abs() > T::ZERO
     }
 
-    pub fn inverse(&self) -> Matrix4 {
-        if !self.invertible() {
-            panic!("Matrix is not invertible");
+    // Panics on a matrix with no inverse. Transforms coming from outside,
+    // like a scene file, should go through `try_inverse` first, as the
+    // `set_transform` of shapes and cameras does.
+    pub fn inverse(&self) -> GenericMatrix4<T> {
+        match self.try_inverse() {
+            Ok(inverse) => inverse,
+            Err(error) => panic!("{}", error),
         }
+    }
 
-        let mut result = Matrix4::empty();
+    pub fn try_inverse(&self) -> Result<GenericMatrix4<T>, Error> {
         let determinant = self.determinant();
+        if determinant.abs().partial_cmp(&T::ZERO) != Some(Ordering::Greater) {
+            return Err(Error::InvalidMatrix);
+        }
+
+        let mut result = GenericMatrix4::empty();
 
         for row in 0..4 {
             for col in 0..4 {
-                let mut cofactor = self.cofactor(col, row);
+                let cofactor = self.cofactor(col, row);
                 result.members[row][col] = cofactor / determinant;
             }
         }
 
-        result
+        Ok(result)
     }
 
-    pub fn translation(x: f64, y: f64, z: f64) -> Matrix4 {
-        let mut result = IDENTITY_MATRIX;
+    pub fn translation(x: T, y: T, z: T) -> GenericMatrix4<T> {
+        let mut result = GenericMatrix4::identity();
         result.members[0][3] = x;
         result.members[1][3] = y;
         result.members[2][3] = z;
         result
     }
 
-    pub fn scaling(x: f64, y: f64, z: f64) -> Matrix4 {
-        let mut result = IDENTITY_MATRIX;
+    pub fn scaling(x: T, y: T, z: T) -> GenericMatrix4<T> {
+        let mut result = GenericMatrix4::identity();
         result.members[0][0] = x;
         result.members[1][1] = y;
         result.members[2][2] = z;
         result
     }
 
-    pub fn rotation_x(radians: f64) -> Matrix4 {
-        let mut result = IDENTITY_MATRIX;
+    pub fn rotation_x(radians: T) -> GenericMatrix4<T> {
+        let mut result = GenericMatrix4::identity();
         result.members[1][1] = radians.cos();
         result.members[1][2] = -radians.sin();
         result.members[2][1] = radians.sin();
@@ -168,8 +244,8 @@ impl Matrix4 {
         result
     }
 
-    pub fn rotation_y(radians: f64) -> Matrix4 {
-        let mut result = IDENTITY_MATRIX;
+    pub fn rotation_y(radians: T) -> GenericMatrix4<T> {
+        let mut result = GenericMatrix4::identity();
         result.members[0][0] = radians.cos();
         result.members[0][2] = radians.sin();
         result.members[2][0] = -radians.sin();
@@ -177,8 +253,8 @@ impl Matrix4 {
         result
     }
 
-    pub fn rotation_z(radians: f64) -> Matrix4 {
-        let mut result = IDENTITY_MATRIX;
+    pub fn rotation_z(radians: T) -> GenericMatrix4<T> {
+        let mut result = GenericMatrix4::identity();
         result.members[0][0] = radians.cos();
         result.members[0][1] = -radians.sin();
         result.members[1][0] = radians.sin();
@@ -186,8 +262,8 @@ impl Matrix4 {
         result
     }
 
-    pub fn shearing(xy: f64, xz: f64, yx: f64, yz: f64, zx: f64, zy: f64) -> Matrix4 {
-        let mut result = IDENTITY_MATRIX;
+    pub fn shearing(xy: T, xz: T, yx: T, yz: T, zx: T, zy: T) -> GenericMatrix4<T> {
+        let mut result = GenericMatrix4::identity();
         result.members[0][1] = xy;
         result.members[0][2] = xz;
         result.members[1][0] = yx;
@@ -198,21 +274,104 @@ impl Matrix4 {
     }
 }
 
+// Indexed by (row, column).
+impl<T: Float> ops::Index<(usize, usize)> for GenericMatrix4<T> {
+    type Output = T;
+
+    fn index(&self, (row, col): (usize, usize)) -> &T {
+        &self.members[row][col]
+    }
+}
+
+impl<T: Float> ops::IndexMut<(usize, usize)> for GenericMatrix4<T> {
+    fn index_mut(&mut self, (row, col): (usize, usize)) -> &mut T {
+        &mut self.members[row][col]
+    }
+}
+
+impl<T: Float> From<[[T; 4]; 4]> for GenericMatrix4<T> {
+    fn from(rows: [[T; 4]; 4]) -> GenericMatrix4<T> {
+        GenericMatrix4::from_rows(rows)
+    }
+}
+
+impl<T: Float> From<GenericMatrix4<T>> for [[T; 4]; 4] {
+    fn from(matrix: GenericMatrix4<T>) -> [[T; 4]; 4] {
+        matrix.to_array()
+    }
+}
+
+impl<T: Float> ops::Mul for GenericMatrix4<T> {
+    type Output = GenericMatrix4<T>;
+
+    fn mul(self, other: GenericMatrix4<T>) -> GenericMatrix4<T> {
+        self.multiply(&other)
+    }
+}
+
+impl<T: Float> ops::MulAssign for GenericMatrix4<T> {
+    fn mul_assign(&mut self, other: GenericMatrix4<T>) {
+        *self = self.multiply(&other);
+    }
+}
+
+impl<T: Float> ops::Mul<GenericPoint<T>> for GenericMatrix4<T> {
+    type Output = GenericPoint<T>;
+
+    fn mul(self, point: GenericPoint<T>) -> GenericPoint<T> {
+        self.multiply_point(&point)
+    }
+}
+
+// One row per line, with each column lined up on its widest entry. A
+// precision, as in `{:.3}`, applies to every entry.
+impl<T: Float> fmt::Display for GenericMatrix4<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let entries: Vec<Vec<String>> = self
+            .members
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|value| match f.precision() {
+                        Some(precision) => format!("{:.*}", precision, value),
+                        None => format!("{}", value),
+                    })
+                    .collect()
+            })
+            .collect();
+        let widths: Vec<usize> = (0..4)
+            .map(|col| entries.iter().map(|row| row[col].len()).max().unwrap_or(0))
+            .collect();
+        for (index, row) in entries.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "|")?;
+            for (entry, width) in row.iter().zip(&widths) {
+                write!(f, " {:>width$} |", entry, width = width)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
-pub struct Matrix3 {
-    members: [[f64; 3]; 3],
+pub struct GenericMatrix3<T> {
+    members: [[T; 3]; 3],
 }
 
-impl Matrix3 {
-    pub fn new(members: [[f64; 3]; 3]) -> Matrix3 {
-        Matrix3 { members: members }
+pub type Matrix3 = GenericMatrix3<f64>;
+
+impl<T: Float> GenericMatrix3<T> {
+    pub fn new(members: [[T; 3]; 3]) -> GenericMatrix3<T> {
+        GenericMatrix3 { members }
     }
 
-    pub fn empty() -> Matrix3 {
-        Matrix3::new([[0.; 3]; 3])
+    pub fn empty() -> GenericMatrix3<T> {
+        GenericMatrix3::new([[T::ZERO; 3]; 3])
     }
 
-    pub fn equal(&self, other: &Matrix3) -> bool {
+    pub fn equal(&self, other: &GenericMatrix3<T>) -> bool {
         for x in 0..3 {
             for y in 0..3 {
                 if !equal(self.members[x][y], other.members[x][y]) {
@@ -223,8 +382,8 @@ impl Matrix3 {
         true
     }
 
-    pub fn submatrix(&self, not_col: usize, not_row: usize) -> Matrix2 {
-        let mut result = Matrix2::empty();
+    pub fn submatrix(&self, not_col: usize, not_row: usize) -> GenericMatrix2<T> {
+        let mut result = GenericMatrix2::empty();
         let mut y = 0;
         for row in 0..3 {
             let mut x = 0;
@@ -241,21 +400,21 @@ impl Matrix3 {
         result
     }
 
-    pub fn minor(&self, col: usize, row: usize) -> f64 {
+    pub fn minor(&self, col: usize, row: usize) -> T {
         self.submatrix(col, row).determinant()
     }
 
-    pub fn cofactor(&self, col: usize, row: usize) -> f64 {
+    pub fn cofactor(&self, col: usize, row: usize) -> T {
         let minor = self.minor(col, row);
         if col + row % 2 == 0 {
-            return minor;
+            minor
         } else {
-            return minor * -1.;
+            -minor
         }
     }
 
-    pub fn determinant(&self) -> f64 {
-        let mut result = 0.;
+    pub fn determinant(&self) -> T {
+        let mut result = T::ZERO;
         for i in 0..3 {
             result += self.members[0][i] * self.cofactor(0, i);
         }
@@ -264,20 +423,22 @@ impl Matrix3 {
 }
 
 #[derive(Debug)]
-pub struct Matrix2 {
-    members: [[f64; 2]; 2],
+pub struct GenericMatrix2<T> {
+    members: [[T; 2]; 2],
 }
 
-impl Matrix2 {
-    pub fn new(members: [[f64; 2]; 2]) -> Matrix2 {
-        Matrix2 { members: members }
+pub type Matrix2 = GenericMatrix2<f64>;
+
+impl<T: Float> GenericMatrix2<T> {
+    pub fn new(members: [[T; 2]; 2]) -> GenericMatrix2<T> {
+        GenericMatrix2 { members }
     }
 
-    pub fn empty() -> Matrix2 {
-        Matrix2::new([[0.; 2]; 2])
+    pub fn empty() -> GenericMatrix2<T> {
+        GenericMatrix2::new([[T::ZERO; 2]; 2])
     }
 
-    pub fn equal(&self, other: &Matrix2) -> bool {
+    pub fn equal(&self, other: &GenericMatrix2<T>) -> bool {
         for x in 0..2 {
             for y in 0..2 {
                 if !equal(self.members[x][y], other.members[x][y]) {
@@ -288,19 +449,22 @@ impl Matrix2 {
         true
     }
 
-    pub fn determinant(&self) -> f64 {
+    pub fn determinant(&self) -> T {
         self.members[0][0] * self.members[1][1] - self.members[1][0] * self.members[0][1]
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use error::Error;
     use matrix::Matrix2;
     use matrix::Matrix3;
     use matrix::Matrix4;
+    use matrix::Matrix4F32;
     use matrix::IDENTITY_MATRIX;
     use point::point;
     use point::vector;
+    use point::PointF32;
     use std::f64::consts::PI;
     use utilities::equal;
 
@@ -570,6 +734,11 @@ mod tests {
         };
 
         assert!(!b.invertible());
+        assert!(a.try_inverse().is_ok());
+        match b.try_inverse() {
+            Err(Error::InvalidMatrix) => (),
+            other => panic!("expected an invalid matrix, got {:?}", other),
+        }
     }
 
     #[test]
@@ -607,6 +776,28 @@ mod tests {
         assert!(a.multiply(&b).multiply(&b.inverse()).equal(&a));
     }
 
+    #[test]
+    fn test_matrix_display() {
+        let a = Matrix4::from_rows([
+            [1., -2.5, 0., 10.],
+            [0., 1., 0., 0.],
+            [0., 0., 1., -100.],
+            [0., 0., 0., 1.],
+        ]);
+
+        assert_eq!(
+            a.to_string(),
+            "| 1 | -2.5 | 0 |   10 |\n\
+             | 0 |    1 | 0 |    0 |\n\
+             | 0 |    0 | 1 | -100 |\n\
+             | 0 |    0 | 0 |    1 |"
+        );
+        assert_eq!(
+            format!("{:.1}", IDENTITY_MATRIX).lines().next(),
+            Some("| 1.0 | 0.0 | 0.0 | 0.0 |")
+        );
+    }
+
     #[test]
     fn test_translation() {
         let transform = Matrix4::translation(5., -3., 2.);
@@ -729,4 +920,61 @@ mod tests {
 
         assert!(c.multiply(&b).multiply(&a).multiply_point(&p).equal(&p4));
     }
+
+    #[test]
+    fn test_matrix_operators() {
+        let p = point(1., 0., 1.);
+        let a = Matrix4::rotation_x(PI / 2.);
+        let b = Matrix4::scaling(5., 5., 5.);
+        let c = Matrix4::translation(10., 5., 7.);
+
+        assert!((c * b * a * p).equal(&point(15., 0., 7.)));
+        assert!((a * vector(0., 1., 0.)).equal(&vector(0., 0., 1.)));
+
+        let mut m = c;
+        m *= b;
+        assert!(m.equal(&c.multiply(&b)));
+    }
+
+    #[test]
+    fn test_matrix_element_access() {
+        let rows = [
+            [1., 2., 3., 4.],
+            [5., 6., 7., 8.],
+            [9., 10., 11., 12.],
+            [13., 14., 15., 16.],
+        ];
+        let mut m = Matrix4::from_rows(rows);
+
+        assert_eq!(m[(1, 2)], 7.);
+        assert_eq!(m.row(3), [13., 14., 15., 16.]);
+        assert_eq!(m.col(0), [1., 5., 9., 13.]);
+        assert_eq!(m.rows().collect::<Vec<_>>(), rows.to_vec());
+        assert_eq!(
+            m.cols().collect::<Vec<_>>(),
+            m.transpose().rows().collect::<Vec<_>>()
+        );
+        assert_eq!(m.to_array(), rows);
+
+        m[(0, 3)] = -4.;
+        let array: [[f64; 4]; 4] = m.into();
+        assert_eq!(array[0], [1., 2., 3., -4.]);
+        assert!(Matrix4::from(array).equal(&m));
+    }
+
+    #[test]
+    fn test_single_precision() {
+        let transform = Matrix4F32::translation(10., 5., 7.)
+            * Matrix4F32::scaling(5., 5., 5.)
+            * Matrix4F32::rotation_x(std::f32::consts::PI / 2.);
+        let p = PointF32::new(1., 0., 1., 1.);
+
+        assert_eq!(transform * p, PointF32::new(15., 0., 7., 1.));
+        assert!(transform
+            .inverse()
+            .multiply(&transform)
+            .equal(&Matrix4F32::identity()));
+        assert_eq!(Matrix4F32::identity().cast::<f64>(), IDENTITY_MATRIX);
+        assert_eq!((transform * p).cast::<f64>(), point(15., 0., 7.));
+    }
 }
@@ -0,0 +1,471 @@
+use bounds::Bounds;
+use color::Color;
+use intersection::FaceHit;
+use material::Material;
+use point::{bounds, point, vector, Point};
+use ray::Ray;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use utilities::EPSILON;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Face {
+    pub group: Option<usize>,
+    pub material: Option<usize>,
+    pub normals: Option<[usize; 3]>,
+    pub uvs: Option<[usize; 3]>,
+    pub vertices: [usize; 3],
+}
+
+// A triangle mesh sharing one vertex list. Faces may pick their own material
+// out of `materials`; faces without one use the material of the shape.
+// `colors` is either empty or holds one color per vertex. Faces that index
+// into `normals` are shaded smoothly, and those that index into `uvs` take
+// their texture coordinates from them. Faces may also belong to one of the
+// named `groups`.
+#[derive(Debug, Clone)]
+pub struct Mesh {
+    pub colors: Vec<Color>,
+    pub faces: Vec<Face>,
+    pub groups: Vec<String>,
+    pub materials: Vec<Material>,
+    pub normals: Vec<Point>,
+    pub uvs: Vec<(f64, f64)>,
+    pub vertices: Vec<Point>,
+}
+
+impl Mesh {
+    pub fn new(vertices: Vec<Point>) -> Mesh {
+        Mesh {
+            colors: Vec::new(),
+            faces: Vec::new(),
+            groups: Vec::new(),
+            materials: Vec::new(),
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            vertices,
+        }
+    }
+
+    // The unit cube as twelve triangles, each side with its own normal.
+    pub fn cube() -> Mesh {
+        let mut mesh = Mesh::new(Vec::new());
+        let x = vector(1., 0., 0.);
+        let y = vector(0., 1., 0.);
+        let z = vector(0., 0., 1.);
+        // Each side's normal, then two edges whose cross product is it.
+        let sides = [
+            (x, y, z),
+            (x.multiply_scalar(-1.), z, y),
+            (y, z, x),
+            (y.multiply_scalar(-1.), x, z),
+            (z, x, y),
+            (z.multiply_scalar(-1.), y, x),
+        ];
+        for (side, &(normal, u, v)) in sides.iter().enumerate() {
+            let center = point(0., 0., 0.).add(&normal);
+            for &(a, b) in &[(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)] {
+                mesh.vertices
+                    .push(center.add(&u.multiply_scalar(a)).add(&v.multiply_scalar(b)));
+            }
+            mesh.normals.push(normal);
+            let first = side * 4;
+            mesh.add_face(first, first + 1, first + 2, None);
+            mesh.add_face(first, first + 2, first + 3, None);
+            let len = mesh.faces.len();
+            for face in &mut mesh.faces[len - 2..] {
+                face.normals = Some([side; 3]);
+            }
+        }
+        mesh
+    }
+
+    // The unit sphere cut into `segments` slices around the y axis and half
+    // as many from pole to pole, smooth shaded and with spherical texture
+    // coordinates.
+    pub fn uv_sphere(segments: usize) -> Mesh {
+        let segments = segments.max(3);
+        let rings = (segments / 2).max(2);
+        let mut mesh = Mesh::new(Vec::new());
+        for ring in 0..=rings {
+            let theta = PI * ring as f64 / rings as f64;
+            for segment in 0..=segments {
+                let phi = 2. * PI * segment as f64 / segments as f64;
+                let p = point(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                );
+                mesh.vertices.push(p);
+                mesh.normals.push(vector(p.x, p.y, p.z));
+                mesh.uvs.push((
+                    segment as f64 / segments as f64,
+                    1. - ring as f64 / rings as f64,
+                ));
+            }
+        }
+        let row = segments + 1;
+        let mut corners = Vec::new();
+        for ring in 0..rings {
+            for segment in 0..segments {
+                let a = ring * row + segment;
+                let (b, c, d) = (a + row, a + row + 1, a + 1);
+                // The triangles that would meet at a pole have no area.
+                if ring != rings - 1 {
+                    corners.push([a, c, b]);
+                }
+                if ring != 0 {
+                    corners.push([a, d, c]);
+                }
+            }
+        }
+        for [a, b, c] in corners {
+            mesh.add_face(a, b, c, None);
+            let face = mesh.faces.last_mut().unwrap();
+            face.normals = Some([a, b, c]);
+            face.uvs = Some([a, b, c]);
+        }
+        mesh
+    }
+
+    // A square `size` wide in the xz plane, facing up: a stand-in for a plane.
+    pub fn square(size: f64) -> Mesh {
+        let h = size / 2.;
+        let mut mesh = Mesh::new(vec![
+            point(-h, 0., -h),
+            point(-h, 0., h),
+            point(h, 0., h),
+            point(h, 0., -h),
+        ]);
+        mesh.normals.push(vector(0., 1., 0.));
+        mesh.add_face(0, 1, 2, None);
+        mesh.add_face(0, 2, 3, None);
+        for face in &mut mesh.faces {
+            face.normals = Some([0; 3]);
+        }
+        mesh
+    }
+
+    pub fn add_material(&mut self, material: Material) -> usize {
+        self.materials.push(material);
+        self.materials.len() - 1
+    }
+
+    pub fn add_face(&mut self, a: usize, b: usize, c: usize, material: Option<usize>) {
+        self.faces.push(Face {
+            group: None,
+            material,
+            normals: None,
+            uvs: None,
+            vertices: [a, b, c],
+        });
+    }
+
+    // The faces of one group as a mesh of their own, keeping only the
+    // vertices, normals and texture coordinates they use.
+    pub fn group_mesh(&self, group: Option<usize>) -> Mesh {
+        let mut mesh = Mesh::new(Vec::new());
+        mesh.materials = self.materials.clone();
+        let mut vertices = HashMap::new();
+        let mut normals = HashMap::new();
+        let mut uvs = HashMap::new();
+        let has_colors = self.colors.len() == self.vertices.len();
+        for face in self.faces.iter().filter(|face| face.group == group) {
+            let mut face = face.clone();
+            face.group = None;
+            for index in face.vertices.iter_mut() {
+                *index = *vertices.entry(*index).or_insert_with(|| {
+                    mesh.vertices.push(self.vertices[*index]);
+                    if has_colors {
+                        mesh.colors.push(self.colors[*index]);
+                    }
+                    mesh.vertices.len() - 1
+                });
+            }
+            for index in face.normals.iter_mut().flat_map(|n| n.iter_mut()) {
+                *index = *normals.entry(*index).or_insert_with(|| {
+                    mesh.normals.push(self.normals[*index]);
+                    mesh.normals.len() - 1
+                });
+            }
+            for index in face.uvs.iter_mut().flat_map(|uv| uv.iter_mut()) {
+                *index = *uvs.entry(*index).or_insert_with(|| {
+                    mesh.uvs.push(self.uvs[*index]);
+                    mesh.uvs.len() - 1
+                });
+            }
+            mesh.faces.push(face);
+        }
+        mesh
+    }
+
+    pub fn face_points(&self, index: usize) -> (Point, Point, Point) {
+        let [a, b, c] = self.faces[index].vertices;
+        (self.vertices[a], self.vertices[b], self.vertices[c])
+    }
+
+    pub fn face_normal(&self, index: usize) -> Point {
+        let (p1, p2, p3) = self.face_points(index);
+        p2.sub(&p1).cross(&p3.sub(&p1)).normalize()
+    }
+
+    // The vertex normals blended across the face, or the flat face normal
+    // if it has none.
+    pub fn normal_at(&self, face: &FaceHit) -> Point {
+        match self.faces[face.index].normals {
+            Some([a, b, c]) => self.normals[a]
+                .multiply_scalar(1. - face.u - face.v)
+                .add(&self.normals[b].multiply_scalar(face.u))
+                .add(&self.normals[c].multiply_scalar(face.v))
+                .normalize(),
+            None => self.face_normal(face.index),
+        }
+    }
+
+    pub fn uv_at(&self, face: &FaceHit) -> Option<(f64, f64)> {
+        let [a, b, c] = self.faces[face.index].uvs?;
+        let w = 1. - face.u - face.v;
+        let blend = |(ua, va): (f64, f64), (ub, vb): (f64, f64), (uc, vc): (f64, f64)| {
+            (
+                ua * w + ub * face.u + uc * face.v,
+                va * w + vb * face.u + vc * face.v,
+            )
+        };
+        Some(blend(self.uvs[a], self.uvs[b], self.uvs[c]))
+    }
+
+    pub fn material(&self, index: usize) -> Option<&Material> {
+        self.faces[index]
+            .material
+            .and_then(|material| self.materials.get(material))
+    }
+
+    pub fn vertex_color(&self, face: &FaceHit) -> Option<Color> {
+        if self.colors.len() != self.vertices.len() {
+            return None;
+        }
+        let [a, b, c] = self.faces[face.index].vertices;
+        Some(
+            self.colors[a]
+                .multiply_scalar(1. - face.u - face.v)
+                .add(&self.colors[b].multiply_scalar(face.u))
+                .add(&self.colors[c].multiply_scalar(face.v)),
+        )
+    }
+
+    pub fn bounds(&self) -> Bounds {
+        bounds(self.vertices.clone())
+    }
+}
+
+// Faces per leaf of a `FaceTree`; below this, splitting costs more box
+// tests than it saves triangle tests.
+const LEAF_FACES: usize = 4;
+
+// A bounding volume hierarchy over the faces of a mesh, so that a ray only
+// has to be tested against the faces whose boxes it passes through.
+#[derive(Debug)]
+pub enum FaceTree {
+    Leaf(Bounds, Vec<usize>),
+    Node(Bounds, Box<FaceTree>, Box<FaceTree>),
+}
+
+impl FaceTree {
+    pub fn new(mesh: &Mesh) -> FaceTree {
+        FaceTree::build(mesh, (0..mesh.faces.len()).collect())
+    }
+
+    // Splits the faces in half by their centers along the longest side of
+    // their box, until each leaf holds a handful.
+    fn build(mesh: &Mesh, mut faces: Vec<usize>) -> FaceTree {
+        let bounds = FaceTree::bounds(mesh, &faces);
+        if faces.len() <= LEAF_FACES {
+            return FaceTree::Leaf(bounds, faces);
+        }
+        let size = bounds.max.sub(&bounds.min);
+        let along = |p: Point| {
+            if size.x >= size.y && size.x >= size.z {
+                p.x
+            } else if size.y >= size.z {
+                p.y
+            } else {
+                p.z
+            }
+        };
+        let center = |index: usize| {
+            let (p1, p2, p3) = mesh.face_points(index);
+            along(p1) + along(p2) + along(p3)
+        };
+        faces.sort_by(|&a, &b| center(a).total_cmp(&center(b)));
+        let right = faces.split_off(faces.len() / 2);
+        FaceTree::Node(
+            bounds,
+            Box::new(FaceTree::build(mesh, faces)),
+            Box::new(FaceTree::build(mesh, right)),
+        )
+    }
+
+    // The box around the faces, padded so that none of its sides is flat.
+    fn bounds(mesh: &Mesh, faces: &[usize]) -> Bounds {
+        if faces.is_empty() {
+            return Bounds::new(0., 0., 0., 0., 0., 0.);
+        }
+        let points = faces
+            .iter()
+            .flat_map(|&index| {
+                let (p1, p2, p3) = mesh.face_points(index);
+                vec![p1, p2, p3]
+            })
+            .collect();
+        let bounds = bounds(points);
+        Bounds::new(
+            bounds.min.x - EPSILON,
+            bounds.max.x + EPSILON,
+            bounds.min.y - EPSILON,
+            bounds.max.y + EPSILON,
+            bounds.min.z - EPSILON,
+            bounds.max.z + EPSILON,
+        )
+    }
+
+    // Adds the faces in every leaf whose box `ray` passes through.
+    pub fn candidates(&self, ray: &Ray, faces: &mut Vec<usize>) {
+        match *self {
+            FaceTree::Leaf(ref bounds, ref indices) => {
+                if bounds.hits(ray) {
+                    faces.extend(indices);
+                }
+            }
+            FaceTree::Node(ref bounds, ref left, ref right) => {
+                if bounds.hits(ray) {
+                    left.candidates(ray, faces);
+                    right.candidates(ray, faces);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use intersection::FaceHit;
+    use material::Material;
+    use mesh::{FaceTree, Mesh};
+    use point::point;
+    use point::vector;
+    use ray::Ray;
+    use utilities::EPSILON;
+
+    fn quad() -> Mesh {
+        Mesh::new(vec![
+            point(-1., 0., -1.),
+            point(1., 0., -1.),
+            point(1., 0., 1.),
+            point(-1., 0., 1.),
+        ])
+    }
+
+    #[test]
+    fn test_mesh_faces() {
+        let mut mesh = quad();
+        mesh.add_face(0, 3, 2, None);
+        mesh.add_face(0, 2, 1, None);
+
+        assert_eq!(mesh.faces.len(), 2);
+        assert_eq!(
+            mesh.face_points(1),
+            (point(-1., 0., -1.), point(1., 0., 1.), point(1., 0., -1.))
+        );
+        assert_eq!(mesh.face_normal(0), vector(0., 1., 0.));
+    }
+
+    #[test]
+    fn test_mesh_face_materials() {
+        let mut mesh = quad();
+        let mut red = Material::new();
+        red.ambient = 0.5;
+        let red = mesh.add_material(red);
+        mesh.add_face(0, 3, 2, None);
+        mesh.add_face(0, 2, 1, Some(red));
+
+        assert!(mesh.material(0).is_none());
+        assert_eq!(mesh.material(1).unwrap().ambient, 0.5);
+    }
+
+    #[test]
+    fn test_mesh_bounds() {
+        let bounds = quad().bounds();
+
+        assert_eq!(bounds.min, point(-1., 0., -1.));
+        assert_eq!(bounds.max, point(1., 0., 1.));
+    }
+
+    #[test]
+    fn test_mesh_group_mesh() {
+        let mut mesh = quad();
+        mesh.vertices.push(point(0., 1., 0.));
+        mesh.groups.push(String::from("roof"));
+        mesh.add_face(0, 3, 2, None);
+        mesh.add_face(3, 2, 4, None);
+        mesh.faces[1].group = Some(0);
+
+        let roof = mesh.group_mesh(Some(0));
+        let rest = mesh.group_mesh(None);
+
+        assert_eq!(roof.faces.len(), 1);
+        assert_eq!(roof.faces[0].vertices, [0, 1, 2]);
+        assert_eq!(
+            roof.vertices,
+            vec![point(-1., 0., 1.), point(1., 0., 1.), point(0., 1., 0.)]
+        );
+        assert_eq!(rest.faces.len(), 1);
+        assert_eq!(rest.vertices.len(), 3);
+    }
+
+    #[test]
+    fn test_mesh_primitives_face_outward() {
+        let primitives = vec![
+            (Mesh::cube(), 12),
+            (Mesh::uv_sphere(8), 48),
+            (Mesh::square(2.), 2),
+        ];
+        for (mesh, faces) in primitives {
+            assert_eq!(mesh.faces.len(), faces);
+            for index in 0..mesh.faces.len() {
+                let (p1, _, _) = mesh.face_points(index);
+                let hit = FaceHit {
+                    index,
+                    u: 1. / 3.,
+                    v: 1. / 3.,
+                };
+                assert!(mesh.face_normal(index).dot(&mesh.normal_at(&hit)) > 0.5);
+                assert!(p1.x.abs() <= 1. + EPSILON && p1.y.abs() <= 1. + EPSILON);
+            }
+        }
+        assert_eq!(Mesh::uv_sphere(8).bounds().max, point(1., 1., 1.));
+    }
+
+    #[test]
+    fn test_face_tree_candidates() {
+        let mesh = Mesh::uv_sphere(16);
+        let tree = FaceTree::new(&mesh);
+
+        let mut through = Vec::new();
+        let ray = Ray {
+            origin: point(0., 0., -5.),
+            direction: vector(0., 0., 1.),
+        };
+        tree.candidates(&ray, &mut through);
+        assert!(!through.is_empty());
+        assert!(through.len() < mesh.faces.len() / 4);
+
+        let mut past = Vec::new();
+        let ray = Ray {
+            origin: point(2., 0., -5.),
+            direction: vector(0., 0., 1.),
+        };
+        tree.candidates(&ray, &mut past);
+        assert!(past.is_empty());
+    }
+}
@@ -0,0 +1,146 @@
+use color::Color;
+use material::Material;
+use patternable::Patternable;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use texture::Texture;
+
+// Reads Wavefront material libraries into named materials. Texture paths
+// are resolved relative to `dir`, which is normally the folder of the .mtl.
+pub struct MtlParser;
+
+impl MtlParser {
+    pub fn parse_file<P: AsRef<Path>>(path: P) -> io::Result<HashMap<String, Material>> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        MtlParser::parse(&text, path.parent().unwrap_or_else(|| Path::new("")))
+    }
+
+    pub fn parse(text: &str, dir: &Path) -> io::Result<HashMap<String, Material>> {
+        let mut materials = HashMap::new();
+        let mut current: Option<(String, Material)> = None;
+        for line in text.lines() {
+            let mut words = line.split_whitespace();
+            let keyword = match words.next() {
+                Some(keyword) => keyword,
+                None => continue,
+            };
+            let args: Vec<&str> = words.collect();
+            if keyword == "newmtl" {
+                if let Some((name, material)) = current.take() {
+                    materials.insert(name, material);
+                }
+                current = Some((args.join(" "), Material::new()));
+                continue;
+            }
+            let material = match current {
+                Some((_, ref mut material)) => material,
+                None => continue,
+            };
+            match keyword {
+                "Kd" => {
+                    let kd = MtlParser::floats(&args, 3)?;
+                    material.pattern = Patternable::solid(Color::new(kd[0], kd[1], kd[2]));
+                }
+                "Ks" => {
+                    let ks = MtlParser::floats(&args, 3)?;
+                    material.specular = (ks[0] + ks[1] + ks[2]) / 3.;
+                }
+                "Ns" => material.shininess = MtlParser::floats(&args, 1)?[0].into(),
+                "d" => material.transparency = (1. - MtlParser::floats(&args, 1)?[0]).into(),
+                "Ni" => material.refractive_index = MtlParser::floats(&args, 1)?[0],
+                "map_Kd" => {
+                    // Options such as -s come before the file name, which is last.
+                    if let Some(file) = args.last() {
+                        let texture = Texture::open(dir.join(file))
+                            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                        material.texture = Some(Arc::new(texture));
+                    }
+                }
+                _ => (),
+            }
+        }
+        if let Some((name, material)) = current {
+            materials.insert(name, material);
+        }
+        Ok(materials)
+    }
+
+    fn floats(args: &[&str], count: usize) -> io::Result<Vec<f64>> {
+        let floats: Vec<f64> = args
+            .iter()
+            .take(count)
+            .map(|arg| arg.parse::<f64>())
+            .collect::<Result<_, _>>()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if floats.len() < count {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("expected {} values, found {}", count, floats.len()),
+            ));
+        }
+        Ok(floats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use color::Color;
+    use image::{Rgb, RgbImage};
+    use material::ChannelValue;
+    use mtl_parser::MtlParser;
+    use point::point;
+    use std::env;
+    use std::path::Path;
+
+    #[test]
+    fn test_parsing_materials() {
+        let text = "# two materials
+newmtl red
+Kd 1.0 0.0 0.0
+Ks 0.5 0.5 0.5
+Ns 50
+
+newmtl glass
+Kd 1 1 1
+d 0.1
+Ni 1.5
+";
+        let materials = MtlParser::parse(text, Path::new("")).unwrap();
+
+        assert_eq!(materials.len(), 2);
+        let red = &materials["red"];
+        assert_eq!(
+            red.pattern.color_at(&point(0., 0., 0.)),
+            Color::new(1., 0., 0.)
+        );
+        assert_eq!(red.specular, 0.5);
+        assert!(red.shininess.equal(&ChannelValue::Constant(50.)));
+        let glass = &materials["glass"];
+        assert!(glass.transparency.equal(&ChannelValue::Constant(0.9)));
+        assert_eq!(glass.refractive_index, 1.5);
+    }
+
+    #[test]
+    fn test_parsing_texture_map() {
+        let dir = env::temp_dir();
+        let mut image = RgbImage::new(1, 1);
+        image.put_pixel(0, 0, Rgb([0, 255, 0]));
+        image.save(dir.join("ray_tracer_test_mtl_map.png")).unwrap();
+        let text = "newmtl textured\nmap_Kd ray_tracer_test_mtl_map.png\n";
+
+        let materials = MtlParser::parse(text, &dir).unwrap();
+        let texture = materials["textured"].texture.as_ref().unwrap();
+
+        assert_eq!(texture.pixel_at(0, 0), Color::new(0., 1., 0.));
+    }
+
+    #[test]
+    fn test_parsing_malformed_values() {
+        assert!(MtlParser::parse("newmtl bad\nKd 1 x 0\n", Path::new("")).is_err());
+        assert!(MtlParser::parse("newmtl bad\nNs\n", Path::new("")).is_err());
+    }
+}
@@ -1,259 +1,362 @@
-use point::{point, Point};
+use intersectable::Intersectable;
+use material::Material;
+use matrix::Matrix4;
+use mesh::{Face, Mesh};
+use mtl_parser::MtlParser;
+use point::{point, vector};
 use shape::Shape;
+use std::collections::HashMap;
+use std::error;
+use std::f64::consts::PI;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
 use std::sync::Arc;
 
-#[derive(Debug, PartialEq, Clone)]
-pub enum ObjNodeType {
-    Decimal,
-    Integer,
-    MinusSign,
-    ShapeStart,
-    Slash,
-    Space,
-    VertexStart,
+#[derive(Debug)]
+pub enum ObjError {
+    Io(io::Error),
+    // A line that could not be read, counting from one.
+    Parse { line: usize, message: String },
 }
 
-#[derive(Debug, Clone)]
-pub struct ObjNode {
-    value: char,
-    node_type: ObjNodeType,
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ObjError::Io(ref error) => write!(f, "could not read model: {}", error),
+            ObjError::Parse { line, ref message } => write!(f, "line {}: {}", line, message),
+        }
+    }
 }
 
-#[derive(Debug)]
-pub struct ObjParser {
-    pub group: Arc<Shape>,
-    vertices: Vec<Point>,
+impl error::Error for ObjError {}
+
+impl From<io::Error> for ObjError {
+    fn from(error: io::Error) -> ObjError {
+        ObjError::Io(error)
+    }
 }
 
-struct ObjLineParser {
-    line: Vec<ObjNode>,
+// The unit a model was authored in. Scenes are measured in metres.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Units {
+    Centimeters,
+    Meters,
+    Millimeters,
 }
 
-impl ObjLineParser {
-    fn run(&mut self, vertices: &[Point]) -> (Vec<Point>, Vec<Shape>) {
-        let mut points: Vec<Point> = Vec::new();
-        let mut shapes: Vec<Shape> = Vec::new();
-        if self.peek(ObjNodeType::VertexStart) {
-            if let Some(vector) = self.parse_point() {
-                points.push(vector);
-            }
-        } else if self.peek(ObjNodeType::ShapeStart) {
-            if let Some(shape) = self.parse_shape(vertices) {
-                shapes.push(shape);
-            }
+impl Units {
+    pub fn scale(self) -> f64 {
+        match self {
+            Units::Centimeters => 0.01,
+            Units::Meters => 1.,
+            Units::Millimeters => 0.001,
         }
-        (points, shapes)
     }
+}
 
-    fn parse_shape(&mut self, vertices: &[Point]) -> Option<Shape> {
-        self.consume(ObjNodeType::ShapeStart)?;
-        self.consume_whitespace();
-        let ai = self.consume_integer()? as usize - 1;
-        let a = vertices[ai];
-        self.consume_whitespace();
-        let bi = self.consume_integer()? as usize - 1;
-        self.consume_whitespace();
-        let b = vertices[bi];
-        let ci = self.consume_integer()? as usize - 1;
-        self.consume_whitespace();
-        let c = vertices[ci];
-        Some(Shape::triangle(a, b, c))
-    }
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UpAxis {
+    Y,
+    Z,
+}
 
-    fn parse_point(&mut self) -> Option<Point> {
-        let mut p = point(0., 0., 0.);
-        self.consume(ObjNodeType::VertexStart)?;
-        self.consume_whitespace();
-        p.x = self.consume_float()?;
-        self.consume_whitespace();
-        p.y = self.consume_float()?;
-        self.consume_whitespace();
-        p.z = self.consume_float()?;
-
-        Some(p)
-    }
+// How a model's conventions differ from the scene's, which is y up and
+// left-handed. Tools that export right-handed coordinates need
+// `right_handed` set, or the model comes out mirrored front to back.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImportOptions {
+    pub right_handed: bool,
+    pub units: Units,
+    pub up: UpAxis,
+}
 
-    fn consume_whitespace(&mut self) {
-        while self.peek(ObjNodeType::Space) {
-            self.consume(ObjNodeType::Space);
+impl ImportOptions {
+    pub fn new() -> ImportOptions {
+        ImportOptions {
+            right_handed: false,
+            units: Units::Meters,
+            up: UpAxis::Y,
         }
     }
 
-    fn consume_float(&mut self) -> Option<f64> {
-        let mut negative = false;
-        let mut decimal = false;
-        let mut float = String::new();
-        while let Some(_) = self.line.iter().next() {
-            if self.peek(ObjNodeType::MinusSign) {
-                self.consume(ObjNodeType::MinusSign);
-                negative = true;
-            }
-            if self.peek(ObjNodeType::Integer) {
-                float.push(self.consume(ObjNodeType::Integer).unwrap().value);
-            } else if self.peek(ObjNodeType::Decimal) {
-                if decimal {
-                    panic!("Malformed float, two decimal points");
-                } else {
-                    decimal = true;
-                }
-                float.push(self.consume(ObjNodeType::Decimal).unwrap().value);
-            } else {
-                break;
-            }
+    // Scales first, then turns z up into y up, then mirrors z, so the
+    // mirror always happens along the scene's depth axis.
+    pub fn transform(&self) -> Matrix4 {
+        let scale = self.units.scale();
+        let mut transform = Matrix4::scaling(scale, scale, scale);
+        if self.up == UpAxis::Z {
+            transform = Matrix4::rotation_x(-PI / 2.).multiply(&transform);
         }
-        if !float.is_empty() {
-            Some(ObjParser::float_from_string(float, negative))
-        } else {
-            None
+        if self.right_handed {
+            transform = Matrix4::scaling(1., 1., -1.).multiply(&transform);
         }
+        transform
     }
+}
 
-    fn consume_integer(&mut self) -> Option<i32> {
-        let mut int = String::new();
-        while let Some(_) = self.line.iter().next() {
-            if self.peek(ObjNodeType::Integer) {
-                int.push(self.consume(ObjNodeType::Integer)?.value);
-            } else if self.peek(ObjNodeType::Slash) {
-                self.consume(ObjNodeType::Slash);
-                self.consume_integer();
-            } else {
-                break;
-            }
-        }
-        if !int.is_empty() {
-            Some(ObjParser::integer_from_string(int))
-        } else {
-            None
-        }
+impl Default for ImportOptions {
+    fn default() -> ImportOptions {
+        ImportOptions::new()
     }
+}
 
-    fn peek(&self, node_type: ObjNodeType) -> bool {
-        if let Some(node) = self.line.iter().next() {
-            node.node_type == node_type
-        } else {
-            false
+struct Corner {
+    normal: Option<usize>,
+    uv: Option<usize>,
+    vertex: usize,
+}
+
+// What a line can change besides the mesh itself: the group and material
+// that the faces after it belong to.
+struct State<'a> {
+    dir: &'a Path,
+    group: Option<usize>,
+    library: HashMap<String, Material>,
+    material: Option<usize>,
+    materials: HashMap<String, usize>,
+    mesh: Mesh,
+}
+
+// Reads Wavefront OBJ models into a mesh, one line at a time. Statements it
+// does not know, such as smoothing groups, are skipped. Material libraries
+// are resolved relative to `dir`, which is normally the folder of the .obj.
+pub struct ObjParser;
+
+impl ObjParser {
+    pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<Arc<Shape>, ObjError> {
+        ObjParser::parse_file_with(path, &ImportOptions::new())
+    }
+
+    pub fn parse_file_with<P: AsRef<Path>>(
+        path: P,
+        options: &ImportOptions,
+    ) -> Result<Arc<Shape>, ObjError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        let mesh = ObjParser::parse(&text, path.parent().unwrap_or_else(|| Path::new("")))?;
+        Ok(ObjParser::to_group(&mesh, options))
+    }
+
+    pub fn parse(text: &str, dir: &Path) -> Result<Mesh, ObjError> {
+        let mut state = State {
+            dir,
+            group: None,
+            library: HashMap::new(),
+            material: None,
+            materials: HashMap::new(),
+            mesh: Mesh::new(Vec::new()),
+        };
+        for (number, line) in text.lines().enumerate() {
+            let mut words = line.split_whitespace();
+            let keyword = match words.next() {
+                Some(keyword) => keyword,
+                None => continue,
+            };
+            let args: Vec<&str> = words.collect();
+            ObjParser::parse_line(&mut state, keyword, &args).map_err(|message| {
+                ObjError::Parse {
+                    line: number + 1,
+                    message,
+                }
+            })?;
         }
+        Ok(state.mesh)
     }
 
-    fn consume(&mut self, node_type: ObjNodeType) -> Option<ObjNode> {
-        if self.line.len() == 0 {
-            return None;
+    fn parse_line(state: &mut State, keyword: &str, args: &[&str]) -> Result<(), String> {
+        let mesh = &mut state.mesh;
+        match keyword {
+            "v" => {
+                let v = ObjParser::floats(args, 3)?;
+                mesh.vertices.push(point(v[0], v[1], v[2]));
+            }
+            "vn" => {
+                let n = ObjParser::floats(args, 3)?;
+                mesh.normals.push(vector(n[0], n[1], n[2]));
+            }
+            "vt" => {
+                let t = ObjParser::floats(args, 2)?;
+                mesh.uvs.push((t[0], t[1]));
+            }
+            "f" => {
+                let faces = ObjParser::faces(args, mesh)?;
+                let (group, material) = (state.group, state.material);
+                mesh.faces.extend(faces.into_iter().map(|face| Face {
+                    group,
+                    material,
+                    ..face
+                }));
+            }
+            "mtllib" => {
+                for file in args.iter() {
+                    let materials = MtlParser::parse_file(state.dir.join(file))
+                        .map_err(|e| format!("could not load {}: {}", file, e))?;
+                    state.library.extend(materials);
+                }
+            }
+            // Materials missing from the libraries leave faces with the
+            // material of the shape.
+            "usemtl" => {
+                let name = args.join(" ");
+                state.material = match state.materials.get(&name) {
+                    Some(&index) => Some(index),
+                    None => match state.library.get(&name) {
+                        Some(found) => {
+                            let index = mesh.add_material(found.clone());
+                            state.materials.insert(name, index);
+                            Some(index)
+                        }
+                        None => None,
+                    },
+                };
+            }
+            // Objects and groups are treated alike, and a repeated name
+            // adds to the group it names.
+            "g" | "o" => {
+                let name = args.join(" ");
+                state.group = if name.is_empty() {
+                    None
+                } else {
+                    match mesh.groups.iter().position(|group| *group == name) {
+                        Some(index) => Some(index),
+                        None => {
+                            mesh.groups.push(name);
+                            Some(mesh.groups.len() - 1)
+                        }
+                    }
+                };
+            }
+            _ => (),
         }
-        let cloned_line = self.line.clone();
-        let (head, tail) = cloned_line.split_at(1);
-        if head[0].node_type == node_type {
-            self.line = tail.to_vec();
-            Some(head[0].clone())
-        } else {
-            None
+        Ok(())
+    }
+
+    // A group holding one mesh per named group in the model, each named
+    // after it, so they can be moved or given materials of their own. Faces
+    // outside any group go in an unnamed mesh.
+    // The import options become the group's transform, so the mesh itself
+    // keeps the model's own coordinates.
+    pub fn to_group(mesh: &Mesh, options: &ImportOptions) -> Arc<Shape> {
+        let mut group = Shape::new(Intersectable::group());
+        group
+            .set_transform(options.transform())
+            .expect("import options only scale, turn and mirror");
+        let shell = Arc::new(group.with_parent(None));
+        let names = mesh.groups.iter().map(Some);
+        for (index, name) in Some(None).into_iter().chain(names).enumerate() {
+            let part = mesh.group_mesh(index.checked_sub(1));
+            if part.faces.is_empty() {
+                continue;
+            }
+            let mut shape = Shape::new(Intersectable::mesh(part));
+            shape.name = name.cloned();
+            shape.parent = Some(shell.clone());
+            group
+                .intersectable
+                .add(Arc::new(shape))
+                .expect("made as a group above");
         }
+        Arc::new(group)
     }
-}
 
-impl ObjParser {
-    fn float_from_string(s: String, negative: bool) -> f64 {
-        let num = s.parse::<f64>().ok().unwrap();
-        if negative {
-            num * -1.
-        } else {
-            num
+    // Polygons are split into a fan of triangles around their first corner,
+    // which suits the convex faces exporters write.
+    fn faces(args: &[&str], mesh: &Mesh) -> Result<Vec<Face>, String> {
+        if args.len() < 3 {
+            return Err(format!("a face needs 3 vertices, found {}", args.len()));
         }
+        let corners: Vec<Corner> = args
+            .iter()
+            .map(|corner| ObjParser::corner(corner, mesh))
+            .collect::<Result<_, _>>()?;
+        Ok((1..corners.len() - 1)
+            .map(|i| ObjParser::face(&corners[0], &corners[i], &corners[i + 1]))
+            .collect())
     }
 
-    fn integer_from_string(s: String) -> i32 {
-        s.parse::<i32>().ok().unwrap()
+    // Each corner is `v`, `v/vt`, `v//vn` or `v/vt/vn`.
+    fn corner(word: &str, mesh: &Mesh) -> Result<Corner, String> {
+        let mut indices = word.split('/');
+        let vertex = indices.next().unwrap_or("");
+        let uv = indices.next().unwrap_or("");
+        let normal = indices.next().unwrap_or("");
+        Ok(Corner {
+            vertex: ObjParser::index(vertex, mesh.vertices.len(), "vertex")?,
+            uv: match uv {
+                "" => None,
+                uv => Some(ObjParser::index(uv, mesh.uvs.len(), "texture coordinate")?),
+            },
+            normal: match normal {
+                "" => None,
+                normal => Some(ObjParser::index(normal, mesh.normals.len(), "normal")?),
+            },
+        })
     }
 
-    fn parse_line(&mut self, line: Vec<ObjNode>) {
-        let output = ObjLineParser { line }.run(&self.vertices);
-        self.vertices.extend(output.0);
-        for shape in output.1 {
-            Shape::add_shape(self.group.clone(), Arc::new(shape));
+    // Texture coordinates and normals are only kept if every corner has them.
+    fn face(a: &Corner, b: &Corner, c: &Corner) -> Face {
+        let uvs = match (a.uv, b.uv, c.uv) {
+            (Some(a), Some(b), Some(c)) => Some([a, b, c]),
+            _ => None,
+        };
+        let normals = match (a.normal, b.normal, c.normal) {
+            (Some(a), Some(b), Some(c)) => Some([a, b, c]),
+            _ => None,
+        };
+        Face {
+            group: None,
+            material: None,
+            normals,
+            uvs,
+            vertices: [a.vertex, b.vertex, c.vertex],
         }
     }
 
-    pub fn parse(text: &str) -> Arc<Shape> {
-        let mut parsed_lines: Vec<Vec<ObjNode>> = Vec::new();
-        let mut obj_parser = ObjParser {
-            vertices: Vec::new(),
-            group: Shape::group(),
+    // OBJ counts from one, and negative indices count back from the end.
+    fn index(word: &str, len: usize, kind: &str) -> Result<usize, String> {
+        let number = word
+            .parse::<i64>()
+            .map_err(|_| format!("invalid {} index `{}`", kind, word))?;
+        let index = if number < 0 {
+            len as i64 + number
+        } else {
+            number - 1
         };
-        for line in text.lines() {
-            let mut parsed: Vec<ObjNode> = Vec::new();
-            let mut chars = line.chars();
-            let mut maybe_char = chars.next();
-            while match maybe_char {
-                Some(_) => true,
-                None => false,
-            } {
-                let c = maybe_char.unwrap();
-                if c == 'v' {
-                    parsed.push(ObjNode {
-                        node_type: ObjNodeType::VertexStart,
-                        value: c,
-                    });
-                }
-                if c == 'f' {
-                    parsed.push(ObjNode {
-                        node_type: ObjNodeType::ShapeStart,
-                        value: c,
-                    });
-                }
-                if c == ' ' {
-                    parsed.push(ObjNode {
-                        node_type: ObjNodeType::Space,
-                        value: c,
-                    });
-                }
-                if c == '0'
-                    || c == '1'
-                    || c == '2'
-                    || c == '3'
-                    || c == '4'
-                    || c == '5'
-                    || c == '6'
-                    || c == '7'
-                    || c == '8'
-                    || c == '9'
-                {
-                    parsed.push(ObjNode {
-                        node_type: ObjNodeType::Integer,
-                        value: c,
-                    });
-                }
-                if c == '.' {
-                    parsed.push(ObjNode {
-                        node_type: ObjNodeType::Decimal,
-                        value: c,
-                    });
-                }
-                if c == '-' {
-                    parsed.push(ObjNode {
-                        node_type: ObjNodeType::MinusSign,
-                        value: c,
-                    });
-                }
-                if c == '/' {
-                    parsed.push(ObjNode {
-                        node_type: ObjNodeType::Slash,
-                        value: c,
-                    });
-                }
-
-                maybe_char = chars.next();
-            }
-            parsed_lines.push(parsed);
+        if index >= 0 && (index as usize) < len {
+            Ok(index as usize)
+        } else {
+            Err(format!("no {} {}, only {} so far", kind, number, len))
         }
-        for line in parsed_lines {
-            obj_parser.parse_line(line);
+    }
+
+    fn floats(args: &[&str], count: usize) -> Result<Vec<f64>, String> {
+        let floats: Vec<f64> = args
+            .iter()
+            .take(count)
+            .map(|arg| {
+                arg.parse::<f64>()
+                    .map_err(|_| format!("invalid number `{}`", arg))
+            })
+            .collect::<Result<_, _>>()?;
+        if floats.len() < count {
+            return Err(format!("expected {} values, found {}", count, floats.len()));
         }
-        obj_parser.group.clone()
+        Ok(floats)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use color::Color;
+    use intersection::{FaceHit, Intersection};
+    use matrix::{Matrix4, IDENTITY_MATRIX};
     use obj_parser::*;
+    use ray::Ray;
+    use std::env;
+    use std::fs;
+    use utilities::equal;
 
     #[test]
     fn test_ignoring_unrecognized_lines() {
@@ -263,7 +366,10 @@ She set out one day
 in a relative way,
 and came back the previous night.
 ";
-        assert_eq!(ObjParser::parse(&str).vertices.len(), 0);
+        assert_eq!(
+            ObjParser::parse(str, Path::new("")).unwrap().vertices.len(),
+            0
+        );
     }
 
     #[test]
@@ -273,7 +379,7 @@ v -1.0000 0.5000 0.0000
 v 1 0 0
 v 1 1 0
 ";
-        let parser = ObjParser::parse(&str);
+        let parser = ObjParser::parse(str, Path::new("")).unwrap();
 
         assert_eq!(parser.vertices.len(), 4);
         assert_eq!(parser.vertices[0], point(-1., 1., 0.));
@@ -284,23 +390,236 @@ v 1 1 0
 
     #[test]
     fn test_parsing_incomplete_data() {
-        let str = "v -1 1";
-        let parser = ObjParser::parse(&str);
+        let str = "v 1 1 0\nv -1 1";
+
+        match ObjParser::parse(str, Path::new("")) {
+            Err(ObjError::Parse { line, message }) => {
+                assert_eq!(line, 2);
+                assert_eq!(message, "expected 3 values, found 2");
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parsing_errors() {
+        let error = |str: &str| {
+            ObjParser::parse(str, Path::new(""))
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!(error("v 1 x 0"), "line 1: invalid number `x`");
+        assert_eq!(
+            error("v 0 0 0\nv 1 0 0\nf 1 2"),
+            "line 3: a face needs 3 vertices, found 2"
+        );
+        assert_eq!(
+            error("v 0 0 0\n\nf 1 1 4"),
+            "line 3: no vertex 4, only 1 so far"
+        );
+        assert_eq!(
+            error("v 0 0 0\nf 1/a 1 1"),
+            "line 2: invalid texture coordinate index `a`"
+        );
+    }
+
+    #[test]
+    fn test_parsing_fixtures() {
+        let teapot = ObjParser::parse_file("fixtures/teapot.obj").unwrap();
+        assert_eq!(teapot.intersectable.children().len(), 1);
+
+        let king = ObjParser::parse_file("fixtures/king.obj").unwrap();
+        let boole = &king.intersectable.children()[0];
+        assert!(boole.is_named("Boole"));
+
+        // The tree names a material library that is not in the fixtures.
+        match ObjParser::parse_file("fixtures/tree.obj") {
+            Err(ObjError::Parse { line, .. }) => assert_eq!(line, 994),
+            other => panic!("expected a missing library, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parsing_faces_with_texture_coordinates_and_normals() {
+        let str = "v 0 1 0
+v -1 0 0
+v 1 0 0
+vt 0.5 1
+vt 0 0
+vt 1 0
+vn -1 0 0
+vn 1 0 0
+vn 0 1 0
+f 1 2 3
+f 1/1/3 2/2/1 3/3/2
+f 1//3 2//1 3//2
+f -3/-3 -2/-2 -1/-1
+";
+        let mesh = ObjParser::parse(str, Path::new("")).unwrap();
+
+        assert_eq!(mesh.normals.len(), 3);
+        assert_eq!(mesh.uvs[0], (0.5, 1.));
+        assert_eq!(mesh.faces.len(), 4);
+        assert_eq!(mesh.faces[0].vertices, [0, 1, 2]);
+        assert!(mesh.faces[0].uvs.is_none() && mesh.faces[0].normals.is_none());
+        assert_eq!(mesh.faces[1].uvs, Some([0, 1, 2]));
+        assert_eq!(mesh.faces[1].normals, Some([2, 0, 1]));
+        assert_eq!(mesh.faces[2].uvs, None);
+        assert_eq!(mesh.faces[2].normals, Some([2, 0, 1]));
+        assert_eq!(mesh.faces[3].vertices, [0, 1, 2]);
+        assert_eq!(mesh.faces[3].uvs, Some([0, 1, 2]));
+        assert_eq!(mesh.faces[3].normals, None);
+
+        let hit = FaceHit {
+            index: 1,
+            u: 0.45,
+            v: 0.25,
+        };
+        assert_eq!(mesh.normal_at(&hit), vector(-0.5547, 0.83205, 0.));
+        let (u, v) = mesh.uv_at(&hit).unwrap();
+        assert!((u - 0.4).abs() < 1e-9 && (v - 0.3).abs() < 1e-9);
+        assert_eq!(
+            mesh.normal_at(&FaceHit { index: 0, ..hit }),
+            vector(0., 0., 1.)
+        );
+    }
+
+    #[test]
+    fn test_triangulating_polygons() {
+        let str = "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+v 0 2 0
+vn 0 0 1
+f 1//1 2//1 3//1 4//1 5//1
+";
+        let mesh = ObjParser::parse(str, Path::new("")).unwrap();
+
+        assert_eq!(mesh.faces.len(), 3);
+        assert_eq!(mesh.faces[0].vertices, [0, 1, 2]);
+        assert_eq!(mesh.faces[1].vertices, [0, 2, 3]);
+        assert_eq!(mesh.faces[2].vertices, [0, 3, 4]);
+        assert_eq!(mesh.faces[2].normals, Some([0, 0, 0]));
+    }
+
+    #[test]
+    fn test_parsing_named_groups() {
+        let str = "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+f 1 2 3
+g FirstGroup
+f 1 2 3
+g SecondGroup
+f 1 3 4
+o FirstGroup
+f 2 3 4
+";
+        let mesh = ObjParser::parse(str, Path::new("")).unwrap();
+
+        assert_eq!(mesh.groups, vec!["FirstGroup", "SecondGroup"]);
+        let groups: Vec<Option<usize>> = mesh.faces.iter().map(|face| face.group).collect();
+        assert_eq!(groups, vec![None, Some(0), Some(1), Some(0)]);
+
+        let mut group = ObjParser::to_group(&mesh, &ImportOptions::new());
+        let children = Arc::get_mut(&mut group)
+            .unwrap()
+            .intersectable
+            .children_mut();
+        assert_eq!(children.len(), 3);
+        assert!(children[0].name.is_none());
+        assert!(children[1].is_named("FirstGroup"));
+        assert!(children[2].is_named("SecondGroup"));
+        let second = Arc::get_mut(&mut children[2]).unwrap();
+        second
+            .set_transform(Matrix4::translation(0., 0., 1.))
+            .unwrap();
 
-        assert_eq!(parser.vertices.len(), 0);
+        let ray = Ray {
+            origin: point(0.5, 0.9, -5.),
+            direction: vector(0., 0., 1.),
+        };
+        let hits = ray.intersect(group.clone());
+        assert_eq!(hits.len(), 1);
+        assert!(hits[0].object.is_named("SecondGroup"));
+        assert_eq!(hits[0].t, 6.);
     }
 
     #[test]
-    fn test_parsing_float_from_string() {
-        assert_eq!(ObjParser::float_from_string(String::from("1"), false), 1.);
-        assert_eq!(ObjParser::float_from_string(String::from("2.0"), false), 2.);
+    fn test_import_options() {
+        let str = "\
+v -100 -200 0
+v 100 -200 0
+v 0 -200 100
+f 1 2 3
+";
+        let mesh = ObjParser::parse(str, Path::new("")).unwrap();
+        let mut options = ImportOptions::new();
+        assert_eq!(options.transform(), IDENTITY_MATRIX);
+        options.units = Units::Centimeters;
+        options.up = UpAxis::Z;
         assert_eq!(
-            ObjParser::float_from_string(String::from("25.0"), true),
-            -25.
+            options.transform().multiply_point(&point(0., -200., 100.)),
+            point(0., 1., 2.)
         );
+
+        let ray = Ray {
+            origin: point(0., 0.5, -5.),
+            direction: vector(0., 0., 1.),
+        };
+        let normal = |hit: &Intersection| {
+            let position = ray.position(hit.t);
+            hit.object.normal_at_hit(&position, hit.face.as_ref())
+        };
+        let hits = ray.intersect(ObjParser::to_group(&mesh, &options));
+        assert_eq!(hits.len(), 1);
+        assert!(equal(hits[0].t, 7.));
+        let z_up = normal(&hits[0]);
+
+        // Mirroring moves the model and turns its normals around with it.
+        options.right_handed = true;
+        let hits = ray.intersect(ObjParser::to_group(&mesh, &options));
+        assert!(equal(hits[0].t, 3.));
+        assert_eq!(normal(&hits[0]), vector(z_up.x, z_up.y, -z_up.z));
+    }
+
+    #[test]
+    fn test_parsing_materials() {
+        let dir = env::temp_dir();
+        fs::write(
+            dir.join("ray_tracer_test_obj.mtl"),
+            "newmtl red\nKd 1 0 0\nnewmtl green\nKd 0 1 0\n",
+        )
+        .unwrap();
+        let str = "mtllib ray_tracer_test_obj.mtl
+v -1 1 0
+v -1 0 0
+v 1 0 0
+f 1 2 3
+usemtl green
+f 1 2 3
+usemtl missing
+f 1 2 3
+usemtl red
+f 1 2 3
+usemtl green
+f 1 2 3
+";
+        let mesh = ObjParser::parse(str, &dir).unwrap();
+
+        let materials: Vec<Option<usize>> = mesh.faces.iter().map(|face| face.material).collect();
+        assert_eq!(materials, vec![None, Some(0), None, Some(1), Some(0)]);
+        assert_eq!(mesh.materials.len(), 2);
         assert_eq!(
-            ObjParser::float_from_string(String::from("0.5"), false),
-            0.5
+            mesh.material(1)
+                .unwrap()
+                .pattern
+                .color_at(&point(0., 0., 0.)),
+            Color::new(0., 1., 0.)
         );
+        assert!(ObjParser::parse("mtllib no_such_library.mtl", &dir).is_err());
     }
 }
@@ -0,0 +1,187 @@
+use intersectable::IntersectableType;
+use matrix::{Matrix4, IDENTITY_MATRIX};
+use mesh::Mesh;
+use shape::Shape;
+use std::io::{self, Write};
+use world::World;
+
+// Writes the shapes of a world as a Wavefront OBJ, for opening scenes in
+// other tools. Everything is written in world space, one group per shape,
+// with analytic shapes tessellated into meshes first.
+pub struct ObjWriter {
+    // Planes are infinite, so they are written as squares this wide.
+    pub plane_size: f64,
+    // Slices around each sphere; they get half as many from pole to pole.
+    pub segments: usize,
+}
+
+// How much has been written so far, since OBJ indices count from the start
+// of the file rather than the start of each group.
+#[derive(Default)]
+struct Written {
+    normals: usize,
+    shapes: usize,
+    uvs: usize,
+    vertices: usize,
+}
+
+impl ObjWriter {
+    pub fn new() -> ObjWriter {
+        ObjWriter {
+            plane_size: 100.,
+            segments: 32,
+        }
+    }
+
+    pub fn write<W: Write>(&self, world: &World, writer: &mut W) -> io::Result<()> {
+        let mut written = Written::default();
+        for object in &world.objects {
+            self.write_shape(object, &IDENTITY_MATRIX, &mut written, writer)?;
+        }
+        Ok(())
+    }
+
+    fn write_shape<W: Write>(
+        &self,
+        shape: &Shape,
+        parent: &Matrix4,
+        written: &mut Written,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        let transform = parent.multiply(&shape.transform());
+        let intersectable = &shape.intersectable;
+        let (kind, mesh) = match *intersectable.intersectable_type() {
+            IntersectableType::Cube => ("cube", Mesh::cube()),
+            // There's no general way to turn one into triangles.
+            IntersectableType::Custom(_) => return Ok(()),
+            IntersectableType::Group => {
+                for child in intersectable.children() {
+                    self.write_shape(child, &transform, written, writer)?;
+                }
+                return Ok(());
+            }
+            IntersectableType::Mesh(ref mesh, _) => {
+                return self.write_mesh(shape, "mesh", mesh, &transform, written, writer);
+            }
+            IntersectableType::Plane => ("plane", Mesh::square(self.plane_size)),
+            IntersectableType::Sphere => ("sphere", Mesh::uv_sphere(self.segments)),
+            IntersectableType::Triangle(ref triangle) => {
+                let mut mesh = Mesh::new(vec![triangle.p1, triangle.p2, triangle.p3]);
+                mesh.add_face(0, 1, 2, None);
+                ("triangle", mesh)
+            }
+        };
+        self.write_mesh(shape, kind, &mesh, &transform, written, writer)
+    }
+
+    // Shapes without a name are named after their kind and position in the
+    // file, like `sphere3`.
+    fn write_mesh<W: Write>(
+        &self,
+        shape: &Shape,
+        kind: &str,
+        mesh: &Mesh,
+        transform: &Matrix4,
+        written: &mut Written,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        written.shapes += 1;
+        match shape.name {
+            Some(ref name) => writeln!(writer, "g {}", name)?,
+            None => writeln!(writer, "g {}{}", kind, written.shapes)?,
+        }
+        for vertex in &mesh.vertices {
+            let v = transform.multiply_point(vertex);
+            writeln!(writer, "v {} {} {}", v.x, v.y, v.z)?;
+        }
+        for &(u, v) in &mesh.uvs {
+            writeln!(writer, "vt {} {}", u, v)?;
+        }
+        let normal_transform = transform.inverse().transpose();
+        for normal in &mesh.normals {
+            let mut n = normal_transform.multiply_point(normal);
+            n.w = 0.;
+            let n = n.normalize();
+            writeln!(writer, "vn {} {} {}", n.x, n.y, n.z)?;
+        }
+        for face in &mesh.faces {
+            write!(writer, "f")?;
+            for i in 0..3 {
+                write!(writer, " {}", face.vertices[i] + written.vertices + 1)?;
+                match (face.uvs, face.normals) {
+                    (Some(uvs), Some(normals)) => write!(
+                        writer,
+                        "/{}/{}",
+                        uvs[i] + written.uvs + 1,
+                        normals[i] + written.normals + 1
+                    )?,
+                    (Some(uvs), None) => write!(writer, "/{}", uvs[i] + written.uvs + 1)?,
+                    (None, Some(normals)) => {
+                        write!(writer, "//{}", normals[i] + written.normals + 1)?
+                    }
+                    (None, None) => (),
+                }
+            }
+            writeln!(writer)?;
+        }
+        written.vertices += mesh.vertices.len();
+        written.uvs += mesh.uvs.len();
+        written.normals += mesh.normals.len();
+        Ok(())
+    }
+}
+
+impl Default for ObjWriter {
+    fn default() -> ObjWriter {
+        ObjWriter::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use obj_parser::ObjParser;
+    use obj_writer::ObjWriter;
+    use point::{point, vector};
+    use std::path::Path;
+    use world::World;
+
+    #[test]
+    fn test_writing_world_as_obj() {
+        let (world, _) = World::from_json(
+            r#"{
+                "camera": { "width": 1, "height": 1, "field_of_view": 1, "from": [0, 0, -1], "to": [0, 0, 0] },
+                "light": { "position": [0, 10, 0], "intensity": [1, 1, 1] },
+                "shapes": [
+                    { "type": "plane" },
+                    {
+                        "type": "group",
+                        "transform": [{ "translate": [0, 0, 5] }],
+                        "children": [
+                            { "type": "cube", "name": "box", "transform": [{ "scale": [2, 1, 1] }] },
+                            { "type": "sphere" },
+                            { "type": "triangle", "points": [[0, 1, 0], [-1, 0, 0], [1, 0, 0]] }
+                        ]
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let mut writer = ObjWriter::new();
+        writer.plane_size = 10.;
+        writer.segments = 8;
+        let mut obj = Vec::new();
+
+        writer.write(&world, &mut obj).unwrap();
+        let mesh = ObjParser::parse(&String::from_utf8(obj).unwrap(), Path::new("")).unwrap();
+
+        assert_eq!(mesh.groups, vec!["plane1", "box", "sphere3", "triangle4"]);
+        assert_eq!(mesh.faces.len(), 2 + 12 + 48 + 1);
+        assert_eq!(mesh.vertices[0], point(-5., 0., -5.));
+        let (p1, _, _) = mesh.face_points(2);
+        assert_eq!(p1, point(2., -1., 4.));
+        assert_eq!(mesh.normals[1], vector(1., 0., 0.));
+        let triangle = mesh.faces.last().unwrap();
+        assert!(triangle.normals.is_none());
+        assert_eq!(mesh.vertices[triangle.vertices[0]], point(0., 1., 5.));
+    }
+}
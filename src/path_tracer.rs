@@ -0,0 +1,266 @@
+use bsdf::Bsdf;
+use color::Color;
+use point::Point;
+use ray::Ray;
+use shape::Shape;
+use world::World;
+
+// Unidirectional path tracing. A path is traced from the camera, choosing
+// each bounce by sampling the surface's BSDF, and at every surface the
+// light is also sampled directly with a shadow ray (next-event
+// estimation). A light with an area can then be reached both ways, so each
+// is weighted by the power heuristic against the other; a small light is
+// found almost every time by sampling it, and a broad one by the BSDF.
+// An environment image is sampled by brightness and weighted the same way,
+// so a small sun in it and a dim overcast sky both converge quickly.
+// `remaining` caps how many surfaces a path may touch.
+//
+// Lights are as bright here as in the bidirectional integrator, so the two
+// converge on the same image.
+pub fn radiance(world: &World, ray: &Ray, remaining: i32) -> Color {
+    radiance_between(world, ray, remaining, 0., f64::INFINITY)
+}
+
+// As `radiance`, seeing only the surfaces between `near` and `far` along
+// the camera ray. Every bounce after it sees the whole world.
+pub fn radiance_between(world: &World, ray: &Ray, remaining: i32, near: f64, far: f64) -> Color {
+    let light = &world.light_source;
+    let mut color = Color::black();
+    let mut beta = Color::white();
+    let mut ray = Ray {
+        origin: ray.origin,
+        direction: ray.direction,
+    };
+    let (mut near, mut far) = (near, far);
+    // How the ray was chosen: by the camera or a delta lobe, which light
+    // sampling can't compete with, or otherwise with this density.
+    let mut scattered_pdf: Option<f64> = None;
+    let mut bounces = 0;
+    loop {
+        let hit = world.hit_between(&ray, near, far);
+        let light_hit = light.intersect(&ray).filter(|t| (near..=far).contains(t));
+        if let Some(t) = light_hit {
+            if hit.as_ref().is_none_or(|hit| t < hit.t) {
+                let weight = match scattered_pdf {
+                    Some(pdf) => {
+                        let hit_point = ray.position(t);
+                        let normal = light.normal_at(&hit_point);
+                        let light_pdf = light_pdf(world, &ray.origin, &hit_point, &normal);
+                        power_heuristic(pdf, light_pdf)
+                    }
+                    None => 1.,
+                };
+                color = color.add(
+                    &beta
+                        .hadamard_product(&light.radiance())
+                        .multiply_scalar(weight),
+                );
+                break;
+            }
+        }
+        let precompute = match hit {
+            Some(precompute) => precompute,
+            None => {
+                let weight = match (scattered_pdf, world.environment.as_ref()) {
+                    (Some(pdf), Some(environment)) => {
+                        power_heuristic(pdf, environment.pdf(&ray.direction))
+                    }
+                    _ => 1.,
+                };
+                color = color.add(
+                    &beta
+                        .hadamard_product(&world.background_color(&ray))
+                        .multiply_scalar(weight),
+                );
+                break;
+            }
+        };
+        if bounces >= remaining {
+            break;
+        }
+        bounces += 1;
+
+        let bsdf = Bsdf::new(&precompute);
+        color = color.add(&beta.hadamard_product(&sample_light(
+            world,
+            &bsdf,
+            &precompute.object,
+            &precompute.point,
+        )));
+        color = color.add(&beta.hadamard_product(&sample_environment(world, &bsdf)));
+
+        let sample = match bsdf.sample() {
+            Some(sample) => sample,
+            None => break,
+        };
+        beta = beta.hadamard_product(&sample.weight);
+        scattered_pdf = if sample.delta {
+            None
+        } else {
+            Some(bsdf.pdf(&sample.direction))
+        };
+        ray = Ray {
+            origin: sample.origin,
+            direction: sample.direction,
+        };
+        near = 0.;
+        far = f64::INFINITY;
+    }
+    color
+}
+
+// The light arriving at the surface straight from a random point on the
+// light, scattered toward the eye, unless the light isn't linked to `shape`.
+fn sample_light(world: &World, bsdf: &Bsdf, shape: &Shape, point: &Point) -> Color {
+    let light = &world.light_source;
+    if !light.illuminates(shape) {
+        return Color::black();
+    }
+    let (light_point, light_normal) = light.sample_surface();
+    let to_light = light_point.sub(point);
+    let distance = to_light.magnitude();
+    let direction = to_light.normalize();
+    let f = bsdf.evaluate(&direction);
+    if f == Color::black() {
+        return Color::black();
+    }
+    let cos = bsdf.normal().dot(&direction);
+    let incoming = if light.area() > 0. {
+        let light_pdf = light_pdf(world, point, &light_point, &light_normal);
+        if light_pdf == 0. {
+            return Color::black();
+        }
+        let weight = power_heuristic(light_pdf, bsdf.pdf(&direction));
+        light.radiance().multiply_scalar(weight / light_pdf)
+    } else {
+        light
+            .intensity_at(point)
+            .multiply_scalar(1. / (distance * distance))
+    };
+    let visibility = world.transmittance_from(&bsdf.over_point(), &light_point);
+    if visibility == 0. {
+        return Color::black();
+    }
+    f.hadamard_product(&incoming)
+        .multiply_scalar(cos * visibility)
+}
+
+// The light arriving at the surface from a direction picked on the
+// environment image, if nothing is in the way, scattered toward the eye.
+fn sample_environment(world: &World, bsdf: &Bsdf) -> Color {
+    let environment = match world.environment {
+        Some(ref environment) => environment,
+        None => return Color::black(),
+    };
+    let (direction, pdf) = match environment.sample() {
+        Some(sample) => sample,
+        None => return Color::black(),
+    };
+    let f = bsdf.evaluate(&direction);
+    if f == Color::black() {
+        return Color::black();
+    }
+    let ray = Ray {
+        origin: bsdf.over_point(),
+        direction,
+    };
+    if world.light_source.intersect(&ray).is_some() || world.hit(&ray).is_some() {
+        return Color::black();
+    }
+    let cos = bsdf.normal().dot(&direction);
+    let weight = power_heuristic(pdf, bsdf.pdf(&direction));
+    f.hadamard_product(&environment.color_in(&direction))
+        .multiply_scalar(cos * weight / pdf)
+}
+
+// The density, per unit of solid angle seen from `from`, of picking
+// `light_point` on the light's surface. Points facing away can't be seen,
+// so they have none.
+fn light_pdf(world: &World, from: &Point, light_point: &Point, light_normal: &Point) -> f64 {
+    let to_light = light_point.sub(from);
+    let distance = to_light.magnitude();
+    let cos = -light_normal.dot(&to_light.normalize());
+    if cos <= 0. {
+        return 0.;
+    }
+    distance * distance / (cos * world.light_source.area())
+}
+
+fn power_heuristic(pdf: f64, other: f64) -> f64 {
+    pdf * pdf / (pdf * pdf + other * other)
+}
+
+#[cfg(test)]
+mod tests {
+    use color::Color;
+    use environment::Environment;
+    use path_tracer::{power_heuristic, radiance};
+    use point::point;
+    use point_light::{LightLink, PointLight};
+    use sampling::reseed;
+    use std::sync::Arc;
+    use test_support::{floor_world, ray, FLOOR_RADIANCE};
+    use texture::Texture;
+
+    #[test]
+    fn test_direct_light_from_point_light() {
+        let world = floor_world(0.);
+
+        let color = radiance(&world, &ray(), 1);
+
+        assert_eq!(
+            color,
+            Color::new(FLOOR_RADIANCE, FLOOR_RADIANCE, FLOOR_RADIANCE)
+        );
+    }
+
+    #[test]
+    fn test_no_direct_light_on_unlinked_shapes() {
+        let mut world = floor_world(0.);
+        let mut light = PointLight::new(point(0., 2., 0.), Color::white());
+        light.illuminates = LightLink::Include(Vec::new());
+        world.light_source = Arc::new(light);
+
+        assert_eq!(radiance(&world, &ray(), 1), Color::black());
+    }
+
+    // Bouncing into a light this small would take many times the paths to
+    // get as close.
+    #[test]
+    fn test_direct_light_from_small_spherical_light() {
+        let world = floor_world(0.1);
+        let samples = 2000;
+        reseed(1, 0);
+
+        let mut total = 0.;
+        for _ in 0..samples {
+            total += radiance(&world, &ray(), 1).red;
+        }
+
+        assert!((total / samples as f64 - FLOOR_RADIANCE).abs() < FLOOR_RADIANCE * 0.05);
+    }
+
+    #[test]
+    fn test_light_from_the_environment() {
+        let mut world = floor_world(0.);
+        world.light_source = Arc::new(PointLight::new(point(0., 2., 0.), Color::black()));
+        world.environment = Some(Environment::new(Texture::new(1, 1, vec![Color::white()])));
+        let samples = 2000;
+        reseed(1, 0);
+
+        let mut total = 0.;
+        for _ in 0..samples {
+            total += radiance(&world, &ray(), 1).red;
+        }
+
+        // Half the light arriving from the whole sky is reflected.
+        assert!((total / samples as f64 - 0.5).abs() < 0.5 * 0.05);
+    }
+
+    #[test]
+    fn test_power_heuristic() {
+        assert_eq!(power_heuristic(1., 0.), 1.);
+        assert_eq!(power_heuristic(1., 1.), 0.5);
+        assert_eq!(power_heuristic(1., 3.), 0.1);
+    }
+}
@@ -0,0 +1,73 @@
+use color::Color;
+use patternable::Patternable;
+use point::Point;
+use std::fmt::Debug;
+
+// A color for every point in space, for procedural patterns defined
+// outside the crate. Wrap one with `Patternable::custom` to give it a
+// transform and use it anywhere a built-in pattern goes, including inside
+// blends and checkers.
+pub trait Pattern: Debug + Send + Sync {
+    // The color at a point in the pattern's own space.
+    fn color_at(&self, point: &Point) -> Color;
+}
+
+// The built-in patterns. As with a custom pattern, the transform is left
+// to whatever holds the pattern.
+impl Pattern for Patternable {
+    fn color_at(&self, point: &Point) -> Color {
+        Patternable::color_at(self, point)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use color::Color;
+    use matrix::Matrix4;
+    use pattern::Pattern;
+    use patternable::{BlendMode, Patternable};
+    use point::{point, Point};
+    use shape::Shape;
+
+    // Brighter the higher up it is, from black at y = 0 to white at y = 1.
+    #[derive(Debug)]
+    struct Height;
+
+    impl Pattern for Height {
+        fn color_at(&self, point: &Point) -> Color {
+            let y = point.y.clamp(0., 1.);
+            Color::new(y, y, y)
+        }
+    }
+
+    #[test]
+    fn test_custom_pattern() {
+        let mut height = Patternable::custom(Height);
+        height.transform = Matrix4::scaling(1., 2., 1.);
+        let shape = Shape::sphere();
+
+        assert_eq!(
+            height.color_at_object(&shape, &point(0., 1., 0.)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+
+        let product = Patternable::blend(
+            Patternable::solid(Color::new(1., 0.5, 1.)),
+            Patternable::custom(Height),
+            BlendMode::Multiply,
+        );
+        assert_eq!(
+            product.color_at(&point(0., 0.25, 0.)),
+            Color::new(0.25, 0.125, 0.25)
+        );
+    }
+
+    #[test]
+    fn test_built_in_patterns_are_patterns() {
+        let stripe = Patternable::stripe(Color::white(), Color::black());
+        let pattern: &dyn Pattern = &stripe;
+
+        assert_eq!(pattern.color_at(&point(0.5, 0., 0.)), Color::white());
+        assert_eq!(pattern.color_at(&point(1.5, 0., 0.)), Color::black());
+    }
+}
@@ -1,21 +1,73 @@
 use color::Color;
+use fractal::{Fbm, NoiseSource};
+use image;
+use intersection::SurfaceHit;
 use matrix::Matrix4;
 use matrix::IDENTITY_MATRIX;
-use noise::{NoiseFn, Perlin as PerlinNoise};
+use noise::{NoiseFn, OpenSimplex, Perlin as PerlinNoise};
+use pattern::Pattern;
 use point::point;
 use point::Point;
+use serde::{Deserialize, Serialize};
 use shape::Shape;
-use utilities::equal;
+use std::f64::consts::PI;
+use std::path::Path;
+use std::sync::Arc;
+use texture::{Texture, Wrap};
+use utilities::{equal, max};
+use uv_mapping::{cube_face_uv, CubeFace};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interpolation {
+    Constant,
+    Linear,
+    Smooth,
+}
+
+// How the second pattern of a blend is combined with the first. Mask
+// mixes from the first to the second by the mask pattern's red channel.
+#[derive(Debug, Clone)]
+pub enum BlendMode {
+    Add,
+    Mask(Box<Patternable>),
+    Mix,
+    Multiply,
+    Overlay,
+    Screen,
+}
 
 #[derive(Debug, Clone)]
 pub enum PatternableType {
-    Blended(Box<Patternable>, Box<Patternable>),
-    Checker(Box<Patternable>, Box<Patternable>),
+    Blended(Box<Patternable>, Box<Patternable>, BlendMode),
+    Checker(Box<Patternable>, Box<Patternable>, f64),
+    CubeMap(Box<[Patternable; 6]>),
+    // A pattern from outside the crate.
+    Custom(Arc<dyn Pattern>),
+    Fbm(Box<Fbm>, Box<Patternable>),
     Gradient(Box<Patternable>, Box<Patternable>),
-    Perlin(PerlinNoise, Box<Patternable>, f64),
+    Image(Arc<Texture>, Wrap, Wrap),
+    Marble(Box<Fbm>, Box<Patternable>, f64),
+    Perlin(Box<PerlinNoise>, Box<Patternable>, f64),
+    RadialGradient(Box<Patternable>, Box<Patternable>),
+    Ramp(Vec<(f64, Color)>, Interpolation),
     Ring(Box<Patternable>, Box<Patternable>),
+    Simplex(Box<OpenSimplex>, Box<Patternable>, f64, f64),
     Solid(Color),
+    SphericalGradient(Box<Patternable>, Box<Patternable>),
+    Stencil(Arc<Texture>, Box<Patternable>, Box<Patternable>),
     Stripe(Box<Patternable>, Box<Patternable>),
+    Uv(Box<Patternable>),
+    VertexColors,
+    Wood(Box<Fbm>, Box<Patternable>, Axis, f64, f64),
 }
 
 #[derive(Debug, Clone)]
@@ -42,11 +94,42 @@ impl Patternable {
         }
     }
 
+    // Blends from `color` on the y axis to `secondary` one unit away from
+    // it, and stays `secondary` beyond that. Scale the pattern to resize.
+    pub fn radial_gradient(color: Color, secondary: Color) -> Patternable {
+        Patternable {
+            patternable_type: PatternableType::RadialGradient(
+                Box::new(Patternable::solid(color)),
+                Box::new(Patternable::solid(secondary)),
+            ),
+            transform: IDENTITY_MATRIX,
+        }
+    }
+
+    // As radial_gradient, but by distance from the origin.
+    pub fn spherical_gradient(color: Color, secondary: Color) -> Patternable {
+        Patternable {
+            patternable_type: PatternableType::SphericalGradient(
+                Box::new(Patternable::solid(color)),
+                Box::new(Patternable::solid(secondary)),
+            ),
+            transform: IDENTITY_MATRIX,
+        }
+    }
+
     pub fn checker(color: Color, secondary: Color) -> Patternable {
+        Patternable::filtered_checker(color, secondary, 0.)
+    }
+
+    // A checker box-filtered over a footprint `spread` units wide per unit
+    // of distance from the eye, so it fades to the average instead of
+    // aliasing far away. A camera's pixel_size is a good spread.
+    pub fn filtered_checker(color: Color, secondary: Color, spread: f64) -> Patternable {
         Patternable {
             patternable_type: PatternableType::Checker(
                 Box::new(Patternable::solid(color)),
                 Box::new(Patternable::solid(secondary)),
+                spread,
             ),
             transform: IDENTITY_MATRIX,
         }
@@ -72,53 +155,285 @@ impl Patternable {
         }
     }
 
+    // Colors at positions along x, held flat before the first stop and
+    // after the last. Marble, wood and the noise patterns feed their value
+    // in as x, so a ramp can color any of them.
+    pub fn ramp(mut stops: Vec<(f64, Color)>, interpolation: Interpolation) -> Patternable {
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Patternable {
+            patternable_type: PatternableType::Ramp(stops, interpolation),
+            transform: IDENTITY_MATRIX,
+        }
+    }
+
+    pub fn image<P: AsRef<Path>>(path: P) -> image::ImageResult<Patternable> {
+        Ok(Patternable::texture(Arc::new(Texture::open(path)?)))
+    }
+
+    pub fn texture(texture: Arc<Texture>) -> Patternable {
+        Patternable::wrapped_texture(texture, Wrap::Repeat, Wrap::Repeat)
+    }
+
+    // The pattern's transform scales and offsets the texture in UV space,
+    // so a scaled-down repeating texture tiles and a clamped one can be
+    // moved about as a decal.
+    pub fn wrapped_texture(texture: Arc<Texture>, wrap_u: Wrap, wrap_v: Wrap) -> Patternable {
+        Patternable {
+            patternable_type: PatternableType::Image(texture, wrap_u, wrap_v),
+            transform: IDENTITY_MATRIX,
+        }
+    }
+
+    // Picks between two patterns by the brightness of a bitmap laid over the
+    // shape's UV coordinates: `color` where it is black and `secondary`
+    // where it is white, blending in between.
+    pub fn stencil(
+        texture: Arc<Texture>,
+        color: Patternable,
+        secondary: Patternable,
+    ) -> Patternable {
+        Patternable {
+            patternable_type: PatternableType::Stencil(
+                texture,
+                Box::new(color),
+                Box::new(secondary),
+            ),
+            transform: IDENTITY_MATRIX,
+        }
+    }
+
+    // The colors of a mesh's vertices, blended across each face. Shapes
+    // without vertex colors are white.
+    pub fn vertex_colors() -> Patternable {
+        Patternable {
+            patternable_type: PatternableType::VertexColors,
+            transform: IDENTITY_MATRIX,
+        }
+    }
+
+    // Evaluates the pattern over the shape's UV coordinates instead of
+    // its object space, so it wraps around curved surfaces.
+    pub fn uv(pattern: Patternable) -> Patternable {
+        Patternable {
+            patternable_type: PatternableType::Uv(Box::new(pattern)),
+            transform: IDENTITY_MATRIX,
+        }
+    }
+
+    // Six patterns, one per face of the unit cube, in the order left, front,
+    // right, back, up, down. Each is sampled with its face's UV coordinates.
+    pub fn cube_map(faces: [Patternable; 6]) -> Patternable {
+        Patternable {
+            patternable_type: PatternableType::CubeMap(Box::new(faces)),
+            transform: IDENTITY_MATRIX,
+        }
+    }
+
     pub fn blended(primary: Patternable, secondary: Patternable) -> Patternable {
+        Patternable::blend(primary, secondary, BlendMode::Mix)
+    }
+
+    pub fn blend(primary: Patternable, secondary: Patternable, mode: BlendMode) -> Patternable {
         Patternable {
-            patternable_type: PatternableType::Blended(Box::new(primary), Box::new(secondary)),
+            patternable_type: PatternableType::Blended(
+                Box::new(primary),
+                Box::new(secondary),
+                mode,
+            ),
             transform: IDENTITY_MATRIX,
         }
     }
 
     pub fn perlin(pattern: Patternable) -> Patternable {
         Patternable {
-            patternable_type: PatternableType::Perlin(PerlinNoise::new(), Box::new(pattern), 0.25),
+            patternable_type: PatternableType::Perlin(
+                Box::new(PerlinNoise::new()),
+                Box::new(pattern),
+                0.25,
+            ),
+            transform: IDENTITY_MATRIX,
+        }
+    }
+
+    // The fBm value at each point becomes the x coordinate at which
+    // `pattern` is sampled, so a gradient turns it into a color.
+    pub fn fbm(fbm: Fbm, pattern: Patternable) -> Patternable {
+        Patternable {
+            patternable_type: PatternableType::Fbm(Box::new(fbm), Box::new(pattern)),
+            transform: IDENTITY_MATRIX,
+        }
+    }
+
+    // Veins along x, bent by turbulence scaled by `distortion`. The vein
+    // value is fed to `ramp` as its x coordinate, like fbm.
+    pub fn marble(ramp: Patternable, distortion: f64) -> Patternable {
+        let mut turbulence = Fbm::new(NoiseSource::perlin());
+        turbulence.octaves = 6;
+        turbulence.turbulence = true;
+        Patternable {
+            patternable_type: PatternableType::Marble(
+                Box::new(turbulence),
+                Box::new(ramp),
+                distortion,
+            ),
+            transform: IDENTITY_MATRIX,
+        }
+    }
+
+    // Unit-spaced rings around `axis`, wobbled by noise scaled by
+    // `distortion`. Higher `sharpness` narrows the late wood of each ring;
+    // the position within a ring is fed to `ramp` as its x coordinate.
+    pub fn wood(ramp: Patternable, axis: Axis, sharpness: f64, distortion: f64) -> Patternable {
+        let mut noise = Fbm::new(NoiseSource::perlin());
+        noise.octaves = 3;
+        Patternable {
+            patternable_type: PatternableType::Wood(
+                Box::new(noise),
+                Box::new(ramp),
+                axis,
+                sharpness,
+                distortion,
+            ),
+            transform: IDENTITY_MATRIX,
+        }
+    }
+
+    // Like perlin, but with OpenSimplex noise sampled at `frequency` and
+    // displacing the pattern by up to `amplitude`.
+    pub fn simplex(pattern: Patternable, frequency: f64, amplitude: f64) -> Patternable {
+        Patternable {
+            patternable_type: PatternableType::Simplex(
+                Box::new(OpenSimplex::new()),
+                Box::new(pattern),
+                frequency,
+                amplitude,
+            ),
             transform: IDENTITY_MATRIX,
         }
     }
 
+    pub fn custom<P: Pattern + 'static>(pattern: P) -> Patternable {
+        Patternable {
+            patternable_type: PatternableType::Custom(Arc::new(pattern)),
+            transform: IDENTITY_MATRIX,
+        }
+    }
+
+    pub fn patternable_type(&self) -> &PatternableType {
+        &self.patternable_type
+    }
+
     pub fn color_at(&self, point: &Point) -> Color {
         match self.patternable_type {
-            PatternableType::Blended(ref a, ref b) => self.color_at_blended(point, a, b),
-            PatternableType::Checker(ref a, ref b) => {
+            PatternableType::Blended(ref a, ref b, ref mode) => {
+                self.color_at_blended(point, a, b, mode)
+            }
+            PatternableType::Checker(ref a, ref b, _) => {
                 self.color_at_checker(point, a.color_at(point), b.color_at(point))
             }
+            PatternableType::CubeMap(ref faces) => self.color_at_cube_map(point, faces),
+            PatternableType::Custom(ref pattern) => pattern.color_at(point),
+            PatternableType::Fbm(ref fbm, ref pattern) => self.color_at_fbm(point, fbm, pattern),
             PatternableType::Gradient(ref a, ref b) => {
                 self.color_at_gradient(point, a.color_at(point), b.color_at(point))
             }
-            PatternableType::Perlin(perlin, ref pattern, factor) => {
+            // Without an object to map from, images lie flat on the xz plane.
+            PatternableType::Image(ref texture, wrap_u, wrap_v) => {
+                texture.color_at_bilinear(wrap_u.apply(point.x), wrap_v.apply(point.z))
+            }
+            PatternableType::Marble(ref turbulence, ref ramp, distortion) => {
+                self.color_at_marble(point, turbulence, ramp, distortion)
+            }
+            PatternableType::Perlin(ref perlin, ref pattern, factor) => {
                 self.color_at_perlin(point, pattern, perlin, factor)
             }
+            PatternableType::RadialGradient(ref a, ref b) => {
+                let distance = (point.x.powi(2) + point.z.powi(2)).sqrt();
+                self.color_at_radius(distance, a.color_at(point), b.color_at(point))
+            }
+            PatternableType::Ramp(ref stops, interpolation) => {
+                self.color_at_ramp(point, stops, interpolation)
+            }
             PatternableType::Ring(ref a, ref b) => {
                 self.color_at_ring(point, a.color_at(point), b.color_at(point))
             }
+            PatternableType::Simplex(ref simplex, ref pattern, frequency, amplitude) => {
+                self.color_at_simplex(point, pattern, simplex, frequency, amplitude)
+            }
             PatternableType::Solid(c) => c,
+            PatternableType::SphericalGradient(ref a, ref b) => {
+                let distance = (point.x.powi(2) + point.y.powi(2) + point.z.powi(2)).sqrt();
+                self.color_at_radius(distance, a.color_at(point), b.color_at(point))
+            }
+            PatternableType::Stencil(ref texture, ref a, ref b) => {
+                self.color_at_stencil(point, texture, a, b)
+            }
             PatternableType::Stripe(ref a, ref b) => {
                 self.color_at_stripe(point, a.color_at(point), b.color_at(point))
             }
+            PatternableType::Uv(ref pattern) => pattern.color_at_uv(point.x, point.z),
+            PatternableType::VertexColors => Color::white(),
+            PatternableType::Wood(ref noise, ref ramp, axis, sharpness, distortion) => {
+                self.color_at_wood(point, noise, ramp, axis, sharpness, distortion)
+            }
         }
     }
 
     pub fn color_at_object(&self, object: &Shape, point: &Point) -> Color {
-        let local = object.transform.inverse().multiply_point(&point);
-        let pattern_local = self.transform.inverse().multiply_point(&local);
-        self.color_at(&pattern_local)
+        self.color_at_hit(object, point, &SurfaceHit::default())
+    }
+
+    // As color_at_object, knowing more about the hit: filtered patterns
+    // size their footprint by its distance, and vertex colors need its face.
+    pub fn color_at_hit(&self, object: &Shape, point: &Point, hit: &SurfaceHit) -> Color {
+        match self.patternable_type {
+            PatternableType::VertexColors => hit
+                .face
+                .and_then(|face| object.vertex_color(&face))
+                .unwrap_or_else(Color::white),
+            PatternableType::Image(..) | PatternableType::Stencil(..) | PatternableType::Uv(_) => {
+                // Meshes with texture coordinates carry their own mapping.
+                let (u, v) = hit
+                    .face
+                    .and_then(|face| object.face_uv(&face))
+                    .unwrap_or_else(|| object.uv_at(point));
+                self.color_at_uv(u, v)
+            }
+            _ => {
+                let local = object.world_to_object(point);
+                let pattern_local = self.transform.inverse().multiply_point(&local);
+                match self.patternable_type {
+                    PatternableType::Checker(ref a, ref b, spread)
+                        if spread * hit.distance > 0. =>
+                    {
+                        self.color_at_filtered_checker(
+                            &pattern_local,
+                            a.color_at(&pattern_local),
+                            b.color_at(&pattern_local),
+                            spread * hit.distance,
+                        )
+                    }
+                    _ => self.color_at(&pattern_local),
+                }
+            }
+        }
+    }
+
+    // UV coordinates are laid out on the xz plane, u along x and v along z,
+    // before the pattern's own transform is applied.
+    pub fn color_at_uv(&self, u: f64, v: f64) -> Color {
+        self.color_at(&self.transform.inverse().multiply_point(&point(u, 0., v)))
     }
 
     fn color_at_gradient(&self, point: &Point, a: Color, b: Color) -> Color {
-        let difference = a.sub(&b);
+        let difference = b.sub(&a);
         a.add(&difference.multiply_scalar(point.x - point.x.floor()))
     }
 
+    fn color_at_radius(&self, distance: f64, a: Color, b: Color) -> Color {
+        a.add(&b.sub(&a).multiply_scalar(distance.min(1.)))
+    }
+
     fn color_at_checker(&self, point: &Point, primary: Color, secondary: Color) -> Color {
         let sum = point.x.round() + point.y.round() + point.z.round();
         if equal(sum.abs() % 2., 0.) {
@@ -128,6 +443,30 @@ impl Patternable {
         }
     }
 
+    // The checker is the product of a square wave on each axis, so the box
+    // filter is the product of each wave's average over the footprint.
+    fn color_at_filtered_checker(&self, point: &Point, a: Color, b: Color, width: f64) -> Color {
+        let average = |x: f64| {
+            let integral = |x: f64| 1. - ((x + 0.5).rem_euclid(2.) - 1.).abs();
+            (integral(x + width / 2.) - integral(x - width / 2.)) / width
+        };
+        let wave = average(point.x) * average(point.y) * average(point.z);
+        a.add(&b.sub(&a).multiply_scalar((1. - wave) / 2.))
+    }
+
+    fn color_at_stencil(
+        &self,
+        point: &Point,
+        texture: &Texture,
+        a: &Patternable,
+        b: &Patternable,
+    ) -> Color {
+        let pixel = texture.color_at_bilinear(point.x.rem_euclid(1.), point.z.rem_euclid(1.));
+        let t = pixel.luminance();
+        let (a, b) = (a.color_at(point), b.color_at(point));
+        a.add(&b.sub(&a).multiply_scalar(t.clamp(0., 1.)))
+    }
+
     fn color_at_stripe(&self, point: &Point, a: Color, b: Color) -> Color {
         if equal(point.x.floor().abs() % 2.0, 0.0) {
             a
@@ -148,7 +487,7 @@ impl Patternable {
         &self,
         local_point: &Point,
         pattern: &Patternable,
-        perlin: PerlinNoise,
+        perlin: &PerlinNoise,
         factor: f64,
     ) -> Color {
         let addition = perlin.get([local_point.x, local_point.y, local_point.z]) * factor;
@@ -159,19 +498,158 @@ impl Patternable {
         ))
     }
 
-    fn color_at_blended(&self, point: &Point, a: &Patternable, b: &Patternable) -> Color {
-        a.color_at(&point).add(&b.color_at(&point)).divide(2.0)
+    // Points off the cube, such as directions, are projected onto it first.
+    fn color_at_cube_map(&self, point: &Point, faces: &[Patternable; 6]) -> Color {
+        let scale = max(&[point.x.abs(), point.y.abs(), point.z.abs()]);
+        if scale == 0. {
+            return Color::black();
+        }
+        let (face, u, v) = cube_face_uv(&point.multiply_scalar(1. / scale));
+        let index = match face {
+            CubeFace::Left => 0,
+            CubeFace::Front => 1,
+            CubeFace::Right => 2,
+            CubeFace::Back => 3,
+            CubeFace::Up => 4,
+            CubeFace::Down => 5,
+        };
+        faces[index].color_at_uv(u, v)
+    }
+
+    fn color_at_fbm(&self, local_point: &Point, fbm: &Fbm, pattern: &Patternable) -> Color {
+        pattern.color_at(&point(fbm.value_at(local_point), 0., 0.))
+    }
+
+    fn color_at_marble(
+        &self,
+        local_point: &Point,
+        turbulence: &Fbm,
+        ramp: &Patternable,
+        distortion: f64,
+    ) -> Color {
+        let phase = local_point.x + distortion * turbulence.value_at(local_point);
+        ramp.color_at(&point(0.5 + 0.5 * (phase * PI).sin(), 0., 0.))
+    }
+
+    fn color_at_ramp(
+        &self,
+        point: &Point,
+        stops: &[(f64, Color)],
+        interpolation: Interpolation,
+    ) -> Color {
+        let (first, last) = match (stops.first(), stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Color::black(),
+        };
+        if point.x <= first.0 {
+            return first.1;
+        }
+        if point.x >= last.0 {
+            return last.1;
+        }
+        // Only a NaN, which no stop is above, gets this far without one.
+        let i = match stops.iter().position(|stop| stop.0 > point.x) {
+            Some(i) => i,
+            None => return last.1,
+        };
+        let ((x0, c0), (x1, c1)) = (stops[i - 1], stops[i]);
+        let t = (point.x - x0) / (x1 - x0);
+        let t = match interpolation {
+            Interpolation::Constant => 0.,
+            Interpolation::Linear => t,
+            Interpolation::Smooth => t * t * (3. - 2. * t),
+        };
+        c0.add(&c1.sub(&c0).multiply_scalar(t))
+    }
+
+    fn color_at_wood(
+        &self,
+        local_point: &Point,
+        noise: &Fbm,
+        ramp: &Patternable,
+        axis: Axis,
+        sharpness: f64,
+        distortion: f64,
+    ) -> Color {
+        let (a, b) = match axis {
+            Axis::X => (local_point.y, local_point.z),
+            Axis::Y => (local_point.x, local_point.z),
+            Axis::Z => (local_point.x, local_point.y),
+        };
+        let radius = (a * a + b * b).sqrt() + distortion * (noise.value_at(local_point) - 0.5);
+        let ring = radius - radius.floor();
+        ramp.color_at(&point(ring.powf(sharpness.max(0.)), 0., 0.))
+    }
+
+    fn color_at_simplex(
+        &self,
+        local_point: &Point,
+        pattern: &Patternable,
+        simplex: &OpenSimplex,
+        frequency: f64,
+        amplitude: f64,
+    ) -> Color {
+        let scaled = local_point.multiply_scalar(frequency);
+        let addition = simplex.get([scaled.x, scaled.y, scaled.z]) * amplitude;
+        pattern.color_at(&point(
+            local_point.x + addition,
+            local_point.y + addition,
+            local_point.z + addition,
+        ))
+    }
+
+    fn color_at_blended(
+        &self,
+        point: &Point,
+        a: &Patternable,
+        b: &Patternable,
+        mode: &BlendMode,
+    ) -> Color {
+        let (a, b) = (a.color_at(point), b.color_at(point));
+        match *mode {
+            BlendMode::Add => a.add(&b),
+            BlendMode::Mask(ref mask) => {
+                let t = mask.color_at(point).red.clamp(0., 1.);
+                a.add(&b.sub(&a).multiply_scalar(t))
+            }
+            BlendMode::Mix => a.add(&b).divide(2.0),
+            BlendMode::Multiply => a.hadamard_product(&b),
+            BlendMode::Overlay => Color::new(
+                overlay(a.red, b.red),
+                overlay(a.green, b.green),
+                overlay(a.blue, b.blue),
+            ),
+            BlendMode::Screen => Color::white().sub(
+                &Color::white()
+                    .sub(&a)
+                    .hadamard_product(&Color::white().sub(&b)),
+            ),
+        }
+    }
+}
+
+// Multiplies darks and screens lights, by the base channel.
+fn overlay(base: f64, blend: f64) -> f64 {
+    if base < 0.5 {
+        2. * base * blend
+    } else {
+        1. - 2. * (1. - base) * (1. - blend)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use color::Color;
+    use fractal::{Fbm, NoiseSource};
+    use intersection::{FaceHit, SurfaceHit};
     use matrix::Matrix4;
-    use patternable::Patternable;
+    use mesh::Mesh;
+    use patternable::{Axis, BlendMode, Interpolation, Patternable};
     use point::point;
     use shape::Shape;
     use std::sync::Arc;
+    use texture::{Texture, Wrap};
+    use uv_mapping::UvMapping;
 
     #[test]
     fn test_color_at_stripe() {
@@ -190,10 +668,402 @@ mod tests {
         assert_eq!(p.color_at(&point(-2.0, 0.0, 0.0)), Color::white());
     }
 
+    #[test]
+    fn test_color_at_image() {
+        let texture = Texture::new(
+            2,
+            1,
+            vec![Color::new(1.0, 0.0, 0.0), Color::new(0.0, 0.0, 1.0)],
+        );
+        let p = Patternable::texture(Arc::new(texture));
+        let sphere = Shape::sphere();
+
+        assert_eq!(p.color_at(&point(0.0, 0.0, 0.5)), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(p.color_at(&point(0.5, 0.0, 0.0)), Color::new(0.5, 0.0, 0.5));
+        assert_eq!(
+            p.color_at_object(&sphere, &point(0.0, 0.0, -1.0)),
+            Color::new(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            p.color_at_object(&sphere, &point(1.0, 0.0, 0.0)),
+            Color::new(0.75, 0.0, 0.25)
+        );
+    }
+
+    #[test]
+    fn test_color_at_stencil() {
+        let bitmap = Texture::new(2, 1, vec![Color::black(), Color::white()]);
+        let p = Patternable::stencil(
+            Arc::new(bitmap),
+            Patternable::solid(Color::new(1.0, 0.0, 0.0)),
+            Patternable::stripe(Color::new(0.0, 0.0, 1.0), Color::black()),
+        );
+        let plane = Shape::plane();
+
+        assert_eq!(p.color_at(&point(0.0, 0.0, 0.5)), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(
+            p.color_at(&point(0.999, 0.0, 0.5)),
+            Color::new(0.001, 0.0, 0.999)
+        );
+        assert_eq!(p.color_at(&point(0.5, 0.0, 0.5)), Color::new(0.5, 0.0, 0.5));
+        assert_eq!(
+            p.color_at_object(&plane, &point(2.0, 0.0, 0.5)),
+            Color::new(1.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_wrapped_texture() {
+        let texture = Arc::new(Texture::new(
+            2,
+            1,
+            vec![Color::new(1.0, 0.0, 0.0), Color::new(0.0, 0.0, 1.0)],
+        ));
+        let mut tiled = Patternable::texture(texture.clone());
+        tiled.transform = Matrix4::scaling(0.25, 1.0, 0.25);
+        let mut decal = Patternable::wrapped_texture(texture.clone(), Wrap::Clamp, Wrap::Clamp);
+        decal.transform = Matrix4::translation(2.0, 0.0, 0.0);
+        let mirrored = Patternable::wrapped_texture(texture, Wrap::Mirror, Wrap::Repeat);
+
+        assert_eq!(tiled.color_at_uv(0.25, 0.5), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(tiled.color_at_uv(0.5, 0.5), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(decal.color_at_uv(0.5, 0.5), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(decal.color_at_uv(3.5, 0.5), Color::new(0.0, 0.0, 1.0));
+        assert_eq!(
+            mirrored.color_at(&point(1.0, 0.0, 0.5)),
+            Color::new(0.0, 0.0, 1.0)
+        );
+        assert_eq!(
+            mirrored.color_at(&point(1.75, 0.0, 0.5)),
+            Color::new(0.75, 0.0, 0.25)
+        );
+    }
+
+    #[test]
+    fn test_color_at_uv() {
+        let mut checkers = Patternable::checker(Color::black(), Color::white());
+        checkers.transform = Matrix4::scaling(0.5, 1.0, 0.5);
+        let p = Patternable::uv(checkers);
+        let sphere = Shape::sphere();
+
+        assert_eq!(p.color_at_uv(0.1, 0.1), Color::black());
+        assert_eq!(p.color_at_uv(0.4, 0.1), Color::white());
+        assert_eq!(p.color_at_uv(0.4, 0.4), Color::black());
+        // The front of the sphere is at u = 0 and the back at u = 0.5.
+        assert_eq!(
+            p.color_at_object(&sphere, &point(0.0, -0.866025, -0.5)),
+            Color::black()
+        );
+        assert_eq!(
+            p.color_at_object(&sphere, &point(0.0, -0.866025, 0.5)),
+            Color::white()
+        );
+    }
+
+    #[test]
+    fn test_color_at_uv_with_shape_mapping() {
+        let p = Patternable::uv(Patternable::gradient(Color::black(), Color::white()));
+        let mut cube = Shape::cube();
+        Arc::get_mut(&mut cube).unwrap().uv_mapping = Some(UvMapping::Cylindrical);
+
+        assert_eq!(
+            p.color_at_object(&cube, &point(1.0, 0.5, 0.0)),
+            Color::new(0.25, 0.25, 0.25)
+        );
+    }
+
+    #[test]
+    fn test_color_at_cube_map() {
+        let faces = [
+            Patternable::solid(Color::new(1.0, 0.0, 0.0)),
+            Patternable::solid(Color::new(0.0, 1.0, 0.0)),
+            Patternable::solid(Color::new(0.0, 0.0, 1.0)),
+            Patternable::solid(Color::new(1.0, 1.0, 0.0)),
+            Patternable::gradient(Color::black(), Color::white()),
+            Patternable::solid(Color::new(0.0, 1.0, 1.0)),
+        ];
+        let p = Patternable::cube_map(faces);
+
+        assert_eq!(
+            p.color_at(&point(-1.0, 0.2, 0.3)),
+            Color::new(1.0, 0.0, 0.0)
+        );
+        assert_eq!(p.color_at(&point(0.1, 0.2, 1.0)), Color::new(0.0, 1.0, 0.0));
+        assert_eq!(
+            p.color_at(&point(1.0, -0.2, 0.3)),
+            Color::new(0.0, 0.0, 1.0)
+        );
+        assert_eq!(
+            p.color_at(&point(0.1, 0.2, -1.0)),
+            Color::new(1.0, 1.0, 0.0)
+        );
+        assert_eq!(
+            p.color_at(&point(0.0, -3.0, 0.5)),
+            Color::new(0.0, 1.0, 1.0)
+        );
+        // A direction on the up face, half way across it in u.
+        assert_eq!(p.color_at(&point(0.0, 2.0, 0.4)), Color::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn test_color_at_simplex() {
+        let gradient = Patternable::gradient(Color::black(), Color::white());
+        let still = Patternable::simplex(gradient.clone(), 1.0, 0.0);
+        let p = Patternable::simplex(gradient.clone(), 0.5, 0.25);
+
+        assert_eq!(
+            still.color_at(&point(0.3, 0.7, 0.1)),
+            gradient.color_at(&point(0.3, 0.7, 0.1))
+        );
+        assert_ne!(
+            p.color_at(&point(0.3, 0.7, 0.1)),
+            gradient.color_at(&point(0.3, 0.7, 0.1))
+        );
+        let displaced = p.color_at(&point(0.3, 0.7, 0.1)).red - 0.3;
+        assert!(displaced.abs() <= 0.25);
+    }
+
+    #[test]
+    fn test_color_at_fbm() {
+        let fbm = Fbm::new(NoiseSource::simplex());
+        let p = Patternable::fbm(
+            fbm.clone(),
+            Patternable::gradient(Color::black(), Color::white()),
+        );
+        let at = point(0.4, 1.3, -0.7);
+        let value = fbm.value_at(&at);
+
+        assert_eq!(p.color_at(&at), Color::new(value, value, value));
+    }
+
+    #[test]
+    fn test_color_at_marble() {
+        let ramp = Patternable::gradient(Color::black(), Color::white());
+        let plain = Patternable::marble(ramp.clone(), 0.0);
+        let veined = Patternable::marble(ramp, 2.0);
+
+        assert_eq!(
+            plain.color_at(&point(0.25, 3.0, 1.0)),
+            Color::new(0.85355, 0.85355, 0.85355)
+        );
+        assert_eq!(
+            plain.color_at(&point(0.0, 3.0, 1.0)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+        assert_eq!(
+            plain.color_at(&point(-0.25, 0.0, 0.0)),
+            Color::new(0.14645, 0.14645, 0.14645)
+        );
+        assert_ne!(
+            veined.color_at(&point(0.3, 0.2, 0.1)),
+            plain.color_at(&point(0.3, 0.2, 0.1))
+        );
+    }
+
+    #[test]
+    fn test_blend_modes() {
+        let a = || Patternable::solid(Color::new(0.2, 0.5, 0.8));
+        let b = || Patternable::solid(Color::new(0.5, 0.5, 0.5));
+        let origin = point(0.0, 0.0, 0.0);
+
+        let add = Patternable::blend(a(), b(), BlendMode::Add);
+        assert_eq!(add.color_at(&origin), Color::new(0.7, 1.0, 1.3));
+
+        let mix = Patternable::blend(a(), b(), BlendMode::Mix);
+        assert_eq!(mix.color_at(&origin), Color::new(0.35, 0.5, 0.65));
+
+        let multiply = Patternable::blend(a(), b(), BlendMode::Multiply);
+        assert_eq!(multiply.color_at(&origin), Color::new(0.1, 0.25, 0.4));
+
+        let screen = Patternable::blend(a(), b(), BlendMode::Screen);
+        assert_eq!(screen.color_at(&origin), Color::new(0.6, 0.75, 0.9));
+
+        let overlay = Patternable::blend(a(), b(), BlendMode::Overlay);
+        assert_eq!(overlay.color_at(&origin), Color::new(0.2, 0.5, 0.8));
+    }
+
+    #[test]
+    fn test_blend_mask() {
+        let mask = Patternable::stripe(Color::black(), Color::white());
+        let p = Patternable::blend(
+            Patternable::solid(Color::new(1.0, 0.0, 0.0)),
+            Patternable::solid(Color::new(0.0, 0.0, 1.0)),
+            BlendMode::Mask(Box::new(mask)),
+        );
+
+        assert_eq!(p.color_at(&point(0.5, 0.0, 0.0)), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(p.color_at(&point(1.5, 0.0, 0.0)), Color::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_color_at_radial_gradient() {
+        let p = Patternable::radial_gradient(Color::white(), Color::black());
+
+        assert_eq!(p.color_at(&point(0.0, 3.0, 0.0)), Color::white());
+        assert_eq!(p.color_at(&point(0.3, 7.0, 0.4)), Color::new(0.5, 0.5, 0.5));
+        assert_eq!(p.color_at(&point(0.0, 0.0, 2.0)), Color::black());
+    }
+
+    #[test]
+    fn test_color_at_spherical_gradient() {
+        let p = Patternable::spherical_gradient(Color::white(), Color::black());
+
+        assert_eq!(p.color_at(&point(0.0, 0.0, 0.0)), Color::white());
+        assert_eq!(p.color_at(&point(0.0, 0.5, 0.0)), Color::new(0.5, 0.5, 0.5));
+        assert_eq!(p.color_at(&point(0.0, 3.0, 0.0)), Color::black());
+    }
+
+    #[test]
+    fn test_filtered_checker() {
+        let plane = Shape::plane();
+        let p = Patternable::filtered_checker(Color::white(), Color::black(), 0.01);
+        let at = point(0.25, 0.0, 0.25);
+        let near = SurfaceHit {
+            distance: 10.0,
+            face: None,
+        };
+        let far = SurfaceHit {
+            distance: 1000.0,
+            face: None,
+        };
+
+        assert_eq!(p.color_at_object(&plane, &at), Color::white());
+        assert_eq!(p.color_at_hit(&plane, &at, &near), Color::white());
+        assert_eq!(
+            p.color_at_hit(&plane, &point(0.5, 0.0, 0.25), &near),
+            Color::new(0.5, 0.5, 0.5)
+        );
+        assert_eq!(p.color_at_hit(&plane, &at, &far), Color::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn test_vertex_colors() {
+        let mut mesh = Mesh::new(vec![
+            point(0.0, 0.0, 0.0),
+            point(1.0, 0.0, 0.0),
+            point(0.0, 1.0, 0.0),
+        ]);
+        mesh.colors = vec![
+            Color::new(1.0, 0.0, 0.0),
+            Color::new(0.0, 1.0, 0.0),
+            Color::new(0.0, 0.0, 1.0),
+        ];
+        mesh.add_face(0, 1, 2, None);
+        let shape = Shape::mesh(mesh);
+        let p = Patternable::vertex_colors();
+        let hit = SurfaceHit {
+            distance: 1.0,
+            face: Some(FaceHit {
+                index: 0,
+                u: 0.25,
+                v: 0.5,
+            }),
+        };
+
+        assert_eq!(
+            p.color_at_hit(&shape, &point(0.25, 0.5, 0.0), &hit),
+            Color::new(0.25, 0.25, 0.5)
+        );
+        assert_eq!(
+            p.color_at_object(&shape, &point(0.25, 0.5, 0.0)),
+            Color::white()
+        );
+        assert_eq!(
+            p.color_at_hit(&Shape::sphere(), &point(0.0, 0.0, -1.0), &hit),
+            Color::white()
+        );
+    }
+
+    #[test]
+    fn test_color_at_ramp() {
+        let stops = vec![
+            (1.0, Color::new(0., 0., 1.)),
+            (0.0, Color::black()),
+            (0.5, Color::white()),
+        ];
+        let linear = Patternable::ramp(stops.clone(), Interpolation::Linear);
+        let smooth = Patternable::ramp(stops.clone(), Interpolation::Smooth);
+        let constant = Patternable::ramp(stops, Interpolation::Constant);
+
+        assert_eq!(linear.color_at(&point(-1.0, 0.0, 0.0)), Color::black());
+        assert_eq!(
+            linear.color_at(&point(0.25, 0.0, 0.0)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+        assert_eq!(
+            linear.color_at(&point(0.75, 0.0, 0.0)),
+            Color::new(0.5, 0.5, 1.0)
+        );
+        assert_eq!(
+            linear.color_at(&point(2.0, 0.0, 0.0)),
+            Color::new(0.0, 0.0, 1.0)
+        );
+        assert_eq!(
+            smooth.color_at(&point(0.125, 0.0, 0.0)),
+            Color::new(0.15625, 0.15625, 0.15625)
+        );
+        assert_eq!(constant.color_at(&point(0.4, 0.0, 0.0)), Color::black());
+        assert_eq!(constant.color_at(&point(0.5, 0.0, 0.0)), Color::white());
+    }
+
+    #[test]
+    fn test_ramp_with_nan() {
+        let ramp = Patternable::ramp(
+            vec![(0.0, Color::black()), (1.0, Color::white())],
+            Interpolation::Linear,
+        );
+        assert_eq!(ramp.color_at(&point(f64::NAN, 0., 0.)), Color::white());
+
+        let ramp = Patternable::ramp(
+            vec![(f64::NAN, Color::white()), (0.0, Color::black())],
+            Interpolation::Linear,
+        );
+        assert_eq!(ramp.color_at(&point(-1., 0., 0.)), Color::black());
+    }
+
+    #[test]
+    fn test_marble_with_ramp() {
+        let ramp = Patternable::ramp(
+            vec![(0.0, Color::black()), (1.0, Color::white())],
+            Interpolation::Linear,
+        );
+        let p = Patternable::marble(ramp, 0.0);
+
+        assert_eq!(p.color_at(&point(0.0, 0.0, 0.0)), Color::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn test_color_at_wood() {
+        let ramp = Patternable::gradient(Color::black(), Color::white());
+        let rings = Patternable::wood(ramp.clone(), Axis::Y, 1.0, 0.0);
+        let sharp = Patternable::wood(ramp.clone(), Axis::Z, 2.0, 0.0);
+        let knotty = Patternable::wood(ramp, Axis::Y, 1.0, 1.0);
+
+        assert_eq!(
+            rings.color_at(&point(0.3, 5.0, 0.4)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+        assert_eq!(
+            rings.color_at(&point(1.6, -2.0, 1.2)),
+            Color::new(0.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            sharp.color_at(&point(0.3, 0.4, 9.0)),
+            Color::new(0.25, 0.25, 0.25)
+        );
+        assert_ne!(
+            knotty.color_at(&point(0.3, 0.7, 0.4)),
+            rings.color_at(&point(0.3, 0.7, 0.4))
+        );
+    }
+
     #[test]
     fn test_color_at_object() {
         let mut sphere = Shape::sphere();
-        Arc::get_mut(&mut sphere).unwrap().transform = Matrix4::translation(2.0, 2.0, 2.0);
+        Arc::get_mut(&mut sphere)
+            .unwrap()
+            .set_transform(Matrix4::translation(2.0, 2.0, 2.0))
+            .unwrap();
         let pattern = Patternable::stripe(Color::black(), Color::white());
         let color = pattern.color_at_object(&sphere, &point(1.5, 0.0, 0.0));
 
@@ -213,7 +1083,10 @@ mod tests {
     #[test]
     fn test_color_at_object_with_both_transforms() {
         let mut sphere = Shape::sphere();
-        Arc::get_mut(&mut sphere).unwrap().transform = Matrix4::translation(2.0, 2.0, 2.0);
+        Arc::get_mut(&mut sphere)
+            .unwrap()
+            .set_transform(Matrix4::translation(2.0, 2.0, 2.0))
+            .unwrap();
         let mut pattern = Patternable::stripe(Color::black(), Color::white());
         pattern.transform = Matrix4::translation(0.5, 0.0, 0.0);
         let color = pattern.color_at_object(&sphere, &point(2.5, 0.0, 0.0));
@@ -221,6 +1094,56 @@ mod tests {
         assert_eq!(color, Color::black());
     }
 
+    #[test]
+    fn test_color_at_object_in_group() {
+        let mut group = Shape::group();
+        Arc::get_mut(&mut group)
+            .unwrap()
+            .set_transform(Matrix4::translation(1.0, 0.0, 0.0))
+            .unwrap();
+        let mut sphere = Shape::sphere();
+        Arc::get_mut(&mut sphere).unwrap().parent = Some(group);
+        let pattern = Patternable::stripe(Color::black(), Color::white());
+
+        assert_eq!(
+            pattern.color_at_object(&sphere, &point(1.5, 0.0, 0.0)),
+            Color::black()
+        );
+        assert_eq!(
+            pattern.color_at_object(&sphere, &point(2.5, 0.0, 0.0)),
+            Color::white()
+        );
+    }
+
+    #[test]
+    fn test_color_at_object_in_nested_groups() {
+        let mut outer = Shape::group();
+        Arc::get_mut(&mut outer)
+            .unwrap()
+            .set_transform(Matrix4::scaling(2.0, 2.0, 2.0))
+            .unwrap();
+        let mut inner = Shape::group();
+        {
+            let inner = Arc::get_mut(&mut inner).unwrap();
+            inner
+                .set_transform(Matrix4::translation(1.0, 0.0, 0.0))
+                .unwrap();
+            inner.parent = Some(outer);
+        }
+        let mut sphere = Shape::sphere();
+        Arc::get_mut(&mut sphere).unwrap().parent = Some(inner);
+        let pattern = Patternable::stripe(Color::black(), Color::white());
+
+        assert_eq!(
+            pattern.color_at_object(&sphere, &point(3.5, 0.0, 0.0)),
+            Color::black()
+        );
+        assert_eq!(
+            pattern.color_at_object(&sphere, &point(4.5, 0.0, 0.0)),
+            Color::white()
+        );
+    }
+
     #[test]
     fn test_color_at_gradient() {
         let p = Patternable::gradient(Color::white(), Color::black());
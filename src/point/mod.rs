@@ -1,34 +1,42 @@
 use bounds::Bounds;
+use float::Float;
+use std::fmt;
+use std::ops;
 use utilities::equal;
 
+// A point, or with w as 0 a vector, over any `Float`. The rest of the
+// crate works in f64 through `Point`.
 #[derive(Copy, Clone, Debug)]
-pub struct Point {
-    pub x: f64,
-    pub y: f64,
-    pub z: f64,
-    pub w: f64,
+pub struct GenericPoint<T> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
+    pub w: T,
 }
 
-impl PartialEq for Point {
-    fn eq(&self, other: &Point) -> bool {
+pub type Point = GenericPoint<f64>;
+pub type PointF32 = GenericPoint<f32>;
+
+impl<T: Float> PartialEq for GenericPoint<T> {
+    fn eq(&self, other: &GenericPoint<T>) -> bool {
         equal(self.x, other.x) && equal(self.y, other.y) && equal(self.z, other.z)
     }
 }
 
 pub fn point(x: f64, y: f64, z: f64) -> Point {
-    return Point { x, y, z, w: 1.0 };
+    Point { x, y, z, w: 1.0 }
 }
 
 pub fn vector(x: f64, y: f64, z: f64) -> Point {
-    return Point { x, y, z, w: 0.0 };
+    Point { x, y, z, w: 0.0 }
 }
 
 pub fn empty_point() -> Point {
-    return point(0.0, 0.0, 0.0);
+    point(0.0, 0.0, 0.0)
 }
 
 pub fn empty_vector() -> Point {
-    return vector(0.0, 0.0, 0.0);
+    vector(0.0, 0.0, 0.0)
 }
 
 pub fn bounds(points: Vec<Point>) -> Bounds {
@@ -59,97 +67,263 @@ pub fn bounds(points: Vec<Point>) -> Bounds {
     bounds
 }
 
-impl Point {
-    fn is_point(&self) -> bool {
-        return equal(self.w, 1.0);
+impl<T: Float> GenericPoint<T> {
+    pub fn new(x: T, y: T, z: T, w: T) -> GenericPoint<T> {
+        GenericPoint { x, y, z, w }
+    }
+
+    // The same point or vector at another precision.
+    pub fn cast<U: Float>(&self) -> GenericPoint<U> {
+        GenericPoint {
+            x: U::from_f64(self.x.to_f64()),
+            y: U::from_f64(self.y.to_f64()),
+            z: U::from_f64(self.z.to_f64()),
+            w: U::from_f64(self.w.to_f64()),
+        }
+    }
+
+    pub fn is_point(&self) -> bool {
+        equal(self.w, T::ONE)
     }
 
-    fn is_vector(&self) -> bool {
-        return equal(self.w, 0.0);
+    pub fn is_vector(&self) -> bool {
+        equal(self.w, T::ZERO)
     }
 
-    pub fn equal(&self, other: &Point) -> bool {
-        return equal(self.x, other.x)
+    pub fn equal(&self, other: &GenericPoint<T>) -> bool {
+        equal(self.x, other.x)
             && equal(self.y, other.y)
             && equal(self.z, other.z)
-            && equal(self.w, other.w);
+            && equal(self.w, other.w)
     }
 
-    pub fn add(&self, other: &Point) -> Point {
-        return Point {
+    pub fn add(&self, other: &GenericPoint<T>) -> GenericPoint<T> {
+        GenericPoint {
             x: self.x + other.x,
             y: self.y + other.y,
             z: self.z + other.z,
             w: self.w + other.w,
-        };
+        }
     }
 
-    pub fn sub(&self, other: &Point) -> Point {
-        return Point {
+    pub fn sub(&self, other: &GenericPoint<T>) -> GenericPoint<T> {
+        GenericPoint {
             x: self.x - other.x,
             y: self.y - other.y,
             z: self.z - other.z,
             w: self.w - other.w,
-        };
+        }
     }
 
-    pub fn multiply_scalar(&self, other: f64) -> Point {
-        return Point {
+    pub fn multiply_scalar(&self, other: T) -> GenericPoint<T> {
+        GenericPoint {
             x: self.x * other,
             y: self.y * other,
             z: self.z * other,
             w: self.w * other,
-        };
+        }
     }
 
-    fn divide_scalar(&self, other: f64) -> Point {
-        return Point {
+    fn divide_scalar(&self, other: T) -> GenericPoint<T> {
+        GenericPoint {
             x: self.x / other,
             y: self.y / other,
             z: self.z / other,
             w: self.w / other,
-        };
+        }
     }
 
-    fn negative(&self) -> Point {
-        return empty_vector().sub(&self);
+    pub fn negative(&self) -> GenericPoint<T> {
+        GenericPoint::new(T::ZERO, T::ZERO, T::ZERO, T::ZERO).sub(self)
     }
 
-    pub fn magnitude(&self) -> f64 {
-        return (self.x.powi(2) + self.y.powi(2) + self.z.powi(2) + self.w.powi(2)).sqrt();
+    pub fn magnitude(&self) -> T {
+        (self.x.powi(2) + self.y.powi(2) + self.z.powi(2) + self.w.powi(2)).sqrt()
     }
 
-    pub fn normalize(&self) -> Point {
-        return self.divide_scalar(self.magnitude());
+    pub fn normalize(&self) -> GenericPoint<T> {
+        self.divide_scalar(self.magnitude())
     }
 
-    pub fn dot(&self, other: &Point) -> f64 {
-        return self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w;
+    pub fn dot(&self, other: &GenericPoint<T>) -> T {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
     }
 
-    pub fn cross(&self, other: &Point) -> Point {
-        return Point {
+    pub fn cross(&self, other: &GenericPoint<T>) -> GenericPoint<T> {
+        GenericPoint {
             x: self.y * other.z - self.z * other.y,
             y: self.z * other.x - self.x * other.z,
             z: self.x * other.y - self.y * other.x,
-            w: 0.0,
-        };
+            w: T::ZERO,
+        }
     }
 
-    pub fn reflect(&self, normal: &Point) -> Point {
+    pub fn reflect(&self, normal: &GenericPoint<T>) -> GenericPoint<T> {
         self.sub(
             &normal
-                .multiply_scalar(2.0)
+                .multiply_scalar(T::from_f64(2.0))
                 .multiply_scalar(self.dot(normal)),
         )
     }
 }
 
+impl<T: Float> ops::Add for GenericPoint<T> {
+    type Output = GenericPoint<T>;
+
+    fn add(self, other: GenericPoint<T>) -> GenericPoint<T> {
+        GenericPoint::add(&self, &other)
+    }
+}
+
+impl<T: Float> ops::AddAssign for GenericPoint<T> {
+    fn add_assign(&mut self, other: GenericPoint<T>) {
+        *self = GenericPoint::add(self, &other);
+    }
+}
+
+impl<T: Float> ops::Sub for GenericPoint<T> {
+    type Output = GenericPoint<T>;
+
+    fn sub(self, other: GenericPoint<T>) -> GenericPoint<T> {
+        GenericPoint::sub(&self, &other)
+    }
+}
+
+impl<T: Float> ops::SubAssign for GenericPoint<T> {
+    fn sub_assign(&mut self, other: GenericPoint<T>) {
+        *self = GenericPoint::sub(self, &other);
+    }
+}
+
+impl<T: Float> ops::Mul<T> for GenericPoint<T> {
+    type Output = GenericPoint<T>;
+
+    fn mul(self, factor: T) -> GenericPoint<T> {
+        self.multiply_scalar(factor)
+    }
+}
+
+impl ops::Mul<Point> for f64 {
+    type Output = Point;
+
+    fn mul(self, point: Point) -> Point {
+        point.multiply_scalar(self)
+    }
+}
+
+impl ops::Mul<PointF32> for f32 {
+    type Output = PointF32;
+
+    fn mul(self, point: PointF32) -> PointF32 {
+        point.multiply_scalar(self)
+    }
+}
+
+impl<T: Float> ops::Div<T> for GenericPoint<T> {
+    type Output = GenericPoint<T>;
+
+    fn div(self, divisor: T) -> GenericPoint<T> {
+        self.divide_scalar(divisor)
+    }
+}
+
+impl<T: Float> ops::Neg for GenericPoint<T> {
+    type Output = GenericPoint<T>;
+
+    fn neg(self) -> GenericPoint<T> {
+        self.negative()
+    }
+}
+
+// x, y, z and w, in that order.
+impl<T> ops::Index<usize> for GenericPoint<T> {
+    type Output = T;
+
+    fn index(&self, axis: usize) -> &T {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            3 => &self.w,
+            _ => panic!("a point has no axis {}", axis),
+        }
+    }
+}
+
+impl<T> ops::IndexMut<usize> for GenericPoint<T> {
+    fn index_mut(&mut self, axis: usize) -> &mut T {
+        match axis {
+            0 => &mut self.x,
+            1 => &mut self.y,
+            2 => &mut self.z,
+            3 => &mut self.w,
+            _ => panic!("a point has no axis {}", axis),
+        }
+    }
+}
+
+// Three components make a point. For a vector, give four with w as 0, or
+// use `vector`.
+impl<T: Float> From<[T; 3]> for GenericPoint<T> {
+    fn from([x, y, z]: [T; 3]) -> GenericPoint<T> {
+        GenericPoint::new(x, y, z, T::ONE)
+    }
+}
+
+impl<T: Float> From<(T, T, T)> for GenericPoint<T> {
+    fn from((x, y, z): (T, T, T)) -> GenericPoint<T> {
+        GenericPoint::new(x, y, z, T::ONE)
+    }
+}
+
+impl<T> From<[T; 4]> for GenericPoint<T> {
+    fn from([x, y, z, w]: [T; 4]) -> GenericPoint<T> {
+        GenericPoint { x, y, z, w }
+    }
+}
+
+impl<T> From<GenericPoint<T>> for [T; 3] {
+    fn from(point: GenericPoint<T>) -> [T; 3] {
+        [point.x, point.y, point.z]
+    }
+}
+
+impl<T> From<GenericPoint<T>> for [T; 4] {
+    fn from(point: GenericPoint<T>) -> [T; 4] {
+        [point.x, point.y, point.z, point.w]
+    }
+}
+
+// Written the way points and vectors are made, so `{:.2}` and the like
+// apply to each component.
+impl<T: Float> fmt::Display for GenericPoint<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_point() {
+            write!(f, "point(")?;
+        } else if self.is_vector() {
+            write!(f, "vector(")?;
+        } else {
+            write!(f, "(")?;
+        }
+        fmt::Display::fmt(&self.x, f)?;
+        write!(f, ", ")?;
+        fmt::Display::fmt(&self.y, f)?;
+        write!(f, ", ")?;
+        fmt::Display::fmt(&self.z, f)?;
+        if !self.is_point() && !self.is_vector() {
+            write!(f, ", ")?;
+            fmt::Display::fmt(&self.w, f)?;
+        }
+        write!(f, ")")
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use point::empty_point;
     use point::empty_vector;
     use point::equal;
+    use point::point;
     use point::vector;
     use point::Point;
 
@@ -456,4 +630,51 @@ mod tests {
 
         assert!(v.reflect(&n).equal(&vector(1.0, 0.0, 0.0)));
     }
+
+    #[test]
+    fn test_point_operators() {
+        let p = point(1.0, 2.0, 3.0);
+        let v = vector(0.5, -1.0, 2.0);
+
+        assert!((p + v * 2.0).equal(&point(2.0, 0.0, 7.0)));
+        assert!((p - v).equal(&p.sub(&v)));
+        assert!((2.0 * v).equal(&v.multiply_scalar(2.0)));
+        assert!((v / 2.0).equal(&vector(0.25, -0.5, 1.0)));
+        assert!((-v).equal(&v.negative()));
+
+        let mut q = p;
+        q += v;
+        q -= v * 2.0;
+        assert!(q.equal(&point(0.5, 3.0, 1.0)));
+        q[1] = 5.0;
+        assert_eq!((q[0], q[1], q[2], q[3]), (0.5, 5.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_point_display() {
+        assert_eq!(point(1.0, -2.5, 0.0).to_string(), "point(1, -2.5, 0)");
+        assert_eq!(
+            format!("{:.2}", vector(1.0, 0.5, 0.0)),
+            "vector(1.00, 0.50, 0.00)"
+        );
+        let q = Point {
+            x: 1.0,
+            y: 2.0,
+            z: 3.0,
+            w: 2.0,
+        };
+        assert_eq!(q.to_string(), "(1, 2, 3, 2)");
+    }
+
+    #[test]
+    fn test_point_conversions() {
+        assert!(Point::from([1.0, 2.0, 3.0]).equal(&point(1.0, 2.0, 3.0)));
+        assert!(Point::from((1.0, 2.0, 3.0)).equal(&point(1.0, 2.0, 3.0)));
+        assert!(Point::from([1.0, 2.0, 3.0, 0.0]).equal(&vector(1.0, 2.0, 3.0)));
+
+        let v: [f64; 4] = vector(1.0, 2.0, 3.0).into();
+        assert_eq!(v, [1.0, 2.0, 3.0, 0.0]);
+        let p: [f64; 3] = point(1.0, 2.0, 3.0).into();
+        assert_eq!(p, [1.0, 2.0, 3.0]);
+    }
 }
@@ -1,27 +1,199 @@
 use color::Color;
+use light::Light;
 use point::Point;
+use ray::Ray;
+use sampling::{random, square_to_sphere, Sampler};
+use shape::Shape;
+use std::f64::consts::PI;
 
+// Which shapes a light applies to, by shape name. A shape inside a named
+// group matches the names of its ancestors as well.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LightLink {
+    All,
+    Exclude(Vec<String>),
+    Include(Vec<String>),
+}
+
+impl LightLink {
+    pub fn links(&self, shape: &Shape) -> bool {
+        match *self {
+            LightLink::All => true,
+            LightLink::Exclude(ref names) => !names.iter().any(|name| shape.is_named(name)),
+            LightLink::Include(ref names) => names.iter().any(|name| shape.is_named(name)),
+        }
+    }
+}
+
+// A light with a radius above zero is a sphere, sampled at `samples`
+// points on its surface, placed by `sampler`, for soft shadows.
+#[derive(Clone)]
 pub struct PointLight {
+    pub illuminates: LightLink,
     pub intensity: Color,
     pub position: Point,
+    pub radius: f64,
+    pub sampler: Sampler,
+    pub samples: usize,
+    pub shadows: LightLink,
+}
+
+impl PointLight {
+    pub fn new(position: Point, intensity: Color) -> PointLight {
+        PointLight {
+            illuminates: LightLink::All,
+            intensity,
+            position,
+            radius: 0.,
+            sampler: Sampler::Stratified,
+            samples: 1,
+            shadows: LightLink::All,
+        }
+    }
 }
 
-impl PointLight {}
+impl Light for PointLight {
+    fn position(&self) -> Point {
+        self.position
+    }
+
+    fn sample_points(&self) -> Vec<Point> {
+        if self.radius == 0. || self.samples <= 1 {
+            return vec![self.position];
+        }
+        self.sampler
+            .square(self.samples)
+            .into_iter()
+            .map(|sample| {
+                self.position
+                    .add(&square_to_sphere(sample).multiply_scalar(self.radius))
+            })
+            .collect()
+    }
+
+    fn intensity_at(&self, _point: &Point) -> Color {
+        self.intensity
+    }
+
+    fn direction_from(&self, point: &Point) -> Point {
+        self.position.sub(point).normalize()
+    }
+
+    fn illuminates(&self, shape: &Shape) -> bool {
+        self.illuminates.links(shape)
+    }
+
+    fn casts_shadow(&self, shape: &Shape) -> bool {
+        self.shadows.links(shape)
+    }
+
+    fn area(&self) -> f64 {
+        4. * PI * self.radius * self.radius
+    }
+
+    // Chosen so that from afar the sphere is as bright as a point light of
+    // the same intensity.
+    fn radiance(&self) -> Color {
+        self.intensity
+            .multiply_scalar(1. / (PI * self.radius * self.radius))
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<f64> {
+        if self.radius == 0. {
+            return None;
+        }
+        let to_origin = ray.origin.sub(&self.position);
+        let a = ray.direction.dot(&ray.direction);
+        let b = to_origin.dot(&ray.direction);
+        let c = to_origin.dot(&to_origin) - self.radius * self.radius;
+        let discriminant = b * b - a * c;
+        if c < 0. || discriminant < 0. {
+            return None;
+        }
+        let t = (-b - discriminant.sqrt()) / a;
+        if t > 0. {
+            Some(t)
+        } else {
+            None
+        }
+    }
+
+    // A light without a radius is all one point.
+    fn sample_surface(&self) -> (Point, Point) {
+        let normal = square_to_sphere((random(), random()));
+        let point = self.position.add(&normal.multiply_scalar(self.radius));
+        (point, normal)
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use color::Color;
-    use point::point;
-    use point_light::PointLight;
+    use light::Light;
+    use point::{point, vector};
+    use point_light::{LightLink, PointLight};
+    use ray::Ray;
+    use shape::Shape;
+    use std::sync::Arc;
+    use utilities::equal;
 
     #[test]
     fn test_point_light() {
-        let l = PointLight {
-            intensity: Color::new(1.0, 1.0, 1.0),
-            position: point(0.0, 0.0, 0.0),
-        };
+        let l = PointLight::new(point(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0));
 
         assert_eq!(l.intensity, Color::new(1.0, 1.0, 1.0));
         assert!(l.position.equal(&point(0.0, 0.0, 0.0)));
+        assert_eq!(l.illuminates, LightLink::All);
+        assert_eq!(l.shadows, LightLink::All);
+    }
+
+    #[test]
+    fn test_spherical_light_sample_points() {
+        let mut l = PointLight::new(point(1.0, 2.0, 3.0), Color::white());
+
+        assert_eq!(l.sample_points(), vec![point(1.0, 2.0, 3.0)]);
+
+        l.radius = 0.5;
+        l.samples = 8;
+        let points = l.sample_points();
+
+        assert_eq!(points.len(), 8);
+        for p in points {
+            assert!(equal(p.sub(&l.position).magnitude(), 0.5));
+        }
+    }
+
+    #[test]
+    fn test_light_link() {
+        let mut s = Shape::sphere();
+        Arc::get_mut(&mut s).unwrap().name = Some(String::from("ball"));
+        let unnamed = Shape::sphere();
+
+        let include = LightLink::Include(vec![String::from("ball")]);
+        let exclude = LightLink::Exclude(vec![String::from("ball")]);
+
+        assert!(LightLink::All.links(&s));
+        assert!(include.links(&s));
+        assert!(!include.links(&unnamed));
+        assert!(!exclude.links(&s));
+        assert!(exclude.links(&unnamed));
+    }
+
+    #[test]
+    fn test_spherical_light_intersect() {
+        let mut l = PointLight::new(point(0.0, 0.0, 5.0), Color::white());
+        let ray = Ray {
+            origin: point(0.0, 0.0, 0.0),
+            direction: vector(0.0, 0.0, 1.0),
+        };
+
+        assert_eq!(l.intersect(&ray), None);
+        l.radius = 1.0;
+        assert_eq!(l.intersect(&ray), Some(4.0));
+        let away = Ray {
+            origin: ray.origin,
+            direction: vector(0.0, 0.0, -1.0),
+        };
+        assert_eq!(l.intersect(&away), None);
     }
 }
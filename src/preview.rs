@@ -0,0 +1,35 @@
+use camera::Camera;
+use canvas::Canvas;
+use color::Encoding;
+use minifb::{Error, Key, Window, WindowOptions};
+use world::World;
+
+// Renders while showing the image in a window as rows finish, then keeps
+// the window up until it is closed or Escape is pressed.
+pub fn render(camera: &Camera, world: &World) -> Result<Canvas, Error> {
+    let mut window = Window::new(
+        "ray_tracer",
+        camera.hsize,
+        camera.vsize,
+        WindowOptions::default(),
+    )?;
+    let mut buffer = vec![0; camera.hsize * camera.vsize];
+    let canvas = camera.render_rows(world, |canvas| {
+        fill(&mut buffer, canvas);
+        // A failed update only costs a frame of preview.
+        let _ = window.update_with_buffer(&buffer, camera.hsize, camera.vsize);
+    });
+    fill(&mut buffer, &canvas);
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        window.update_with_buffer(&buffer, camera.hsize, camera.vsize)?;
+    }
+    Ok(canvas)
+}
+
+// Screens expect sRGB, whatever the canvas will be written as.
+fn fill(buffer: &mut [u32], canvas: &Canvas) {
+    for (pixel, color) in buffer.iter_mut().zip(canvas.pixels.iter()) {
+        let [red, green, blue] = color.encode(Encoding::Srgb).bytes();
+        *pixel = (u32::from(red) << 16) | (u32::from(green) << 8) | u32::from(blue);
+    }
+}
@@ -0,0 +1,306 @@
+use bounds::Bounds;
+use point::{point, vector, Point};
+use ray::Ray;
+use std::fmt::Debug;
+use utilities::{max, min, EPSILON};
+use uv_mapping::UvMapping;
+
+// The geometry of a single surface, in its own space. Shapes take care of
+// transforms, materials and groups, so a new kind of shape only needs to
+// say where a ray crosses it and which way it faces. Wrap one in a shape
+// with `Shape::primitive`.
+pub trait Primitive: Debug + Send + Sync {
+    // How far along the ray it crosses the surface, in any order.
+    fn local_intersect(&self, ray: &Ray) -> Vec<f64>;
+
+    fn local_normal_at(&self, point: &Point) -> Point;
+
+    // An infinite box is fine for a surface without edges, but leaves the
+    // groups holding it unable to skip rays that miss.
+    fn bounds(&self) -> Bounds;
+
+    fn uv_at(&self, _point: &Point) -> (f64, f64) {
+        (0., 0.)
+    }
+}
+
+// A unit sphere around the origin.
+#[derive(Clone, Copy, Debug)]
+pub struct Sphere;
+
+impl Primitive for Sphere {
+    fn local_intersect(&self, ray: &Ray) -> Vec<f64> {
+        let shape_to_ray = ray.origin.sub(&point(0., 0., 0.));
+        let a = ray.direction.dot(&ray.direction);
+        let b = ray.direction.dot(&shape_to_ray) * 2.0;
+        let c = shape_to_ray.dot(&shape_to_ray) - 1.;
+
+        let discriminant = b.powi(2) - 4.0 * a * c;
+        if discriminant < 0. {
+            Vec::new()
+        } else {
+            vec![
+                (-b - discriminant.sqrt()) / (2.0 * a),
+                (-b + discriminant.sqrt()) / (2.0 * a),
+            ]
+        }
+    }
+
+    fn local_normal_at(&self, local_point: &Point) -> Point {
+        local_point.sub(&point(0., 0., 0.))
+    }
+
+    fn bounds(&self) -> Bounds {
+        Bounds::new(-1.0, 1.0, -1.0, 1.0, -1.0, 1.0)
+    }
+
+    fn uv_at(&self, point: &Point) -> (f64, f64) {
+        UvMapping::Spherical.uv_at(point)
+    }
+}
+
+// The xz plane, facing up.
+#[derive(Clone, Copy, Debug)]
+pub struct Plane;
+
+impl Primitive for Plane {
+    fn local_intersect(&self, ray: &Ray) -> Vec<f64> {
+        if ray.direction.y.abs() < EPSILON {
+            return Vec::new();
+        }
+        vec![-ray.origin.y / ray.direction.y]
+    }
+
+    fn local_normal_at(&self, _local_point: &Point) -> Point {
+        point(0., 1., 0.)
+    }
+
+    fn bounds(&self) -> Bounds {
+        Bounds::new(
+            -f64::INFINITY,
+            f64::INFINITY,
+            0.0,
+            0.0,
+            -f64::INFINITY,
+            f64::INFINITY,
+        )
+    }
+
+    fn uv_at(&self, point: &Point) -> (f64, f64) {
+        UvMapping::Planar.uv_at(point)
+    }
+}
+
+// The cube from -1 to 1 on every axis.
+#[derive(Clone, Copy, Debug)]
+pub struct Cube;
+
+impl Cube {
+    fn check_axis(&self, origin: f64, direction: f64) -> (f64, f64) {
+        let tmin: f64;
+        let tmax: f64;
+        let tmin_numerator = -1. - origin;
+        let tmax_numerator = 1. - origin;
+        if direction.abs() >= EPSILON {
+            tmin = tmin_numerator / direction;
+            tmax = tmax_numerator / direction;
+        } else {
+            tmin = tmin_numerator * f64::INFINITY;
+            tmax = tmax_numerator * f64::INFINITY;
+        }
+        if tmin > tmax {
+            (tmax, tmin)
+        } else {
+            (tmin, tmax)
+        }
+    }
+}
+
+impl Primitive for Cube {
+    fn local_intersect(&self, ray: &Ray) -> Vec<f64> {
+        let (xmin, xmax) = self.check_axis(ray.origin.x, ray.direction.x);
+        let (ymin, ymax) = self.check_axis(ray.origin.y, ray.direction.y);
+        let (zmin, zmax) = self.check_axis(ray.origin.z, ray.direction.z);
+
+        let mins: Vec<f64> = vec![xmin, ymin, zmin];
+        let maxs: Vec<f64> = vec![xmax, ymax, zmax];
+
+        let tmin = max(&mins);
+        let tmax = min(&maxs);
+
+        if tmin > tmax {
+            return Vec::new();
+        }
+        vec![tmin, tmax]
+    }
+
+    fn local_normal_at(&self, local_point: &Point) -> Point {
+        let maxc = [
+            local_point.x.abs(),
+            local_point.y.abs(),
+            local_point.z.abs(),
+        ]
+        .iter()
+        .cloned()
+        .fold(f64::NAN, f64::max);
+
+        if maxc == local_point.x.abs() {
+            vector(local_point.x, 0., 0.)
+        } else if maxc == local_point.y.abs() {
+            vector(0., local_point.y, 0.)
+        } else {
+            vector(0., 0., local_point.z)
+        }
+    }
+
+    fn bounds(&self) -> Bounds {
+        Bounds::new(-1.0, 1.0, -1.0, 1.0, -1.0, 1.0)
+    }
+
+    fn uv_at(&self, point: &Point) -> (f64, f64) {
+        UvMapping::Cubic.uv_at(point)
+    }
+}
+
+// A flat triangle through three points. The edges from `p1` to the other
+// two are kept for intersecting it, and its normal for shading it.
+#[derive(Clone, Copy, Debug)]
+pub struct Triangle {
+    pub e1: Point,
+    pub e2: Point,
+    pub normal: Point,
+    pub p1: Point,
+    pub p2: Point,
+    pub p3: Point,
+}
+
+impl Triangle {
+    pub fn new(p1: Point, p2: Point, p3: Point) -> Triangle {
+        let e1 = p2.sub(&p1);
+        let e2 = p3.sub(&p1);
+        Triangle {
+            e1,
+            e2,
+            normal: e1.cross(&e2).normalize(),
+            p1,
+            p2,
+            p3,
+        }
+    }
+}
+
+impl Primitive for Triangle {
+    fn local_intersect(&self, ray: &Ray) -> Vec<f64> {
+        match triangle_hit(&self.p1, &self.e1, &self.e2, ray) {
+            Some((t, _, _)) => vec![t],
+            None => Vec::new(),
+        }
+    }
+
+    fn local_normal_at(&self, _local_point: &Point) -> Point {
+        self.normal
+    }
+
+    fn bounds(&self) -> Bounds {
+        Bounds::new(
+            min(&[self.p1.x, self.p2.x, self.p3.x]),
+            max(&[self.p1.x, self.p2.x, self.p3.x]),
+            min(&[self.p1.y, self.p2.y, self.p3.y]),
+            max(&[self.p1.y, self.p2.y, self.p3.y]),
+            min(&[self.p1.z, self.p2.z, self.p3.z]),
+            max(&[self.p1.z, self.p2.z, self.p3.z]),
+        )
+    }
+
+    // Barycentric coordinates of the point relative to p2 and p3.
+    fn uv_at(&self, local_point: &Point) -> (f64, f64) {
+        let p1_to_point = local_point.sub(&self.p1);
+        let d00 = self.e1.dot(&self.e1);
+        let d01 = self.e1.dot(&self.e2);
+        let d11 = self.e2.dot(&self.e2);
+        let d20 = p1_to_point.dot(&self.e1);
+        let d21 = p1_to_point.dot(&self.e2);
+        let denominator = d00 * d11 - d01 * d01;
+        (
+            (d11 * d20 - d01 * d21) / denominator,
+            (d00 * d21 - d01 * d20) / denominator,
+        )
+    }
+}
+
+// Möller–Trumbore intersection of a ray with the triangle spanned by e1 and
+// e2 from p1, giving the distance and barycentric coordinates of the hit.
+pub fn triangle_hit(p1: &Point, e1: &Point, e2: &Point, ray: &Ray) -> Option<(f64, f64, f64)> {
+    let dir_cross_e2 = ray.direction.cross(e2);
+    let det = e1.dot(&dir_cross_e2);
+    if det.abs() < EPSILON {
+        return None;
+    }
+
+    let f = 1. / det;
+    let p1_to_origin = ray.origin.sub(p1);
+    let u = f * p1_to_origin.dot(&dir_cross_e2);
+    if !(0. ..=1.).contains(&u) {
+        return None;
+    }
+
+    let origin_cross_e1 = p1_to_origin.cross(e1);
+    let v = f * ray.direction.dot(&origin_cross_e1);
+    if v < 0. || (u + v) > 1. {
+        return None;
+    }
+
+    Some((f * e2.dot(&origin_cross_e1), u, v))
+}
+
+#[cfg(test)]
+mod tests {
+    use bounds::Bounds;
+    use color::Color;
+    use point::{point, vector, Point};
+    use primitive::Primitive;
+    use ray::Ray;
+    use shape::Shape;
+    use world::World;
+
+    // A slab between y = -1 and y = 1, infinite in x and z.
+    #[derive(Debug)]
+    struct Slab;
+
+    impl Primitive for Slab {
+        fn local_intersect(&self, ray: &Ray) -> Vec<f64> {
+            if ray.direction.y == 0. {
+                return Vec::new();
+            }
+            vec![
+                (-1. - ray.origin.y) / ray.direction.y,
+                (1. - ray.origin.y) / ray.direction.y,
+            ]
+        }
+
+        fn local_normal_at(&self, point: &Point) -> Point {
+            vector(0., point.y.signum(), 0.)
+        }
+
+        fn bounds(&self) -> Bounds {
+            let infinity = f64::INFINITY;
+            Bounds::new(-infinity, infinity, -1., 1., -infinity, infinity)
+        }
+    }
+
+    #[test]
+    fn test_custom_primitive() {
+        let slab = Shape::primitive(Slab);
+        let ray = Ray {
+            origin: point(0., 5., 0.),
+            direction: vector(0., -1., 0.),
+        };
+
+        let ts: Vec<f64> = ray.intersect(slab.clone()).iter().map(|i| i.t).collect();
+        assert_eq!(ts, vec![6., 4.]);
+        assert_eq!(slab.normal_at(&point(0., 1., 0.)), vector(0., 1., 0.));
+
+        let world = World::builder().shape(slab).build();
+        assert_ne!(world.color_at(&ray, 1), Color::black());
+    }
+}
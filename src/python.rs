@@ -0,0 +1,319 @@
+use camera::Camera;
+use canvas::Canvas;
+use color::{Color, Encoding};
+use intersectable::Intersectable;
+use material::Material;
+use matrix::Matrix4;
+use obj_parser::ObjParser;
+use point::point;
+use point_light::PointLight;
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict};
+use shape::Shape;
+use std::sync::Arc;
+use transformation_matrix::TransformationMatrix;
+use world::World;
+
+// Python bindings, built with the `python` feature as an extension module
+// named `ray_tracer`. Points and colors are passed as 3-tuples.
+//
+//     world = ray_tracer.World()
+//     world.add(ray_tracer.Shape.sphere().translate(0, 1, 0))
+//     camera = ray_tracer.Camera(320, 200, 1.0).look_at((0, 1, -5), (0, 1, 0))
+//     image = numpy.asarray(ray_tracer.render(world, camera))
+type Triple = (f64, f64, f64);
+
+fn color((red, green, blue): Triple) -> Color {
+    Color::new(red, green, blue)
+}
+
+#[pyclass(name = "Material")]
+#[derive(Clone)]
+pub struct PyMaterial {
+    material: Material,
+}
+
+#[pymethods]
+impl PyMaterial {
+    #[new]
+    #[pyo3(signature = (
+        color = (1., 1., 1.),
+        ambient = 0.1,
+        diffuse = 0.9,
+        specular = 0.9,
+        shininess = 200.,
+        reflective = 0.,
+        transparency = 0.,
+        refractive_index = 1.,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        color: Triple,
+        ambient: f64,
+        diffuse: f64,
+        specular: f64,
+        shininess: f64,
+        reflective: f64,
+        transparency: f64,
+        refractive_index: f64,
+    ) -> PyResult<PyMaterial> {
+        Material::builder()
+            .color(self::color(color))
+            .ambient(ambient)
+            .diffuse(diffuse)
+            .specular(specular)
+            .shininess(shininess)
+            .reflective(reflective)
+            .transparency(transparency)
+            .refractive_index(refractive_index)
+            .build()
+            .map(|material| PyMaterial { material })
+            .map_err(|error| PyValueError::new_err(error.to_string()))
+    }
+}
+
+// A shape that hasn't been added to a world yet. Transforms apply on top
+// of the ones before, and return the shape so they can be chained.
+#[pyclass(name = "Shape")]
+pub struct PyShape {
+    shape: Shape,
+}
+
+impl PyShape {
+    fn from(intersectable: Intersectable) -> PyShape {
+        PyShape {
+            shape: Shape::new(intersectable),
+        }
+    }
+}
+
+#[pymethods]
+impl PyShape {
+    #[staticmethod]
+    fn sphere() -> PyShape {
+        PyShape::from(Intersectable::sphere())
+    }
+
+    #[staticmethod]
+    fn plane() -> PyShape {
+        PyShape::from(Intersectable::plane())
+    }
+
+    #[staticmethod]
+    fn cube() -> PyShape {
+        PyShape::from(Intersectable::cube())
+    }
+
+    #[staticmethod]
+    fn triangle(a: Triple, b: Triple, c: Triple) -> PyShape {
+        PyShape::from(Intersectable::triangle(
+            point(a.0, a.1, a.2),
+            point(b.0, b.1, b.2),
+            point(c.0, c.1, c.2),
+        ))
+    }
+
+    #[staticmethod]
+    fn load_obj(path: &str) -> PyResult<PyShape> {
+        let model = ObjParser::parse_file(path).map_err(|e| PyIOError::new_err(e.to_string()))?;
+        Ok(PyShape {
+            shape: model.with_parent(None),
+        })
+    }
+
+    #[getter]
+    fn material(&self) -> PyMaterial {
+        PyMaterial {
+            material: self.shape.material.clone(),
+        }
+    }
+
+    #[setter]
+    fn set_material(&mut self, material: PyMaterial) {
+        self.shape.material = material.material;
+    }
+
+    fn translate(mut slf: PyRefMut<Self>, x: f64, y: f64, z: f64) -> PyResult<PyRefMut<Self>> {
+        slf.transform(Matrix4::translation(x, y, z))?;
+        Ok(slf)
+    }
+
+    fn scale(mut slf: PyRefMut<Self>, x: f64, y: f64, z: f64) -> PyResult<PyRefMut<Self>> {
+        slf.transform(Matrix4::scaling(x, y, z))?;
+        Ok(slf)
+    }
+
+    fn rotate_x(mut slf: PyRefMut<Self>, radians: f64) -> PyResult<PyRefMut<Self>> {
+        slf.transform(Matrix4::rotation_x(radians))?;
+        Ok(slf)
+    }
+
+    fn rotate_y(mut slf: PyRefMut<Self>, radians: f64) -> PyResult<PyRefMut<Self>> {
+        slf.transform(Matrix4::rotation_y(radians))?;
+        Ok(slf)
+    }
+
+    fn rotate_z(mut slf: PyRefMut<Self>, radians: f64) -> PyResult<PyRefMut<Self>> {
+        slf.transform(Matrix4::rotation_z(radians))?;
+        Ok(slf)
+    }
+}
+
+impl PyShape {
+    fn transform(&mut self, transform: Matrix4) -> PyResult<()> {
+        self.shape
+            .transform_by(&transform)
+            .map_err(|error| PyValueError::new_err(error.to_string()))
+    }
+}
+
+#[pyclass(name = "Camera")]
+pub struct PyCamera {
+    camera: Camera,
+}
+
+#[pymethods]
+impl PyCamera {
+    #[new]
+    fn new(width: usize, height: usize, field_of_view: f64) -> PyCamera {
+        PyCamera {
+            camera: Camera::new(width, height, field_of_view),
+        }
+    }
+
+    #[pyo3(signature = (from, to, up = (0., 1., 0.)))]
+    fn look_at(
+        mut slf: PyRefMut<Self>,
+        from: Triple,
+        to: Triple,
+        up: Triple,
+    ) -> PyResult<PyRefMut<Self>> {
+        let transform = TransformationMatrix::new(
+            &point(from.0, from.1, from.2),
+            &point(to.0, to.1, to.2),
+            &point(up.0, up.1, up.2),
+        );
+        slf.camera
+            .set_transform(transform)
+            .map_err(|error| PyValueError::new_err(error.to_string()))?;
+        Ok(slf)
+    }
+
+    #[getter]
+    fn samples(&self) -> usize {
+        self.camera.samples
+    }
+
+    #[setter]
+    fn set_samples(&mut self, samples: usize) {
+        self.camera.samples = samples;
+    }
+}
+
+// Starts empty, lit by a white light above and to the left of the origin.
+#[pyclass(name = "World")]
+pub struct PyWorld {
+    world: World,
+}
+
+#[pymethods]
+impl PyWorld {
+    #[new]
+    fn new() -> PyWorld {
+        let mut world = World::new();
+        world.objects = Vec::new();
+        PyWorld { world }
+    }
+
+    // A world and its camera from a scene file's JSON.
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<(PyWorld, PyCamera)> {
+        let (world, camera) =
+            World::from_json(json).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok((PyWorld { world }, PyCamera { camera }))
+    }
+
+    // Adds a copy of the shape, so it can be changed and added again.
+    fn add(&mut self, shape: &PyShape) {
+        self.world
+            .objects
+            .push(Arc::new(shape.shape.with_parent(None)));
+    }
+
+    #[pyo3(signature = (position, intensity = (1., 1., 1.)))]
+    fn light(&mut self, position: Triple, intensity: Triple) {
+        let position = point(position.0, position.1, position.2);
+        self.world.light_source = Arc::new(PointLight::new(position, color(intensity)));
+    }
+
+    fn __len__(&self) -> usize {
+        self.world.objects.len()
+    }
+
+    fn __str__(&self) -> String {
+        self.world.to_string()
+    }
+}
+
+// A rendered image. numpy.asarray gives its linear colors as a height by
+// width by 3 array of float32 without copying them.
+#[pyclass(name = "Image")]
+pub struct PyImage {
+    canvas: Canvas,
+    floats: Vec<f32>,
+}
+
+#[pymethods]
+impl PyImage {
+    #[getter]
+    fn width(&self) -> i64 {
+        self.canvas.width
+    }
+
+    #[getter]
+    fn height(&self) -> i64 {
+        self.canvas.height
+    }
+
+    #[getter]
+    fn __array_interface__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let interface = PyDict::new(py);
+        interface.set_item("shape", (self.canvas.height, self.canvas.width, 3))?;
+        interface.set_item("typestr", "<f4")?;
+        interface.set_item("data", (self.floats.as_ptr() as usize, true))?;
+        interface.set_item("version", 3)?;
+        Ok(interface)
+    }
+
+    // sRGB encoded bytes, four per pixel, for saving or display.
+    fn rgba<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        let mut canvas = Canvas::empty(self.canvas.width, self.canvas.height);
+        canvas.pixels = self.canvas.pixels.clone();
+        canvas.encoding = Encoding::Srgb;
+        PyBytes::new(py, &canvas.to_rgba())
+    }
+}
+
+// Renders without holding the GIL, so other Python threads keep running.
+#[pyfunction]
+fn render(py: Python, world: &PyWorld, camera: &PyCamera) -> PyImage {
+    let canvas = py.detach(|| camera.camera.render(&world.world));
+    let floats = canvas
+        .pixels
+        .iter()
+        .flat_map(|pixel| <[f32; 3]>::from(*pixel).to_vec())
+        .collect();
+    PyImage { canvas, floats }
+}
+
+#[pymodule]
+fn ray_tracer(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyCamera>()?;
+    module.add_class::<PyImage>()?;
+    module.add_class::<PyMaterial>()?;
+    module.add_class::<PyShape>()?;
+    module.add_class::<PyWorld>()?;
+    module.add_function(wrap_pyfunction!(self::render, module)?)?;
+    Ok(())
+}
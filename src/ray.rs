@@ -2,11 +2,17 @@ use intersection::Intersection;
 use matrix::Matrix4;
 use point::Point;
 use shape::Shape;
-use std::cell::RefCell;
+use std::cell::Cell;
 use std::sync::Arc;
 use world::World;
 
-thread_local!(static ray_count: RefCell<i64> = RefCell::new(0));
+thread_local!(static INTERSECTION_TESTS: Cell<u64> = const { Cell::new(0) });
+
+// How many times rays on this thread have been tested against a shape,
+// counting each shape inside a group as well as the group.
+pub fn intersection_tests() -> u64 {
+    INTERSECTION_TESTS.with(Cell::get)
+}
 
 pub struct Ray {
     pub origin: Point,
@@ -19,25 +25,19 @@ impl Ray {
     }
 
     pub fn intersect(&self, shape: Arc<Shape>) -> Vec<Intersection> {
-        // ray_count.with(|count_cell| {
-        //     let plus = *count_cell.borrow() + 1;
-        //     count_cell.replace(plus);
-        //     if *count_cell.borrow() % 10000 == 0 {
-        //         println!("{:?}", *count_cell.borrow());
-        //     }
-        // });
-        let ray = self.transform(shape.transform.inverse());
+        INTERSECTION_TESTS.with(|tests| tests.set(tests.get() + 1));
+        let ray = self.transform(*shape.inverse_transform());
         shape.intersectable.local_intersect(&ray, shape.clone())
     }
 
     pub fn intersect_world(&self, world: &World) -> Vec<Intersection> {
         let mut intersections: Vec<Intersection> = Vec::new();
-        for object in &world.objects {
+        for object in world.objects_along(self) {
             intersections.extend(self.intersect(object.clone()));
         }
         let mut positive_intersections: Vec<Intersection> = Vec::new();
         for intersection in intersections {
-            if intersection.t > 0. {
+            if intersection.t > 0. && !intersection.is_cut_out(self) {
                 positive_intersections.push(intersection)
             }
         }
@@ -56,7 +56,6 @@ impl Ray {
 #[cfg(test)]
 mod tests {
     use intersectable::Intersectable;
-    use material::Material;
     use matrix::Matrix4;
     use point::point;
     use point::vector;
@@ -189,12 +188,11 @@ mod tests {
             origin: point(0.0, 0.0, -5.0),
             direction: vector(0.0, 0.0, 1.0),
         };
-        let s = Arc::new(Shape {
-            parent: None,
-            transform: Matrix4::scaling(2.0, 2.0, 2.0),
-            material: Material::new(),
-            intersectable: Intersectable::sphere(),
-        });
+        let mut shape = Shape::new(Intersectable::sphere());
+        shape
+            .set_transform(Matrix4::scaling(2.0, 2.0, 2.0))
+            .unwrap();
+        let s = Arc::new(shape);
 
         let xs = r.intersect(s);
 
@@ -209,12 +207,11 @@ mod tests {
             origin: point(0.0, 0.0, -5.0),
             direction: vector(0.0, 0.0, 1.0),
         };
-        let s = Arc::new(Shape {
-            parent: None,
-            intersectable: Intersectable::sphere(),
-            transform: Matrix4::translation(5.0, 0.0, 0.0),
-            material: Material::new(),
-        });
+        let mut shape = Shape::new(Intersectable::sphere());
+        shape
+            .set_transform(Matrix4::translation(5.0, 0.0, 0.0))
+            .unwrap();
+        let s = Arc::new(shape);
 
         let xs = r.intersect(s);
 
@@ -0,0 +1,197 @@
+use point::vector;
+use point::Point;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::f64::consts::PI;
+
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+// Every random number in a render comes from here, so reseeding makes it
+// repeat exactly.
+pub fn random() -> f64 {
+    RNG.with(|rng| rng.borrow_mut().gen::<f64>())
+}
+
+// Restarts this thread's random numbers. Each `stream` of a seed, like
+// each pixel of an image, gets its own sequence, so the result doesn't
+// depend on which thread gets there first.
+pub fn reseed(seed: u64, stream: u64) {
+    let mixed = seed ^ stream.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(mixed));
+}
+
+// Two unit vectors perpendicular to the normal and to each other.
+pub fn orthonormal_basis(normal: &Point) -> (Point, Point) {
+    let helper = if normal.x.abs() > 0.9 {
+        vector(0., 1., 0.)
+    } else {
+        vector(1., 0., 0.)
+    };
+    let tangent = normal.cross(&helper).normalize();
+    let bitangent = normal.cross(&tangent);
+    (tangent, bitangent)
+}
+
+pub fn cosine_hemisphere(normal: &Point) -> Point {
+    let (tangent, bitangent) = orthonormal_basis(normal);
+    let r = random().sqrt();
+    let theta = 2. * PI * random();
+    let up = (1. - r * r).max(0.).sqrt();
+    tangent
+        .multiply_scalar(r * theta.cos())
+        .add(&bitangent.multiply_scalar(r * theta.sin()))
+        .add(&normal.multiply_scalar(up))
+}
+
+// A direction about the axis with density (n + 1) / 2π · cosⁿ of the angle
+// to it, matching a Phong highlight of exponent n.
+pub fn phong_lobe(axis: &Point, exponent: f64) -> Point {
+    let (tangent, bitangent) = orthonormal_basis(axis);
+    let cos = random().powf(1. / (exponent + 1.));
+    let sin = (1. - cos * cos).max(0.).sqrt();
+    let phi = 2. * PI * random();
+    tangent
+        .multiply_scalar(sin * phi.cos())
+        .add(&bitangent.multiply_scalar(sin * phi.sin()))
+        .add(&axis.multiply_scalar(cos))
+}
+
+pub fn random_in_unit_sphere() -> Point {
+    loop {
+        let p = vector(2. * random() - 1., 2. * random() - 1., 2. * random() - 1.);
+        if p.magnitude() < 1. {
+            return p;
+        }
+    }
+}
+
+// How sets of sample positions in the unit square are chosen. Stratified
+// splits the square into a grid and jitters one sample inside each cell,
+// which covers it more evenly than independent random samples.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sampler {
+    Random,
+    Stratified,
+}
+
+impl Sampler {
+    pub fn square(&self, count: usize) -> Vec<(f64, f64)> {
+        match *self {
+            Sampler::Random => (0..count).map(|_| (random(), random())).collect(),
+            Sampler::Stratified => {
+                let columns = (count as f64).sqrt().ceil() as usize;
+                let rows = (count + columns - 1) / columns.max(1);
+                let mut samples: Vec<(f64, f64)> = (0..rows * columns)
+                    .map(|cell| {
+                        (
+                            ((cell % columns) as f64 + random()) / columns as f64,
+                            ((cell / columns) as f64 + random()) / rows as f64,
+                        )
+                    })
+                    .collect();
+                // Leftover cells are dropped at random rather than always
+                // from the top of the square.
+                shuffle(&mut samples);
+                samples.truncate(count);
+                samples
+            }
+        }
+    }
+}
+
+pub fn shuffle<T>(items: &mut [T]) {
+    for i in (1..items.len()).rev() {
+        let j = ((random() * (i + 1) as f64) as usize).min(i);
+        items.swap(i, j);
+    }
+}
+
+// Maps the unit square onto the unit disc, keeping areas proportional.
+pub fn square_to_disk(sample: (f64, f64)) -> (f64, f64) {
+    let r = sample.0.sqrt();
+    let theta = 2. * PI * sample.1;
+    (r * theta.cos(), r * theta.sin())
+}
+
+// Maps the unit square onto the unit sphere, keeping areas proportional.
+pub fn square_to_sphere(sample: (f64, f64)) -> Point {
+    let z = 1. - 2. * sample.0;
+    let r = (1. - z * z).max(0.).sqrt();
+    let phi = 2. * PI * sample.1;
+    vector(r * phi.cos(), r * phi.sin(), z)
+}
+
+#[cfg(test)]
+mod tests {
+    use point::vector;
+    use sampling::*;
+    use utilities::equal;
+
+    #[test]
+    fn test_reseed() {
+        reseed(7, 3);
+        let first: Vec<f64> = (0..4).map(|_| random()).collect();
+        reseed(7, 4);
+        let other: Vec<f64> = (0..4).map(|_| random()).collect();
+        reseed(7, 3);
+        let again: Vec<f64> = (0..4).map(|_| random()).collect();
+
+        assert_eq!(first, again);
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn test_orthonormal_basis() {
+        let normal = vector(1., 2., 3.).normalize();
+        let (t, b) = orthonormal_basis(&normal);
+
+        assert!(equal(t.dot(&normal), 0.));
+        assert!(equal(b.dot(&normal), 0.));
+        assert!(equal(t.dot(&b), 0.));
+        assert!(equal(t.magnitude(), 1.));
+        assert!(equal(b.magnitude(), 1.));
+    }
+
+    #[test]
+    fn test_stratified_sampler() {
+        let samples = Sampler::Stratified.square(16);
+
+        assert_eq!(samples.len(), 16);
+        for row in 0..4 {
+            for column in 0..4 {
+                let in_cell = samples
+                    .iter()
+                    .filter(|&&(u, v)| (u * 4.) as usize == column && (v * 4.) as usize == row);
+                assert_eq!(in_cell.count(), 1);
+            }
+        }
+        assert_eq!(Sampler::Stratified.square(5).len(), 5);
+        assert_eq!(Sampler::Random.square(5).len(), 5);
+    }
+
+    #[test]
+    fn test_square_to_disk_and_sphere() {
+        let (x, y) = square_to_disk((1., 0.25));
+
+        assert!(equal(x, 0.));
+        assert!(equal(y, 1.));
+        assert!(equal(square_to_sphere((0.3, 0.8)).magnitude(), 1.));
+        assert_eq!(square_to_sphere((0., 0.)), vector(0., 0., 1.));
+    }
+
+    #[test]
+    fn test_cosine_hemisphere() {
+        let normal = vector(0., 0., -1.);
+        for _ in 0..100 {
+            let direction = cosine_hemisphere(&normal);
+
+            assert!(direction.dot(&normal) >= 0.);
+            assert!(equal(direction.magnitude(), 1.));
+        }
+    }
+}
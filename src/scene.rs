@@ -0,0 +1,2262 @@
+use ambient_occlusion::AmbientOcclusion;
+use background::{Background, Sky};
+use camera::{Adaptive, Camera};
+use color::Color;
+use environment::Environment;
+use fog::Fog;
+use fractal::{Fbm, NoiseSource};
+use image;
+use intersectable::{Intersectable, IntersectableType};
+use intersection::Bias;
+use keyframe::{self, Keyframe, Lerp};
+use light::Light;
+use material::{Backface, ChannelValue, Mask, Material, SpecularModel};
+use matrix::Matrix4;
+use matrix::IDENTITY_MATRIX;
+use mesh::{Face, Mesh};
+use patternable::{Axis, BlendMode, Interpolation, Patternable, PatternableType};
+use point::{point, vector, Point};
+use point_light::PointLight;
+use sampling::Sampler;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{self, Map, Value};
+use shape::{Shape, ShapeQuery};
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap};
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use texture::{Texture, Wrap};
+use transformation_matrix::TransformationMatrix;
+use utilities::EPSILON;
+use uv_mapping::UvMapping;
+use volume::Volume;
+use world::{Integrator, RenderMode, World};
+
+// Scenes are described in JSON, with colors and points written as
+// three-element arrays and angles in radians. See `World::from_json`.
+#[derive(Debug)]
+pub enum SceneError {
+    Image(image::ImageError),
+    Io(io::Error),
+    Json(serde_json::Error),
+    // A transform that flattens space, which nothing could be seen through.
+    SingularTransform,
+    // A `use` of a material or pattern the scene does not define.
+    Undefined { kind: &'static str, name: String },
+    // Part of a world the format has no way to describe.
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SceneError::Image(ref error) => write!(f, "could not load texture: {}", error),
+            SceneError::Io(ref error) => write!(f, "could not read scene: {}", error),
+            SceneError::Json(ref error) => write!(f, "invalid scene: {}", error),
+            SceneError::SingularTransform => write!(f, "scene has a transform with no inverse"),
+            SceneError::Undefined { kind, ref name } => {
+                write!(f, "scene has no {} named `{}`", kind, name)
+            }
+            SceneError::Unsupported(what) => write!(f, "scenes cannot describe {}", what),
+        }
+    }
+}
+
+impl error::Error for SceneError {}
+
+impl From<image::ImageError> for SceneError {
+    fn from(error: image::ImageError) -> SceneError {
+        SceneError::Image(error)
+    }
+}
+
+impl From<io::Error> for SceneError {
+    fn from(error: io::Error) -> SceneError {
+        SceneError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for SceneError {
+    fn from(error: serde_json::Error) -> SceneError {
+        SceneError::Json(error)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SceneDescription {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ambient_occlusion: Option<AmbientOcclusion>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub animation: Option<AnimationDescription>,
+    #[serde(default)]
+    pub background: BackgroundDescription,
+    #[serde(default = "fixed_bias")]
+    pub bias: Bias,
+    pub camera: CameraDescription,
+    // More views of the same world, which renders pick by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub cameras: BTreeMap<String, CameraDescription>,
+    // What texture paths in the scene are relative to.
+    #[serde(skip)]
+    pub dir: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<EnvironmentDescription>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fog: Option<FogDescription>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<IncludeDescription>,
+    #[serde(default)]
+    pub integrator: Integrator,
+    pub light: LightDescription,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reflection_depth: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refraction_depth: Option<i32>,
+    #[serde(default)]
+    pub render_mode: RenderMode,
+    #[serde(default)]
+    pub shapes: Vec<ShapeDescription>,
+    // The files read to describe the scene: its own and any it includes.
+    #[serde(skip)]
+    pub sources: Vec<PathBuf>,
+}
+
+// Another scene file whose shapes are added as a group, placed by
+// `transform`. Its path is relative to the file that includes it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IncludeDescription {
+    pub path: String,
+    #[serde(default)]
+    pub transform: Vec<Transform>,
+}
+
+// The part of a scene file that including it uses. Anything else in the
+// file, like its camera, is ignored, so whole scenes can be included too.
+#[derive(Debug, Deserialize)]
+struct SceneFragment {
+    #[serde(default)]
+    include: Vec<IncludeDescription>,
+    #[serde(default)]
+    shapes: Vec<ShapeDescription>,
+}
+
+impl SceneDescription {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<SceneDescription, SceneError> {
+        let path = path.as_ref();
+        let mut description: SceneDescription = read_json(&fs::read_to_string(path)?)?;
+        let includes = description.include.drain(..).collect::<Vec<_>>();
+        let mut stack = vec![fs::canonicalize(path)?];
+        let mut sources = vec![path.to_path_buf()];
+        description
+            .shapes
+            .extend(resolve(includes, &dir_of(path), &mut stack, &mut sources)?);
+        description.dir = dir_of(path);
+        description.sources = sources;
+        Ok(description)
+    }
+
+    // Includes and textures in a scene that is not read from a file are
+    // relative to `dir`.
+    pub fn from_json(json: &str, dir: &Path) -> Result<SceneDescription, SceneError> {
+        let mut description: SceneDescription = read_json(json)?;
+        let includes = description.include.drain(..).collect::<Vec<_>>();
+        let mut sources = Vec::new();
+        description
+            .shapes
+            .extend(resolve(includes, dir, &mut Vec::new(), &mut sources)?);
+        description.dir = dir.to_path_buf();
+        description.sources = sources;
+        Ok(description)
+    }
+
+    // Every file the scene depends on, for noticing when it changes.
+    pub fn dependencies(&self) -> Vec<PathBuf> {
+        let mut files = self.sources.clone();
+        files.extend(self.environment.iter().map(|e| self.dir.join(&e.path)));
+        self.background.textures(&self.dir, &mut files);
+        for shape in &self.shapes {
+            shape.textures(&self.dir, &mut files);
+        }
+        files
+    }
+
+    pub fn build(&self) -> Result<(World, Camera), SceneError> {
+        Ok((self.build_world()?, self.build_camera(None)?))
+    }
+
+    pub fn build_world(&self) -> Result<World, SceneError> {
+        let mut world = World::new();
+        world.ambient_occlusion = self.ambient_occlusion;
+        world.background = self.background.build(&self.dir)?;
+        world.bias = self.bias;
+        world.environment = match self.environment {
+            Some(ref environment) => Some(environment.build(&self.dir)?),
+            None => None,
+        };
+        world.fog = self.fog.as_ref().map(FogDescription::build);
+        world.integrator = self.integrator;
+        world.light_source = Arc::new(self.light.build());
+        world.objects = self
+            .shapes
+            .iter()
+            .map(|shape| shape.build(None, &self.dir).map(Arc::new))
+            .collect::<Result<_, _>>()?;
+        world.reflection_depth = self.reflection_depth;
+        world.refraction_depth = self.refraction_depth;
+        world.render_mode = self.render_mode;
+        Ok(world)
+    }
+
+    // The main camera, or one of `cameras` by name.
+    pub fn build_camera(&self, name: Option<&str>) -> Result<Camera, SceneError> {
+        match name {
+            None => self.camera.build(),
+            Some(name) => match self.cameras.get(name) {
+                Some(camera) => camera.build(),
+                None => Err(SceneError::Undefined {
+                    kind: "camera",
+                    name: name.to_string(),
+                }),
+            },
+        }
+    }
+
+    pub fn cameras_mut(&mut self) -> impl Iterator<Item = &mut CameraDescription> {
+        Some(&mut self.camera)
+            .into_iter()
+            .chain(self.cameras.values_mut())
+    }
+
+    pub fn from_world(world: &World, camera: &Camera) -> Result<SceneDescription, SceneError> {
+        Ok(SceneDescription {
+            ambient_occlusion: world.ambient_occlusion,
+            animation: None,
+            background: BackgroundDescription::from_background(&world.background)?,
+            bias: world.bias,
+            camera: CameraDescription::from_camera(camera),
+            cameras: BTreeMap::new(),
+            dir: PathBuf::new(),
+            environment: match world.environment {
+                Some(ref environment) => {
+                    Some(EnvironmentDescription::from_environment(environment)?)
+                }
+                None => None,
+            },
+            fog: world.fog.as_ref().map(FogDescription::from_fog),
+            include: Vec::new(),
+            integrator: world.integrator,
+            light: LightDescription::from_light(&*world.light_source)?,
+            reflection_depth: world.reflection_depth,
+            refraction_depth: world.refraction_depth,
+            render_mode: world.render_mode,
+            sources: Vec::new(),
+            shapes: world
+                .objects
+                .iter()
+                .map(|shape| ShapeDescription::from_shape(shape))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackgroundDescription {
+    Gradient { horizon: [f64; 3], zenith: [f64; 3] },
+    Pattern(PatternDescription),
+    Sky(SkyDescription),
+    Solid([f64; 3]),
+}
+
+impl Default for BackgroundDescription {
+    fn default() -> BackgroundDescription {
+        BackgroundDescription::Solid([0., 0., 0.])
+    }
+}
+
+impl BackgroundDescription {
+    fn textures(&self, dir: &Path, files: &mut Vec<PathBuf>) {
+        if let BackgroundDescription::Pattern(ref pattern) = *self {
+            pattern.textures(dir, files);
+        }
+    }
+
+    fn build(&self, dir: &Path) -> Result<Background, SceneError> {
+        Ok(match *self {
+            BackgroundDescription::Gradient { horizon, zenith } => {
+                Background::Gradient(color(horizon), color(zenith))
+            }
+            BackgroundDescription::Pattern(ref pattern) => Background::Pattern(pattern.build(dir)?),
+            BackgroundDescription::Sky(ref sky) => Background::Sky(sky.build()),
+            BackgroundDescription::Solid(background) => Background::Solid(color(background)),
+        })
+    }
+
+    fn from_background(background: &Background) -> Result<BackgroundDescription, SceneError> {
+        match *background {
+            Background::Gradient(horizon, zenith) => Ok(BackgroundDescription::Gradient {
+                horizon: rgb(&horizon),
+                zenith: rgb(&zenith),
+            }),
+            Background::Solid(background) => Ok(BackgroundDescription::Solid(rgb(&background))),
+            Background::Pattern(ref pattern) => Ok(BackgroundDescription::Pattern(
+                PatternDescription::from_pattern(pattern)?,
+            )),
+            Background::Sky(ref sky) => {
+                Ok(BackgroundDescription::Sky(SkyDescription::from_sky(sky)))
+            }
+        }
+    }
+}
+
+// Any field left out keeps the value from `Sky::new`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SkyDescription {
+    pub ground: [f64; 3],
+    pub horizon: [f64; 3],
+    pub sun: [f64; 3],
+    pub sun_size: f64,
+    pub zenith: [f64; 3],
+}
+
+impl Default for SkyDescription {
+    fn default() -> SkyDescription {
+        SkyDescription::from_sky(&Sky::new())
+    }
+}
+
+impl SkyDescription {
+    fn build(&self) -> Sky {
+        Sky {
+            ground: color(self.ground),
+            horizon: color(self.horizon),
+            sun: color(self.sun),
+            sun_size: self.sun_size,
+            zenith: color(self.zenith),
+        }
+    }
+
+    fn from_sky(sky: &Sky) -> SkyDescription {
+        SkyDescription {
+            ground: rgb(&sky.ground),
+            horizon: rgb(&sky.horizon),
+            sun: rgb(&sky.sun),
+            sun_size: sky.sun_size,
+            zenith: rgb(&sky.zenith),
+        }
+    }
+}
+
+// An equirectangular image around the scene; see `Environment`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EnvironmentDescription {
+    #[serde(default = "one")]
+    pub intensity: f64,
+    pub path: String,
+    #[serde(default)]
+    pub samples: usize,
+}
+
+impl EnvironmentDescription {
+    fn build(&self, dir: &Path) -> Result<Environment, SceneError> {
+        let mut environment = Environment::open(dir.join(&self.path))?;
+        environment.intensity = self.intensity;
+        environment.samples = self.samples;
+        Ok(environment)
+    }
+
+    fn from_environment(environment: &Environment) -> Result<EnvironmentDescription, SceneError> {
+        Ok(EnvironmentDescription {
+            intensity: environment.intensity,
+            path: texture_path(environment.texture())?,
+            samples: environment.samples,
+        })
+    }
+}
+
+// The camera is placed either by `from`, `to` and `up` or by a view
+// `transform`, which is how a saved camera is written.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CameraDescription {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub adaptive: Option<Adaptive>,
+    #[serde(default)]
+    pub aperture: f64,
+    #[serde(default)]
+    pub auto_exposure: bool,
+    #[serde(default = "camera_depth")]
+    pub depth: i32,
+    #[serde(default)]
+    pub exposure: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub far: Option<f64>,
+    pub field_of_view: f64,
+    #[serde(default = "one")]
+    pub focal_distance: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<[f64; 3]>,
+    pub height: usize,
+    #[serde(default)]
+    pub near: f64,
+    #[serde(default = "stratified")]
+    pub sampler: Sampler,
+    #[serde(default = "one_sample")]
+    pub samples: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub shift_x: f64,
+    #[serde(default)]
+    pub shift_y: f64,
+    #[serde(default)]
+    pub tilt_x: f64,
+    #[serde(default)]
+    pub tilt_y: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<[f64; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<[[f64; 4]; 4]>,
+    #[serde(default = "up")]
+    pub up: [f64; 3],
+    pub width: usize,
+}
+
+impl CameraDescription {
+    fn build(&self) -> Result<Camera, SceneError> {
+        let mut camera = Camera::new(self.width, self.height, self.field_of_view);
+        camera.adaptive = self.adaptive;
+        camera.aperture = self.aperture;
+        camera.auto_exposure = self.auto_exposure;
+        camera.depth = self.depth;
+        camera.exposure = self.exposure;
+        camera.far = self.far.unwrap_or(f64::INFINITY);
+        camera.focal_distance = self.focal_distance;
+        camera.near = self.near;
+        camera.sampler = self.sampler;
+        camera.samples = self.samples;
+        camera.seed = self.seed;
+        camera.shift_x = self.shift_x;
+        camera.shift_y = self.shift_y;
+        camera.tilt_x = self.tilt_x;
+        camera.tilt_y = self.tilt_y;
+        let transform = match (self.transform, self.from, self.to) {
+            (Some(members), _, _) => Matrix4::new(members),
+            (None, Some(from), Some(to)) => {
+                TransformationMatrix::new(&to_point(from), &to_point(to), &to_point(self.up))
+            }
+            _ => return Err(SceneError::Unsupported("a camera without from and to")),
+        };
+        camera
+            .set_transform(transform)
+            .map_err(|_| SceneError::SingularTransform)?;
+        Ok(camera)
+    }
+
+    fn from_camera(camera: &Camera) -> CameraDescription {
+        CameraDescription {
+            adaptive: camera.adaptive,
+            aperture: camera.aperture,
+            auto_exposure: camera.auto_exposure,
+            depth: camera.depth,
+            exposure: camera.exposure,
+            far: Some(camera.far).filter(|far| far.is_finite()),
+            field_of_view: camera.field_of_view(),
+            focal_distance: camera.focal_distance,
+            from: None,
+            height: camera.vsize,
+            near: camera.near,
+            sampler: camera.sampler,
+            samples: camera.samples,
+            seed: camera.seed,
+            shift_x: camera.shift_x,
+            shift_y: camera.shift_y,
+            tilt_x: camera.tilt_x,
+            tilt_y: camera.tilt_y,
+            to: None,
+            transform: Some(camera.transform().members()),
+            up: up(),
+            width: camera.hsize,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FogDescription {
+    #[serde(default)]
+    pub color: [f64; 3],
+    pub density: f64,
+    #[serde(default)]
+    pub falloff: f64,
+    #[serde(default)]
+    pub horizon: bool,
+    #[serde(default)]
+    pub scattering: f64,
+    #[serde(default = "fog_steps")]
+    pub steps: usize,
+}
+
+impl FogDescription {
+    fn build(&self) -> Fog {
+        let mut fog = Fog::new(color(self.color), self.density);
+        fog.falloff = self.falloff;
+        fog.horizon = self.horizon;
+        fog.scattering = self.scattering;
+        fog.steps = self.steps;
+        fog
+    }
+
+    fn from_fog(fog: &Fog) -> FogDescription {
+        FogDescription {
+            color: rgb(&fog.color),
+            density: fog.density,
+            falloff: fog.falloff,
+            horizon: fog.horizon,
+            scattering: fog.scattering,
+            steps: fog.steps,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LightDescription {
+    pub intensity: [f64; 3],
+    pub position: [f64; 3],
+    #[serde(default)]
+    pub radius: f64,
+    #[serde(default = "one_sample")]
+    pub samples: usize,
+}
+
+impl LightDescription {
+    fn build(&self) -> PointLight {
+        let mut light = PointLight::new(to_point(self.position), color(self.intensity));
+        light.radius = self.radius;
+        light.samples = self.samples;
+        light
+    }
+
+    fn from_light(light: &dyn Light) -> Result<LightDescription, SceneError> {
+        let light = point_light(light).ok_or(SceneError::Unsupported("custom lights"))?;
+        Ok(LightDescription {
+            intensity: rgb(&light.intensity),
+            position: xyz(&light.position),
+            radius: light.radius,
+            samples: light.samples,
+        })
+    }
+}
+
+// Keyframes for a scene, counting frames from one. Shapes are animated by
+// name, each keyframe moving the shape from where the scene puts it, and
+// a camera's keyframes replace its placement. `camera` moves the main
+// camera and `cameras` the named ones.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnimationDescription {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub camera: Vec<Keyframe<CameraKey>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub cameras: BTreeMap<String, Vec<Keyframe<CameraKey>>>,
+    pub frames: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub light: Vec<Keyframe<LightKey>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub shapes: BTreeMap<String, Vec<Keyframe<ShapeKey>>>,
+}
+
+impl AnimationDescription {
+    pub fn apply_to_world(&self, frame: usize, world: &mut World) -> Result<(), SceneError> {
+        let frame = frame as f64;
+        for (name, keyframes) in &self.shapes {
+            if let Some(key) = keyframe::sample(keyframes, frame) {
+                world
+                    .transform_shapes(&ShapeQuery::Name(name.clone()), key.matrix())
+                    .map_err(|_| SceneError::SingularTransform)?;
+            }
+        }
+        // Scenes only describe point lights, so only those are keyed.
+        if let Some(key) = keyframe::sample(&self.light, frame) {
+            if let Some(light) = point_light(&*world.light_source) {
+                let mut light = light.clone();
+                light.intensity = color(key.intensity);
+                world.light_source = Arc::new(light);
+            }
+        }
+        Ok(())
+    }
+
+    // `name` picks the camera the same way as `SceneDescription::build_camera`.
+    pub fn apply_to_camera(
+        &self,
+        frame: usize,
+        name: Option<&str>,
+        camera: &mut Camera,
+    ) -> Result<(), SceneError> {
+        let keyframes = match name {
+            None => &self.camera,
+            Some(name) => match self.cameras.get(name) {
+                Some(keyframes) => keyframes,
+                None => return Ok(()),
+            },
+        };
+        if let Some(key) = keyframe::sample(keyframes, frame as f64) {
+            camera
+                .set_transform(TransformationMatrix::new(
+                    &to_point(key.from),
+                    &to_point(key.to),
+                    &to_point(key.up),
+                ))
+                .map_err(|_| SceneError::SingularTransform)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CameraKey {
+    pub from: [f64; 3],
+    pub to: [f64; 3],
+    #[serde(default = "up")]
+    pub up: [f64; 3],
+}
+
+impl Lerp for CameraKey {
+    fn lerp(&self, other: &CameraKey, t: f64) -> CameraKey {
+        CameraKey {
+            from: self.from.lerp(&other.from, t),
+            to: self.to.lerp(&other.to, t),
+            up: self.up.lerp(&other.up, t),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LightKey {
+    pub intensity: [f64; 3],
+}
+
+impl Lerp for LightKey {
+    fn lerp(&self, other: &LightKey, t: f64) -> LightKey {
+        LightKey {
+            intensity: self.intensity.lerp(&other.intensity, t),
+        }
+    }
+}
+
+// Scaled, then rotated about x, y and z in turn, then translated. Each part
+// is interpolated on its own, so rotations can go past a half turn.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ShapeKey {
+    #[serde(default)]
+    pub rotate: [f64; 3],
+    #[serde(default = "unscaled")]
+    pub scale: [f64; 3],
+    #[serde(default)]
+    pub translate: [f64; 3],
+}
+
+impl ShapeKey {
+    fn matrix(&self) -> Matrix4 {
+        let [x, y, z] = self.translate;
+        let [rx, ry, rz] = self.rotate;
+        let [sx, sy, sz] = self.scale;
+        Matrix4::translation(x, y, z)
+            .multiply(&Matrix4::rotation_z(rz))
+            .multiply(&Matrix4::rotation_y(ry))
+            .multiply(&Matrix4::rotation_x(rx))
+            .multiply(&Matrix4::scaling(sx, sy, sz))
+    }
+}
+
+impl Lerp for ShapeKey {
+    fn lerp(&self, other: &ShapeKey, t: f64) -> ShapeKey {
+        ShapeKey {
+            rotate: self.rotate.lerp(&other.rotate, t),
+            scale: self.scale.lerp(&other.scale, t),
+            translate: self.translate.lerp(&other.translate, t),
+        }
+    }
+}
+
+// Steps applied to a shape or pattern in the order they are listed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transform {
+    Matrix([[f64; 4]; 4]),
+    RotateX(f64),
+    RotateY(f64),
+    RotateZ(f64),
+    Scale([f64; 3]),
+    Shear([f64; 6]),
+    Translate([f64; 3]),
+}
+
+impl Transform {
+    fn matrix(&self) -> Matrix4 {
+        match *self {
+            Transform::Matrix(members) => Matrix4::new(members),
+            Transform::RotateX(radians) => Matrix4::rotation_x(radians),
+            Transform::RotateY(radians) => Matrix4::rotation_y(radians),
+            Transform::RotateZ(radians) => Matrix4::rotation_z(radians),
+            Transform::Scale([x, y, z]) => Matrix4::scaling(x, y, z),
+            Transform::Shear([xy, xz, yx, yz, zx, zy]) => Matrix4::shearing(xy, xz, yx, yz, zx, zy),
+            Transform::Translate([x, y, z]) => Matrix4::translation(x, y, z),
+        }
+    }
+}
+
+// Replaces each include with a group of the shapes in its file. `stack`
+// holds the files being read, to catch one that includes itself.
+fn resolve(
+    includes: Vec<IncludeDescription>,
+    dir: &Path,
+    stack: &mut Vec<PathBuf>,
+    sources: &mut Vec<PathBuf>,
+) -> Result<Vec<ShapeDescription>, SceneError> {
+    let mut shapes = Vec::new();
+    for include in includes {
+        let path = dir.join(&include.path);
+        let canonical = fs::canonicalize(&path)?;
+        if stack.contains(&canonical) {
+            return Err(SceneError::Unsupported("scenes that include themselves"));
+        }
+        let mut fragment: SceneFragment = read_json(&fs::read_to_string(&path)?)?;
+        stack.push(canonical);
+        let fragment_dir = dir_of(&path);
+        let nested = resolve(fragment.include, &fragment_dir, stack, sources)?;
+        sources.push(path);
+        stack.pop();
+        fragment.shapes.extend(nested);
+        shapes.push(ShapeDescription {
+            dir: Some(fragment_dir),
+            kind: ShapeKind::Group {
+                children: fragment.shapes,
+            },
+            material: MaterialDescription::default(),
+            name: None,
+            tags: Vec::new(),
+            transform: include.transform,
+            uv_mapping: None,
+        });
+    }
+    Ok(shapes)
+}
+
+// Reads a scene or fragment after swapping each `use` for what it names.
+// Definitions belong to the file they are written in.
+fn read_json<T: DeserializeOwned>(json: &str) -> Result<T, SceneError> {
+    let mut scene: Value = serde_json::from_str(json)?;
+    if let Value::Object(ref mut fields) = scene {
+        let definitions = Definitions {
+            materials: definitions(fields.remove("materials"))?,
+            patterns: definitions(fields.remove("patterns"))?,
+        };
+        if let Some(shapes) = fields.get_mut("shapes") {
+            definitions.expand(shapes, DefinitionKind::Pattern, &mut Vec::new())?;
+        }
+    }
+    Ok(serde_json::from_value(scene)?)
+}
+
+fn definitions(section: Option<Value>) -> Result<HashMap<String, Map<String, Value>>, SceneError> {
+    match section {
+        Some(section) => Ok(serde_json::from_value(section)?),
+        None => Ok(HashMap::new()),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum DefinitionKind {
+    Material,
+    Pattern,
+}
+
+impl DefinitionKind {
+    fn name(self) -> &'static str {
+        match self {
+            DefinitionKind::Material => "material",
+            DefinitionKind::Pattern => "pattern",
+        }
+    }
+}
+
+// The `materials` and `patterns` sections of a scene file, each a map from
+// name to definition. A shape's material can be a name on its own, and a
+// material or pattern can be `{ "use": name }` with fields to override.
+struct Definitions {
+    materials: HashMap<String, Map<String, Value>>,
+    patterns: HashMap<String, Map<String, Value>>,
+}
+
+impl Definitions {
+    // Only a shape's material uses a material; any other `use` is a pattern.
+    fn expand(
+        &self,
+        value: &mut Value,
+        kind: DefinitionKind,
+        stack: &mut Vec<String>,
+    ) -> Result<(), SceneError> {
+        if kind == DefinitionKind::Material && value.is_string() {
+            let mut fields = Map::new();
+            fields.insert("use".to_string(), value.take());
+            *value = Value::Object(fields);
+        }
+        match *value {
+            Value::Array(ref mut items) => {
+                for item in items {
+                    self.expand(item, DefinitionKind::Pattern, stack)?;
+                }
+            }
+            Value::Object(ref mut fields) => self.expand_fields(fields, kind, stack)?,
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn expand_fields(
+        &self,
+        fields: &mut Map<String, Value>,
+        kind: DefinitionKind,
+        stack: &mut Vec<String>,
+    ) -> Result<(), SceneError> {
+        for (key, field) in fields.iter_mut() {
+            let kind = if key == "material" {
+                DefinitionKind::Material
+            } else {
+                DefinitionKind::Pattern
+            };
+            self.expand(field, kind, stack)?;
+        }
+        if let Some(name) = fields.remove("use") {
+            let mut definition = self.definition(kind, name, stack)?;
+            definition.extend(mem::take(fields));
+            *fields = definition;
+        }
+        Ok(())
+    }
+
+    // `stack` holds the definitions being expanded, to catch one that uses
+    // itself.
+    fn definition(
+        &self,
+        kind: DefinitionKind,
+        name: Value,
+        stack: &mut Vec<String>,
+    ) -> Result<Map<String, Value>, SceneError> {
+        let name = match name {
+            Value::String(name) => name,
+            _ => return Err(SceneError::Unsupported("a `use` without a name")),
+        };
+        let definitions = match kind {
+            DefinitionKind::Material => &self.materials,
+            DefinitionKind::Pattern => &self.patterns,
+        };
+        let mut definition = match definitions.get(&name) {
+            Some(definition) => definition.clone(),
+            None => {
+                return Err(SceneError::Undefined {
+                    kind: kind.name(),
+                    name,
+                })
+            }
+        };
+        let key = format!("{} {}", kind.name(), name);
+        if stack.contains(&key) {
+            return Err(SceneError::Unsupported("definitions that use themselves"));
+        }
+        stack.push(key);
+        self.expand_fields(&mut definition, kind, stack)?;
+        stack.pop();
+        Ok(definition)
+    }
+}
+
+fn dir_of(path: &Path) -> PathBuf {
+    path.parent().map(Path::to_path_buf).unwrap_or_default()
+}
+
+fn transform(steps: &[Transform]) -> Result<Matrix4, SceneError> {
+    let matrix = steps.iter().fold(IDENTITY_MATRIX, |matrix, step| {
+        step.matrix().multiply(&matrix)
+    });
+    match matrix.try_inverse() {
+        Ok(_) => Ok(matrix),
+        Err(_) => Err(SceneError::SingularTransform),
+    }
+}
+
+fn steps(matrix: &Matrix4) -> Vec<Transform> {
+    if matrix.equal(&IDENTITY_MATRIX) {
+        Vec::new()
+    } else {
+        vec![Transform::Matrix(matrix.members())]
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShapeDescription {
+    // Set on the group made for an include, whose textures are relative
+    // to the included file.
+    #[serde(skip)]
+    pub dir: Option<PathBuf>,
+    #[serde(flatten)]
+    pub kind: ShapeKind,
+    #[serde(default)]
+    pub material: MaterialDescription,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub transform: Vec<Transform>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uv_mapping: Option<UvMapping>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ShapeKind {
+    Cube,
+    Group { children: Vec<ShapeDescription> },
+    Mesh(MeshDescription),
+    Plane,
+    Sphere,
+    Triangle { points: [[f64; 3]; 3] },
+}
+
+// A triangle mesh written out in full, as `Mesh` holds it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MeshDescription {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub colors: Vec<[f64; 3]>,
+    pub faces: Vec<Face>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub materials: Vec<MaterialDescription>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub normals: Vec<[f64; 3]>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub uvs: Vec<(f64, f64)>,
+    pub vertices: Vec<[f64; 3]>,
+}
+
+impl MeshDescription {
+    fn build(&self, dir: &Path) -> Result<Mesh, SceneError> {
+        let mut mesh = Mesh::new(self.vertices.iter().cloned().map(to_point).collect());
+        mesh.colors = self.colors.iter().cloned().map(color).collect();
+        mesh.faces = self.faces.clone();
+        mesh.groups = self.groups.clone();
+        mesh.materials = self
+            .materials
+            .iter()
+            .map(|material| material.build(dir))
+            .collect::<Result<_, _>>()?;
+        mesh.normals = self.normals.iter().cloned().map(to_vector).collect();
+        mesh.uvs = self.uvs.clone();
+        Ok(mesh)
+    }
+
+    fn from_mesh(mesh: &Mesh) -> Result<MeshDescription, SceneError> {
+        Ok(MeshDescription {
+            colors: mesh.colors.iter().map(rgb).collect(),
+            faces: mesh.faces.clone(),
+            groups: mesh.groups.clone(),
+            materials: mesh
+                .materials
+                .iter()
+                .map(MaterialDescription::from_material)
+                .collect::<Result<_, _>>()?,
+            normals: mesh.normals.iter().map(xyz).collect(),
+            uvs: mesh.uvs.clone(),
+            vertices: mesh.vertices.iter().map(xyz).collect(),
+        })
+    }
+}
+
+impl ShapeDescription {
+    fn textures(&self, dir: &Path, files: &mut Vec<PathBuf>) {
+        let dir = self.dir.as_deref().unwrap_or(dir);
+        self.material.textures(dir, files);
+        match self.kind {
+            ShapeKind::Group { ref children } => {
+                for child in children {
+                    child.textures(dir, files);
+                }
+            }
+            ShapeKind::Mesh(ref mesh) => {
+                for material in &mesh.materials {
+                    material.textures(dir, files);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // Each child of a group gets a childless copy of the group as its
+    // parent, which is all it needs to find its way to world space.
+    fn build(&self, parent: Option<Arc<Shape>>, dir: &Path) -> Result<Shape, SceneError> {
+        let dir = self.dir.as_deref().unwrap_or(dir);
+        let intersectable = match self.kind {
+            ShapeKind::Cube => Intersectable::cube(),
+            ShapeKind::Group { .. } => Intersectable::group(),
+            ShapeKind::Mesh(ref mesh) => Intersectable::mesh(mesh.build(dir)?),
+            ShapeKind::Plane => Intersectable::plane(),
+            ShapeKind::Sphere => Intersectable::sphere(),
+            ShapeKind::Triangle { points: [a, b, c] } => {
+                Intersectable::triangle(to_point(a), to_point(b), to_point(c))
+            }
+        };
+        let mut shape = Shape::new(intersectable);
+        shape.material = self.material.build(dir)?;
+        shape.name = self.name.clone();
+        shape.tags = self.tags.clone();
+        shape.parent = parent;
+        shape.uv_mapping = self.uv_mapping;
+        shape
+            .set_transform(transform(&self.transform)?)
+            .map_err(|_| SceneError::SingularTransform)?;
+        if let ShapeKind::Group { ref children } = self.kind {
+            // Its children aren't added yet, so this copies nothing else.
+            let shell = Arc::new(shape.with_parent(shape.parent.clone()));
+            for child in children {
+                shape
+                    .intersectable
+                    .add(Arc::new(child.build(Some(shell.clone()), dir)?))
+                    .expect("made as a group above");
+            }
+        }
+        Ok(shape)
+    }
+
+    fn from_shape(shape: &Shape) -> Result<ShapeDescription, SceneError> {
+        let intersectable = &shape.intersectable;
+        let kind = match *intersectable.intersectable_type() {
+            IntersectableType::Cube => ShapeKind::Cube,
+            IntersectableType::Custom(_) => {
+                return Err(SceneError::Unsupported("custom primitives"))
+            }
+            IntersectableType::Group => ShapeKind::Group {
+                children: intersectable
+                    .children()
+                    .iter()
+                    .map(|child| ShapeDescription::from_shape(child))
+                    .collect::<Result<_, _>>()?,
+            },
+            IntersectableType::Mesh(ref mesh, _) => {
+                ShapeKind::Mesh(MeshDescription::from_mesh(mesh)?)
+            }
+            IntersectableType::Plane => ShapeKind::Plane,
+            IntersectableType::Sphere => ShapeKind::Sphere,
+            IntersectableType::Triangle(ref triangle) => ShapeKind::Triangle {
+                points: [xyz(&triangle.p1), xyz(&triangle.p2), xyz(&triangle.p3)],
+            },
+        };
+        Ok(ShapeDescription {
+            dir: None,
+            kind,
+            material: MaterialDescription::from_material(&shape.material)?,
+            name: shape.name.clone(),
+            tags: shape.tags.clone(),
+            transform: steps(&shape.transform()),
+            uv_mapping: shape.uv_mapping,
+        })
+    }
+}
+
+// Any field left out keeps the value from `Material::new`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaterialDescription {
+    pub ambient: f64,
+    pub backface: BackfaceDescription,
+    pub clearcoat: f64,
+    pub clearcoat_roughness: f64,
+    pub diffuse: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mask: Option<MaskDescription>,
+    pub pattern: PatternDescription,
+    pub reflective: ChannelDescription,
+    pub refractive_index: f64,
+    pub shininess: ChannelDescription,
+    pub specular: f64,
+    pub specular_model: SpecularModel,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub texture: Option<String>,
+    pub transparency: ChannelDescription,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volume: Option<VolumeDescription>,
+}
+
+impl Default for MaterialDescription {
+    fn default() -> MaterialDescription {
+        let material = Material::new();
+        MaterialDescription {
+            ambient: material.ambient,
+            backface: BackfaceDescription::Shade,
+            clearcoat: material.clearcoat,
+            clearcoat_roughness: material.clearcoat_roughness,
+            diffuse: material.diffuse,
+            mask: None,
+            pattern: PatternDescription::solid([1., 1., 1.]),
+            reflective: ChannelDescription::Constant(0.),
+            refractive_index: material.refractive_index,
+            shininess: ChannelDescription::Constant(200.),
+            specular: material.specular,
+            specular_model: material.specular_model,
+            texture: None,
+            transparency: ChannelDescription::Constant(0.),
+            volume: None,
+        }
+    }
+}
+
+impl MaterialDescription {
+    fn textures(&self, dir: &Path, files: &mut Vec<PathBuf>) {
+        files.extend(self.texture.iter().map(|path| dir.join(path)));
+        match self.mask {
+            Some(MaskDescription::Pattern(ref pattern)) => pattern.textures(dir, files),
+            Some(MaskDescription::Texture(ref path)) => files.push(dir.join(path)),
+            None => (),
+        }
+        self.pattern.textures(dir, files);
+        for channel in &[&self.reflective, &self.shininess, &self.transparency] {
+            if let ChannelDescription::Pattern(ref pattern) = **channel {
+                pattern.textures(dir, files);
+            }
+        }
+    }
+
+    fn build(&self, dir: &Path) -> Result<Material, SceneError> {
+        let mut material = Material::new();
+        material.ambient = self.ambient;
+        material.backface = match self.backface {
+            BackfaceDescription::Cull => Backface::Cull,
+            BackfaceDescription::Inside(inside) => Backface::Inside(color(inside)),
+            BackfaceDescription::Shade => Backface::Shade,
+        };
+        material.clearcoat = self.clearcoat;
+        material.clearcoat_roughness = self.clearcoat_roughness;
+        material.diffuse = self.diffuse;
+        material.mask = match self.mask {
+            Some(MaskDescription::Pattern(ref pattern)) => Some(Mask::Pattern(pattern.build(dir)?)),
+            Some(MaskDescription::Texture(ref path)) => {
+                Some(Mask::Texture(Arc::new(Texture::open(dir.join(path))?)))
+            }
+            None => None,
+        };
+        material.pattern = self.pattern.build(dir)?;
+        material.reflective = self.reflective.build(dir)?;
+        material.refractive_index = self.refractive_index;
+        material.shininess = self.shininess.build(dir)?;
+        material.specular = self.specular;
+        material.specular_model = self.specular_model;
+        material.texture = match self.texture {
+            Some(ref path) => Some(Arc::new(Texture::open(dir.join(path))?)),
+            None => None,
+        };
+        material.transparency = self.transparency.build(dir)?;
+        material.volume = self.volume.as_ref().map(VolumeDescription::build);
+        Ok(material)
+    }
+
+    fn from_material(material: &Material) -> Result<MaterialDescription, SceneError> {
+        Ok(MaterialDescription {
+            ambient: material.ambient,
+            backface: match material.backface {
+                Backface::Cull => BackfaceDescription::Cull,
+                Backface::Inside(inside) => BackfaceDescription::Inside(rgb(&inside)),
+                Backface::Shade => BackfaceDescription::Shade,
+            },
+            clearcoat: material.clearcoat,
+            clearcoat_roughness: material.clearcoat_roughness,
+            diffuse: material.diffuse,
+            mask: match material.mask {
+                Some(Mask::Pattern(ref pattern)) => Some(MaskDescription::Pattern(
+                    PatternDescription::from_pattern(pattern)?,
+                )),
+                Some(Mask::Texture(ref texture)) => {
+                    Some(MaskDescription::Texture(texture_path(texture)?))
+                }
+                None => None,
+            },
+            pattern: PatternDescription::from_pattern(&material.pattern)?,
+            reflective: ChannelDescription::from_channel(&material.reflective)?,
+            refractive_index: material.refractive_index,
+            shininess: ChannelDescription::from_channel(&material.shininess)?,
+            specular: material.specular,
+            specular_model: material.specular_model,
+            texture: match material.texture {
+                Some(ref texture) => Some(texture_path(texture)?),
+                None => None,
+            },
+            transparency: ChannelDescription::from_channel(&material.transparency)?,
+            volume: material.volume.as_ref().map(VolumeDescription::from_volume),
+        })
+    }
+}
+
+// What the back of a surface shows; see `Backface`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackfaceDescription {
+    Cull,
+    Inside([f64; 3]),
+    #[default]
+    Shade,
+}
+
+// Where a surface is solid: by a pattern's red channel, or by the alpha of
+// an image read from a path.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaskDescription {
+    Pattern(PatternDescription),
+    Texture(String),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VolumeDescription {
+    #[serde(default)]
+    pub absorption: f64,
+    #[serde(default = "white")]
+    pub color: [f64; 3],
+    #[serde(default)]
+    pub scattering: f64,
+    #[serde(default = "volume_steps")]
+    pub steps: usize,
+}
+
+impl VolumeDescription {
+    fn build(&self) -> Volume {
+        let mut volume = Volume::new(self.absorption, self.scattering);
+        volume.color = color(self.color);
+        volume.steps = self.steps;
+        volume
+    }
+
+    fn from_volume(volume: &Volume) -> VolumeDescription {
+        VolumeDescription {
+            absorption: volume.absorption,
+            color: rgb(&volume.color),
+            scattering: volume.scattering,
+            steps: volume.steps,
+        }
+    }
+}
+
+// A number, or a pattern whose red channel gives the value at each point.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ChannelDescription {
+    Constant(f64),
+    Pattern(PatternDescription),
+}
+
+impl ChannelDescription {
+    fn build(&self, dir: &Path) -> Result<ChannelValue, SceneError> {
+        Ok(match *self {
+            ChannelDescription::Constant(value) => ChannelValue::Constant(value),
+            ChannelDescription::Pattern(ref pattern) => ChannelValue::Pattern(pattern.build(dir)?),
+        })
+    }
+
+    fn from_channel(channel: &ChannelValue) -> Result<ChannelDescription, SceneError> {
+        Ok(match *channel {
+            ChannelValue::Constant(value) => ChannelDescription::Constant(value),
+            ChannelValue::Pattern(ref pattern) => {
+                ChannelDescription::Pattern(PatternDescription::from_pattern(pattern)?)
+            }
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PatternDescription {
+    #[serde(flatten)]
+    pub kind: PatternKind,
+    #[serde(default)]
+    pub transform: Vec<Transform>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PatternKind {
+    Blend {
+        primary: Box<PatternDescription>,
+        secondary: Box<PatternDescription>,
+        #[serde(default)]
+        mode: BlendModeDescription,
+    },
+    Checker {
+        color: [f64; 3],
+        secondary: [f64; 3],
+        #[serde(default)]
+        spread: f64,
+    },
+    CubeMap {
+        faces: Box<[PatternDescription; 6]>,
+    },
+    Fbm {
+        #[serde(default = "half")]
+        gain: f64,
+        #[serde(default = "two")]
+        lacunarity: f64,
+        #[serde(default = "perlin")]
+        noise: NoiseDescription,
+        #[serde(default = "four_octaves")]
+        octaves: usize,
+        pattern: Box<PatternDescription>,
+        #[serde(default)]
+        turbulence: bool,
+    },
+    Gradient {
+        color: [f64; 3],
+        secondary: [f64; 3],
+    },
+    Image {
+        path: String,
+        #[serde(default = "repeat")]
+        wrap_u: Wrap,
+        #[serde(default = "repeat")]
+        wrap_v: Wrap,
+    },
+    Marble {
+        distortion: f64,
+        ramp: Box<PatternDescription>,
+    },
+    Perlin {
+        pattern: Box<PatternDescription>,
+    },
+    RadialGradient {
+        color: [f64; 3],
+        secondary: [f64; 3],
+    },
+    Ramp {
+        #[serde(default = "linear")]
+        interpolation: Interpolation,
+        stops: Vec<(f64, [f64; 3])>,
+    },
+    Ring {
+        color: [f64; 3],
+        secondary: [f64; 3],
+    },
+    Simplex {
+        amplitude: f64,
+        frequency: f64,
+        pattern: Box<PatternDescription>,
+    },
+    Solid {
+        color: [f64; 3],
+    },
+    SphericalGradient {
+        color: [f64; 3],
+        secondary: [f64; 3],
+    },
+    Stencil {
+        color: Box<PatternDescription>,
+        path: String,
+        secondary: Box<PatternDescription>,
+    },
+    Stripe {
+        color: [f64; 3],
+        secondary: [f64; 3],
+    },
+    Uv {
+        pattern: Box<PatternDescription>,
+    },
+    VertexColors,
+    Wood {
+        axis: Axis,
+        distortion: f64,
+        ramp: Box<PatternDescription>,
+        sharpness: f64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoiseDescription {
+    Perlin,
+    Simplex,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlendModeDescription {
+    Add,
+    Mask(Box<PatternDescription>),
+    #[default]
+    Mix,
+    Multiply,
+    Overlay,
+    Screen,
+}
+
+impl PatternDescription {
+    fn textures(&self, dir: &Path, files: &mut Vec<PathBuf>) {
+        match self.kind {
+            PatternKind::Blend {
+                ref primary,
+                ref secondary,
+                ref mode,
+            } => {
+                primary.textures(dir, files);
+                secondary.textures(dir, files);
+                if let BlendModeDescription::Mask(ref mask) = *mode {
+                    mask.textures(dir, files);
+                }
+            }
+            PatternKind::CubeMap { ref faces } => {
+                for face in faces.iter() {
+                    face.textures(dir, files);
+                }
+            }
+            PatternKind::Image { ref path, .. } => files.push(dir.join(path)),
+            PatternKind::Marble { ref ramp, .. } | PatternKind::Wood { ref ramp, .. } => {
+                ramp.textures(dir, files)
+            }
+            PatternKind::Fbm { ref pattern, .. }
+            | PatternKind::Perlin { ref pattern }
+            | PatternKind::Simplex { ref pattern, .. }
+            | PatternKind::Uv { ref pattern } => pattern.textures(dir, files),
+            PatternKind::Stencil {
+                ref color,
+                ref path,
+                ref secondary,
+            } => {
+                files.push(dir.join(path));
+                color.textures(dir, files);
+                secondary.textures(dir, files);
+            }
+            _ => (),
+        }
+    }
+
+    pub fn solid(solid: [f64; 3]) -> PatternDescription {
+        PatternDescription {
+            kind: PatternKind::Solid { color: solid },
+            transform: Vec::new(),
+        }
+    }
+
+    fn build(&self, dir: &Path) -> Result<Patternable, SceneError> {
+        let mut pattern = match self.kind {
+            PatternKind::Blend {
+                ref primary,
+                ref secondary,
+                ref mode,
+            } => {
+                let mode = match *mode {
+                    BlendModeDescription::Add => BlendMode::Add,
+                    BlendModeDescription::Mask(ref mask) => {
+                        BlendMode::Mask(Box::new(mask.build(dir)?))
+                    }
+                    BlendModeDescription::Mix => BlendMode::Mix,
+                    BlendModeDescription::Multiply => BlendMode::Multiply,
+                    BlendModeDescription::Overlay => BlendMode::Overlay,
+                    BlendModeDescription::Screen => BlendMode::Screen,
+                };
+                Patternable::blend(primary.build(dir)?, secondary.build(dir)?, mode)
+            }
+            PatternKind::Checker {
+                color: a,
+                secondary: b,
+                spread,
+            } => Patternable::filtered_checker(color(a), color(b), spread),
+            PatternKind::CubeMap { ref faces } => Patternable::cube_map([
+                faces[0].build(dir)?,
+                faces[1].build(dir)?,
+                faces[2].build(dir)?,
+                faces[3].build(dir)?,
+                faces[4].build(dir)?,
+                faces[5].build(dir)?,
+            ]),
+            PatternKind::Fbm {
+                gain,
+                lacunarity,
+                noise,
+                octaves,
+                ref pattern,
+                turbulence,
+            } => {
+                let mut fbm = Fbm::new(match noise {
+                    NoiseDescription::Perlin => NoiseSource::perlin(),
+                    NoiseDescription::Simplex => NoiseSource::simplex(),
+                });
+                fbm.gain = gain;
+                fbm.lacunarity = lacunarity;
+                fbm.octaves = octaves;
+                fbm.turbulence = turbulence;
+                Patternable::fbm(fbm, pattern.build(dir)?)
+            }
+            PatternKind::Gradient {
+                color: a,
+                secondary: b,
+            } => Patternable::gradient(color(a), color(b)),
+            PatternKind::Image {
+                ref path,
+                wrap_u,
+                wrap_v,
+            } => Patternable::wrapped_texture(
+                Arc::new(Texture::open(dir.join(path))?),
+                wrap_u,
+                wrap_v,
+            ),
+            PatternKind::Marble {
+                distortion,
+                ref ramp,
+            } => Patternable::marble(ramp.build(dir)?, distortion),
+            PatternKind::Perlin { ref pattern } => Patternable::perlin(pattern.build(dir)?),
+            PatternKind::RadialGradient {
+                color: a,
+                secondary: b,
+            } => Patternable::radial_gradient(color(a), color(b)),
+            PatternKind::Ramp {
+                interpolation,
+                ref stops,
+            } => Patternable::ramp(
+                stops.iter().map(|&(t, stop)| (t, color(stop))).collect(),
+                interpolation,
+            ),
+            PatternKind::Ring {
+                color: a,
+                secondary: b,
+            } => Patternable::ring(color(a), color(b)),
+            PatternKind::Simplex {
+                amplitude,
+                frequency,
+                ref pattern,
+            } => Patternable::simplex(pattern.build(dir)?, frequency, amplitude),
+            PatternKind::Solid { color: solid } => Patternable::solid(color(solid)),
+            PatternKind::SphericalGradient {
+                color: a,
+                secondary: b,
+            } => Patternable::spherical_gradient(color(a), color(b)),
+            PatternKind::Stencil {
+                color: ref a,
+                ref path,
+                secondary: ref b,
+            } => Patternable::stencil(
+                Arc::new(Texture::open_linear(dir.join(path))?),
+                a.build(dir)?,
+                b.build(dir)?,
+            ),
+            PatternKind::Stripe {
+                color: a,
+                secondary: b,
+            } => Patternable::stripe(color(a), color(b)),
+            PatternKind::Uv { ref pattern } => Patternable::uv(pattern.build(dir)?),
+            PatternKind::VertexColors => Patternable::vertex_colors(),
+            PatternKind::Wood {
+                axis,
+                distortion,
+                ref ramp,
+                sharpness,
+            } => Patternable::wood(ramp.build(dir)?, axis, sharpness, distortion),
+        };
+        pattern.transform = transform(&self.transform)?;
+        Ok(pattern)
+    }
+
+    // Two-color patterns are written with solid colors, the only kind the
+    // format gives them.
+    fn from_pattern(pattern: &Patternable) -> Result<PatternDescription, SceneError> {
+        let describe =
+            |pattern: &Patternable| PatternDescription::from_pattern(pattern).map(Box::new);
+        let kind = match *pattern.patternable_type() {
+            PatternableType::Blended(ref primary, ref secondary, ref mode) => PatternKind::Blend {
+                primary: describe(primary)?,
+                secondary: describe(secondary)?,
+                mode: match *mode {
+                    BlendMode::Add => BlendModeDescription::Add,
+                    BlendMode::Mask(ref mask) => BlendModeDescription::Mask(describe(mask)?),
+                    BlendMode::Mix => BlendModeDescription::Mix,
+                    BlendMode::Multiply => BlendModeDescription::Multiply,
+                    BlendMode::Overlay => BlendModeDescription::Overlay,
+                    BlendMode::Screen => BlendModeDescription::Screen,
+                },
+            },
+            PatternableType::Checker(ref a, ref b, spread) => PatternKind::Checker {
+                color: solid_color(a)?,
+                secondary: solid_color(b)?,
+                spread,
+            },
+            PatternableType::CubeMap(ref faces) => PatternKind::CubeMap {
+                faces: Box::new([
+                    PatternDescription::from_pattern(&faces[0])?,
+                    PatternDescription::from_pattern(&faces[1])?,
+                    PatternDescription::from_pattern(&faces[2])?,
+                    PatternDescription::from_pattern(&faces[3])?,
+                    PatternDescription::from_pattern(&faces[4])?,
+                    PatternDescription::from_pattern(&faces[5])?,
+                ]),
+            },
+            PatternableType::Custom(_) => return Err(SceneError::Unsupported("custom patterns")),
+            PatternableType::Fbm(ref fbm, ref pattern) => PatternKind::Fbm {
+                gain: fbm.gain,
+                lacunarity: fbm.lacunarity,
+                noise: match *fbm.source() {
+                    NoiseSource::Perlin(_) => NoiseDescription::Perlin,
+                    NoiseSource::Simplex(_) => NoiseDescription::Simplex,
+                },
+                octaves: fbm.octaves,
+                pattern: describe(pattern)?,
+                turbulence: fbm.turbulence,
+            },
+            PatternableType::Gradient(ref a, ref b) => PatternKind::Gradient {
+                color: solid_color(a)?,
+                secondary: solid_color(b)?,
+            },
+            PatternableType::Image(ref texture, wrap_u, wrap_v) => PatternKind::Image {
+                path: texture_path(texture)?,
+                wrap_u,
+                wrap_v,
+            },
+            PatternableType::Marble(_, ref ramp, distortion) => PatternKind::Marble {
+                distortion,
+                ramp: describe(ramp)?,
+            },
+            PatternableType::Perlin(_, ref pattern, _) => PatternKind::Perlin {
+                pattern: describe(pattern)?,
+            },
+            PatternableType::RadialGradient(ref a, ref b) => PatternKind::RadialGradient {
+                color: solid_color(a)?,
+                secondary: solid_color(b)?,
+            },
+            PatternableType::Ramp(ref stops, interpolation) => PatternKind::Ramp {
+                interpolation,
+                stops: stops.iter().map(|&(t, stop)| (t, rgb(&stop))).collect(),
+            },
+            PatternableType::Ring(ref a, ref b) => PatternKind::Ring {
+                color: solid_color(a)?,
+                secondary: solid_color(b)?,
+            },
+            PatternableType::Simplex(_, ref pattern, frequency, amplitude) => {
+                PatternKind::Simplex {
+                    amplitude,
+                    frequency,
+                    pattern: describe(pattern)?,
+                }
+            }
+            PatternableType::Solid(solid) => PatternKind::Solid { color: rgb(&solid) },
+            PatternableType::SphericalGradient(ref a, ref b) => PatternKind::SphericalGradient {
+                color: solid_color(a)?,
+                secondary: solid_color(b)?,
+            },
+            PatternableType::Stencil(ref texture, ref a, ref b) => PatternKind::Stencil {
+                color: describe(a)?,
+                path: texture_path(texture)?,
+                secondary: describe(b)?,
+            },
+            PatternableType::Stripe(ref a, ref b) => PatternKind::Stripe {
+                color: solid_color(a)?,
+                secondary: solid_color(b)?,
+            },
+            PatternableType::Uv(ref pattern) => PatternKind::Uv {
+                pattern: describe(pattern)?,
+            },
+            PatternableType::VertexColors => PatternKind::VertexColors,
+            PatternableType::Wood(_, ref ramp, axis, sharpness, distortion) => PatternKind::Wood {
+                axis,
+                distortion,
+                ramp: describe(ramp)?,
+                sharpness,
+            },
+        };
+        Ok(PatternDescription {
+            kind,
+            transform: steps(&pattern.transform),
+        })
+    }
+}
+
+fn color([red, green, blue]: [f64; 3]) -> Color {
+    Color::new(red, green, blue)
+}
+
+fn rgb(color: &Color) -> [f64; 3] {
+    [color.red, color.green, color.blue]
+}
+
+fn xyz(point: &Point) -> [f64; 3] {
+    [point.x, point.y, point.z]
+}
+
+// The light as a point light, the only kind a scene can describe.
+fn point_light(light: &dyn Light) -> Option<&PointLight> {
+    let light: &dyn Any = light;
+    light.downcast_ref()
+}
+
+fn solid_color(pattern: &Patternable) -> Result<[f64; 3], SceneError> {
+    match *pattern.patternable_type() {
+        PatternableType::Solid(solid) if pattern.transform.equal(&IDENTITY_MATRIX) => {
+            Ok(rgb(&solid))
+        }
+        _ => Err(SceneError::Unsupported(
+            "nested patterns in two-color patterns",
+        )),
+    }
+}
+
+fn texture_path(texture: &Texture) -> Result<String, SceneError> {
+    match texture.path {
+        Some(ref path) => Ok(path.to_string_lossy().into_owned()),
+        None => Err(SceneError::Unsupported("textures not loaded from a file")),
+    }
+}
+
+fn to_point([x, y, z]: [f64; 3]) -> Point {
+    point(x, y, z)
+}
+
+fn to_vector([x, y, z]: [f64; 3]) -> Point {
+    vector(x, y, z)
+}
+
+fn one() -> f64 {
+    1.
+}
+
+fn one_sample() -> usize {
+    1
+}
+
+fn camera_depth() -> i32 {
+    8
+}
+
+fn stratified() -> Sampler {
+    Sampler::Stratified
+}
+
+fn fixed_bias() -> Bias {
+    Bias::Fixed(EPSILON)
+}
+
+fn fog_steps() -> usize {
+    32
+}
+
+fn volume_steps() -> usize {
+    16
+}
+
+fn white() -> [f64; 3] {
+    [1., 1., 1.]
+}
+
+fn unscaled() -> [f64; 3] {
+    [1., 1., 1.]
+}
+
+fn up() -> [f64; 3] {
+    [0., 1., 0.]
+}
+
+fn half() -> f64 {
+    0.5
+}
+
+fn two() -> f64 {
+    2.
+}
+
+fn perlin() -> NoiseDescription {
+    NoiseDescription::Perlin
+}
+
+fn four_octaves() -> usize {
+    4
+}
+
+fn repeat() -> Wrap {
+    Wrap::Repeat
+}
+
+fn linear() -> Interpolation {
+    Interpolation::Linear
+}
+
+#[cfg(test)]
+mod tests {
+    use canvas::Canvas;
+    use color::Color;
+    use environment::Environment;
+    use matrix::Matrix4;
+    use point::{point, vector};
+    use ray::Ray;
+    use scene::SceneDescription;
+    use scene::SceneError;
+    use std::env;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use texture::Texture;
+    use world::World;
+
+    const DEFAULT_WORLD: &str = r#"{
+        "camera": {
+            "width": 11,
+            "height": 11,
+            "field_of_view": 1.5707963267948966,
+            "from": [0, 0, -5],
+            "to": [0, 0, 0]
+        },
+        "light": { "position": [-10, 10, -10], "intensity": [1, 1, 1] },
+        "shapes": [
+            {
+                "type": "sphere",
+                "material": {
+                    "diffuse": 0.7,
+                    "specular": 0.2,
+                    "pattern": { "type": "solid", "color": [0.8, 1.0, 0.6] }
+                }
+            },
+            { "type": "sphere", "transform": [{ "scale": [0.5, 0.5, 0.5] }] }
+        ]
+    }"#;
+
+    #[test]
+    fn test_from_json_matches_default_world() {
+        let (world, camera) = World::from_json(DEFAULT_WORLD).unwrap();
+
+        assert_eq!(camera.hsize, 11);
+        assert_eq!(camera.vsize, 11);
+        assert_eq!(
+            camera.render(&world).pixel_at(5, 5),
+            Color::new(0.38066, 0.47583, 0.2855)
+        );
+        let ray = Ray {
+            origin: point(0., 0., 0.75),
+            direction: vector(0., 0., -1.),
+        };
+        assert_eq!(world.color_at(&ray, 5), World::new().color_at(&ray, 5));
+    }
+
+    #[test]
+    fn test_from_json_groups_and_patterns() {
+        let json = r#"{
+            "camera": {
+                "width": 4, "height": 4, "field_of_view": 1,
+                "from": [0, 0, -5], "to": [0, 0, 0]
+            },
+            "light": { "position": [0, 10, -10], "intensity": [1, 1, 1] },
+            "background": { "solid": [0.1, 0.2, 0.3] },
+            "shapes": [{
+                "type": "group",
+                "name": "outer",
+                "transform": [{ "rotate_y": -1.5707963267948966 }],
+                "children": [{
+                    "type": "sphere",
+                    "transform": [{ "scale": [2, 2, 2] }, { "translate": [5, 0, 0] }],
+                    "material": {
+                        "reflective": 0.5,
+                        "shininess": { "type": "solid", "color": [10, 0, 0] },
+                        "pattern": {
+                            "type": "blend",
+                            "primary": { "type": "stripe", "color": [1, 1, 1], "secondary": [0, 0, 0] },
+                            "secondary": { "type": "solid", "color": [1, 0, 0] },
+                            "mode": "multiply"
+                        }
+                    }
+                }]
+            }]
+        }"#;
+
+        let (world, _) = World::from_json(json).unwrap();
+
+        assert_eq!(world.objects.len(), 1);
+        assert!(world.objects[0].is_named("outer"));
+        let ray = Ray {
+            origin: point(0., 0., 10.),
+            direction: vector(0., 0., -1.),
+        };
+        let mut intersections = ray.intersect_world(&world);
+        let hit = ::intersection::Intersection::hit(&mut intersections).unwrap();
+        assert!((hit.t - 3.).abs() < 1e-9);
+        let sphere = hit.object.clone();
+        assert!(sphere.is_named("outer"));
+        assert_eq!(
+            sphere.world_to_object(&point(0., 0., 7.)),
+            point(1., 0., 0.)
+        );
+        assert_eq!(
+            sphere.material.reflective.at(&sphere, &point(0., 0., 7.)),
+            0.5
+        );
+        assert_eq!(
+            sphere.material.shininess.at(&sphere, &point(0., 0., 7.)),
+            10.
+        );
+    }
+
+    #[test]
+    fn test_from_json_definitions() {
+        let json = r#"{
+            "camera": { "width": 1, "height": 1, "field_of_view": 1, "from": [0, 0, -1], "to": [0, 0, 0] },
+            "light": { "position": [0, 10, -10], "intensity": [1, 1, 1] },
+            "materials": {
+                "shiny": { "specular": 1, "pattern": { "use": "red" } },
+                "matte": { "use": "shiny", "specular": 0 }
+            },
+            "patterns": {
+                "red": { "type": "solid", "color": [1, 0, 0] },
+                "stripes": { "type": "stripe", "color": [1, 1, 1], "secondary": [0, 0, 0] }
+            },
+            "shapes": [
+                { "type": "sphere", "material": "shiny" },
+                { "type": "sphere", "material": { "use": "matte", "diffuse": 0.2 } },
+                {
+                    "type": "cube",
+                    "material": {
+                        "pattern": { "use": "stripes", "transform": [{ "scale": [2, 2, 2] }] }
+                    }
+                }
+            ]
+        }"#;
+
+        let (world, _) = World::from_json(json).unwrap();
+
+        let shiny = &world.objects[0].material;
+        assert_eq!(shiny.specular, 1.);
+        assert_eq!(
+            shiny.pattern.color_at(&point(0., 0., 0.)),
+            Color::new(1., 0., 0.)
+        );
+        let matte = &world.objects[1].material;
+        assert_eq!(matte.specular, 0.);
+        assert_eq!(matte.diffuse, 0.2);
+        assert_eq!(
+            matte.pattern.color_at(&point(0., 0., 0.)),
+            Color::new(1., 0., 0.)
+        );
+        let stripes = &world.objects[2].material.pattern;
+        assert_eq!(stripes.transform, Matrix4::scaling(2., 2., 2.));
+        assert_eq!(
+            stripes.color_at(&point(1.5, 0., 0.)),
+            Color::new(0., 0., 0.)
+        );
+
+        match World::from_json(&json.replace(r#""material": "shiny""#, r#""material": "glass""#)) {
+            Err(SceneError::Undefined { kind, name }) => {
+                assert_eq!((kind, name.as_str()), ("material", "glass"))
+            }
+            _ => panic!("expected an undefined material"),
+        }
+        let cycle = json
+            .replace(r#"{ "use": "red" }"#, r#"{ "use": "loop" }"#)
+            .replace(r#""red": {"#, r#""loop": { "use": "loop" }, "red": {"#);
+        match World::from_json(&cycle) {
+            Err(SceneError::Unsupported("definitions that use themselves")) => (),
+            _ => panic!("expected a cycle error"),
+        }
+        let flat = json.replace("[2, 2, 2]", "[2, 0, 2]");
+        match World::from_json(&flat) {
+            Err(SceneError::SingularTransform) => (),
+            _ => panic!("expected a singular transform"),
+        }
+    }
+
+    #[test]
+    fn test_animation() {
+        let json = r#"{
+            "camera": { "width": 1, "height": 1, "field_of_view": 1, "from": [0, 0, -5], "to": [0, 0, 0] },
+            "light": { "position": [0, 10, -10], "intensity": [1, 1, 1] },
+            "shapes": [{ "type": "sphere", "name": "ball", "transform": [{ "scale": [2, 2, 2] }] }],
+            "animation": {
+                "frames": 5,
+                "shapes": {
+                    "ball": [
+                        { "frame": 1 },
+                        { "frame": 5, "translate": [4, 0, 0], "curve": "ease_in" }
+                    ]
+                },
+                "camera": [{ "frame": 1, "from": [0, 0, -5], "to": [0, 0, 0] }, { "frame": 5, "from": [0, 0, -9], "to": [0, 0, 0] }],
+                "light": [{ "frame": 3, "intensity": [0.5, 0.5, 0.5] }]
+            }
+        }"#;
+        let description = SceneDescription::from_json(json, Path::new("")).unwrap();
+        let animation = description.animation.as_ref().unwrap();
+        assert_eq!(animation.frames, 5);
+
+        let (mut world, mut camera) = description.build().unwrap();
+        animation.apply_to_world(3, &mut world).unwrap();
+        animation.apply_to_camera(3, None, &mut camera).unwrap();
+
+        assert_eq!(
+            world.objects[0].transform(),
+            Matrix4::translation(2., 0., 0.).multiply(&Matrix4::scaling(2., 2., 2.))
+        );
+        assert_eq!(
+            camera.transform().multiply_point(&point(0., 0., 0.)),
+            point(0., 0., -7.)
+        );
+        assert_eq!(
+            world.light_source.intensity_at(&point(0., 0., 0.)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+    }
+
+    #[test]
+    fn test_named_cameras() {
+        let json = DEFAULT_WORLD.replace(
+            r#""light""#,
+            r#""cameras": {
+            "top": { "width": 3, "height": 2, "field_of_view": 1, "from": [0, 5, 0], "to": [0, 0, 0], "up": [0, 0, 1] }
+        },
+        "light""#,
+        );
+        let mut description = SceneDescription::from_json(&json, Path::new("")).unwrap();
+        for camera in description.cameras_mut() {
+            camera.samples = 4;
+        }
+
+        let main = description.build_camera(None).unwrap();
+        assert_eq!((main.hsize, main.samples), (11, 4));
+        let top = description.build_camera(Some("top")).unwrap();
+        assert_eq!((top.hsize, top.vsize, top.samples), (3, 2, 4));
+        assert_eq!(
+            top.transform().multiply_point(&point(0., 0., 0.)),
+            point(0., 0., -5.)
+        );
+        match description.build_camera(Some("side")) {
+            Err(SceneError::Undefined { kind: "camera", .. }) => (),
+            _ => panic!("expected an undefined camera"),
+        }
+    }
+
+    #[test]
+    fn test_from_json_errors() {
+        match World::from_json(r#"{ "light": {} }"#) {
+            Err(SceneError::Json(_)) => (),
+            _ => panic!("expected a JSON error"),
+        }
+
+        let missing = DEFAULT_WORLD.replace(
+            r#""type": "solid", "color": [0.8, 1.0, 0.6]"#,
+            r#""type": "image", "path": "no/such/texture.png""#,
+        );
+        match World::from_json(&missing) {
+            Err(SceneError::Image(_)) => (),
+            _ => panic!("expected a texture error"),
+        }
+    }
+
+    #[test]
+    fn test_to_json_round_trips() {
+        let json = DEFAULT_WORLD.replace(
+            r#""light""#,
+            r#""background": { "gradient": { "horizon": [1, 1, 1], "zenith": [0, 0, 1] } },
+        "light""#,
+        );
+        let (world, camera) = World::from_json(&json).unwrap();
+
+        let saved = world.to_json(&camera).unwrap();
+        let (loaded, loaded_camera) = World::from_json(&saved).unwrap();
+
+        assert_eq!(loaded.objects.len(), 2);
+        assert_eq!(loaded_camera.hsize, 11);
+        assert_eq!(loaded_camera.vsize, 11);
+        assert_eq!(
+            loaded_camera.render(&loaded).pixels,
+            camera.render(&world).pixels
+        );
+    }
+
+    #[test]
+    fn test_to_json_keeps_everything_it_renders() {
+        let json = r#"{
+            "ambient_occlusion": { "samples": 4, "distance": 1 },
+            "background": { "sky": { "sun_size": 0.1 } },
+            "bias": { "relative": 0.0001 },
+            "reflection_depth": 1,
+            "camera": {
+                "width": 9,
+                "height": 7,
+                "field_of_view": 1.2,
+                "from": [0, 1.5, -5],
+                "to": [0, 0.5, 0],
+                "adaptive": { "max_samples": 8, "threshold": 0.05 },
+                "auto_exposure": true,
+                "depth": 3,
+                "sampler": "random",
+                "samples": 2,
+                "seed": 7,
+                "shift_y": 0.1,
+                "tilt_x": 0.05
+            },
+            "light": { "position": [-10, 10, -10], "intensity": [1, 1, 1] },
+            "shapes": [
+                {
+                    "type": "sphere",
+                    "uv_mapping": "spherical",
+                    "material": {
+                        "backface": { "inside": [1, 0, 0] },
+                        "mask": { "pattern": { "type": "stripe", "color": [1, 1, 1], "secondary": [0, 0, 0] } },
+                        "pattern": {
+                            "type": "fbm",
+                            "noise": "simplex",
+                            "octaves": 3,
+                            "pattern": { "type": "gradient", "color": [1, 0, 0], "secondary": [0, 0, 1] }
+                        },
+                        "reflective": 0.3
+                    }
+                },
+                {
+                    "type": "mesh",
+                    "vertices": [[-3, -1, -3], [3, -1, -3], [0, -1, 3]],
+                    "colors": [[1, 0, 0], [0, 1, 0], [0, 0, 1]],
+                    "faces": [{ "vertices": [0, 1, 2] }],
+                    "material": { "pattern": { "type": "vertex_colors" } }
+                }
+            ]
+        }"#;
+        let (world, camera) = World::from_json(json).unwrap();
+
+        let saved = world.to_json(&camera).unwrap();
+        let (loaded, loaded_camera) = World::from_json(&saved).unwrap();
+
+        assert_eq!(loaded.reflection_depth, Some(1));
+        assert_eq!(loaded_camera.seed, Some(7));
+        assert_eq!(loaded_camera.depth, 3);
+        assert_eq!(
+            loaded_camera.render(&loaded).pixels,
+            camera.render(&world).pixels
+        );
+    }
+
+    #[test]
+    fn test_to_json_unsupported() {
+        let (mut world, camera) = World::from_json(DEFAULT_WORLD).unwrap();
+        world.environment = Some(Environment::new(Texture::new(1, 1, vec![Color::white()])));
+
+        match world.to_json(&camera) {
+            Err(SceneError::Unsupported(_)) => (),
+            _ => panic!("expected an unsupported error"),
+        }
+    }
+
+    #[test]
+    fn test_including_scene_files() {
+        let dir = env::temp_dir().join("ray_tracer_test_include");
+        fs::create_dir_all(dir.join("props")).unwrap();
+        fs::write(
+            dir.join("props/ball.json"),
+            r#"{ "shapes": [{ "type": "sphere", "name": "ball" }] }"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("props/balls.json"),
+            r#"{ "include": [
+                { "path": "ball.json" },
+                { "path": "ball.json", "transform": [{ "translate": [3, 0, 0] }] }
+            ] }"#,
+        )
+        .unwrap();
+        let scene = DEFAULT_WORLD.replace(
+            r#""shapes": ["#,
+            r#""include": [{ "path": "props/balls.json", "transform": [{ "translate": [0, 0, 5] }] }],
+            "shapes": ["#,
+        );
+        fs::write(dir.join("scene.json"), &scene).unwrap();
+
+        let (world, _) = World::from_file(dir.join("scene.json")).unwrap();
+
+        assert_eq!(world.objects.len(), 3);
+        let ray = Ray {
+            origin: point(3., 0., -5.),
+            direction: vector(0., 0., 1.),
+        };
+        let mut intersections = ray.intersect_world(&world);
+        let hit = ::intersection::Intersection::hit(&mut intersections).unwrap();
+        assert_eq!(hit.t, 9.);
+        assert!(hit.object.is_named("ball"));
+
+        let description = SceneDescription::load(dir.join("scene.json")).unwrap();
+        assert_eq!(
+            description.dependencies(),
+            vec![
+                dir.join("scene.json"),
+                dir.join("props/ball.json"),
+                dir.join("props/ball.json"),
+                dir.join("props/balls.json"),
+            ]
+        );
+
+        match World::from_json(&scene) {
+            Err(SceneError::Io(_)) => (),
+            _ => panic!("expected the include to be missing from the working directory"),
+        }
+
+        fs::write(
+            dir.join("props/ball.json"),
+            r#"{ "include": [{ "path": "balls.json" }] }"#,
+        )
+        .unwrap();
+        match World::from_file(dir.join("scene.json")) {
+            Err(SceneError::Unsupported(_)) => (),
+            _ => panic!("expected an include cycle"),
+        }
+    }
+
+    #[test]
+    fn test_textures_are_relative_to_their_scene_file() {
+        let dir = env::temp_dir().join("ray_tracer_test_texture_paths");
+        fs::create_dir_all(dir.join("props")).unwrap();
+        for path in &["sky.png", "props/ball.png"] {
+            let file = fs::File::create(dir.join(path)).unwrap();
+            Canvas::empty(1, 1).write_png(file).unwrap();
+        }
+        fs::write(
+            dir.join("props/ball.json"),
+            r#"{ "shapes": [{ "type": "sphere", "material": { "texture": "ball.png" } }] }"#,
+        )
+        .unwrap();
+        let scene = DEFAULT_WORLD.replace(
+            r#""shapes": ["#,
+            r#""background": { "pattern": { "type": "image", "path": "sky.png" } },
+            "include": [{ "path": "props/ball.json" }],
+            "shapes": ["#,
+        );
+        fs::write(dir.join("scene.json"), &scene).unwrap();
+
+        let description = SceneDescription::load(dir.join("scene.json")).unwrap();
+
+        assert!(description.build().is_ok());
+        assert_eq!(
+            description.dependencies(),
+            vec![
+                dir.join("scene.json"),
+                dir.join("props/ball.json"),
+                dir.join("sky.png"),
+                dir.join("props/ball.png"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dependencies_include_textures() {
+        let json = DEFAULT_WORLD.replace(
+            r#""type": "solid", "color": [0.8, 1.0, 0.6]"#,
+            r#""type": "blend",
+               "primary": { "type": "image", "path": "wood.png" },
+               "secondary": { "type": "solid", "color": [1, 1, 1] },
+               "mode": { "mask": { "type": "stencil", "path": "mask.png",
+                   "color": { "type": "solid", "color": [0, 0, 0] },
+                   "secondary": { "type": "solid", "color": [1, 1, 1] } } }"#,
+        );
+        let description = SceneDescription::from_json(&json, Path::new("")).unwrap();
+
+        assert_eq!(
+            description.dependencies(),
+            vec![PathBuf::from("wood.png"), PathBuf::from("mask.png")]
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use camera::Camera;
+use color::Encoding;
+use scene::SceneDescription;
+use serde::Deserialize;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use toml;
+use world::{RenderMode, World};
+
+#[derive(Debug)]
+pub enum SettingsError {
+    BitDepth(u8),
+    Io(io::Error),
+    Toml(toml::de::Error),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SettingsError::BitDepth(depth) => {
+                write!(f, "bit depth must be from 1 to 16, not {}", depth)
+            }
+            SettingsError::Io(ref error) => write!(f, "could not read settings: {}", error),
+            SettingsError::Toml(ref error) => write!(f, "invalid settings: {}", error),
+        }
+    }
+}
+
+impl error::Error for SettingsError {}
+
+impl From<io::Error> for SettingsError {
+    fn from(error: io::Error) -> SettingsError {
+        SettingsError::Io(error)
+    }
+}
+
+impl From<toml::de::Error> for SettingsError {
+    fn from(error: toml::de::Error) -> SettingsError {
+        SettingsError::Toml(error)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    Png,
+    Ppm,
+}
+
+// How to render a scene, as opposed to what is in it. Settings come from a
+// TOML file and the command line; anything left unset keeps the value from
+// the scene, or the default below.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RenderSettings {
+    pub auto_exposure: Option<bool>,
+    pub bit_depth: Option<u8>,
+    pub depth: Option<i32>,
+    pub encoding: Option<Encoding>,
+    pub exposure: Option<f64>,
+    pub format: Option<Format>,
+    pub height: Option<usize>,
+    // A false-color view for debugging in place of the shaded image.
+    pub mode: Option<RenderMode>,
+    pub output: Option<PathBuf>,
+    // Limits on reflections, and refractions, within `depth`.
+    pub reflection_depth: Option<i32>,
+    pub refraction_depth: Option<i32>,
+    pub samples: Option<usize>,
+    // Repeats the same noise from render to render.
+    pub seed: Option<u64>,
+    pub threads: Option<usize>,
+    // A PPM that finished tiles are written into as the render goes, so a
+    // render that dies part way still leaves what it got done.
+    pub tiles: Option<PathBuf>,
+    pub width: Option<usize>,
+}
+
+impl RenderSettings {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<RenderSettings, SettingsError> {
+        RenderSettings::from_toml(&fs::read_to_string(path)?)
+    }
+
+    pub fn from_toml(text: &str) -> Result<RenderSettings, SettingsError> {
+        let settings: RenderSettings = toml::from_str(text)?;
+        match settings.bit_depth {
+            Some(depth) if !(1..=16).contains(&depth) => Err(SettingsError::BitDepth(depth)),
+            _ => Ok(settings),
+        }
+    }
+
+    // These settings, with any that `overrides` sets replaced.
+    pub fn overridden_by(self, overrides: RenderSettings) -> RenderSettings {
+        RenderSettings {
+            auto_exposure: overrides.auto_exposure.or(self.auto_exposure),
+            bit_depth: overrides.bit_depth.or(self.bit_depth),
+            depth: overrides.depth.or(self.depth),
+            encoding: overrides.encoding.or(self.encoding),
+            exposure: overrides.exposure.or(self.exposure),
+            format: overrides.format.or(self.format),
+            height: overrides.height.or(self.height),
+            mode: overrides.mode.or(self.mode),
+            output: overrides.output.or(self.output),
+            reflection_depth: overrides.reflection_depth.or(self.reflection_depth),
+            refraction_depth: overrides.refraction_depth.or(self.refraction_depth),
+            samples: overrides.samples.or(self.samples),
+            seed: overrides.seed.or(self.seed),
+            threads: overrides.threads.or(self.threads),
+            tiles: overrides.tiles.or(self.tiles),
+            width: overrides.width.or(self.width),
+        }
+    }
+
+    // The camera is built from the scene, so settings that shape it are
+    // applied to the description first.
+    pub fn apply_to_scene(&self, description: &mut SceneDescription) {
+        for camera in description.cameras_mut() {
+            camera.width = self.width.unwrap_or(camera.width);
+            camera.height = self.height.unwrap_or(camera.height);
+            camera.samples = self.samples.unwrap_or(camera.samples);
+            camera.exposure = self.exposure.unwrap_or(camera.exposure);
+        }
+    }
+
+    pub fn apply_to_camera(&self, camera: &mut Camera) {
+        camera.depth = self.depth.unwrap_or(camera.depth);
+        camera.auto_exposure = self.auto_exposure.unwrap_or(camera.auto_exposure);
+        camera.seed = self.seed.or(camera.seed);
+    }
+
+    pub fn apply_to_world(&self, world: &mut World) {
+        world.reflection_depth = self.reflection_depth.or(world.reflection_depth);
+        world.refraction_depth = self.refraction_depth.or(world.refraction_depth);
+        world.render_mode = self.mode.unwrap_or(world.render_mode);
+    }
+
+    pub fn bit_depth(&self) -> u8 {
+        self.bit_depth.unwrap_or(8)
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding.unwrap_or(Encoding::Srgb)
+    }
+
+    // Without a format, the extension of the output picks one.
+    pub fn format(&self, output: &Path) -> Format {
+        self.format.unwrap_or_else(|| {
+            match output.extension().and_then(|extension| extension.to_str()) {
+                Some("png") => Format::Png,
+                _ => Format::Ppm,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use color::Encoding;
+    use settings::{Format, RenderSettings, SettingsError};
+    use std::path::{Path, PathBuf};
+    use world::RenderMode;
+
+    #[test]
+    fn test_settings_from_toml() {
+        let settings = RenderSettings::from_toml(
+            r#"
+            width = 1920
+            height = 1080
+            samples = 16
+            depth = 4
+            refraction_depth = 2
+            threads = 8
+            seed = 42
+            output = "renders/out.png"
+            tiles = "renders/partial.ppm"
+            encoding = { gamma = 2.2 }
+            mode = "object_id"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(settings.width, Some(1920));
+        assert_eq!(settings.samples, Some(16));
+        assert_eq!(settings.refraction_depth, Some(2));
+        assert_eq!(settings.seed, Some(42));
+        assert_eq!(settings.tiles, Some(PathBuf::from("renders/partial.ppm")));
+        assert_eq!(settings.mode, Some(RenderMode::ObjectId));
+        assert_eq!(settings.encoding(), Encoding::Gamma(2.2));
+        assert_eq!(settings.bit_depth(), 8);
+        assert_eq!(
+            settings.format(settings.output.as_ref().unwrap()),
+            Format::Png
+        );
+        assert!(RenderSettings::from_toml("sample = 4").is_err());
+    }
+
+    #[test]
+    fn test_settings_refuse_bit_depths_canvas_cannot_write() {
+        assert_eq!(
+            RenderSettings::from_toml("bit_depth = 16")
+                .unwrap()
+                .bit_depth(),
+            16
+        );
+        for depth in &[0, 17] {
+            match RenderSettings::from_toml(&format!("bit_depth = {}", depth)) {
+                Err(SettingsError::BitDepth(bad)) => assert_eq!(bad, *depth),
+                _ => panic!("expected a bit depth error"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_settings_overrides() {
+        let file = RenderSettings {
+            depth: Some(4),
+            format: Some(Format::Png),
+            width: Some(100),
+            ..RenderSettings::default()
+        };
+        let command_line = RenderSettings {
+            output: Some(PathBuf::from("out.ppm")),
+            width: Some(50),
+            ..RenderSettings::default()
+        };
+
+        let settings = file.overridden_by(command_line);
+
+        assert_eq!(settings.width, Some(50));
+        assert_eq!(settings.depth, Some(4));
+        assert_eq!(settings.format(Path::new("out.ppm")), Format::Png);
+        assert_eq!(
+            RenderSettings::default().format(Path::new("out.ppm")),
+            Format::Ppm
+        );
+    }
+}
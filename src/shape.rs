@@ -1,42 +1,84 @@
 use bounds::Bounds;
 use color::Color;
+use error::Error;
 use intersectable::*;
-use material::Material;
+use intersection::FaceHit;
+use material::{ChannelValue, Material};
 use matrix::Matrix4;
 use matrix::IDENTITY_MATRIX;
+use mesh::Mesh;
 use patternable::Patternable;
 use point::Point;
+use primitive::Primitive;
 use std::sync::Arc;
+use uv_mapping::UvMapping;
+
+// Picks shapes out of a scene by how they were labelled.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ShapeQuery {
+    Name(String),
+    Tag(String),
+}
+
+impl ShapeQuery {
+    pub fn matches(&self, shape: &Shape) -> bool {
+        match *self {
+            ShapeQuery::Name(ref name) => shape.name.as_ref() == Some(name),
+            ShapeQuery::Tag(ref tag) => shape.tags.contains(tag),
+        }
+    }
+
+    // True for the shapes inside a matching group as well.
+    pub fn matches_within(&self, shape: &Shape) -> bool {
+        self.matches(shape)
+            || shape
+                .parent
+                .as_ref()
+                .is_some_and(|p| self.matches_within(p))
+    }
+}
 
 #[derive(Debug)]
 pub struct Shape {
+    pub name: Option<String>,
+    // Labels shared by many shapes, for finding them with a `ShapeQuery`.
+    pub tags: Vec<String>,
     pub parent: Option<Arc<Shape>>,
-    pub transform: Matrix4,
+    // Only set together, by `set_transform`, so rays can be brought into
+    // the shape's space without inverting the transform every time.
+    transform: Matrix4,
+    inverse: Matrix4,
     pub material: Material,
     pub intersectable: Intersectable,
+    pub uv_mapping: Option<UvMapping>,
 }
 
 impl Shape {
-    pub fn sphere() -> Arc<Shape> {
-        Arc::new(Shape {
+    // A shape with no name, material or transform of its own yet. Set those,
+    // and add children to a group, before sharing it with `share`.
+    pub fn new(intersectable: Intersectable) -> Shape {
+        Shape {
+            name: None,
+            tags: Vec::new(),
+            uv_mapping: None,
             parent: None,
             transform: IDENTITY_MATRIX,
+            inverse: IDENTITY_MATRIX,
             material: Material::new(),
-            intersectable: Intersectable::sphere(),
-        })
+            intersectable,
+        }
+    }
+
+    pub fn sphere() -> Arc<Shape> {
+        Arc::new(Shape::new(Intersectable::sphere()))
     }
 
     pub fn glass_sphere() -> Arc<Shape> {
-        let mut s = Shape {
-            parent: None,
-            transform: IDENTITY_MATRIX,
-            material: Material::new(),
-            intersectable: Intersectable::sphere(),
-        };
+        let mut s = Shape::new(Intersectable::sphere());
         s.material.refractive_index = 1.5;
-        s.material.transparency = 1.;
+        s.material.transparency = ChannelValue::Constant(1.);
         s.material.specular = 1.;
-        s.material.shininess = 300.;
+        s.material.shininess = ChannelValue::Constant(300.);
         s.material.diffuse = 0.1;
         s.material.ambient = 0.2;
         s.material.pattern = Patternable::solid(Color::black());
@@ -44,70 +86,172 @@ impl Shape {
     }
 
     pub fn plane() -> Arc<Shape> {
-        Arc::new(Shape {
-            parent: None,
-            transform: IDENTITY_MATRIX,
-            material: Material::new(),
-            intersectable: Intersectable::plane(),
-        })
+        Arc::new(Shape::new(Intersectable::plane()))
     }
 
     pub fn cube() -> Arc<Shape> {
-        Arc::new(Shape {
-            parent: None,
-            transform: IDENTITY_MATRIX,
-            material: Material::new(),
-            intersectable: Intersectable::cube(),
-        })
+        Arc::new(Shape::new(Intersectable::cube()))
     }
 
     pub fn triangle(a: Point, b: Point, c: Point) -> Arc<Shape> {
-        Arc::new(Shape {
-            parent: None,
-            transform: IDENTITY_MATRIX,
-            material: Material::new(),
-            intersectable: Intersectable::triangle(a, b, c),
-        })
+        Arc::new(Shape::new(Intersectable::triangle(a, b, c)))
+    }
+
+    pub fn primitive<P: Primitive + 'static>(primitive: P) -> Arc<Shape> {
+        Arc::new(Shape::new(Intersectable::primitive(primitive)))
+    }
+
+    pub fn mesh(mesh: Mesh) -> Arc<Shape> {
+        Arc::new(Shape::new(Intersectable::mesh(mesh)))
     }
 
     pub fn group() -> Arc<Shape> {
-        Arc::new(Shape {
-            parent: None,
-            transform: IDENTITY_MATRIX,
-            material: Material::new(),
-            intersectable: Intersectable::group(),
-        })
+        Arc::new(Shape::new(Intersectable::group()))
+    }
+
+    pub fn transform(&self) -> Matrix4 {
+        self.transform
+    }
+
+    // Takes points and rays from the parent's space into the shape's.
+    pub fn inverse_transform(&self) -> &Matrix4 {
+        &self.inverse
     }
 
-    pub fn add_group(mut group: Arc<Shape>, mut shape: Arc<Shape>) {
-        Arc::get_mut(&mut shape).unwrap().parent = Some(group.clone());
-        Arc::get_mut(&mut group)
-            .unwrap()
-            .intersectable
-            .add(shape.clone());
+    // Replaces the transform, unless it flattens the shape and so can't be
+    // undone to bring rays into the shape's space.
+    pub fn set_transform(&mut self, transform: Matrix4) -> Result<(), Error> {
+        self.inverse = transform.try_inverse()?;
+        self.transform = transform;
+        Ok(())
     }
 
-    pub fn add_shape(mut group: Arc<Shape>, mut shape: Arc<Shape>) {
-        Arc::get_mut(&mut shape).unwrap().parent = Some(group.clone());
-        Arc::get_mut(&mut group).unwrap().intersectable.add(shape);
+    // Moves the shape by `transform`, on top of the transform it has.
+    pub fn transform_by(&mut self, transform: &Matrix4) -> Result<(), Error> {
+        self.inverse = self.inverse.multiply(&transform.try_inverse()?);
+        self.transform = transform.multiply(&self.transform);
+        Ok(())
+    }
+
+    // Puts `child` in this group. It isn't linked back to the group until
+    // the group is shared, so its transform can still change until then.
+    pub fn add_child<S: Into<Arc<Shape>>>(&mut self, child: S) -> Result<(), Error> {
+        self.intersectable.add(child.into())
+    }
+
+    // The finished shape, with everything inside it pointing back at the
+    // groups that hold it. Children that are already shared elsewhere are
+    // copied rather than changed.
+    pub fn share(self) -> Arc<Shape> {
+        Arc::new(self.with_parent(self.parent.clone()))
+    }
+
+    // A copy of the shape under another parent. Groups are copied all the
+    // way down, since their children point back at them.
+    pub fn with_parent(&self, parent: Option<Arc<Shape>>) -> Shape {
+        let mut shape = Shape {
+            name: self.name.clone(),
+            tags: self.tags.clone(),
+            uv_mapping: self.uv_mapping,
+            parent,
+            transform: self.transform,
+            inverse: self.inverse,
+            material: self.material.clone(),
+            intersectable: self.intersectable.clone(),
+        };
+        if !shape.intersectable.children().is_empty() {
+            let shell = Arc::new(Shape {
+                name: shape.name.clone(),
+                tags: shape.tags.clone(),
+                uv_mapping: None,
+                parent: shape.parent.clone(),
+                transform: shape.transform,
+                inverse: shape.inverse,
+                material: shape.material.clone(),
+                intersectable: Intersectable::group(),
+            });
+            for child in shape.intersectable.children_mut() {
+                *child = Arc::new(child.with_parent(Some(shell.clone())));
+            }
+        }
+        shape
+    }
+
+    // Every shape inside this one, depth first, with each group before its
+    // children.
+    pub fn descendants(&self) -> Vec<&Arc<Shape>> {
+        let mut shapes = Vec::new();
+        for child in self.intersectable.children() {
+            shapes.push(child);
+            shapes.extend(child.descendants());
+        }
+        shapes
+    }
+
+    // A copy of the shape with `edit` run on it and everything inside it
+    // that `should_edit` picks. The copy still points at the old groups, so
+    // pass it through `with_parent` to bring them up to date.
+    pub fn edited<P, F>(&self, should_edit: &P, edit: &F) -> Shape
+    where
+        P: Fn(&Shape) -> bool,
+        F: Fn(&mut Shape),
+    {
+        let mut shape = Shape {
+            name: self.name.clone(),
+            tags: self.tags.clone(),
+            uv_mapping: self.uv_mapping,
+            parent: self.parent.clone(),
+            transform: self.transform,
+            inverse: self.inverse,
+            material: self.material.clone(),
+            intersectable: self.intersectable.clone(),
+        };
+        if should_edit(self) {
+            edit(&mut shape);
+        }
+        for child in shape.intersectable.children_mut() {
+            *child = Arc::new(child.edited(should_edit, edit));
+        }
+        shape
     }
 
     pub fn normal_at(&self, world_point: &Point) -> Point {
-        let local_point = self.transform.inverse().multiply_point(&world_point);
-        let local_normal = self.intersectable.local_normal_at(&local_point);
+        self.normal_at_hit(world_point, None)
+    }
+
+    pub fn normal_at_hit(&self, world_point: &Point, face: Option<&FaceHit>) -> Point {
+        let local_point = self.world_to_object(world_point);
+        let local_normal = match face {
+            Some(face) => self.intersectable.local_normal_at_face(&local_point, face),
+            None => self.intersectable.local_normal_at(&local_point),
+        };
         self.normal_to_world(&local_normal)
     }
 
+    // Mesh faces can carry their own material; everything else uses the shape's.
+    pub fn material_at(&self, face: Option<&FaceHit>) -> &Material {
+        face.and_then(|face| self.intersectable.face_material(face))
+            .unwrap_or(&self.material)
+    }
+
+    pub fn vertex_color(&self, face: &FaceHit) -> Option<Color> {
+        self.intersectable.vertex_color(face)
+    }
+
+    pub fn face_uv(&self, face: &FaceHit) -> Option<(f64, f64)> {
+        self.intersectable.face_uv(face)
+    }
+
     pub fn world_to_object(&self, world_point: &Point) -> Point {
         let point = match self.parent {
             Some(ref p) => p.world_to_object(world_point),
             None => *world_point,
         };
-        self.transform.inverse().multiply_point(&point)
+        self.inverse.multiply_point(&point)
     }
 
     pub fn normal_to_world(&self, normal: &Point) -> Point {
-        let mut local_normal = self.transform.inverse().transpose().multiply_point(&normal);
+        let mut local_normal = self.inverse.transpose().multiply_point(normal);
         local_normal.w = 0.;
         if let Some(ref p) = self.parent {
             p.normal_to_world(&local_normal).normalize()
@@ -116,8 +260,23 @@ impl Shape {
         }
     }
 
+    // True if this shape or any group containing it has the given name.
+    pub fn is_named(&self, name: &str) -> bool {
+        self.name.as_ref().is_some_and(|n| n == name)
+            || self.parent.as_ref().is_some_and(|p| p.is_named(name))
+    }
+
     pub fn bounds(&self) -> Bounds {
-        self.intersectable.bounds(self)
+        self.intersectable.bounds()
+    }
+
+    // The shape's own mapping if it has one, otherwise its natural one.
+    pub fn uv_at(&self, world_point: &Point) -> (f64, f64) {
+        let local_point = self.world_to_object(world_point);
+        match self.uv_mapping {
+            Some(mapping) => mapping.uv_at(&local_point),
+            None => self.intersectable.uv_at(&local_point),
+        }
     }
 }
 
@@ -150,13 +309,19 @@ mod tests {
     #[test]
     fn test_shape_with_non_default_transform() {
         let t = Matrix4::translation(2., 3., 4.);
-        let s = Shape {
-            parent: None,
-            transform: t,
-            material: Material::new(),
-            intersectable: Intersectable::sphere(),
-        };
+        let mut s = Shape::new(Intersectable::sphere());
+        s.set_transform(t).unwrap();
+
+        assert_eq!(s.transform, t);
+    }
 
+    #[test]
+    fn test_shape_set_transform() {
+        let mut s = Shape::new(Intersectable::sphere());
+        let t = Matrix4::translation(2., 3., 4.);
+
+        assert!(s.set_transform(t).is_ok());
+        assert!(s.set_transform(Matrix4::scaling(1., 0., 1.)).is_err());
         assert_eq!(s.transform, t);
     }
 
@@ -176,23 +341,17 @@ mod tests {
 
     #[test]
     fn test_shape_normal_at_with_transformation() {
-        let s = Shape {
-            parent: None,
-            transform: Matrix4::translation(0., 1., 0.),
-            material: Material::new(),
-            intersectable: Intersectable::sphere(),
-        };
+        let mut s = Shape::new(Intersectable::sphere());
+        s.set_transform(Matrix4::translation(0., 1., 0.)).unwrap();
 
+        let sqrt_2_over_2 = 2.0_f64.sqrt() / 2.;
         assert!(s
-            .normal_at(&point(0., 1.70711, -0.70711))
-            .equal(&vector(0., 0.70711, -0.70711)));
+            .normal_at(&point(0., 1. + sqrt_2_over_2, -sqrt_2_over_2))
+            .equal(&vector(0., sqrt_2_over_2, -sqrt_2_over_2)));
 
-        let s = Shape {
-            parent: None,
-            intersectable: Intersectable::sphere(),
-            transform: Matrix4::scaling(1., 0.5, 1.).multiply(&Matrix4::rotation_z(PI / 5.)),
-            material: Material::new(),
-        };
+        let mut s = Shape::new(Intersectable::sphere());
+        s.set_transform(Matrix4::scaling(1., 0.5, 1.).multiply(&Matrix4::rotation_z(PI / 5.)))
+            .unwrap();
 
         assert!(s
             .normal_at(&point(0., 2.0_f64.sqrt() / 2., -2.0_f64.sqrt() / 2.))
@@ -360,17 +519,49 @@ mod tests {
         assert_eq!(ray3.intersect(t.clone()).len(), 0);
     }
 
-    //     #[test]
-    //     fn test_triangle_intersection_hits() {
-    //         let t = triangle();
-    //         let ray = Ray {
-    //             origin: point(0., 0.5, -2.),
-    //             direction: vector(0., 0., 1.),
-    //         };
+    #[test]
+    fn test_triangle_intersection_hits() {
+        let t = triangle();
+        let ray = Ray {
+            origin: point(0., 0.5, -2.),
+            direction: vector(0., 0., 1.),
+        };
+
+        assert_eq!(ray.intersect(t.clone()).len(), 1);
+        assert_eq!(ray.intersect(t.clone())[0].t, 2.);
+    }
+
+    #[test]
+    fn test_mesh_face_material() {
+        let mut mesh = Mesh::new(vec![
+            point(-1., -1., 0.),
+            point(1., -1., 0.),
+            point(1., 1., 0.),
+            point(-1., 1., 0.),
+        ]);
+        let mut red = Material::new();
+        red.ambient = 0.5;
+        let red = mesh.add_material(red);
+        mesh.add_face(0, 1, 2, Some(red));
+        mesh.add_face(0, 2, 3, None);
+        let s = Shape::mesh(mesh);
+
+        let upper = Ray {
+            origin: point(-0.5, 0.5, -2.),
+            direction: vector(0., 0., 1.),
+        };
+        let lower = Ray {
+            origin: point(0.5, -0.5, -2.),
+            direction: vector(0., 0., 1.),
+        };
+        let upper = upper.intersect(s.clone())[0].precompute(&upper, Vec::new());
+        let lower = lower.intersect(s.clone())[0].precompute(&lower, Vec::new());
 
-    //         assert_eq!(ray.intersect(t.clone()).len(), 1);
-    //         assert_eq!(ray.intersect(t.clone())[0].t, 2.);
-    //     }
+        assert_eq!(lower.t, 2.);
+        assert_eq!(lower.material().ambient, 0.5);
+        assert_eq!(upper.material().ambient, Material::new().ambient);
+        assert!(upper.normalv.equal(&vector(0., 0., -1.)));
+    }
 
     #[test]
     fn test_glass_sphere() {
@@ -384,38 +575,18 @@ mod tests {
         };
 
         let xs: Vec<Intersection> = vec![
-            Intersection {
-                object: a.clone(),
-                t: 2.,
-            },
-            Intersection {
-                object: b.clone(),
-                t: 2.75,
-            },
-            Intersection {
-                object: c.clone(),
-                t: 3.25,
-            },
-            Intersection {
-                object: b.clone(),
-                t: 4.75,
-            },
-            Intersection {
-                object: c.clone(),
-                t: 5.25,
-            },
-            Intersection {
-                object: a.clone(),
-                t: 6.,
-            },
+            Intersection::new(2., a.clone()),
+            Intersection::new(2.75, b.clone()),
+            Intersection::new(3.25, c.clone()),
+            Intersection::new(4.75, b.clone()),
+            Intersection::new(5.25, c.clone()),
+            Intersection::new(6., a.clone()),
         ];
         let prepared_xs: Vec<Precompute> = xs
             .iter()
             .map(|int| int.precompute(&r, xs.clone()))
             .collect();
 
-        let ns: Vec<(f64, f64)> = prepared_xs.iter().map(|x| (x.n1, x.n2)).collect();
-
         let expectations: Vec<(usize, f64, f64)> = vec![
             (0, 1.0, 1.5),
             (1, 1.5, 2.0),
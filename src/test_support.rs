@@ -0,0 +1,38 @@
+use color::Color;
+use point::{point, vector};
+use point_light::PointLight;
+use ray::Ray;
+use shape::Shape;
+use std::f64::consts::PI;
+use std::sync::Arc;
+use world::World;
+
+// What the floor of `floor_world` sends back along `ray()` when lit by its
+// light alone: the light is 2 above the floor, so a quarter of its
+// intensity arrives, and the half diffuse floor reflects 1/PI of that.
+pub const FLOOR_RADIANCE: f64 = 0.5 / PI / 4.;
+
+// A matte floor under a white light of the given radius, for checking the
+// path tracers against each other and against the answer above.
+pub fn floor_world(radius: f64) -> World {
+    let mut world = World::new();
+    let mut floor = Shape::plane();
+    {
+        let floor = Arc::get_mut(&mut floor).unwrap();
+        floor.material.diffuse = 0.5;
+        floor.material.specular = 0.;
+    }
+    world.objects = vec![floor];
+    let mut light = PointLight::new(point(0., 2., 0.), Color::white());
+    light.radius = radius;
+    world.light_source = Arc::new(light);
+    world
+}
+
+// Looks down at the floor right under the light.
+pub fn ray() -> Ray {
+    Ray {
+        origin: point(0., 1., -1.),
+        direction: vector(0., -1., 1.).normalize(),
+    }
+}
@@ -0,0 +1,233 @@
+use color::{Color, Encoding};
+use image::{self, ColorType};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// How texture coordinates outside 0..1 are brought back in: tiled,
+// pinned to the edge pixels, or tiled with every other copy flipped.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Wrap {
+    Clamp,
+    Mirror,
+    Repeat,
+}
+
+impl Wrap {
+    pub fn apply(&self, coordinate: f64) -> f64 {
+        match *self {
+            Wrap::Clamp => coordinate.clamp(0., 1.),
+            Wrap::Mirror => {
+                let t = coordinate.rem_euclid(2.);
+                if t > 1. {
+                    2. - t
+                } else {
+                    t
+                }
+            }
+            Wrap::Repeat => coordinate.rem_euclid(1.),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Texture {
+    alpha: Vec<f64>,
+    pub height: usize,
+    // Where the texture was loaded from, so a scene using it can be saved.
+    pub path: Option<PathBuf>,
+    pub width: usize,
+    pixels: Vec<Color>,
+}
+
+impl Texture {
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> Texture {
+        assert_eq!(pixels.len(), width * height);
+        Texture {
+            alpha: vec![1.; width * height],
+            height,
+            path: None,
+            width,
+            pixels,
+        }
+    }
+
+    // Eight and sixteen bit images hold sRGB encoded color, so they're
+    // decoded to linear as they load. Floating point ones, like HDR, are
+    // linear already.
+    pub fn open<P: AsRef<Path>>(path: P) -> image::ImageResult<Texture> {
+        Texture::load(path, Encoding::Srgb)
+    }
+
+    // For an image holding data rather than color, like a stencil, with its
+    // values kept as they're stored.
+    pub fn open_linear<P: AsRef<Path>>(path: P) -> image::ImageResult<Texture> {
+        Texture::load(path, Encoding::Linear)
+    }
+
+    fn load<P: AsRef<Path>>(path: P, encoding: Encoding) -> image::ImageResult<Texture> {
+        let image = image::open(&path)?;
+        let encoding = match image.color() {
+            ColorType::Rgb32F | ColorType::Rgba32F => Encoding::Linear,
+            _ => encoding,
+        };
+        let image = image.to_rgba32f();
+        let pixels = image
+            .pixels()
+            .map(|p| Color::new(f64::from(p[0]), f64::from(p[1]), f64::from(p[2])).decode(encoding))
+            .collect();
+        let alpha = image.pixels().map(|p| f64::from(p[3])).collect();
+        let mut texture =
+            Texture::new(image.width() as usize, image.height() as usize, pixels).with_alpha(alpha);
+        texture.path = Some(path.as_ref().to_path_buf());
+        Ok(texture)
+    }
+
+    pub fn with_alpha(mut self, alpha: Vec<f64>) -> Texture {
+        assert_eq!(alpha.len(), self.width * self.height);
+        self.alpha = alpha;
+        self
+    }
+
+    pub fn pixel_at(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+
+    // u runs left to right and v runs bottom to top, both in 0..1.
+    pub fn color_at(&self, u: f64, v: f64) -> Color {
+        self.pixels[self.index_at(u, v)]
+    }
+
+    // Blends the four pixels around (u, v) by distance.
+    pub fn color_at_bilinear(&self, u: f64, v: f64) -> Color {
+        let x = u.clamp(0., 1.) * (self.width - 1) as f64;
+        let y = (1. - v.clamp(0., 1.)) * (self.height - 1) as f64;
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (fx, fy) = (x - x0 as f64, y - y0 as f64);
+        let top = self
+            .pixel_at(x0, y0)
+            .multiply_scalar(1. - fx)
+            .add(&self.pixel_at(x1, y0).multiply_scalar(fx));
+        let bottom = self
+            .pixel_at(x0, y1)
+            .multiply_scalar(1. - fx)
+            .add(&self.pixel_at(x1, y1).multiply_scalar(fx));
+        top.multiply_scalar(1. - fy)
+            .add(&bottom.multiply_scalar(fy))
+    }
+
+    pub fn alpha_at(&self, u: f64, v: f64) -> f64 {
+        self.alpha[self.index_at(u, v)]
+    }
+
+    pub fn index_at(&self, u: f64, v: f64) -> usize {
+        let x = (u * (self.width - 1) as f64).round() as usize;
+        let y = ((1. - v) * (self.height - 1) as f64).round() as usize;
+        y.min(self.height - 1) * self.width + x.min(self.width - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use color::Color;
+    use image::{Rgb, RgbImage, Rgba, RgbaImage};
+    use std::env;
+    use texture::{Texture, Wrap};
+    use utilities::equal;
+
+    fn corners() -> Texture {
+        Texture::new(
+            2,
+            2,
+            vec![
+                Color::new(1., 0., 0.),
+                Color::new(0., 1., 0.),
+                Color::new(0., 0., 1.),
+                Color::white(),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(Wrap::Repeat.apply(1.25), 0.25);
+        assert_eq!(Wrap::Repeat.apply(-0.25), 0.75);
+        assert_eq!(Wrap::Clamp.apply(1.25), 1.);
+        assert_eq!(Wrap::Clamp.apply(-0.25), 0.);
+        assert_eq!(Wrap::Mirror.apply(1.25), 0.75);
+        assert_eq!(Wrap::Mirror.apply(-0.25), 0.25);
+        assert_eq!(Wrap::Mirror.apply(2.25), 0.25);
+    }
+
+    #[test]
+    fn test_texture_color_at() {
+        let t = corners();
+
+        assert_eq!(t.color_at(0., 1.), Color::new(1., 0., 0.));
+        assert_eq!(t.color_at(1., 1.), Color::new(0., 1., 0.));
+        assert_eq!(t.color_at(0., 0.), Color::new(0., 0., 1.));
+        assert_eq!(t.color_at(1., 0.), Color::white());
+        assert_eq!(t.color_at(0.9, 0.1), Color::white());
+    }
+
+    #[test]
+    fn test_texture_color_at_bilinear() {
+        let t = corners();
+
+        assert_eq!(t.color_at_bilinear(0., 1.), Color::new(1., 0., 0.));
+        assert_eq!(t.color_at_bilinear(1., 0.), Color::white());
+        assert_eq!(t.color_at_bilinear(0.5, 1.), Color::new(0.5, 0.5, 0.));
+        assert_eq!(t.color_at_bilinear(0.5, 0.5), Color::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn test_texture_open() {
+        let path = env::temp_dir().join("ray_tracer_test_texture_open.png");
+        let mut image = RgbImage::new(3, 1);
+        image.put_pixel(2, 0, Rgb([255, 0, 0]));
+        image.save(&path).unwrap();
+
+        let t = Texture::open(&path).unwrap();
+
+        assert_eq!(t.width, 3);
+        assert_eq!(t.height, 1);
+        assert_eq!(t.pixel_at(0, 0), Color::black());
+        assert_eq!(t.pixel_at(2, 0), Color::new(1., 0., 0.));
+        assert_eq!(t.path, Some(path.clone()));
+    }
+
+    #[test]
+    fn test_texture_open_decodes_srgb() {
+        let path = env::temp_dir().join("ray_tracer_test_texture_srgb.png");
+        let mut image = RgbImage::new(1, 1);
+        image.put_pixel(0, 0, Rgb([128, 128, 128]));
+        image.save(&path).unwrap();
+
+        let gray = Texture::open(&path).unwrap().pixel_at(0, 0);
+        assert!((gray.red - 0.214).abs() < 0.002);
+        assert_eq!(gray.red, gray.blue);
+
+        let stored = Texture::open_linear(&path).unwrap().pixel_at(0, 0);
+        assert!(equal(stored.red, 128. / 255.));
+    }
+
+    #[test]
+    fn test_texture_alpha() {
+        let t = corners().with_alpha(vec![0., 1., 1., 0.5]);
+
+        assert_eq!(t.alpha_at(0., 1.), 0.);
+        assert_eq!(t.alpha_at(1., 0.), 0.5);
+        assert_eq!(corners().alpha_at(0., 1.), 1.);
+
+        let path = env::temp_dir().join("ray_tracer_test_texture_alpha.png");
+        let mut image = RgbaImage::new(2, 1);
+        image.put_pixel(0, 0, Rgba([255, 255, 255, 0]));
+        image.put_pixel(1, 0, Rgba([255, 255, 255, 255]));
+        image.save(&path).unwrap();
+        let t = Texture::open(&path).unwrap();
+
+        assert_eq!(t.alpha_at(0., 0.), 0.);
+        assert_eq!(t.alpha_at(1., 0.), 1.);
+    }
+}
@@ -0,0 +1,93 @@
+use canvas::Canvas;
+use color::Encoding;
+use std::fs::File;
+use std::io;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::Path;
+
+// A binary PPM laid out in full up front, black throughout, so finished
+// tiles can be written into place as a render goes. If the render dies
+// part way, the file still opens with whatever was done.
+pub struct TileFile {
+    pub encoding: Encoding,
+    file: File,
+    header_length: u64,
+    height: i64,
+    width: i64,
+}
+
+impl TileFile {
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        width: i64,
+        height: i64,
+        encoding: Encoding,
+    ) -> io::Result<TileFile> {
+        let mut file = File::create(path)?;
+        let header = format!("P6\n{} {}\n255\n", width, height);
+        file.write_all(header.as_bytes())?;
+        let row = vec![0; width as usize * 3];
+        for _ in 0..height {
+            file.write_all(&row)?;
+        }
+        file.flush()?;
+        Ok(TileFile {
+            encoding,
+            file,
+            header_length: header.len() as u64,
+            height,
+            width,
+        })
+    }
+
+    // Writes `tile` with its top left corner at (x, y), dropping whatever
+    // falls outside the image.
+    pub fn write_tile(&mut self, tile: &Canvas, x: usize, y: usize) -> io::Result<()> {
+        let width = (tile.width as usize).min((self.width as usize).saturating_sub(x));
+        let height = (tile.height as usize).min((self.height as usize).saturating_sub(y));
+        let mut bytes = Vec::with_capacity(width * 3);
+        for row in 0..height {
+            bytes.clear();
+            let first = row * tile.width as usize;
+            for pixel in &tile.pixels[first..first + width] {
+                bytes.extend_from_slice(&pixel.encode(self.encoding).bytes());
+            }
+            let offset = ((y + row) * self.width as usize + x) * 3;
+            self.file
+                .seek(SeekFrom::Start(self.header_length + offset as u64))?;
+            self.file.write_all(&bytes)?;
+        }
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use canvas::Canvas;
+    use color::{Color, Encoding};
+    use std::env;
+    use std::fs;
+    use tile_file::TileFile;
+
+    #[test]
+    fn test_write_tiles() {
+        let path = env::temp_dir().join("ray_tracer_test_tile_file.ppm");
+        let mut tile = Canvas::empty(2, 2);
+        tile.write_pixel(0, 0, &Color::new(1.0, 0.5, 0.0));
+        tile.write_pixel(1, 1, &Color::white());
+        let mut expected = Canvas::empty(3, 3);
+        expected.blit(&tile, 2, 1);
+
+        {
+            let mut file = TileFile::create(&path, 3, 3, Encoding::Linear).unwrap();
+            assert_eq!(
+                fs::read(&path).unwrap(),
+                Canvas::empty(3, 3).render_ppm_binary()
+            );
+            file.write_tile(&tile, 2, 1).unwrap();
+        }
+
+        assert_eq!(fs::read(&path).unwrap(), expected.render_ppm_binary());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -4,6 +4,7 @@ use point::Point;
 pub struct TransformationMatrix {}
 
 impl TransformationMatrix {
+    #[allow(clippy::new_ret_no_self)]
     pub fn new(from: &Point, to: &Point, up: &Point) -> Matrix4 {
         let forward = to.sub(from).normalize();
         let up_normal = up.normalize();
@@ -1,10 +1,12 @@
-pub const EPSILON: f64 = 0.00001;
+use float::Float;
 
-pub fn equal(a: f64, b: f64) -> bool {
-    (a - b).abs() < EPSILON
+pub const EPSILON: f64 = <f64 as Float>::EPSILON;
+
+pub fn equal<T: Float>(a: T, b: T) -> bool {
+    (a - b).abs() < T::EPSILON
 }
 
-pub fn clamp(number: f64, min: f64, max: f64) -> f64 {
+pub fn clamp<T: Float>(number: T, min: T, max: T) -> T {
     if number > max {
         max
     } else if number < min {
@@ -14,12 +16,12 @@ pub fn clamp(number: f64, min: f64, max: f64) -> f64 {
     }
 }
 
-pub fn min(xs: &[f64]) -> f64 {
-    xs.iter().cloned().fold(std::f64::NAN, f64::min)
+pub fn min<T: Float>(xs: &[T]) -> T {
+    xs.iter().cloned().fold(T::NAN, T::min)
 }
 
-pub fn max(xs: &[f64]) -> f64 {
-    xs.iter().cloned().fold(std::f64::NAN, f64::max)
+pub fn max<T: Float>(xs: &[T]) -> T {
+    xs.iter().cloned().fold(T::NAN, T::max)
 }
 
 #[cfg(test)]
@@ -29,11 +31,11 @@ mod tests {
 
     #[test]
     fn test_min() {
-        assert_eq!(min(&vec!(0., 1.)), 0.);
+        assert_eq!(min(&[0., 1.]), 0.);
     }
 
     #[test]
     fn test_max() {
-        assert_eq!(max(&vec!(1., 2.)), 2.);
+        assert_eq!(max(&[1., 2.]), 2.);
     }
 }
@@ -0,0 +1,130 @@
+use point::Point;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use utilities::max;
+
+// Ways of flattening an object-space point onto the unit square.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UvMapping {
+    Cubic,
+    Cylindrical,
+    Planar,
+    Spherical,
+}
+
+impl UvMapping {
+    pub fn uv_at(&self, point: &Point) -> (f64, f64) {
+        match *self {
+            UvMapping::Cubic => uv_at_cubic(point),
+            UvMapping::Cylindrical => uv_at_cylindrical(point),
+            UvMapping::Planar => uv_at_planar(point),
+            UvMapping::Spherical => uv_at_spherical(point),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CubeFace {
+    Back,
+    Down,
+    Front,
+    Left,
+    Right,
+    Up,
+}
+
+// The face of the unit cube a point lies on, and where on that face.
+// Each face is mapped onto the whole of the unit square.
+pub fn cube_face_uv(point: &Point) -> (CubeFace, f64, f64) {
+    let (x, y, z) = (point.x, point.y, point.z);
+    let coord = max(&[x.abs(), y.abs(), z.abs()]);
+    let (face, u, v) = if coord == x {
+        (CubeFace::Right, 1. - z, y + 1.)
+    } else if coord == -x {
+        (CubeFace::Left, z + 1., y + 1.)
+    } else if coord == y {
+        (CubeFace::Up, x + 1., 1. - z)
+    } else if coord == -y {
+        (CubeFace::Down, x + 1., z + 1.)
+    } else if coord == z {
+        (CubeFace::Front, x + 1., y + 1.)
+    } else {
+        (CubeFace::Back, 1. - x, y + 1.)
+    };
+    (face, u.rem_euclid(2.) / 2., v.rem_euclid(2.) / 2.)
+}
+
+fn uv_at_cubic(point: &Point) -> (f64, f64) {
+    let (_, u, v) = cube_face_uv(point);
+    (u, v)
+}
+
+// Around the y axis, repeating once per unit of height.
+fn uv_at_cylindrical(point: &Point) -> (f64, f64) {
+    let theta = point.x.atan2(point.z);
+    let raw_u = theta / (2. * PI);
+    (1. - (raw_u + 0.5), point.y.rem_euclid(1.))
+}
+
+fn uv_at_planar(point: &Point) -> (f64, f64) {
+    (point.x.rem_euclid(1.), point.z.rem_euclid(1.))
+}
+
+fn uv_at_spherical(point: &Point) -> (f64, f64) {
+    let theta = point.x.atan2(point.z);
+    let radius = (point.x.powi(2) + point.y.powi(2) + point.z.powi(2)).sqrt();
+    let phi = (point.y / radius).acos();
+    let raw_u = theta / (2. * PI);
+    (1. - (raw_u + 0.5), 1. - phi / PI)
+}
+
+#[cfg(test)]
+mod tests {
+    use point::point;
+    use std::f64::consts::FRAC_1_SQRT_2;
+    use utilities::equal;
+    use uv_mapping::{cube_face_uv, CubeFace, UvMapping};
+
+    #[test]
+    fn test_cylindrical_uv_at() {
+        let cases = [
+            (point(0., 0., -1.), (0., 0.)),
+            (point(0., 0.5, -1.), (0., 0.5)),
+            (point(0., 1., -1.), (0., 0.)),
+            (point(FRAC_1_SQRT_2, 0.5, -FRAC_1_SQRT_2), (0.125, 0.5)),
+            (point(1., 0.5, 0.), (0.25, 0.5)),
+            (point(0., -0.25, 1.), (0.5, 0.75)),
+            (point(-1., 1.25, 0.), (0.75, 0.25)),
+        ];
+        for &(p, (u, v)) in cases.iter() {
+            let (actual_u, actual_v) = UvMapping::Cylindrical.uv_at(&p);
+
+            assert!(equal(actual_u, u), "{:?}", p);
+            assert!(equal(actual_v, v), "{:?}", p);
+        }
+    }
+
+    #[test]
+    fn test_planar_uv_at() {
+        assert_eq!(UvMapping::Planar.uv_at(&point(0.25, 0., 0.5)), (0.25, 0.5));
+        assert_eq!(
+            UvMapping::Planar.uv_at(&point(-0.25, 0.5, -1.75)),
+            (0.75, 0.25)
+        );
+    }
+
+    #[test]
+    fn test_cube_face_uv() {
+        assert_eq!(cube_face_uv(&point(-1., 0.5, -0.25)).0, CubeFace::Left);
+        assert_eq!(cube_face_uv(&point(1.1, -0.75, 0.8)).0, CubeFace::Right);
+        assert_eq!(cube_face_uv(&point(0.1, 0.6, 0.9)).0, CubeFace::Front);
+        assert_eq!(cube_face_uv(&point(-0.7, 0., -2.)).0, CubeFace::Back);
+        assert_eq!(cube_face_uv(&point(0.5, 1., 0.9)).0, CubeFace::Up);
+        assert_eq!(cube_face_uv(&point(-0.2, -1.3, 1.1)).0, CubeFace::Down);
+        assert_eq!(
+            cube_face_uv(&point(-0.5, 0.5, 1.)),
+            (CubeFace::Front, 0.25, 0.75)
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use color::Color;
+use intersection::Intersection;
+use point::Point;
+use ray::Ray;
+use sampling::random;
+use shape::Shape;
+use std::sync::Arc;
+use world::World;
+
+// A medium filling a closed shape, like smoke or murky water, in place of
+// its surface. Light crossing it is lost to `absorption` and `scattering`,
+// both per unit of distance, and the scattered part of the world's light
+// is sent toward the eye tinted by `color`, found by stepping through the
+// shape and testing for shadow `steps` times.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Volume {
+    pub absorption: f64,
+    pub color: Color,
+    pub scattering: f64,
+    pub steps: usize,
+}
+
+// Where along a ray it passes through a volume.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Segment {
+    pub from: f64,
+    pub to: f64,
+    pub volume: Volume,
+}
+
+impl Volume {
+    pub fn new(absorption: f64, scattering: f64) -> Volume {
+        Volume {
+            absorption,
+            color: Color::white(),
+            scattering,
+            steps: 16,
+        }
+    }
+
+    pub fn extinction(&self) -> f64 {
+        self.absorption + self.scattering
+    }
+
+    // The fraction of light that gets through `distance` of the volume.
+    pub fn transmittance(&self, distance: f64) -> f64 {
+        (-self.extinction() * distance).exp()
+    }
+
+    // The color seen along the ray from `from` to `to`, where `behind` is
+    // what's seen past `to`.
+    pub fn apply(&self, world: &World, ray: &Ray, from: f64, to: f64, behind: &Color) -> Color {
+        let extinction = self.extinction();
+        if extinction <= 0. {
+            return *behind;
+        }
+        let length = ray.direction.magnitude();
+        // This also bounds shapes that aren't closed after all.
+        let to = to.min(from + MAX_OPTICAL_DEPTH / (extinction * length));
+        let seen = behind.multiply_scalar(self.transmittance((to - from) * length));
+        if self.scattering <= 0. {
+            return seen;
+        }
+        let scattered = in_scattered(
+            world,
+            ray,
+            from,
+            to,
+            self.steps,
+            |_| 1.,
+            |t| self.transmittance((t - from) * length),
+        );
+        seen.add(
+            &self
+                .color
+                .hadamard_product(&scattered)
+                .multiply_scalar(self.scattering),
+        )
+    }
+}
+
+// Past this optical depth too little light gets back to matter, so media
+// stop stepping there.
+pub const MAX_OPTICAL_DEPTH: f64 = 5.;
+
+// The world's light scattered toward the ray's origin by a medium between
+// `from` and `to`, per unit of scattering. It is found by testing for
+// shadow at `steps` points along the way, jittered together so banding
+// turns to noise. `density` scales the scattering at a point, and
+// `transmittance` is how much of what's scattered `t` along the ray makes
+// it back.
+pub fn in_scattered<D, T>(
+    world: &World,
+    ray: &Ray,
+    from: f64,
+    to: f64,
+    steps: usize,
+    density: D,
+    transmittance: T,
+) -> Color
+where
+    D: Fn(&Point) -> f64,
+    T: Fn(f64) -> f64,
+{
+    if steps == 0 || to <= from {
+        return Color::black();
+    }
+    let length = ray.direction.magnitude();
+    let step = (to - from) / steps as f64;
+    let offset = random();
+    let mut scattered = Color::black();
+    for i in 0..steps {
+        let t = from + (i as f64 + offset) * step;
+        let point = ray.position(t);
+        let lit = world.light_transmittance(&point);
+        scattered = scattered.add(
+            &world
+                .light_source
+                .intensity_at(&point)
+                .multiply_scalar(lit * density(&point) * transmittance(t) * step * length),
+        );
+    }
+    scattered
+}
+
+pub fn volume_of(hit: &Intersection) -> Option<Volume> {
+    hit.object.material_at(hit.face.as_ref()).volume
+}
+
+// The stretches of the ray from its origin to `to` that lie within volumes,
+// given every hit along the ray in order. A shape hit an odd number of
+// times must hold the ray's origin.
+pub fn segments(hits: &[Intersection], to: f64) -> Vec<Segment> {
+    let mut inside: Vec<(*const Shape, Volume, f64)> = Vec::new();
+    for hit in hits {
+        if let Some(volume) = volume_of(hit) {
+            toggle(&mut inside, Arc::as_ptr(&hit.object), volume, 0.);
+        }
+    }
+    let mut segments = Vec::new();
+    for hit in hits.iter().take_while(|hit| hit.t < to) {
+        if let Some(volume) = volume_of(hit) {
+            if let Some((_, volume, from)) =
+                toggle(&mut inside, Arc::as_ptr(&hit.object), volume, hit.t)
+            {
+                segments.push(Segment {
+                    from,
+                    to: hit.t,
+                    volume,
+                });
+            }
+        }
+    }
+    segments.extend(
+        inside
+            .into_iter()
+            .map(|(_, volume, from)| Segment { from, to, volume }),
+    );
+    segments
+}
+
+// Leaves the shape if the ray was inside it, giving back where it entered,
+// and otherwise enters it at `t`.
+fn toggle(
+    inside: &mut Vec<(*const Shape, Volume, f64)>,
+    shape: *const Shape,
+    volume: Volume,
+    t: f64,
+) -> Option<(*const Shape, Volume, f64)> {
+    match inside.iter().position(|entry| entry.0 == shape) {
+        Some(i) => Some(inside.remove(i)),
+        None => {
+            inside.push((shape, volume, t));
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use background::Background;
+    use color::Color;
+    use point::{point, vector};
+    use ray::Ray;
+    use shape::Shape;
+    use std::sync::Arc;
+    use utilities::equal;
+    use volume::{segments, Volume};
+    use world::World;
+
+    fn smoke_world(absorption: f64) -> World {
+        let mut world = World::new();
+        let mut smoke = Shape::sphere();
+        Arc::get_mut(&mut smoke).unwrap().material.volume = Some(Volume::new(absorption, 0.));
+        world.objects = vec![smoke];
+        world.background = Background::Solid(Color::white());
+        world
+    }
+
+    #[test]
+    fn test_segments() {
+        let world = smoke_world(1.);
+        let ray = Ray {
+            origin: point(0., 0., -5.),
+            direction: vector(0., 0., 1.),
+        };
+        let hits = ray.intersect_world(&world);
+
+        let through = segments(&hits, f64::INFINITY);
+        assert_eq!(through.len(), 1);
+        assert_eq!((through[0].from, through[0].to), (4., 6.));
+        let partway = segments(&hits, 5.);
+        assert_eq!((partway[0].from, partway[0].to), (4., 5.));
+        assert!(segments(&hits, 3.).is_empty());
+
+        let ray = Ray {
+            origin: point(0., 0., 0.),
+            direction: vector(0., 0., 1.),
+        };
+        let inside = segments(&ray.intersect_world(&world), f64::INFINITY);
+        assert_eq!((inside[0].from, inside[0].to), (0., 1.));
+    }
+
+    #[test]
+    fn test_seeing_through_a_volume() {
+        let world = smoke_world(0.5);
+        let ray = Ray {
+            origin: point(0., 0., -5.),
+            direction: vector(0., 0., 1.),
+        };
+
+        let seen = world.color_at(&ray, 5);
+        assert!(equal(seen.red, (-1_f64).exp()));
+    }
+
+    #[test]
+    fn test_volume_dims_shadows() {
+        let world = smoke_world(0.5);
+        // Straight through the middle, away from the light.
+        let behind = point(10., -10., 10.);
+
+        assert!(equal(world.light_transmittance(&behind), (-1_f64).exp()));
+        assert!(!world.is_shadowed(&behind));
+    }
+}
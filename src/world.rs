@@ -1,96 +1,677 @@
+use ambient_occlusion::AmbientOcclusion;
+use background::Background;
+use bidirectional;
+use bounds::Bounds;
+use camera::Camera;
 use color::Color;
+use environment::Environment;
+use error::Error;
+use fog::Fog;
+use gbuffer::{id_color, SurfaceSample};
 use intersectable::Intersectable;
+use intersection::Bias;
 use intersection::Intersection;
 use intersection::Precompute;
+use light::Light;
+use material::Backface;
+use material::ChannelValue;
 use material::Material;
+use material::SpecularModel;
 use matrix::Matrix4;
-use matrix::IDENTITY_MATRIX;
+use path_tracer;
 use patternable::Patternable;
 use point::point;
+use point::vector;
 use point::Point;
 use point_light::PointLight;
-use ray::Ray;
-use shape::Shape;
+use ray::{intersection_tests, Ray};
+use sampling::random_in_unit_sphere;
+use scene::{SceneDescription, SceneError};
+use serde::{Deserialize, Serialize};
+use serde_json;
+use settings::RenderSettings;
+use shape::{Shape, ShapeQuery};
+use std::fmt;
+use std::path::Path;
 use std::sync::Arc;
+use utilities::EPSILON;
+use volume::{self, volume_of};
+
+// How the color arriving along a camera ray is worked out. Whitted shades
+// the material's terms directly; the others trace paths of light through
+// the scene, which is slower and noisy but lets every surface light every
+// other one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Integrator {
+    Bidirectional,
+    Path,
+    #[default]
+    Whitted,
+}
+
+// How much further a ray may go: `remaining` bounces of any kind, and
+// the reflections and refractions already on its path, which the world
+// may limit separately. A plain number is that many bounces of any kind.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Depth {
+    pub reflections: i32,
+    pub refractions: i32,
+    pub remaining: i32,
+}
+
+impl Depth {
+    fn reflected(self) -> Depth {
+        Depth {
+            reflections: self.reflections + 1,
+            remaining: self.remaining - 1,
+            ..self
+        }
+    }
+
+    fn refracted(self) -> Depth {
+        Depth {
+            refractions: self.refractions + 1,
+            remaining: self.remaining - 1,
+            ..self
+        }
+    }
+}
+
+impl From<i32> for Depth {
+    fn from(remaining: i32) -> Depth {
+        Depth {
+            reflections: 0,
+            refractions: 0,
+            remaining,
+        }
+    }
+}
+
+// The color seen along a ray along with the parts of the surface's shading
+// that make it up: the light straight from the light source, the light
+// from the environment, and what's seen in reflection and refraction.
+// `color` is what the parts add up to once volumes and fog in front of the
+// surface are taken into account. A miss has no hit and the background as
+// its indirect light.
+#[derive(Debug, PartialEq)]
+pub struct ShadingResult {
+    pub color: Color,
+    pub direct: Color,
+    pub hit: Option<Precompute>,
+    pub indirect: Color,
+    pub reflection: Color,
+    pub refraction: Color,
+}
+
+impl ShadingResult {
+    // Everything but the direct light.
+    pub fn bounced(&self) -> Color {
+        self.indirect.add(&self.reflection).add(&self.refraction)
+    }
+}
+
+// What a render shows. Besides the shaded image, false-color views of the
+// first visible hit help track down wrong normals and transforms: its
+// distance, which the camera scales so the farthest is white; its outward
+// normal, each axis mapped from -1..1 to 0..1; the object it belongs to;
+// and its texture coordinates as red and green. Heat shades as usual but
+// shows how many intersection tests that took, from black through red and
+// yellow to white for the costliest pixel, to find what makes a render
+// slow.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RenderMode {
+    Depth,
+    Heat,
+    Normals,
+    ObjectId,
+    #[default]
+    Shaded,
+    Uv,
+}
 
 pub struct World {
+    pub ambient_occlusion: Option<AmbientOcclusion>,
+    pub background: Background,
+    pub bias: Bias,
+    pub environment: Option<Environment>,
+    pub fog: Option<Fog>,
+    pub integrator: Integrator,
     pub objects: Vec<Arc<Shape>>,
-    pub light_source: PointLight,
+    pub light_source: Arc<dyn Light>,
+    // The most reflections, and refractions, on any one path, within the
+    // depth the camera allows.
+    pub reflection_depth: Option<i32>,
+    pub refraction_depth: Option<i32>,
+    pub render_mode: RenderMode,
+    // Built along with the world by `WorldBuilder`, and dropped by anything
+    // here that changes `objects`. Code changing them directly is left
+    // testing every object.
+    tree: Option<ObjectTree>,
 }
 
 impl World {
     pub fn new() -> World {
-        return World {
-            objects: vec![
-                Arc::new(Shape {
-                    intersectable: Intersectable::sphere(),
-                    material: Material {
-                        ambient: 0.1,
-                        diffuse: 0.7,
-                        pattern: Patternable::solid(Color::new(0.8, 1.0, 0.6)),
-                        reflective: 0.,
-                        refractive_index: 1.,
-                        shininess: 200.,
-                        specular: 0.2,
-                        transparency: 0.,
-                    },
-                    parent: None,
-                    transform: IDENTITY_MATRIX,
-                }),
-                Arc::new(Shape {
-                    intersectable: Intersectable::sphere(),
-                    material: Material::new(),
-                    parent: None,
-                    transform: Matrix4::scaling(0.5, 0.5, 0.5),
-                }),
-            ],
-            light_source: PointLight {
-                intensity: Color::new(1.0, 1.0, 1.0),
-                position: point(-10.0, 10.0, -10.0),
-            },
+        let mut outer = Shape::new(Intersectable::sphere());
+        outer.material = Material {
+            ambient: 0.1,
+            backface: Backface::Shade,
+            clearcoat: 0.,
+            clearcoat_roughness: 0.,
+            diffuse: 0.7,
+            mask: None,
+            pattern: Patternable::solid(Color::new(0.8, 1.0, 0.6)),
+            reflective: ChannelValue::Constant(0.),
+            refractive_index: 1.,
+            shininess: ChannelValue::Constant(200.),
+            specular: 0.2,
+            specular_model: SpecularModel::Phong,
+            texture: None,
+            transparency: ChannelValue::Constant(0.),
+            volume: None,
         };
+        let mut inner = Shape::new(Intersectable::sphere());
+        inner
+            .set_transform(Matrix4::scaling(0.5, 0.5, 0.5))
+            .expect("halving a shape can be undone");
+        World {
+            ambient_occlusion: None,
+            background: Background::Solid(Color::black()),
+            bias: Bias::Fixed(EPSILON),
+            environment: None,
+            fog: None,
+            integrator: Integrator::Whitted,
+            objects: vec![Arc::new(outer), Arc::new(inner)],
+            light_source: Arc::new(PointLight::new(
+                point(-10.0, 10.0, -10.0),
+                Color::new(1.0, 1.0, 1.0),
+            )),
+            reflection_depth: None,
+            refraction_depth: None,
+            render_mode: RenderMode::Shaded,
+            tree: None,
+        }
+    }
+
+    // Starts from a world with nothing in it, lit as `new` lights it.
+    pub fn builder() -> WorldBuilder {
+        WorldBuilder {
+            lit: false,
+            world: World {
+                objects: Vec::new(),
+                ..World::new()
+            },
+        }
+    }
+
+    // Builds a world and the camera to view it from a JSON scene, as
+    // described by `scene::SceneDescription`.
+    pub fn from_json(json: &str) -> Result<(World, Camera), SceneError> {
+        SceneDescription::from_json(json, Path::new(""))?.build()
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<(World, Camera), SceneError> {
+        SceneDescription::load(path)?.build()
+    }
+
+    // Adds the shapes of another world as one group, moved by `transform`.
+    // Its light and background are left behind.
+    pub fn merge(&mut self, other: &World, transform: Matrix4) -> Result<(), Error> {
+        let mut group = Shape::new(Intersectable::group());
+        group.set_transform(transform)?;
+        let shell = Arc::new(group.with_parent(None));
+        for object in &other.objects {
+            group
+                .intersectable
+                .add(Arc::new(object.with_parent(Some(shell.clone()))))?;
+        }
+        self.objects.push(Arc::new(group));
+        self.tree = None;
+        Ok(())
     }
 
-    pub fn shade_hit(&self, precompute: Precompute, remaining: i32) -> Color {
-        let is_shadowed = self.is_shadowed(&precompute.over_point);
-        let surface_color = precompute.object.material.lighting(
-            &self.light_source,
+    // The objects `ray` could hit: those whose boxes it passes through, if
+    // the world has a tree over them, or else all of them.
+    pub fn objects_along(&self, ray: &Ray) -> Vec<&Arc<Shape>> {
+        match self.tree {
+            Some(ref tree) => {
+                let mut indices = Vec::new();
+                tree.candidates(ray, &mut indices);
+                indices.into_iter().map(|i| &self.objects[i]).collect()
+            }
+            None => self.objects.iter().collect(),
+        }
+    }
+
+    // Every shape in the world, groups included, depth first.
+    pub fn shapes(&self) -> Vec<&Arc<Shape>> {
+        let mut shapes = Vec::new();
+        for object in &self.objects {
+            shapes.push(object);
+            shapes.extend(object.descendants());
+        }
+        shapes
+    }
+
+    // The box around every object, in world space, or nothing for an empty
+    // world. A plane anywhere makes it unbounded.
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.objects
+            .iter()
+            .map(|object| object.bounds().transformed(&object.transform()))
+            .fold(None, |total: Option<Bounds>, bounds| match total {
+                Some(total) => Some(total.union(&bounds)),
+                None => Some(bounds),
+            })
+    }
+
+    pub fn find(&self, query: &ShapeQuery) -> Vec<&Arc<Shape>> {
+        self.shapes()
+            .into_iter()
+            .filter(|shape| query.matches(shape))
+            .collect()
+    }
+
+    // Moves each matching shape by `transform`, on top of the transform it
+    // already has. A matching group carries its children along.
+    pub fn transform_shapes(
+        &mut self,
+        query: &ShapeQuery,
+        transform: Matrix4,
+    ) -> Result<(), Error> {
+        transform.try_inverse()?;
+        self.edit(&|shape| query.matches(shape), &|shape| {
+            shape
+                .transform_by(&transform)
+                .expect("checked for an inverse above")
+        });
+        Ok(())
+    }
+
+    // Gives each matching shape, and every shape in a matching group, a copy
+    // of `material`.
+    pub fn set_material(&mut self, query: &ShapeQuery, material: &Material) {
+        self.edit(&|shape| query.matches_within(shape), &|shape| {
+            shape.material = material.clone()
+        });
+    }
+
+    fn edit<P, F>(&mut self, should_edit: &P, edit: &F)
+    where
+        P: Fn(&Shape) -> bool,
+        F: Fn(&mut Shape),
+    {
+        for object in &mut self.objects {
+            *object = Arc::new(object.edited(should_edit, edit).with_parent(None));
+        }
+        self.tree = None;
+    }
+
+    // The inverse of from_json, for saving a world built in code.
+    pub fn to_json(&self, camera: &Camera) -> Result<String, SceneError> {
+        let description = SceneDescription::from_world(self, camera)?;
+        Ok(serde_json::to_string_pretty(&description)?)
+    }
+
+    pub fn shade_hit<D: Into<Depth>>(&self, precompute: Precompute, depth: D) -> Color {
+        self.shading(precompute, depth).color
+    }
+
+    // The shading of the surface hit, part by part.
+    pub fn shading<D: Into<Depth>>(&self, hit: Precompute, depth: D) -> ShadingResult {
+        let depth = depth.into();
+        let precompute = &hit;
+        let light_visibility = if self.light_source.illuminates(&precompute.object) {
+            self.light_visibility(&precompute.over_point)
+        } else {
+            0.
+        };
+        let ambient_visibility = match self.ambient_occlusion {
+            Some(ref ao) => ao.visibility(self, &precompute.over_point, &precompute.normalv),
+            None => 1.,
+        };
+        let direct = precompute.material().for_side(precompute.inside).lighting(
+            &*self.light_source,
             &precompute.point,
             &precompute.eyev,
             &precompute.normalv,
-            is_shadowed,
+            light_visibility,
+            ambient_visibility,
+            &precompute.surface_hit(),
             &precompute.object,
         );
 
-        let reflected_color = self.reflected_color(&precompute, remaining);
-        let refracted_color = self.refracted_color(&precompute, remaining);
+        let environment_color = match self.environment {
+            Some(ref environment) if environment.samples > 0 => {
+                let material = precompute.material();
+                let irradiance =
+                    environment.irradiance(self, &precompute.over_point, &precompute.normalv);
+                material
+                    .color_at_hit(
+                        &precompute.object,
+                        &precompute.point,
+                        &precompute.surface_hit(),
+                    )
+                    .hadamard_product(&irradiance)
+                    .multiply_scalar(material.diffuse)
+            }
+            _ => Color::black(),
+        };
 
-        if precompute.object.material.transparency > 0.
-            && precompute.object.material.reflective > 0.
-        {
-            let reflectance = Intersection::schlick(&precompute);
-            surface_color
-                .add(&reflected_color.multiply_scalar(reflectance))
-                .add(&refracted_color.multiply_scalar(1. - reflectance))
-        } else {
-            surface_color.add(&reflected_color).add(&refracted_color)
+        let mut reflection = self.reflected_color(precompute, depth);
+        let mut refraction = self.refracted_color(precompute, depth);
+
+        let transparency = precompute
+            .material()
+            .transparency
+            .at(&precompute.object, &precompute.point);
+        let reflective = precompute
+            .material()
+            .reflective
+            .at(&precompute.object, &precompute.point);
+        if transparency > 0. && reflective > 0. {
+            let reflectance = Intersection::schlick(precompute);
+            reflection = reflection.multiply_scalar(reflectance);
+            refraction = refraction.multiply_scalar(1. - reflectance);
         }
+
+        let mut result = ShadingResult {
+            color: Color::black(),
+            direct,
+            hit: None,
+            indirect: environment_color,
+            reflection,
+            refraction,
+        };
+        let clearcoat_reflectance = precompute
+            .material()
+            .clearcoat_reflectance(&precompute.eyev, &precompute.normalv);
+        if clearcoat_reflectance > 0. {
+            let under = 1. - clearcoat_reflectance;
+            result.direct = result
+                .direct
+                .multiply_scalar(under)
+                .add(&self.clearcoat_highlight(precompute, light_visibility));
+            result.indirect = result.indirect.multiply_scalar(under);
+            result.reflection = result
+                .reflection
+                .multiply_scalar(under)
+                .add(&self.clearcoat_reflection(precompute, depth));
+            result.refraction = result.refraction.multiply_scalar(under);
+        }
+        result.color = result.direct.add(&result.bounced());
+        result.hit = Some(hit);
+        result
     }
 
-    pub fn color_at(&self, ray: &Ray, remaining: i32) -> Color {
-        let hits = ray.intersect_world(&self);
-        if hits.is_empty() {
-            Color::black()
-        } else {
-            self.shade_hit(hits[0].precompute(&ray, hits.clone()), remaining)
+    // The highlight and reflection of the clear lacquer on top of the
+    // material, weighted by its reflectance.
+    pub fn clearcoat_color<D: Into<Depth>>(
+        &self,
+        precompute: &Precompute,
+        light_visibility: f64,
+        depth: D,
+    ) -> Color {
+        self.clearcoat_highlight(precompute, light_visibility)
+            .add(&self.clearcoat_reflection(precompute, depth.into()))
+    }
+
+    fn clearcoat_highlight(&self, precompute: &Precompute, light_visibility: f64) -> Color {
+        let material = precompute.material();
+        let reflectance = material.clearcoat_reflectance(&precompute.eyev, &precompute.normalv);
+        if reflectance == 0. || light_visibility == 0. {
+            return Color::black();
+        }
+        let lightv = self.light_source.direction_from(&precompute.point);
+        let reflect_dot_eye = lightv
+            .multiply_scalar(-1.)
+            .reflect(&precompute.normalv)
+            .dot(&precompute.eyev);
+        if reflect_dot_eye <= 0. {
+            return Color::black();
         }
+        self.light_source
+            .intensity_at(&precompute.point)
+            .multiply_scalar(reflect_dot_eye.powf(material.clearcoat_shininess()))
+            .multiply_scalar(light_visibility)
+            .multiply_scalar(reflectance)
     }
 
-    pub fn refracted_color(&self, precompute: &Precompute, remaining: i32) -> Color {
-        if remaining == 0 {
+    fn clearcoat_reflection(&self, precompute: &Precompute, depth: Depth) -> Color {
+        let material = precompute.material();
+        let reflectance = material.clearcoat_reflectance(&precompute.eyev, &precompute.normalv);
+        if reflectance == 0. || !self.can_reflect(depth) {
             return Color::black();
         }
-        if precompute.object.material.transparency == 0. {
+        let mut direction = precompute
+            .reflectv
+            .add(&random_in_unit_sphere().multiply_scalar(material.clearcoat_roughness))
+            .normalize();
+        if direction.dot(&precompute.normalv) <= 0. {
+            direction = precompute.reflectv;
+        }
+        let ray = Ray {
+            origin: precompute.over_point,
+            direction,
+        };
+        self.color_at(&ray, depth.reflected())
+            .multiply_scalar(reflectance)
+    }
+
+    // The color seen along a camera ray, by whichever integrator is set.
+    pub fn radiance(&self, ray: &Ray, remaining: i32) -> Color {
+        self.radiance_between(ray, remaining, 0., f64::INFINITY)
+    }
+
+    // As `radiance`, seeing only the surfaces between `near` and `far` along
+    // the ray. What's seen from those surfaces isn't cut away.
+    pub fn radiance_between(&self, ray: &Ray, remaining: i32, near: f64, far: f64) -> Color {
+        if self.render_mode == RenderMode::Heat {
+            let before = intersection_tests();
+            self.integrate(ray, remaining, near, far);
+            let tests = (intersection_tests() - before) as f64;
+            return Color::new(tests, tests, tests);
+        }
+        if let Some(color) = self.diagnostic_color(ray, near, far) {
+            return color;
+        }
+        self.integrate(ray, remaining, near, far)
+    }
+
+    fn integrate(&self, ray: &Ray, remaining: i32, near: f64, far: f64) -> Color {
+        match self.integrator {
+            Integrator::Bidirectional => {
+                bidirectional::radiance_between(self, ray, remaining, near, far)
+            }
+            Integrator::Path => path_tracer::radiance_between(self, ray, remaining, near, far),
+            Integrator::Whitted => self.shading_between(ray, remaining, near, far).color,
+        }
+    }
+
+    // The false color for the render mode, or nothing when shading.
+    fn diagnostic_color(&self, ray: &Ray, near: f64, far: f64) -> Option<Color> {
+        let hit = || self.hit_between(ray, near, far);
+        let black = Color::black();
+        match self.render_mode {
+            RenderMode::Depth => Some(hit().map_or(black, |hit| Color::new(hit.t, hit.t, hit.t))),
+            RenderMode::Normals => Some(hit().map_or(black, |hit| {
+                let normal = if hit.inside {
+                    hit.normalv.multiply_scalar(-1.)
+                } else {
+                    hit.normalv
+                };
+                Color::new(
+                    (normal.x + 1.) / 2.,
+                    (normal.y + 1.) / 2.,
+                    (normal.z + 1.) / 2.,
+                )
+            })),
+            RenderMode::ObjectId => Some(
+                hit()
+                    .and_then(|hit| self.object_id(&hit.object))
+                    .map_or(black, id_color),
+            ),
+            RenderMode::Heat | RenderMode::Shaded => None,
+            RenderMode::Uv => Some(hit().map_or(black, |hit| {
+                let (u, v) = hit.object.uv_at(&hit.point);
+                Color::new(u, v, 0.)
+            })),
+        }
+    }
+
+    pub fn color_at<D: Into<Depth>>(&self, ray: &Ray, depth: D) -> Color {
+        self.shading_at(ray, depth).color
+    }
+
+    // As `color_at`, but keeping the parts of the shading and the hit.
+    pub fn shading_at<D: Into<Depth>>(&self, ray: &Ray, depth: D) -> ShadingResult {
+        self.shading_between(ray, depth, 0., f64::INFINITY)
+    }
+
+    // As `shading_at`, seeing only the surfaces, and volumes, between `near`
+    // and `far` along the ray.
+    fn shading_between<D: Into<Depth>>(
+        &self,
+        ray: &Ray,
+        depth: D,
+        near: f64,
+        far: f64,
+    ) -> ShadingResult {
+        let hits = self.hits_between(ray, near, far);
+        let mut result = match self.first_hit(ray, &hits) {
+            Some(precompute) => self.shading(precompute, depth),
+            None => {
+                let background = self.background_color(ray);
+                ShadingResult {
+                    color: background,
+                    direct: Color::black(),
+                    hit: None,
+                    indirect: background,
+                    reflection: Color::black(),
+                    refraction: Color::black(),
+                }
+            }
+        };
+        let distance = result.hit.as_ref().map_or(f64::INFINITY, |hit| hit.t);
+        // Nearer volumes go over what's seen through the further ones.
+        let mut segments = volume::segments(&hits, distance);
+        segments.sort_by(|a, b| b.from.partial_cmp(&a.from).unwrap());
+        let color = segments.iter().fold(result.color, |color, segment| {
+            segment
+                .volume
+                .apply(self, ray, segment.from, segment.to, &color)
+        });
+        result.color = match self.fog {
+            Some(ref fog) if fog.horizon => Fog {
+                color: self.background_color(ray),
+                ..*fog
+            }
+            .apply(ray, distance, &color)
+            .add(&fog.scattered(self, ray, distance)),
+            Some(ref fog) => fog
+                .apply(ray, distance, &color)
+                .add(&fog.scattered(self, ray, distance)),
+            None => color,
+        };
+        result
+    }
+
+    // The first surface along the ray, skipping the insides of culled ones
+    // and the bounds of volumes.
+    pub fn hit(&self, ray: &Ray) -> Option<Precompute> {
+        self.first_hit(ray, &ray.intersect_world(self))
+    }
+
+    // As `hit`, seeing only the surfaces between `near` and `far`.
+    pub fn hit_between(&self, ray: &Ray, near: f64, far: f64) -> Option<Precompute> {
+        self.first_hit(ray, &self.hits_between(ray, near, far))
+    }
+
+    fn hits_between(&self, ray: &Ray, near: f64, far: f64) -> Vec<Intersection> {
+        let mut hits = ray.intersect_world(self);
+        hits.retain(|hit| (near..=far).contains(&hit.t));
+        hits
+    }
+
+    fn first_hit(&self, ray: &Ray, hits: &[Intersection]) -> Option<Precompute> {
+        for hit in hits {
+            if volume_of(hit).is_some() {
+                continue;
+            }
+            let precompute = hit.precompute_with_bias(ray, hits.to_vec(), &self.bias);
+            if precompute.inside && precompute.material().backface == Backface::Cull {
+                continue;
+            }
+            return Some(precompute);
+        }
+        None
+    }
+
+    pub fn background_color(&self, ray: &Ray) -> Color {
+        match self.environment {
+            Some(ref environment) => environment.color_in(&ray.direction),
+            // The sun is infinitely far away, so it stands in the light's
+            // direction from the world origin wherever the ray starts.
+            None => self.background.color_in(
+                &ray.direction,
+                &self.light_source.position().sub(&point(0., 0., 0.)),
+            ),
+        }
+    }
+
+    // What lies behind the first visible hit along the ray, pass by pass.
+    pub fn surface_sample(&self, ray: &Ray, remaining: i32) -> SurfaceSample {
+        if let Some(precompute) = self.hit(ray) {
+            let albedo = precompute.material().color_at_hit(
+                &precompute.object,
+                &precompute.point,
+                &precompute.surface_hit(),
+            );
+            let depth = precompute.t;
+            let normal = precompute.normalv;
+            let object_id = self.object_id(&precompute.object);
+            let shading = self.shading(precompute, remaining);
+            return SurfaceSample {
+                albedo,
+                depth,
+                direct: shading.direct,
+                indirect: shading.bounced(),
+                normal,
+                object_id,
+            };
+        }
+        SurfaceSample {
+            albedo: Color::black(),
+            depth: 0.,
+            direct: Color::black(),
+            indirect: self.background_color(ray),
+            normal: vector(0., 0., 0.),
+            object_id: None,
+        }
+    }
+
+    // The index in `objects` of the shape, or of the group containing it.
+    pub fn object_id(&self, shape: &Shape) -> Option<usize> {
+        let mut root = shape;
+        while let Some(ref parent) = root.parent {
+            root = parent;
+        }
+        self.objects
+            .iter()
+            .position(|object| std::ptr::eq(&**object, root))
+    }
+
+    pub fn refracted_color<D: Into<Depth>>(&self, precompute: &Precompute, depth: D) -> Color {
+        let depth = depth.into();
+        if !self.can_refract(depth) {
+            return Color::black();
+        }
+        let transparency = precompute
+            .material()
+            .transparency
+            .at(&precompute.object, &precompute.point);
+        if transparency == 0. {
             return Color::black();
         }
 
@@ -113,128 +694,787 @@ impl World {
                 origin: precompute.under_point,
                 direction,
             },
-            remaining - 1,
+            depth.refracted(),
         )
-        .multiply_scalar(precompute.object.material.transparency)
+        .multiply_scalar(transparency)
+    }
+
+    fn can_reflect(&self, depth: Depth) -> bool {
+        depth.remaining > 0
+            && self
+                .reflection_depth
+                .is_none_or(|limit| depth.reflections < limit)
+    }
+
+    fn can_refract(&self, depth: Depth) -> bool {
+        depth.remaining > 0
+            && self
+                .refraction_depth
+                .is_none_or(|limit| depth.refractions < limit)
+    }
+
+    // How much of the light reaches the point, averaged over the light's
+    // sample points.
+    pub fn light_visibility(&self, point: &Point) -> f64 {
+        let samples = self.light_source.sample_points();
+        let visible: f64 = samples
+            .iter()
+            .map(|light_point| self.transmittance_from(point, light_point))
+            .sum();
+        visible / samples.len() as f64
     }
 
     pub fn is_shadowed(&self, point: &Point) -> bool {
-        let from_object_to_light_source = self.light_source.position.sub(&point);
+        self.light_transmittance(point) == 0.
+    }
+
+    // The fraction of the light from the light's center that reaches the
+    // point, which volumes in the way only dim.
+    pub fn light_transmittance(&self, point: &Point) -> f64 {
+        self.transmittance_from(point, &self.light_source.position())
+    }
+
+    pub fn transmittance_from(&self, point: &Point, light_point: &Point) -> f64 {
+        let from_object_to_light_source = light_point.sub(point);
         let distance = from_object_to_light_source.magnitude();
         let ray = Ray {
             direction: from_object_to_light_source.normalize(),
             origin: *point,
         };
-        match Intersection::hit(&mut ray.intersect_world(self)) {
-            Some(hit) => hit.t < distance,
-            None => false,
+        let mut hits = ray.intersect_world(self);
+        hits.retain(|hit| self.light_source.casts_shadow(&hit.object));
+        if hits
+            .iter()
+            .any(|hit| hit.t < distance && volume_of(hit).is_none())
+        {
+            return 0.;
         }
+        volume::segments(&hits, distance)
+            .iter()
+            .map(|segment| segment.volume.transmittance(segment.to - segment.from))
+            .product()
     }
 
-    pub fn reflected_color(&self, precompute: &Precompute, remaining: i32) -> Color {
-        if precompute.object.material.reflective == 0.0 || remaining == 0 {
+    pub fn reflected_color<D: Into<Depth>>(&self, precompute: &Precompute, depth: D) -> Color {
+        let depth = depth.into();
+        let reflective = precompute
+            .material()
+            .reflective
+            .at(&precompute.object, &precompute.point);
+        if reflective == 0.0 || !self.can_reflect(depth) {
             Color::black()
         } else {
             let ray = Ray {
                 origin: precompute.over_point,
                 direction: precompute.reflectv,
             };
-            let color = self.color_at(&ray, remaining - 1);
-            color.multiply_scalar(precompute.object.material.reflective)
+            let color = self.color_at(&ray, depth.reflected());
+            color.multiply_scalar(reflective)
+        }
+    }
+}
+
+impl Default for World {
+    fn default() -> World {
+        World::new()
+    }
+}
+
+// A one line summary, for telling at a glance whether a scene loaded as
+// expected.
+impl fmt::Display for World {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let objects = self.objects.len();
+        let shapes = self.shapes().len();
+        write!(
+            f,
+            "{} object{} ({} shape{} in all), 1 light",
+            objects,
+            if objects == 1 { "" } else { "s" },
+            shapes,
+            if shapes == 1 { "" } else { "s" },
+        )?;
+        match self.bounds() {
+            Some(bounds) => write!(f, ", bounds {}", bounds),
+            None => Ok(()),
+        }
+    }
+}
+
+// Assembles a world piece by piece. Shapes can be handed over as they are
+// or already shared, and are shared from then on, as is the world built.
+pub struct WorldBuilder {
+    // Whether `light` has been called yet.
+    lit: bool,
+    world: World,
+}
+
+impl WorldBuilder {
+    pub fn ambient_occlusion(mut self, ambient_occlusion: AmbientOcclusion) -> WorldBuilder {
+        self.world.ambient_occlusion = Some(ambient_occlusion);
+        self
+    }
+
+    pub fn background(mut self, background: Background) -> WorldBuilder {
+        self.world.background = background;
+        self
+    }
+
+    pub fn bias(mut self, bias: Bias) -> WorldBuilder {
+        self.world.bias = bias;
+        self
+    }
+
+    pub fn environment(mut self, environment: Environment) -> WorldBuilder {
+        self.world.environment = Some(environment);
+        self
+    }
+
+    pub fn fog(mut self, fog: Fog) -> WorldBuilder {
+        self.world.fog = Some(fog);
+        self
+    }
+
+    pub fn integrator(mut self, integrator: Integrator) -> WorldBuilder {
+        self.world.integrator = integrator;
+        self
+    }
+
+    // A world has a single light, so this replaces the one `new` lights it
+    // with, and fails if called again rather than drop the first light.
+    pub fn light<L: Light>(mut self, light: L) -> Result<WorldBuilder, Error> {
+        if self.lit {
+            return Err(Error::ExtraLight);
+        }
+        self.lit = true;
+        self.world.light_source = Arc::new(light);
+        Ok(self)
+    }
+
+    // The depth limits and render mode of the settings; the rest are for
+    // the camera and the image.
+    pub fn settings(mut self, settings: &RenderSettings) -> WorldBuilder {
+        settings.apply_to_world(&mut self.world);
+        self
+    }
+
+    pub fn shape<S: Into<Arc<Shape>>>(mut self, shape: S) -> WorldBuilder {
+        self.world.objects.push(shape.into());
+        self
+    }
+
+    pub fn shapes<I>(mut self, shapes: I) -> WorldBuilder
+    where
+        I: IntoIterator,
+        I::Item: Into<Arc<Shape>>,
+    {
+        self.world
+            .objects
+            .extend(shapes.into_iter().map(|shape| shape.into()));
+        self
+    }
+
+    // Shared, so that nothing changes the world once it is built; renders
+    // and threads take it as it is, tree and all.
+    pub fn build(mut self) -> Arc<World> {
+        self.world.tree = Some(ObjectTree::new(&self.world.objects));
+        Arc::new(self.world)
+    }
+}
+
+// Objects per leaf of an `ObjectTree`, as with `LEAF_FACES` for meshes.
+const LEAF_OBJECTS: usize = 4;
+
+// A bounding volume hierarchy over the objects of a world, in world space,
+// so that a ray is only tested against the objects whose boxes it passes
+// through. Objects without bounds, like planes, are tested by every ray.
+#[derive(Debug)]
+pub struct ObjectTree {
+    root: Option<ObjectNode>,
+    unbounded: Vec<usize>,
+}
+
+#[derive(Debug)]
+enum ObjectNode {
+    Leaf(Bounds, Vec<usize>),
+    Node(Bounds, Box<ObjectNode>, Box<ObjectNode>),
+}
+
+impl ObjectTree {
+    pub fn new(objects: &[Arc<Shape>]) -> ObjectTree {
+        let mut bounded = Vec::new();
+        let mut unbounded = Vec::new();
+        for (index, object) in objects.iter().enumerate() {
+            let bounds = object.bounds().transformed(&object.transform());
+            let finite = [bounds.min, bounds.max]
+                .iter()
+                .all(|p| p.x.is_finite() && p.y.is_finite() && p.z.is_finite());
+            if finite {
+                bounded.push((index, padded(&bounds)));
+            } else {
+                unbounded.push(index);
+            }
+        }
+        ObjectTree {
+            root: if bounded.is_empty() {
+                None
+            } else {
+                Some(ObjectNode::build(bounded))
+            },
+            unbounded,
+        }
+    }
+
+    // Adds every object `ray` may hit, by index in the world's objects.
+    pub fn candidates(&self, ray: &Ray, objects: &mut Vec<usize>) {
+        objects.extend(&self.unbounded);
+        if let Some(ref root) = self.root {
+            root.candidates(ray, objects);
+        }
+        // Keeps the order of the world's objects, which ties are broken by.
+        objects.sort_unstable();
+    }
+}
+
+impl ObjectNode {
+    // Splits the objects in half by the centers of their boxes along the
+    // longest side of the box around them all, as `FaceTree` does faces.
+    fn build(mut objects: Vec<(usize, Bounds)>) -> ObjectNode {
+        let bounds = objects[1..]
+            .iter()
+            .fold(objects[0].1, |total, (_, bounds)| total.union(bounds));
+        if objects.len() <= LEAF_OBJECTS {
+            return ObjectNode::Leaf(bounds, objects.iter().map(|&(i, _)| i).collect());
+        }
+        let size = bounds.max.sub(&bounds.min);
+        let along = |p: &Point| {
+            if size.x >= size.y && size.x >= size.z {
+                p.x
+            } else if size.y >= size.z {
+                p.y
+            } else {
+                p.z
+            }
+        };
+        let center = |bounds: &Bounds| along(&bounds.min) + along(&bounds.max);
+        objects.sort_by(|a, b| center(&a.1).total_cmp(&center(&b.1)));
+        let right = objects.split_off(objects.len() / 2);
+        ObjectNode::Node(
+            bounds,
+            Box::new(ObjectNode::build(objects)),
+            Box::new(ObjectNode::build(right)),
+        )
+    }
+
+    fn candidates(&self, ray: &Ray, objects: &mut Vec<usize>) {
+        match *self {
+            ObjectNode::Leaf(ref bounds, ref indices) => {
+                if bounds.hits(ray) {
+                    objects.extend(indices);
+                }
+            }
+            ObjectNode::Node(ref bounds, ref left, ref right) => {
+                if bounds.hits(ray) {
+                    left.candidates(ray, objects);
+                    right.candidates(ray, objects);
+                }
+            }
         }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use color::Color;
-    use intersectable::Intersectable;
-    use intersection::Intersection;
-    use material::Material;
-    use matrix::Matrix4;
-    use matrix::IDENTITY_MATRIX;
-    use patternable::Patternable;
-    use point::point;
-    use point::vector;
-    use point_light::PointLight;
-    use ray::Ray;
-    use shape::Shape;
-    use std::sync::Arc;
-    use world::World;
+// So that a flat object, like a lone triangle, has a box a ray can enter.
+fn padded(bounds: &Bounds) -> Bounds {
+    Bounds::new(
+        bounds.min.x - EPSILON,
+        bounds.max.x + EPSILON,
+        bounds.min.y - EPSILON,
+        bounds.max.y + EPSILON,
+        bounds.min.z - EPSILON,
+        bounds.max.z + EPSILON,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use background::{Background, Sky};
+    use camera::Camera;
+    use color::Color;
+    use environment::Environment;
+    use error::Error;
+    use fog::Fog;
+    use gbuffer::id_color;
+    use intersectable::Intersectable;
+    use intersection::Bias;
+    use intersection::Intersection;
+    use light::Light;
+    use material::Backface;
+    use material::ChannelValue;
+    use material::Mask;
+    use material::Material;
+    use material::SpecularModel;
+    use matrix::Matrix4;
+    use patternable::Patternable;
+    use point::point;
+    use point::vector;
+    use point::Point;
+    use point_light::{LightLink, PointLight};
+    use ray::Ray;
+    use settings::RenderSettings;
+    use shape::{Shape, ShapeQuery};
+    use std::f64::consts::PI;
+    use std::sync::Arc;
+    use std::thread;
+    use texture::Texture;
+    use transformation_matrix::TransformationMatrix;
+    use utilities::EPSILON;
+    use world::{Depth, Integrator, RenderMode, World};
+
+    #[test]
+    fn test_default_world() {
+        let default_world = World::new();
+
+        assert_eq!(
+            default_world
+                .light_source
+                .intensity_at(&point(0.0, 0.0, 0.0)),
+            Color::new(1.0, 1.0, 1.0)
+        );
+        assert!(default_world
+            .light_source
+            .position()
+            .equal(&point(-10.0, 10.0, -10.0)));
+        assert_eq!(default_world.objects.len(), 2);
+        assert_eq!(default_world.bias, Bias::Fixed(EPSILON));
+    }
+
+    #[test]
+    fn test_shade_color() {
+        let default_world = World::new();
+        let r = Ray {
+            origin: point(0.0, 0.0, -5.0),
+            direction: vector(0.0, 0.0, 1.0),
+        };
+        let i = Intersection::new(4.0, default_world.objects[0].clone());
+        let comps = i.precompute(&r, Vec::new());
+        let c = default_world.shade_hit(comps, 10);
+
+        assert_eq!(c, Color::new(0.38066, 0.47583, 0.2855));
+    }
+
+    #[test]
+    fn test_shade_color_2() {
+        let mut world = World::new();
+        world.light_source = Arc::new(PointLight::new(
+            point(0.0, 0.25, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
+        let r = Ray {
+            origin: point(0.0, 0.0, 0.0),
+            direction: vector(0.0, 0.0, 1.0),
+        };
+        let i = Intersection::new(0.5, world.objects[1].clone());
+        let comps = i.precompute(&r, Vec::new());
+        let c = world.shade_hit(comps, 10);
+
+        assert_eq!(c, Color::new(0.904984472, 0.904984472, 0.904984472));
+    }
+
+    #[test]
+    fn test_world_color_at() {
+        let world = World::new();
+        let r = Ray {
+            origin: point(0.0, 0.0, -5.0),
+            direction: vector(0.0, 1.0, 0.0),
+        };
+
+        assert_eq!(world.color_at(&r, 10), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_world_color_at_2() {
+        let world = World::new();
+        let r = Ray {
+            origin: point(0.0, 0.0, -5.0),
+            direction: vector(0.0, 0.0, 1.0),
+        };
+
+        assert_eq!(world.color_at(&r, 10), Color::new(0.38066, 0.47583, 0.2855));
+    }
+
+    #[test]
+    fn test_world_color_at_culls_backfaces() {
+        let mut world = World::new();
+        Arc::get_mut(&mut world.objects[0])
+            .unwrap()
+            .material
+            .backface = Backface::Cull;
+        let r = Ray {
+            origin: point(0.0, 0.0, 0.75),
+            direction: vector(0.0, 0.0, 1.0),
+        };
+
+        assert_eq!(world.color_at(&r, 10), Color::black());
+
+        let r = Ray {
+            origin: point(0.0, 0.0, -5.0),
+            direction: vector(0.0, 0.0, 1.0),
+        };
+
+        assert_eq!(world.color_at(&r, 10), Color::new(0.38066, 0.47583, 0.2855));
+    }
+
+    #[test]
+    fn test_world_color_at_background() {
+        let mut world = World::new();
+        world.background = Background::Gradient(Color::white(), Color::new(0.0, 0.0, 1.0));
+        let r = Ray {
+            origin: point(0.0, 0.0, -5.0),
+            direction: vector(0.0, 1.0, 0.0),
+        };
+
+        assert_eq!(world.color_at(&r, 10), Color::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_sun_stays_put_as_the_ray_origin_moves() {
+        let mut world = World::new();
+        world.background = Background::Sky(Sky::new());
+        world.light_source = Arc::new(PointLight::new(point(0., 10., 0.), Color::white()));
+        let r = Ray {
+            origin: point(0.0, 0.0, -5.0),
+            direction: vector(0.0, 1.0, 0.0),
+        };
+
+        assert_eq!(
+            world.background_color(&r),
+            Sky::new().zenith.add(&Sky::new().sun)
+        );
+    }
+
+    #[test]
+    fn test_diagnostic_render_modes() {
+        let mut world = World::new();
+        let r = Ray {
+            origin: point(0.0, 0.0, -5.0),
+            direction: vector(0.0, 0.0, 1.0),
+        };
+
+        world.render_mode = RenderMode::Depth;
+        assert_eq!(world.radiance(&r, 5), Color::new(4.0, 4.0, 4.0));
+        world.render_mode = RenderMode::Normals;
+        assert_eq!(world.radiance(&r, 5), Color::new(0.5, 0.5, 0.0));
+        world.render_mode = RenderMode::ObjectId;
+        assert_eq!(world.radiance(&r, 5), id_color(0));
+        world.render_mode = RenderMode::Uv;
+        let (u, v) = world.objects[0].uv_at(&point(0.0, 0.0, -1.0));
+        assert_eq!(world.radiance(&r, 5), Color::new(u, v, 0.0));
+
+        let miss = Ray {
+            origin: point(0.0, 0.0, -5.0),
+            direction: vector(0.0, 1.0, 0.0),
+        };
+        assert_eq!(world.radiance(&miss, 5), Color::black());
+
+        // A miss only tests the two spheres; a hit also casts a shadow ray.
+        world.render_mode = RenderMode::Heat;
+        assert_eq!(world.radiance(&miss, 5), Color::new(2.0, 2.0, 2.0));
+        assert_eq!(world.radiance(&r, 5), Color::new(4.0, 4.0, 4.0));
+    }
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    // Cameras render a world from many threads at once, which only works
+    // while nothing in it holds state that can't be shared.
+    #[test]
+    fn test_world_is_send_and_sync() {
+        assert_send_sync::<World>();
+        assert_send_sync::<Shape>();
+        assert_send_sync::<Patternable>();
+        assert_send_sync::<Material>();
+        assert_send_sync::<Camera>();
+
+        let world = Arc::new(World::new());
+        let mut camera = Camera::new(8, 6, PI / 3.);
+        camera
+            .set_transform(TransformationMatrix::new(
+                &point(0., 0., -5.),
+                &point(0., 0., 0.),
+                &vector(0., 1., 0.),
+            ))
+            .unwrap();
+        let camera = Arc::new(camera);
+        let expected = camera.render(&world).pixels;
+
+        let renders: Vec<_> = (0..2)
+            .map(|_| {
+                let world = world.clone();
+                let camera = camera.clone();
+                thread::spawn(move || camera.render(&world).pixels)
+            })
+            .collect();
+        for render in renders {
+            assert_eq!(render.join().unwrap(), expected);
+        }
+    }
+
+    // A light of the crate's user's own, red and without a surface.
+    struct RedLight;
+
+    impl Light for RedLight {
+        fn position(&self) -> Point {
+            point(-10., 10., -10.)
+        }
+
+        fn sample_points(&self) -> Vec<Point> {
+            vec![self.position()]
+        }
+
+        fn intensity_at(&self, _point: &Point) -> Color {
+            Color::new(1., 0., 0.)
+        }
+
+        fn direction_from(&self, point: &Point) -> Point {
+            self.position().sub(point).normalize()
+        }
+    }
+
+    #[test]
+    fn test_render_with_custom_light() {
+        let mut camera = Camera::new(5, 5, PI / 3.);
+        camera
+            .set_transform(TransformationMatrix::new(
+                &point(0., 0., -5.),
+                &point(0., 0., 0.),
+                &vector(0., 1., 0.),
+            ))
+            .unwrap();
+        for &integrator in &[
+            Integrator::Whitted,
+            Integrator::Path,
+            Integrator::Bidirectional,
+        ] {
+            let world = World::builder()
+                .shape(Shape::sphere())
+                .light(RedLight)
+                .unwrap()
+                .integrator(integrator)
+                .build();
+            let center = camera.render(&world).pixel_at(2, 2);
+
+            assert!(center.red > 0.);
+            assert_eq!((center.green, center.blue), (0., 0.));
+        }
+    }
+
+    #[test]
+    fn test_world_display() {
+        let mut world = World::new();
+
+        assert_eq!(
+            world.to_string(),
+            "2 objects (2 shapes in all), 1 light, bounds (-1, -1, -1) to (1, 1, 1)"
+        );
+
+        let mut group = Shape::new(Intersectable::group());
+        group
+            .set_transform(Matrix4::translation(3., 0., 0.))
+            .unwrap();
+        group.add_child(Shape::sphere()).unwrap();
+        world.objects = vec![group.share()];
+        assert_eq!(
+            world.to_string(),
+            "1 object (2 shapes in all), 1 light, bounds (2, -1, -1) to (4, 1, 1)"
+        );
+
+        world.objects = Vec::new();
+        assert_eq!(world.to_string(), "0 objects (0 shapes in all), 1 light");
+    }
+
+    #[test]
+    fn test_world_builder() {
+        let light = PointLight::new(point(0.0, 10.0, 0.0), Color::white());
+        let mut sphere = Shape::new(Intersectable::sphere());
+        sphere.material = Material::builder()
+            .color(Color::new(1.0, 0.0, 0.0))
+            .build()
+            .unwrap();
+        let settings = RenderSettings {
+            mode: Some(RenderMode::Normals),
+            reflection_depth: Some(2),
+            ..RenderSettings::default()
+        };
+
+        let world = World::builder()
+            .shape(sphere)
+            .shapes(vec![Shape::plane(), Shape::cube()])
+            .light(light)
+            .unwrap()
+            .background(Background::Solid(Color::white()))
+            .integrator(Integrator::Path)
+            .settings(&settings)
+            .build();
+
+        assert_eq!(world.objects.len(), 3);
+        assert_eq!(
+            world.objects[0]
+                .material
+                .color_at(&world.objects[0], &point(0.0, 0.0, 0.0)),
+            Color::new(1.0, 0.0, 0.0)
+        );
+        assert_eq!(world.light_source.position(), point(0.0, 10.0, 0.0));
+        assert_eq!(world.integrator, Integrator::Path);
+        assert_eq!(world.render_mode, RenderMode::Normals);
+        assert_eq!(world.reflection_depth, Some(2));
+        assert!(World::builder().build().objects.is_empty());
+    }
+
+    #[test]
+    fn test_world_builder_with_second_light() {
+        let light = PointLight::new(point(0.0, 10.0, 0.0), Color::white());
+
+        match World::builder().light(light.clone()).unwrap().light(light) {
+            Err(Error::ExtraLight) => (),
+            _ => panic!("expected a second light to be refused"),
+        }
+    }
+
+    #[test]
+    fn test_built_world_tests_only_objects_along_the_ray() {
+        let spheres = (0..20).map(|i| {
+            let mut sphere = Shape::new(Intersectable::sphere());
+            sphere
+                .set_transform(Matrix4::translation(3. * i as f64, 0., 0.))
+                .unwrap();
+            sphere
+        });
+        let world = World::builder()
+            .shapes(spheres)
+            .shape(Shape::plane())
+            .build();
+        let ray = Ray {
+            origin: point(30., 0., -5.),
+            direction: vector(0., 0., 1.),
+        };
+
+        let candidates = world.objects_along(&ray);
+        let xs = ray.intersect_world(&world);
+
+        assert!(candidates.len() < 10);
+        assert!(Arc::ptr_eq(candidates[0], &world.objects[10]));
+        assert!(Arc::ptr_eq(&xs[0].object, &world.objects[10]));
+        assert_eq!(xs[0].t, 4.);
+        assert_eq!(xs.len(), 2);
+    }
 
     #[test]
-    fn test_default_world() {
-        let default_world = World::new();
+    fn test_radiance_between() {
+        let mut world = World::new();
+        world.background = Background::Solid(Color::new(0.2, 0.4, 0.6));
+        let r = Ray {
+            origin: point(0.0, 0.0, -5.0),
+            direction: vector(0.0, 0.0, 1.0),
+        };
 
         assert_eq!(
-            default_world.light_source.intensity,
-            Color::new(1.0, 1.0, 1.0)
+            world.radiance_between(&r, 5, 0.0, f64::INFINITY),
+            world.radiance(&r, 5)
+        );
+        assert_eq!(
+            world.radiance_between(&r, 5, 0.0, 3.0),
+            Color::new(0.2, 0.4, 0.6)
+        );
+        // Past the outer sphere, the inner one shows.
+        world.render_mode = RenderMode::ObjectId;
+        assert_eq!(world.radiance_between(&r, 5, 4.25, 10.0), id_color(1));
+        assert_eq!(world.radiance_between(&r, 5, 0.0, 3.0), Color::black());
+        // Depth is still measured from the ray's own origin.
+        world.render_mode = RenderMode::Depth;
+        assert_eq!(
+            world.radiance_between(&r, 5, 4.25, 10.0),
+            Color::new(4.5, 4.5, 4.5)
         );
-        assert!(default_world
-            .light_source
-            .position
-            .equal(&point(-10.0, 10.0, -10.0)));
-        assert_eq!(default_world.objects.len(), 2);
     }
 
     #[test]
-    fn test_shade_color() {
-        let default_world = World::new();
+    fn test_world_color_at_with_fog() {
+        let mut world = World::new();
+        // Half the light gets through the four units to the sphere.
+        world.fog = Some(Fog::new(Color::white(), 2_f64.ln() / 4.));
         let r = Ray {
             origin: point(0.0, 0.0, -5.0),
             direction: vector(0.0, 0.0, 1.0),
         };
-        let i = Intersection {
-            object: default_world.objects[0].clone(),
-            t: 4.0,
+
+        assert_eq!(
+            world.color_at(&r, 10),
+            Color::new(0.69033, 0.737915, 0.64275)
+        );
+
+        let r = Ray {
+            origin: point(0.0, 0.0, -5.0),
+            direction: vector(0.0, 1.0, 0.0),
         };
-        let comps = i.precompute(&r, Vec::new());
-        let c = default_world.shade_hit(comps, 10);
 
-        assert_eq!(c, Color::new(0.38066, 0.47583, 0.2855));
+        assert_eq!(world.color_at(&r, 10), Color::white());
     }
 
     #[test]
-    fn test_shade_color_2() {
+    fn test_world_color_at_with_horizon_fog() {
         let mut world = World::new();
-        world.light_source = PointLight {
-            position: point(0.0, 0.25, 0.0),
-            intensity: Color::new(1.0, 1.0, 1.0),
-        };
+        world.background = Background::Solid(Color::new(0.2, 0.4, 0.6));
+        let mut fog = Fog::new(Color::white(), 2_f64.ln() / 4.);
+        fog.horizon = true;
+        world.fog = Some(fog);
         let r = Ray {
-            origin: point(0.0, 0.0, 0.0),
+            origin: point(0.0, 0.0, -5.0),
             direction: vector(0.0, 0.0, 1.0),
         };
-        let i = Intersection {
-            object: world.objects[1].clone(),
-            t: 0.5,
-        };
-        let comps = i.precompute(&r, Vec::new());
-        let c = world.shade_hit(comps, 10);
 
-        assert_eq!(c, Color::new(0.904984472, 0.904984472, 0.904984472));
+        assert_eq!(
+            world.color_at(&r, 10),
+            Color::new(0.29033, 0.437915, 0.44275)
+        );
     }
 
     #[test]
-    fn test_world_color_at() {
-        let world = World::new();
+    fn test_world_color_at_environment() {
+        let mut world = World::new();
+        world.environment = Some(Environment::new(Texture::new(
+            1,
+            1,
+            vec![Color::new(0.2, 0.4, 0.6)],
+        )));
         let r = Ray {
             origin: point(0.0, 0.0, -5.0),
             direction: vector(0.0, 1.0, 0.0),
         };
 
-        assert_eq!(world.color_at(&r, 10), Color::new(0.0, 0.0, 0.0));
+        assert_eq!(world.color_at(&r, 10), Color::new(0.2, 0.4, 0.6));
     }
 
     #[test]
-    fn test_world_color_at_2() {
-        let world = World::new();
+    fn test_shade_hit_with_environment_lighting() {
+        let mut world = World::new();
+        world.objects.truncate(1);
+        let mut environment = Environment::new(Texture::new(1, 1, vec![Color::white()]));
+        environment.samples = 8;
+        world.environment = Some(environment);
+        let mut light = PointLight::new(point(-10.0, 10.0, -10.0), Color::white());
+        light.illuminates = LightLink::Include(Vec::new());
+        world.light_source = Arc::new(light);
         let r = Ray {
             origin: point(0.0, 0.0, -5.0),
             direction: vector(0.0, 0.0, 1.0),
         };
+        let i = Intersection::new(4.0, world.objects[0].clone());
+        let comps = i.precompute(&r, Vec::new());
 
-        assert_eq!(world.color_at(&r, 10), Color::new(0.38066, 0.47583, 0.2855));
+        // Ambient plus the full diffuse response to a white sky.
+        assert_eq!(
+            world.shade_hit(comps, 10),
+            Color::new(0.08 + 0.56, 0.1 + 0.7, 0.06 + 0.42)
+        );
     }
 
     #[test]
@@ -269,6 +1509,90 @@ mod tests {
         assert!(!world.is_shadowed(&point));
     }
 
+    #[test]
+    fn test_world_light_visibility_with_spherical_light() {
+        let mut world = World::new();
+        let mut occluder = Shape::sphere();
+        Arc::get_mut(&mut occluder)
+            .unwrap()
+            .set_transform(Matrix4::translation(0.0, 5.0, 0.0))
+            .unwrap();
+        world.objects = vec![occluder];
+        let mut light = PointLight::new(point(0.0, 10.0, 0.0), Color::white());
+        world.light_source = Arc::new(light.clone());
+
+        assert_eq!(world.light_visibility(&point(0.0, 0.0, 0.0)), 0.0);
+        assert_eq!(world.light_visibility(&point(5.0, 0.0, 0.0)), 1.0);
+
+        light.radius = 3.0;
+        light.samples = 64;
+        world.light_source = Arc::new(light);
+        let visibility = world.light_visibility(&point(0.0, 0.0, 0.0));
+
+        assert!(visibility > 0.0 && visibility < 1.0);
+    }
+
+    #[test]
+    fn test_world_respects_masks() {
+        let mut world = World::new();
+        let mut plane = Shape::plane();
+        Arc::get_mut(&mut plane)
+            .unwrap()
+            .set_transform(Matrix4::translation(0.0, 5.0, 0.0))
+            .unwrap();
+        Arc::get_mut(&mut plane).unwrap().material.mask = Some(Mask::Pattern(Patternable::stripe(
+            Color::white(),
+            Color::black(),
+        )));
+        world.objects = vec![plane];
+        world.light_source = Arc::new(PointLight::new(point(1.0, 10.0, 0.0), Color::white()));
+
+        assert!(world.is_shadowed(&point(0.5, 0.0, 0.0)));
+        assert!(!world.is_shadowed(&point(1.5, 0.0, 0.0)));
+
+        let r = Ray {
+            origin: point(1.5, 0.0, 0.0),
+            direction: vector(0.0, 1.0, 0.0),
+        };
+
+        assert_eq!(world.color_at(&r, 10), Color::black());
+    }
+
+    #[test]
+    fn test_world_in_shadow_with_light_link() {
+        let mut world = World::new();
+        Arc::get_mut(&mut world.objects[0]).unwrap().name = Some(String::from("outer"));
+        Arc::get_mut(&mut world.objects[1]).unwrap().name = Some(String::from("inner"));
+        let mut light = PointLight::new(point(-10.0, 10.0, -10.0), Color::white());
+        light.shadows = LightLink::Exclude(vec![String::from("outer")]);
+        world.light_source = Arc::new(light.clone());
+        let point = point(10.0, -10.0, 10.0);
+
+        assert!(world.is_shadowed(&point));
+
+        light.shadows = LightLink::Exclude(vec![String::from("outer"), String::from("inner")]);
+        world.light_source = Arc::new(light);
+
+        assert!(!world.is_shadowed(&point));
+    }
+
+    #[test]
+    fn test_shade_hit_with_light_link() {
+        let mut world = World::new();
+        Arc::get_mut(&mut world.objects[0]).unwrap().name = Some(String::from("outer"));
+        let mut light = PointLight::new(point(-10.0, 10.0, -10.0), Color::white());
+        light.illuminates = LightLink::Include(vec![String::from("floor")]);
+        world.light_source = Arc::new(light);
+        let r = Ray {
+            origin: point(0.0, 0.0, -5.0),
+            direction: vector(0.0, 0.0, 1.0),
+        };
+        let i = Intersection::new(4.0, world.objects[0].clone());
+        let comps = i.precompute(&r, Vec::new());
+
+        assert_eq!(world.shade_hit(comps, 10), Color::new(0.08, 0.1, 0.06));
+    }
+
     #[test]
     fn test_world_reflected_color_for_non_reflective_material() {
         let mut world = World::new();
@@ -286,11 +1610,49 @@ mod tests {
         assert_eq!(color, Color::black());
     }
 
+    #[test]
+    fn test_world_reflected_color_for_patterned_reflectivity() {
+        let sqrt_two_over_two = 2.0_f64.sqrt() / 2.0;
+        let ray = Ray {
+            origin: point(0.0, 0.0, -3.0),
+            direction: vector(0.0, -sqrt_two_over_two, sqrt_two_over_two),
+        };
+        let reflected = |tiles: Patternable| {
+            let mut plane = Shape::plane();
+            Arc::get_mut(&mut plane)
+                .unwrap()
+                .set_transform(Matrix4::translation(0.0, -1.0, 0.0))
+                .unwrap();
+            Arc::get_mut(&mut plane).unwrap().material.reflective = ChannelValue::Pattern(tiles);
+            let mut world = World::new();
+            world.objects.push(plane.clone());
+            let intersection = Intersection::new(2.0_f64.sqrt(), plane);
+            world.reflected_color(&intersection.precompute(&ray, Vec::new()), 10)
+        };
+        let mut tiles = Patternable::stripe(Color::new(0.5, 0.5, 0.5), Color::black());
+
+        assert_eq!(
+            reflected(tiles.clone()),
+            Color::new(
+                0.19033232037953468,
+                0.23791540047441834,
+                0.14274924028465102
+            )
+        );
+
+        tiles.transform = Matrix4::translation(1.0, 0.0, 0.0);
+
+        assert_eq!(reflected(tiles), Color::black());
+    }
+
     #[test]
     fn test_world_reflected_color_for_reflective_material() {
         let mut plane = Shape::plane();
-        Arc::get_mut(&mut plane).unwrap().transform = Matrix4::translation(0.0, -1.0, 0.0);
-        Arc::get_mut(&mut plane).unwrap().material.reflective = 0.5;
+        Arc::get_mut(&mut plane)
+            .unwrap()
+            .set_transform(Matrix4::translation(0.0, -1.0, 0.0))
+            .unwrap();
+        Arc::get_mut(&mut plane).unwrap().material.reflective = ChannelValue::Constant(0.5);
         let mut world = World::new();
         let sqrt_two_over_two = 2.0_f64.sqrt() / 2.0;
         world.objects.push(plane.clone());
@@ -298,10 +1660,7 @@ mod tests {
             origin: point(0.0, 0.0, -3.0),
             direction: vector(0.0, -sqrt_two_over_two, sqrt_two_over_two),
         };
-        let intersection = Intersection {
-            object: plane,
-            t: 2.0_f64.sqrt(),
-        };
+        let intersection = Intersection::new(2.0_f64.sqrt(), plane);
         let comps = intersection.precompute(&ray, Vec::new());
         let color = world.reflected_color(&comps, 10);
         assert_eq!(
@@ -315,18 +1674,54 @@ mod tests {
     }
 
     #[test]
-    fn test_world_reflected_color_infinite_recursion() {
+    fn test_world_reflection_depth() {
+        let mut plane = Shape::plane();
+        Arc::get_mut(&mut plane)
+            .unwrap()
+            .set_transform(Matrix4::translation(0.0, -1.0, 0.0))
+            .unwrap();
+        Arc::get_mut(&mut plane).unwrap().material.reflective = ChannelValue::Constant(0.5);
         let mut world = World::new();
-        world.light_source = PointLight {
-            position: point(0.0, 0.0, 0.0),
-            intensity: Color::new(1.0, 1.0, 1.0),
+        let sqrt_two_over_two = 2.0_f64.sqrt() / 2.0;
+        world.objects.push(plane.clone());
+        world.reflection_depth = Some(1);
+        let ray = Ray {
+            origin: point(0.0, 0.0, -3.0),
+            direction: vector(0.0, -sqrt_two_over_two, sqrt_two_over_two),
+        };
+        let intersection = Intersection::new(2.0_f64.sqrt(), plane);
+        let comps = intersection.precompute(&ray, Vec::new());
+
+        assert_ne!(world.reflected_color(&comps, 10), Color::black());
+        let reflected = Depth {
+            reflections: 1,
+            refractions: 0,
+            remaining: 9,
         };
+        assert_eq!(world.reflected_color(&comps, reflected), Color::black());
+        world.refraction_depth = Some(0);
+        assert_ne!(world.reflected_color(&comps, 10), Color::black());
+    }
+
+    #[test]
+    fn test_world_reflected_color_infinite_recursion() {
+        let mut world = World::new();
+        world.light_source = Arc::new(PointLight::new(
+            point(0.0, 0.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+        ));
         let mut lower = Shape::plane();
-        Arc::get_mut(&mut lower).unwrap().material.reflective = 1.0;
-        Arc::get_mut(&mut lower).unwrap().transform = Matrix4::translation(0.0, -1.0, 0.0);
+        Arc::get_mut(&mut lower).unwrap().material.reflective = ChannelValue::Constant(1.0);
+        Arc::get_mut(&mut lower)
+            .unwrap()
+            .set_transform(Matrix4::translation(0.0, -1.0, 0.0))
+            .unwrap();
         let mut upper = Shape::plane();
-        Arc::get_mut(&mut upper).unwrap().material.reflective = 1.0;
-        Arc::get_mut(&mut upper).unwrap().transform = Matrix4::translation(0.0, 1.0, 0.0);
+        Arc::get_mut(&mut upper).unwrap().material.reflective = ChannelValue::Constant(1.0);
+        Arc::get_mut(&mut upper)
+            .unwrap()
+            .set_transform(Matrix4::translation(0.0, 1.0, 0.0))
+            .unwrap();
         world.objects.push(lower);
         world.objects.push(upper);
         let ray = Ray {
@@ -337,6 +1732,136 @@ mod tests {
         assert_eq!(world.color_at(&ray, 10), Color::new(1.9, 1.9, 1.9));
     }
 
+    #[test]
+    fn test_shading_parts() {
+        let mut world = World::new();
+        let mut plane = Shape::plane();
+        {
+            let plane = Arc::get_mut(&mut plane).unwrap();
+            plane
+                .set_transform(Matrix4::translation(0.0, -1.0, 0.0))
+                .unwrap();
+            plane.material.reflective = ChannelValue::Constant(0.5);
+        }
+        world.objects.push(plane.clone());
+        let sqrt_two_over_two = 2.0_f64.sqrt() / 2.0;
+        let ray = Ray {
+            origin: point(0.0, 0.0, -3.0),
+            direction: vector(0.0, -sqrt_two_over_two, sqrt_two_over_two),
+        };
+        let comps = Intersection::new(2.0_f64.sqrt(), plane).precompute(&ray, Vec::new());
+        let reflected = world.reflected_color(&comps, 5);
+        let shading = world.shading(comps, 5);
+
+        assert_eq!(shading.reflection, reflected);
+        assert_eq!(shading.indirect, Color::black());
+        assert_eq!(shading.refraction, Color::black());
+        assert_eq!(shading.direct.add(&reflected), shading.color);
+        assert_eq!(shading.hit.unwrap().t, 2.0_f64.sqrt());
+    }
+
+    #[test]
+    fn test_shading_at_through_fog() {
+        let mut world = World::new();
+        world.fog = Some(Fog::new(Color::white(), 0.1));
+        let ray = Ray {
+            origin: point(0.0, 0.0, -5.0),
+            direction: vector(0.0, 0.0, 1.0),
+        };
+
+        let shading = world.shading_at(&ray, 5);
+        let surface = world.shade_hit(world.hit(&ray).unwrap(), 5);
+
+        assert_eq!(shading.hit.as_ref().map(|hit| hit.t), Some(4.0));
+        assert_eq!(shading.direct.add(&shading.bounced()), surface);
+        assert_eq!(shading.color, world.color_at(&ray, 5));
+        assert!(shading.color != surface);
+
+        let miss = Ray {
+            origin: point(0.0, 0.0, -5.0),
+            direction: vector(0.0, 1.0, 0.0),
+        };
+        let shading = world.shading_at(&miss, 5);
+        assert!(shading.hit.is_none());
+        assert_eq!(shading.indirect, world.background_color(&miss));
+    }
+
+    #[test]
+    fn test_surface_sample() {
+        let world = World::new();
+        let ray = Ray {
+            origin: point(0.0, 0.0, -5.0),
+            direction: vector(0.0, 0.0, 1.0),
+        };
+        let sample = world.surface_sample(&ray, 5);
+
+        assert_eq!(sample.depth, 4.0);
+        assert_eq!(sample.normal, vector(0.0, 0.0, -1.0));
+        assert_eq!(sample.object_id, Some(0));
+        assert_eq!(sample.albedo, Color::new(0.8, 1.0, 0.6));
+        assert_eq!(sample.direct.add(&sample.indirect), world.color_at(&ray, 5));
+
+        let miss = world.surface_sample(
+            &Ray {
+                origin: point(0.0, 0.0, -5.0),
+                direction: vector(0.0, 1.0, 0.0),
+            },
+            5,
+        );
+
+        assert_eq!(miss.object_id, None);
+        assert_eq!(miss.direct, Color::black());
+    }
+
+    #[test]
+    fn test_clearcoat_color_without_coat() {
+        let world = World::new();
+        let ray = Ray {
+            origin: point(0.0, 0.0, -5.0),
+            direction: vector(0.0, 0.0, 1.0),
+        };
+        let intersection = Intersection::new(4.0, world.objects[0].clone());
+        let comps = intersection.precompute(&ray, Vec::new());
+
+        assert_eq!(world.clearcoat_color(&comps, 1.0, 10), Color::black());
+    }
+
+    #[test]
+    fn test_clearcoat_color_reflects_scene() {
+        let mut plane = Shape::plane();
+        Arc::get_mut(&mut plane)
+            .unwrap()
+            .set_transform(Matrix4::translation(0.0, -1.0, 0.0))
+            .unwrap();
+        Arc::get_mut(&mut plane).unwrap().material.clearcoat = 1.0;
+        let mut world = World::new();
+        world.objects.push(plane.clone());
+        let sqrt_two_over_two = 2.0_f64.sqrt() / 2.0;
+        let ray = Ray {
+            origin: point(0.0, 0.0, -3.0),
+            direction: vector(0.0, -sqrt_two_over_two, sqrt_two_over_two),
+        };
+        let intersection = Intersection::new(2.0_f64.sqrt(), plane);
+        let comps = intersection.precompute(&ray, Vec::new());
+        let reflected = world.color_at(
+            &Ray {
+                origin: comps.over_point,
+                direction: comps.reflectv,
+            },
+            9,
+        );
+        let reflectance = comps
+            .object
+            .material
+            .clearcoat_reflectance(&comps.eyev, &comps.normalv);
+
+        assert_eq!(
+            world.clearcoat_color(&comps, 0.0, 10),
+            reflected.multiply_scalar(reflectance)
+        );
+        assert_eq!(world.clearcoat_color(&comps, 0.0, 0), Color::black());
+    }
+
     #[test]
     fn test_refracted_color_of_opaque_object() {
         let w = World::new();
@@ -346,14 +1871,8 @@ mod tests {
             direction: vector(0., 0., 1.),
         };
         let xs: Vec<Intersection> = vec![
-            Intersection {
-                t: 4.,
-                object: shape.clone(),
-            },
-            Intersection {
-                t: 6.,
-                object: shape.clone(),
-            },
+            Intersection::new(4., shape.clone()),
+            Intersection::new(6., shape.clone()),
         ];
         let comps = Intersection::precompute(&xs[0].clone(), &ray, xs);
         let color = w.refracted_color(&comps, 5);
@@ -364,35 +1883,33 @@ mod tests {
     #[test]
     fn test_refracted_color_of_maximum_depth() {
         let mut w = World::new();
-        w.objects = vec![Arc::new(Shape {
-            intersectable: Intersectable::sphere(),
-            material: Material {
-                ambient: 0.1,
-                diffuse: 0.7,
-                pattern: Patternable::solid(Color::new(0.8, 1.0, 0.6)),
-                reflective: 0.,
-                refractive_index: 1.5,
-                shininess: 200.,
-                specular: 0.2,
-                transparency: 1.,
-            },
-            parent: None,
-            transform: IDENTITY_MATRIX,
-        })];
+        let mut shape = Shape::new(Intersectable::sphere());
+        shape.material = Material {
+            ambient: 0.1,
+            backface: Backface::Shade,
+            clearcoat: 0.,
+            clearcoat_roughness: 0.,
+            diffuse: 0.7,
+            mask: None,
+            pattern: Patternable::solid(Color::new(0.8, 1.0, 0.6)),
+            reflective: ChannelValue::Constant(0.),
+            refractive_index: 1.5,
+            shininess: ChannelValue::Constant(200.),
+            specular: 0.2,
+            specular_model: SpecularModel::Phong,
+            texture: None,
+            transparency: ChannelValue::Constant(1.),
+            volume: None,
+        };
+        w.objects = vec![Arc::new(shape)];
         let shape = w.objects[0].clone();
         let ray = Ray {
             origin: point(0., 0., -5.),
             direction: vector(0., 0., 1.),
         };
         let xs: Vec<Intersection> = vec![
-            Intersection {
-                t: 4.,
-                object: shape.clone(),
-            },
-            Intersection {
-                t: 6.,
-                object: shape.clone(),
-            },
+            Intersection::new(4., shape.clone()),
+            Intersection::new(6., shape.clone()),
         ];
         let comps = Intersection::precompute(&xs[0].clone(), &ray, xs);
         let color = w.refracted_color(&comps, 0);
@@ -403,35 +1920,33 @@ mod tests {
     #[test]
     fn test_refracted_color_total_internal_reflection() {
         let mut w = World::new();
-        w.objects = vec![Arc::new(Shape {
-            intersectable: Intersectable::sphere(),
-            material: Material {
-                ambient: 0.1,
-                diffuse: 0.7,
-                pattern: Patternable::solid(Color::new(0.8, 1.0, 0.6)),
-                reflective: 0.,
-                refractive_index: 1.5,
-                shininess: 200.,
-                specular: 0.2,
-                transparency: 1.,
-            },
-            parent: None,
-            transform: IDENTITY_MATRIX,
-        })];
+        let mut shape = Shape::new(Intersectable::sphere());
+        shape.material = Material {
+            ambient: 0.1,
+            backface: Backface::Shade,
+            clearcoat: 0.,
+            clearcoat_roughness: 0.,
+            diffuse: 0.7,
+            mask: None,
+            pattern: Patternable::solid(Color::new(0.8, 1.0, 0.6)),
+            reflective: ChannelValue::Constant(0.),
+            refractive_index: 1.5,
+            shininess: ChannelValue::Constant(200.),
+            specular: 0.2,
+            specular_model: SpecularModel::Phong,
+            texture: None,
+            transparency: ChannelValue::Constant(1.),
+            volume: None,
+        };
+        w.objects = vec![Arc::new(shape)];
         let shape = w.objects[0].clone();
         let ray = Ray {
             origin: point(0., 0., (2.0_f64).sqrt() / 2.),
             direction: vector(0., 1., 0.),
         };
         let xs: Vec<Intersection> = vec![
-            Intersection {
-                t: -((2.0_f64).sqrt()) / 2.,
-                object: shape.clone(),
-            },
-            Intersection {
-                t: (2.0_f64).sqrt() / 2.,
-                object: shape.clone(),
-            },
+            Intersection::new(-((2.0_f64).sqrt()) / 2., shape.clone()),
+            Intersection::new((2.0_f64).sqrt() / 2., shape.clone()),
         ];
         let comps = Intersection::precompute(&xs[1].clone(), &ray, xs);
         let color = w.refracted_color(&comps, 0);
@@ -439,6 +1954,110 @@ mod tests {
         assert_eq!(color, Color::black());
     }
 
+    #[test]
+    fn test_merging_worlds() {
+        let (other, _) = World::from_json(
+            r#"{
+                "camera": { "width": 1, "height": 1, "field_of_view": 1, "from": [0, 0, -1], "to": [0, 0, 0] },
+                "light": { "position": [0, 0, -10], "intensity": [1, 1, 1] },
+                "shapes": [{
+                    "type": "group",
+                    "transform": [{ "translate": [2, 0, 0] }],
+                    "children": [{ "type": "sphere", "name": "ball" }]
+                }]
+            }"#,
+        )
+        .unwrap();
+        let mut world = World::new();
+        world.objects = Vec::new();
+
+        world
+            .merge(&other, Matrix4::translation(0., 0., 5.))
+            .unwrap();
+
+        assert!(world.merge(&other, Matrix4::scaling(0., 1., 1.)).is_err());
+        assert_eq!(world.objects.len(), 1);
+        let ray = Ray {
+            origin: point(2., 0., -5.),
+            direction: vector(0., 0., 1.),
+        };
+        let mut xs = ray.intersect_world(&world);
+        let hit = Intersection::hit(&mut xs).unwrap();
+        assert_eq!(hit.t, 9.);
+        assert!(hit.object.is_named("ball"));
+        assert_eq!(
+            hit.object.world_to_object(&point(2., 0., 4.)),
+            point(0., 0., -1.)
+        );
+        assert_eq!(
+            hit.object.normal_at(&point(2., 0., 4.)),
+            vector(0., 0., -1.)
+        );
+    }
+
+    #[test]
+    fn test_querying_shapes() {
+        let (mut world, _) = World::from_json(
+            r#"{
+                "camera": { "width": 1, "height": 1, "field_of_view": 1, "from": [0, 0, -1], "to": [0, 0, 0] },
+                "light": { "position": [0, 0, -10], "intensity": [1, 1, 1] },
+                "shapes": [
+                    { "type": "plane", "name": "floor" },
+                    {
+                        "type": "group",
+                        "name": "props",
+                        "children": [
+                            { "type": "sphere", "name": "ball", "tags": ["red"] },
+                            { "type": "cube", "tags": ["red", "metal"] }
+                        ]
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(world.shapes().len(), 4);
+        assert_eq!(world.objects[1].descendants().len(), 2);
+        let found = world.find(&ShapeQuery::Name("ball".to_string()));
+        assert_eq!(found.len(), 1);
+        assert!(found[0].parent.as_ref().unwrap().is_named("props"));
+        assert_eq!(world.find(&ShapeQuery::Tag("red".to_string())).len(), 2);
+        assert!(world
+            .find(&ShapeQuery::Name("missing".to_string()))
+            .is_empty());
+
+        world
+            .transform_shapes(
+                &ShapeQuery::Name("props".to_string()),
+                Matrix4::translation(0., 0., 5.),
+            )
+            .unwrap();
+        assert!(world
+            .transform_shapes(
+                &ShapeQuery::Name("props".to_string()),
+                Matrix4::scaling(1., 0., 1.),
+            )
+            .is_err());
+        let ray = Ray {
+            origin: point(0., 0., -5.),
+            direction: vector(0., 0., 1.),
+        };
+        let mut xs = ray.intersect_world(&world);
+        let hit = Intersection::hit(&mut xs).unwrap();
+        assert_eq!(hit.t, 9.);
+        assert_eq!(hit.object.normal_at(&ray.position(9.)), vector(0., 0., -1.));
+
+        let mut gold = Material::new();
+        gold.specular = 1.;
+        world.set_material(&ShapeQuery::Name("props".to_string()), &gold);
+        let specular: Vec<f64> = world
+            .shapes()
+            .iter()
+            .map(|shape| shape.material.specular)
+            .collect();
+        assert_eq!(specular, vec![0.9, 1., 1., 1.]);
+    }
+
     // #[test]
     // fn test_refracted_color_with_refracted_ray() {
     //     let mut w = World::new();
@@ -448,12 +2067,14 @@ mod tests {
     //             ambient: 1.0,
     //             diffuse: 0.7,
     //             pattern: Patternable::solid(Color::new(0.8, 1.0, 0.6)),
-    //             reflective: 0.,
+    //             reflective: ChannelValue::Constant(0.),
     //             refractive_index: 1.,
-    //             shininess: 200.,
+    //             shininess: ChannelValue::Constant(200.),
     //             specular: 0.2,
-    //             transparency: 0.,
+    //             transparency: ChannelValue::Constant(0.),
     //         },
+    //         name: None,
+    //         uv_mapping: None,
     //         parent: None,
     //         transform: IDENTITY_MATRIX,
     //     });
@@ -463,12 +2084,14 @@ mod tests {
     //             ambient: 0.1,
     //             diffuse: 0.7,
     //             pattern: Patternable::solid(Color::new(0.8, 1.0, 0.6)),
-    //             reflective: 0.,
+    //             reflective: ChannelValue::Constant(0.),
     //             refractive_index: 1.5,
-    //             shininess: 200.,
+    //             shininess: ChannelValue::Constant(200.),
     //             specular: 0.2,
-    //             transparency: 1.,
+    //             transparency: ChannelValue::Constant(1.),
     //         },
+    //         name: None,
+    //         uv_mapping: None,
     //         parent: None,
     //         transform: Matrix4::scaling(0.5, 0.5, 0.5),
     //     });